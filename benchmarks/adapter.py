@@ -71,15 +71,25 @@ class SimulationResult:
             else:
                 setattr(self, field.name, val / by)
 
-def run(simulation_options: SimulationOptions):
+def run_raw(simulation_options: SimulationOptions):
+    """Runs the simulator and returns its full result as a dict."""
     output = subprocess.run([BINARY, *simulation_options.to_flags_and_vals()], stdout=subprocess.PIPE).stdout.decode("utf-8").strip().split("\n")[-1]
-    metrics = json.loads(output)
-    return SimulationResult(metrics)
+    return json.loads(output)
+
+def run(simulation_options: SimulationOptions):
+    return SimulationResult(run_raw(simulation_options))
 
-def run_average(simulation_options: SimulationOptions, simulations: int):
+def run_average(simulation_options: SimulationOptions, simulations: int, raw_results_path: Optional[str] = None):
+    """Runs the simulation `simulations` times and averages the metrics. With `raw_results_path`,
+    the full result of every run is also appended to that file as one JSON object per line
+    (JSONL), so that other statistics can be computed later."""
     average_metrics: Optional[SimulationResult] = None
     for r in range(simulations):
-        metrics = run(simulation_options)
+        raw_metrics = run_raw(simulation_options)
+        if raw_results_path != None:
+            with open(raw_results_path, "a") as raw_results:
+                raw_results.write(json.dumps(raw_metrics) + "\n")
+        metrics = SimulationResult(raw_metrics)
         if average_metrics == None:
             average_metrics = metrics
             continue
@@ -0,0 +1,15 @@
+fn main() {
+    // Regenerates the header of the C API from its module.
+    #[cfg(feature = "cabi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        cbindgen::Builder::new()
+            .with_src(std::path::Path::new(&crate_dir).join("src/cabi.rs"))
+            .with_language(cbindgen::Language::C)
+            .with_include_guard("CELLULAR_AUTOMATON_TRAFFIC_SIMULATION_H")
+            .with_documentation(true)
+            .generate()
+            .expect("Unable to generate the header of the C API.")
+            .write_to_file(std::path::Path::new(&crate_dir).join("include/cellular_automaton_traffic_simulation.h"));
+    }
+}
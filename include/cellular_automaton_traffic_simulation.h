@@ -0,0 +1,112 @@
+#ifndef CELLULAR_AUTOMATON_TRAFFIC_SIMULATION_H
+#define CELLULAR_AUTOMATON_TRAFFIC_SIMULATION_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The occupancy of a cell without a car.
+ */
+#define SIM_FREE 0
+
+/**
+ * The occupancy of a cell with a car.
+ */
+#define SIM_OCCUPIED 1
+
+/**
+ * The occupancy of a blocked cell.
+ */
+#define SIM_BLOCKED 2
+
+/**
+ * The occupancy of a cell with a red traffic light and without a car.
+ */
+#define SIM_RED_LIGHT 3
+
+/**
+ * A road that C and C++ programs can run round by round. It is created with `sim_create` and
+ * has to be freed with `sim_destroy`.
+ */
+typedef struct TrafficSimulation TrafficSimulation;
+
+/**
+ * Creates a road with cars placed according to the vehicle types, given in the format of the
+ * `vehicles` option, e.g. `"(5, 1, 0.2); (3, 2, 0.05)"`. Without a seed (`NULL`), every road makes
+ * different random decisions. Returns `NULL` if an argument is invalid.
+ *
+ * # Safety
+ *
+ * `vehicles` must be a valid, nul-terminated string and `seed` either `NULL` or a valid pointer.
+ */
+struct TrafficSimulation *sim_create(uint32_t lanes,
+                                     uint32_t length,
+                                     const char *vehicles,
+                                     float dilly_dally_probability,
+                                     float stay_in_lane_probability,
+                                     const uint64_t *seed);
+
+/**
+ * Runs the given number of rounds.
+ *
+ * # Safety
+ *
+ * `sim` must have been created by `sim_create` and not yet been destroyed.
+ */
+void sim_step(struct TrafficSimulation *sim, uint32_t rounds);
+
+/**
+ * Writes the state of the cells, lane by lane, to the given buffers of `len` bytes: the occupancy
+ * (`SIM_FREE`, `SIM_OCCUPIED`, `SIM_BLOCKED` or `SIM_RED_LIGHT`) and the speed of the car in the
+ * cell (0 without a car). Either buffer may be `NULL`. Returns the number of cells of the road, of
+ * which at most `len` are written.
+ *
+ * # Safety
+ *
+ * `sim` must have been created by `sim_create` and not yet been destroyed, and the buffers must
+ * be `NULL` or hold at least `len` bytes.
+ */
+uintptr_t sim_get_cells(const struct TrafficSimulation *sim,
+                        uint8_t *occupancy,
+                        uint8_t *speeds,
+                        uintptr_t len);
+
+/**
+ * Returns the number of lanes.
+ *
+ * # Safety
+ *
+ * `sim` must have been created by `sim_create` and not yet been destroyed.
+ */
+uint32_t sim_lanes(const struct TrafficSimulation *sim);
+
+/**
+ * Returns the number of cells in each lane.
+ *
+ * # Safety
+ *
+ * `sim` must have been created by `sim_create` and not yet been destroyed.
+ */
+uint32_t sim_length(const struct TrafficSimulation *sim);
+
+/**
+ * Returns the number of rounds run so far.
+ *
+ * # Safety
+ *
+ * `sim` must have been created by `sim_create` and not yet been destroyed.
+ */
+uint32_t sim_rounds(const struct TrafficSimulation *sim);
+
+/**
+ * Frees the road. `NULL` is ignored.
+ *
+ * # Safety
+ *
+ * `sim` must have been created by `sim_create` and not yet been destroyed.
+ */
+void sim_destroy(struct TrafficSimulation *sim);
+
+#endif  /* CELLULAR_AUTOMATON_TRAFFIC_SIMULATION_H */
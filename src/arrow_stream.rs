@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use arrow_array::{RecordBatch, UInt32Array, UInt8Array};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use crate::observer::Observer;
+use crate::Road;
+
+/// Streams the state of the road as Apache Arrow record batches in the IPC streaming format. Each
+/// round is written as one batch holding one row per car.
+pub struct ArrowStreamWriter {
+    writer: StreamWriter<BufWriter<Box<dyn Write>>>,
+    schema: SchemaRef,
+}
+
+impl ArrowStreamWriter {
+    /// Opens the stream. The target may be `-` for stdout, `tcp://host:port` for a socket or the
+    /// path of a file.
+    pub fn new(target: &str) -> Result<Self, ArrowError> {
+        let sink: Box<dyn Write> = if target == "-" {
+            Box::new(io::stdout())
+        } else if let Some(address) = target.strip_prefix("tcp://") {
+            Box::new(TcpStream::connect(address)?)
+        } else {
+            Box::new(File::create(target)?)
+        };
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("round", DataType::UInt32, false),
+            Field::new("car", DataType::UInt32, false),
+            Field::new("lane", DataType::UInt32, false),
+            Field::new("cell", DataType::UInt32, false),
+            Field::new("speed", DataType::UInt8, false),
+        ]));
+        Ok(Self {
+            writer: StreamWriter::try_new_buffered(sink, &schema)?,
+            schema,
+        })
+    }
+
+    /// Writes the current state of all cars on the road as one record batch.
+    pub fn take_snapshot(&mut self, road: &Road) -> Result<(), ArrowError> {
+        let mut cars = Vec::new();
+        let mut lanes = Vec::new();
+        let mut cells = Vec::new();
+        let mut speeds = Vec::new();
+        for (lane_i, lane) in road.cells().iter().enumerate() {
+            for (cell_i, cell) in lane.iter().enumerate() {
+                if let Some(car) = cell.car() {
+                    cars.push(car.id());
+                    lanes.push(lane_i as u32);
+                    cells.push(cell_i as u32);
+                    speeds.push(car.speed());
+                }
+            }
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), vec![
+            Arc::new(UInt32Array::from(vec![road.rounds(); cars.len()])),
+            Arc::new(UInt32Array::from(cars)),
+            Arc::new(UInt32Array::from(lanes)),
+            Arc::new(UInt32Array::from(cells)),
+            Arc::new(UInt8Array::from(speeds)),
+        ])?;
+        self.writer.write(&batch)
+    }
+
+    /// Ends the stream and flushes all buffered batches.
+    pub fn close(&mut self) -> Result<(), ArrowError> {
+        self.writer.finish()?;
+        self.writer.flush()
+    }
+}
+
+impl Observer for ArrowStreamWriter {
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(self.take_snapshot(road)?)
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(self.close()?)
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.writer.flush()?)
+    }
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::path::Path;
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use crate::{read_settings_file, run_sim, SimulationConfig, SimulationResult};
+
+/// One run of a batch with the parameter values that it was run with.
+#[derive(Serialize, Debug)]
+pub struct BatchRun {
+    pub parameters: Map<String, Value>,
+    pub result: SimulationResult,
+}
+
+/// Returns every combination of the values of the parameters of a grid. A parameter with a list of
+/// values takes each of them in turn, any other value is used in every combination.
+fn combinations(grid: &Map<String, Value>) -> Vec<Map<String, Value>> {
+    let mut combinations = vec![Map::new()];
+    for (name, values) in grid {
+        let values = match values {
+            Value::Array(values) => values.clone(),
+            value => vec![value.clone()],
+        };
+        combinations = combinations
+            .iter()
+            .flat_map(|combination| values.iter().map(move |value| {
+                let mut combination = combination.clone();
+                combination.insert(name.clone(), value.clone());
+                combination
+            }))
+            .collect();
+    }
+    combinations
+}
+
+/// Runs the scenario once for every combination of the parameter values in the manifest, a JSON or
+/// yaml file that maps settings to lists of values, e.g. `{"lanes": [1, 2], "dilly_dally_probability":
+/// [0.1, 0.2, 0.3]}`. The runs are spread over all cores. Outputs of the scenario are not written.
+/// The runs are returned in the order of the combinations, with the parameters in alphabetical
+/// order and the last one changing fastest.
+pub fn run(args: &SimulationConfig, manifest: &Path) -> Result<Vec<BatchRun>, Box<dyn Error>> {
+    let grid = read_settings_file(manifest)?;
+    if let Some((name, _)) = grid.iter().find(|(_, values)| values.as_array().is_some_and(Vec::is_empty)) {
+        return Err(format!("The parameter `{}` of the batch has no values.", name).into());
+    }
+    let base = serde_json::to_value(args.clone().without_outputs())?;
+    let combinations = combinations(&grid);
+    let configs = combinations
+        .iter()
+        .map(|parameters| {
+            let mut config = base.clone();
+            for (name, value) in parameters {
+                if config.get(name).is_none() {
+                    return Err(format!("The batch varies `{}`, which is not a setting of a scenario.", name).into());
+                }
+                config[name] = value.clone();
+            }
+            Ok(serde_json::from_value::<SimulationConfig>(config)?)
+        })
+        .collect::<Result<Vec<SimulationConfig>, Box<dyn Error>>>()?;
+    Ok(configs
+        .into_par_iter()
+        .zip(combinations)
+        .map(|(config, parameters)| BatchRun { parameters, result: run_sim(config) })
+        .collect())
+}
+
+/// Writes all runs of a batch to one JSON file.
+pub fn write(path: &Path, runs: &[BatchRun]) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, serde_json::to_string(runs)?)?;
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use serde::Serialize;
+use crate::{Road, ROUND_S};
+
+/// Detects the breakdown of traffic flow, i.e. the first jam that persists. A jam is a cluster of
+/// at least `min_cluster` stopped cars in consecutive cells of a lane that are not queueing up in
+/// front of a red traffic light, a failed one or a blocked cell. It persists once there has been
+/// such a cluster somewhere on the road for `min_rounds` rounds in a row.
+#[derive(Debug)]
+pub struct BreakdownDetector {
+    min_cluster: u32,
+    min_rounds: u32,
+    streak_start: Option<u32>,
+    first_round: Option<u32>,
+    largest_cluster: u32,
+}
+
+/// Whether and when traffic broke down. The first round is the round in which the persisting jam
+/// formed.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Breakdown {
+    pub occurred: bool,
+    pub first_round: Option<u32>,
+    pub time_to_breakdown_s: Option<f64>,
+    /// The most stopped cars in a single jam in any round.
+    pub largest_jam_cars: u32,
+}
+
+impl BreakdownDetector {
+    pub fn new(min_cluster: u32, min_rounds: u32) -> Self {
+        if min_cluster == 0 || min_rounds == 0 {
+            panic!("A jam must consist of at least one car and persist for at least one round.");
+        }
+        Self {
+            min_cluster,
+            min_rounds,
+            streak_start: None,
+            first_round: None,
+            largest_cluster: 0,
+        }
+    }
+
+    /// Looks for jams after a round. (Cars start standing still, so the state before the first
+    /// round is not checked.)
+    pub fn take_snapshot(&mut self, road: &Road) {
+        let largest_cluster = largest_jam(road);
+        self.largest_cluster = self.largest_cluster.max(largest_cluster);
+        if largest_cluster < self.min_cluster {
+            self.streak_start = None;
+            return;
+        }
+        let streak_start = *self.streak_start.get_or_insert(road.rounds());
+        if self.first_round.is_none() && road.rounds() + 1 - streak_start >= self.min_rounds {
+            self.first_round = Some(streak_start);
+        }
+    }
+
+    pub fn breakdown(self) -> Breakdown {
+        Breakdown {
+            occurred: self.first_round.is_some(),
+            first_round: self.first_round,
+            time_to_breakdown_s: self.first_round.map(|round| round as f64 * ROUND_S),
+            largest_jam_cars: self.largest_cluster,
+        }
+    }
+}
+
+/// Returns the number of cars in the largest cluster of stopped cars on the road that is not a
+/// queue in front of an obstacle. On a closed road, clusters can wrap around the end.
+fn largest_jam(road: &Road) -> u32 {
+    let length = road.length() as usize;
+    let stopped = |cell_i: usize, lane: usize| road.cells()[lane][cell_i].car().is_some_and(|car| car.speed() == 0);
+    let mut largest = 0;
+    for (lane_i, lane) in road.cells().iter().enumerate() {
+        if (0..length).all(|cell_i| stopped(cell_i, lane_i)) {
+            return length as u32;
+        }
+        // Start right behind a cell without a stopped car so that no cluster is cut in two.
+        let start = match road.open() {
+            true => 0,
+            false => (0..length).find(|cell_i| !stopped(*cell_i, lane_i)).unwrap() + 1,
+        };
+        let mut cluster = 0;
+        for step in 0..length {
+            let cell_i = (start + step) % length;
+            if stopped(cell_i, lane_i) {
+                cluster += 1;
+                continue;
+            }
+            let cell = &lane[cell_i];
+            let obstacle = cell.blocked() || cell.is_red_light() || cell.is_stop_sign();
+            if !obstacle {
+                largest = largest.max(cluster);
+            }
+            cluster = 0;
+        }
+        if road.open() && cluster > 0 {
+            // The front of a cluster at the end of an open road is free to leave.
+            largest = largest.max(cluster);
+        }
+    }
+    largest
+}
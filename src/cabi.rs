@@ -0,0 +1,138 @@
+use std::ffi::{c_char, CStr};
+use crate::car::VehicleBlueprint;
+use crate::Road;
+
+use crate::cell::{BLOCKED, FREE, OCCUPIED, RED_LIGHT};
+
+/// The occupancy of a cell without a car.
+pub const SIM_FREE: u8 = 0;
+/// The occupancy of a cell with a car.
+pub const SIM_OCCUPIED: u8 = 1;
+/// The occupancy of a blocked cell.
+pub const SIM_BLOCKED: u8 = 2;
+/// The occupancy of a cell with a red traffic light and without a car.
+pub const SIM_RED_LIGHT: u8 = 3;
+
+// The header only sees the values written out above, so they must match those of the cells.
+const _: () = assert!(SIM_FREE == FREE && SIM_OCCUPIED == OCCUPIED && SIM_BLOCKED == BLOCKED && SIM_RED_LIGHT == RED_LIGHT);
+
+/// A road that C and C++ programs can run round by round. It is created with `sim_create` and
+/// has to be freed with `sim_destroy`.
+pub struct TrafficSimulation {
+    road: Road,
+}
+
+/// Creates a road with cars placed according to the vehicle types, given in the format of the
+/// `vehicles` option, e.g. `"(5, 1, 0.2); (3, 2, 0.05)"`. Without a seed (`NULL`), every road makes
+/// different random decisions. Returns `NULL` if an argument is invalid.
+///
+/// # Safety
+///
+/// `vehicles` must be a valid, nul-terminated string and `seed` either `NULL` or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn sim_create(
+    lanes: u32,
+    length: u32,
+    vehicles: *const c_char,
+    dilly_dally_probability: f32,
+    stay_in_lane_probability: f32,
+    seed: *const u64,
+) -> *mut TrafficSimulation {
+    if vehicles.is_null() || lanes == 0 || length == 0 {
+        return std::ptr::null_mut();
+    }
+    if !(0.0..=1.0).contains(&dilly_dally_probability) || !(0.0..=1.0).contains(&stay_in_lane_probability) {
+        return std::ptr::null_mut();
+    }
+    let Ok(vehicles) = CStr::from_ptr(vehicles).to_str() else { return std::ptr::null_mut(); };
+    let Ok(vehicles) = vehicles
+        .split(';')
+        .filter(|vehicle| !vehicle.trim().is_empty())
+        .map(|vehicle| vehicle.trim().parse::<VehicleBlueprint>())
+        .collect::<Result<Vec<VehicleBlueprint>, _>>()
+    else {
+        return std::ptr::null_mut();
+    };
+    let seed = seed.as_ref().copied();
+    let road = Road::new(lanes, length, &vehicles, dilly_dally_probability, stay_in_lane_probability, &[], &[], seed);
+    Box::into_raw(Box::new(TrafficSimulation { road }))
+}
+
+/// Runs the given number of rounds.
+///
+/// # Safety
+///
+/// `sim` must have been created by `sim_create` and not yet been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn sim_step(sim: *mut TrafficSimulation, rounds: u32) {
+    let Some(sim) = sim.as_mut() else { return; };
+    for _ in 0..rounds {
+        sim.road.round();
+    }
+}
+
+/// Writes the state of the cells, lane by lane, to the given buffers of `len` bytes: the occupancy
+/// (`SIM_FREE`, `SIM_OCCUPIED`, `SIM_BLOCKED` or `SIM_RED_LIGHT`) and the speed of the car in the
+/// cell (0 without a car). Either buffer may be `NULL`. Returns the number of cells of the road, of
+/// which at most `len` are written.
+///
+/// # Safety
+///
+/// `sim` must have been created by `sim_create` and not yet been destroyed, and the buffers must
+/// be `NULL` or hold at least `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sim_get_cells(sim: *const TrafficSimulation, occupancy: *mut u8, speeds: *mut u8, len: usize) -> usize {
+    let Some(sim) = sim.as_ref() else { return 0; };
+    let cells = sim.road.cells().iter().flatten();
+    for (i, cell) in cells.clone().take(len).enumerate() {
+        if !occupancy.is_null() {
+            *occupancy.add(i) = cell.occupancy_code();
+        }
+        if !speeds.is_null() {
+            *speeds.add(i) = cell.car().map_or(0, |car| car.speed());
+        }
+    }
+    cells.count()
+}
+
+/// Returns the number of lanes.
+///
+/// # Safety
+///
+/// `sim` must have been created by `sim_create` and not yet been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn sim_lanes(sim: *const TrafficSimulation) -> u32 {
+    sim.as_ref().map_or(0, |sim| sim.road.lanes())
+}
+
+/// Returns the number of cells in each lane.
+///
+/// # Safety
+///
+/// `sim` must have been created by `sim_create` and not yet been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn sim_length(sim: *const TrafficSimulation) -> u32 {
+    sim.as_ref().map_or(0, |sim| sim.road.length())
+}
+
+/// Returns the number of rounds run so far.
+///
+/// # Safety
+///
+/// `sim` must have been created by `sim_create` and not yet been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn sim_rounds(sim: *const TrafficSimulation) -> u32 {
+    sim.as_ref().map_or(0, |sim| sim.road.rounds())
+}
+
+/// Frees the road. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `sim` must have been created by `sim_create` and not yet been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn sim_destroy(sim: *mut TrafficSimulation) {
+    if !sim.is_null() {
+        drop(Box::from_raw(sim));
+    }
+}
@@ -1,54 +1,142 @@
 use std::cmp::Ordering;
 use std::cmp;
+use std::collections::VecDeque;
 use std::str::FromStr;
+use rand::Rng;
 use serde::Serialize;
 use crate::flip_flop::FlipFlop;
 
+/// Identifies a car. IDs are unique for the lifetime of a road.
+pub type CarId = u32;
+
 #[derive(Debug)]
 pub struct Car {
+    id: CarId,
+    vehicle_type: usize,
+    vehicle_class: VehicleClass,
     max_speed: u8,
     acceleration_time: u8,
     acceleration_time_accumulated: u8,
     last_speed: u8,
     speed: u8,
+    /// The cells per round by which the car slowed down in the current round.
+    deceleration: u8,
+    brake_light: bool,
+    wrecked_rounds: u32,
     distance: u32,
     accelerations: u32,
     deaccelerations: u32,
+    stops: u32,
+    lane_changes: u32,
+    rounds: u32,
+    congested_rounds: u32,
+    /// The number of rounds in a row up to the current one in which the car has driven at most one
+    /// cell.
+    slow_rounds: u32,
+    last_lap_round: Option<u32>,
+    last_lane_change_round: Option<u32>,
+    signal: Option<isize>,
+    departure: Option<(u32, usize)>,
+    /// The probabilities that the driver has drawn for themselves instead of sharing those of the
+    /// road.
+    dilly_dally_probability: Option<f32>,
+    stay_in_lane_probability: Option<f32>,
+    reaction_rounds: u8,
+    /// The cells that the car could drive in the last `reaction_rounds + 1` rounds, oldest first,
+    /// or `u8::MAX` for rounds in which the gap ahead did not hold it back.
+    perceived_gaps: VecDeque<u8>,
     overflow_flip_flop: FlipFlop
 }
 
 impl Car {
-    pub fn new(vehicle_blueprint: &VehicleBlueprint) -> Self {
+    pub fn new(id: CarId, vehicle_type: usize, vehicle_blueprint: &VehicleBlueprint) -> Self {
         const INITIAL_SPEED: u8 = 0;
         Self {
+            id,
+            vehicle_type,
+            vehicle_class: vehicle_blueprint.vehicle_class,
             max_speed: vehicle_blueprint.max_speed,
             acceleration_time: vehicle_blueprint.acceleration_time,
             acceleration_time_accumulated: 0,
             last_speed: INITIAL_SPEED,
+            deceleration: 0,
             speed: INITIAL_SPEED,
+            brake_light: false,
+            wrecked_rounds: 0,
             distance: 0,
             accelerations: 0,
             deaccelerations: 0,
+            stops: 0,
+            lane_changes: 0,
+            rounds: 0,
+            congested_rounds: 0,
+            slow_rounds: 0,
+            last_lap_round: None,
+            last_lane_change_round: None,
+            signal: None,
+            departure: None,
+            dilly_dally_probability: None,
+            stay_in_lane_probability: None,
+            reaction_rounds: vehicle_blueprint.reaction_rounds,
+            perceived_gaps: VecDeque::with_capacity(vehicle_blueprint.reaction_rounds as usize + 1),
             overflow_flip_flop: FlipFlop::new()
         }
     }
 
+    /// Returns the number that uniquely identifies the car on its road.
+    pub fn id(&self) -> CarId {
+        self.id
+    }
+
+    /// Returns the index of the vehicle blueprint that the car was built from.
+    pub fn vehicle_type(&self) -> usize {
+        self.vehicle_type
+    }
+
+    /// Returns the class of the vehicle. (See: `VehicleClass`)
+    pub fn vehicle_class(&self) -> VehicleClass {
+        self.vehicle_class
+    }
+
     /// Returns the speed in cells per round. (`1cell/round = 7.5m/s`)
     pub fn speed(&self) -> u8 {
         self.speed
     }
 
+    /// Returns the cells per round by which the car slowed down in its last round.
+    pub fn deceleration(&self) -> u8 {
+        self.deceleration
+    }
+
+    /// Returns whether the brake lights of the car are on, because it slowed down in its last round.
+    pub fn brake_light(&self) -> bool {
+        self.brake_light
+    }
+
+    /// Returns the speed in the last round, before the car accelerated in the current one.
+    pub fn last_speed(&self) -> u8 {
+        self.last_speed
+    }
+
+    /// Wrecks the car in a collision. It stands still for the given number of rounds.
+    pub fn crash(&mut self, rounds: u32) {
+        // The round of the collision is counted down as well.
+        self.wrecked_rounds = rounds.saturating_add(1);
+    }
+
+    /// Returns whether the car is a wreck that cannot drive.
+    pub fn wrecked(&self) -> bool {
+        self.wrecked_rounds > 0
+    }
+
+    /// Returns the maximum speed in cells per round.
+    pub fn max_speed(&self) -> u8 {
+        self.max_speed
+    }
+
     /// Converts the speed to an RGB color based on the percentage of the max speed.
     pub fn speed_rgb(&self) -> [u8; 3] {
-        let speed_norm: f32 = Into::<f32>::into(self.speed()) / Into::<f32>::into(self.max_speed);
-        let mut red = 255;
-        let mut green = 255;
-        if speed_norm <= 0.5 {
-            green = (255.0 * 2.0 * speed_norm).floor() as u8;
-        } else {
-            red = (255.0 * 2.0 * (1.0 - speed_norm)).floor() as u8;
-        }
-        [red, green, 0]
+        norm_rgb(Into::<f32>::into(self.speed()) / Into::<f32>::into(self.max_speed()))
     }
 
     /// Returns the distance in cells. (`1cell = 7.5m`)
@@ -66,21 +154,131 @@ impl Car {
         self.deaccelerations
     }
 
-    /// Finishes the simulation round for the car. (breaking and recording)
+    /// Returns the number of rounds that the car has come to a standstill.
+    pub fn stops(&self) -> u32 {
+        self.stops
+    }
+
+    /// Returns the number of rounds that the car has switched lanes.
+    pub fn lane_changes(&self) -> u32 {
+        self.lane_changes
+    }
+
+    /// Returns the number of rounds that the car has been on the road.
+    pub fn rounds(&self) -> u32 {
+        self.rounds
+    }
+
+    /// Returns the number of rounds that the car has been congested.
+    pub fn congested_rounds(&self) -> u32 {
+        self.congested_rounds
+    }
+
+    /// Returns the number of rounds in a row up to the current one in which the car has driven at
+    /// most one cell.
+    pub fn slow_rounds(&self) -> u32 {
+        self.slow_rounds
+    }
+
+    /// Records whether the car is congested in the current round, meaning that its speed is below
+    /// the threshold fraction of its max speed.
+    pub fn record_congestion(&mut self, threshold: f32) -> bool {
+        let congested = (self.speed as f32) < threshold * self.max_speed as f32;
+        if congested {
+            self.congested_rounds += 1;
+        }
+        congested
+    }
+
+    /// Forgets the records of the car, e.g. those of a warm-up period, while it keeps driving.
+    pub fn reset_records(&mut self) {
+        self.rounds = 0;
+        self.distance = 0;
+        self.accelerations = 0;
+        self.deaccelerations = 0;
+        self.stops = 0;
+        self.lane_changes = 0;
+        self.congested_rounds = 0;
+    }
+
+    /// Records that the car wraps around the end of the road in the given round. Returns the
+    /// number of rounds since the last wrap-around, which is the duration of a full lap. The first
+    /// lap is incomplete, because cars do not start at the beginning of the road.
+    pub fn complete_lap(&mut self, round: u32) -> Option<u32> {
+        let lap_time = self.last_lap_round.map(|last_lap_round| round - last_lap_round);
+        self.last_lap_round = Some(round);
+        lap_time
+    }
+
+    /// Records that the car switches lanes in the current round.
+    pub fn change_lane(&mut self, round: u32) {
+        self.lane_changes += 1;
+        self.last_lane_change_round = Some(round);
+        // The gaps seen in the old lane say nothing about the new leader.
+        self.perceived_gaps.clear();
+    }
+
+    /// Returns whether the car switched lanes in the round.
+    pub fn changed_lane_in(&self, round: u32) -> bool {
+        self.last_lane_change_round == Some(round)
+    }
+
+    /// Returns the lane offset (`-1` for left, `1` for right) of the lane change that the car
+    /// announces, if any.
+    pub fn signal(&self) -> Option<isize> {
+        self.signal
+    }
+
+    /// Announces a lane change to the neighboring cars or, with `None`, stops announcing one.
+    pub fn set_signal(&mut self, signal: Option<isize>) {
+        self.signal = signal;
+    }
+
+    /// Finishes the simulation round for the car. (breaking and recording) Automated vehicles do
+    /// not dilly-dally.
     pub fn finish(&mut self, cells_to_next_car: u8, dilly_dally: bool) {
+        let cells_to_next_car = self.react(cells_to_next_car);
         self.decrease_speed_to(cells_to_next_car);
-        if dilly_dally {
+        if dilly_dally && self.vehicle_class != VehicleClass::Automated {
             self.decrease_speed();
         }
         self.record();
     }
 
+    /// Returns the number of cells the car responds to being able to drive. A car with a reaction
+    /// delay only notices that its leader drove off or sped up `reaction_rounds` rounds later, so
+    /// it keeps to the smaller of what it can drive now and what it could drive back then. Less
+    /// space always takes effect at once, as the car cannot drive into its leader.
+    fn react(&mut self, driveable: u8) -> u8 {
+        if self.reaction_rounds == 0 {
+            return driveable;
+        }
+        if self.perceived_gaps.len() > self.reaction_rounds as usize {
+            self.perceived_gaps.pop_front();
+        }
+        self.perceived_gaps.push_back(if driveable < self.speed { driveable } else { u8::MAX });
+        cmp::min(driveable, self.perceived_gaps[0])
+    }
+
     /// Records the current round
     fn record(&mut self) {
+        self.rounds += 1;
         self.distance += self.speed() as u32;
+        self.brake_light = self.speed < self.last_speed;
+        self.deceleration = self.last_speed.saturating_sub(self.speed);
+        self.wrecked_rounds = self.wrecked_rounds.saturating_sub(1);
+        self.slow_rounds = match self.speed {
+            0 | 1 => self.slow_rounds + 1,
+            _ => 0,
+        };
         match self.speed.cmp(&self.last_speed) {
             Ordering::Greater => self.accelerations += 1,
-            Ordering::Less => self.deaccelerations += 1,
+            Ordering::Less => {
+                self.deaccelerations += 1;
+                if self.speed == 0 {
+                    self.stops += 1;
+                }
+            },
             Ordering::Equal => ()
         }
         self.last_speed = self.speed;
@@ -90,8 +288,62 @@ impl Car {
         self.overflow_flip_flop.unsync(other)
     }
 
-    /// Increases the speed by one if the maximum speed has not yet been reached.
+    pub fn flip_flop_sync(&mut self, other: &FlipFlop) {
+        self.overflow_flip_flop.sync(other)
+    }
+
+    /// Sets the speed without recording it. Used for cars that enter the road while already moving.
+    pub fn set_speed(&mut self, speed: u8) {
+        self.speed = cmp::min(speed, self.max_speed);
+        self.last_speed = self.speed;
+    }
+
+    /// Records the round in which and the lane on which the car entered an open road.
+    pub fn depart(&mut self, round: u32, lane: usize) {
+        self.departure = Some((round, lane));
+    }
+
+    /// Returns the round in which and the lane on which the car entered an open road, or `None` if
+    /// it was placed on the road at the start.
+    pub fn departure(&self) -> Option<(u32, usize)> {
+        self.departure
+    }
+
+    /// Gives the driver their own dilly-dally and stay-in-lane probabilities or, with `None`, lets
+    /// them share those of the road.
+    pub fn set_driver_probabilities(&mut self, dilly_dally_probability: Option<f32>, stay_in_lane_probability: Option<f32>) {
+        self.dilly_dally_probability = dilly_dally_probability;
+        self.stay_in_lane_probability = stay_in_lane_probability;
+    }
+
+    /// Returns the dilly-dally probability of the driver, if they have their own.
+    pub fn dilly_dally_probability(&self) -> Option<f32> {
+        self.dilly_dally_probability
+    }
+
+    /// Returns the stay-in-lane probability of the driver, if they have their own.
+    pub fn stay_in_lane_probability(&self) -> Option<f32> {
+        self.stay_in_lane_probability
+    }
+
+    /// Changes the maximum speed, e.g. when the drivers behave differently at another time of day.
+    /// A car that is faster than its new maximum speed slows down to it in its next update.
+    pub fn set_max_speed(&mut self, max_speed: u8) {
+        self.max_speed = max_speed;
+    }
+
+    /// Increases the speed by one if the maximum speed has not yet been reached. Automated vehicles
+    /// speed up to their maximum speed at once.
     pub fn increase_speed(&mut self) {
+        if self.wrecked() {
+            self.speed = 0;
+            return;
+        }
+        if self.vehicle_class == VehicleClass::Automated {
+            self.speed = self.max_speed;
+            return;
+        }
+        self.speed = cmp::min(self.speed, self.max_speed);
         self.acceleration_time_accumulated += 1;
         if self.acceleration_time_accumulated != self.acceleration_time {
             return;
@@ -103,6 +355,11 @@ impl Car {
         self.speed += 1;
     }
 
+    /// Decreases the speed to the limit if the car is faster.
+    pub fn limit_speed(&mut self, limit: u8) {
+        self.speed = cmp::min(self.speed, limit);
+    }
+
     /// Decreases the speed by one if the car is not already stopped.
     fn decrease_speed(&mut self) { 
         self.acceleration_time_accumulated = 0;
@@ -124,11 +381,112 @@ impl Car {
     }
 }
 
-#[derive(Serialize, Debug)]
+/// Converts a number between 0 and 1 to an RGB color ranging from red (0) over yellow to green (1).
+pub fn norm_rgb(norm: f32) -> [u8; 3] {
+    let mut red = 255;
+    let mut green = 255;
+    if norm <= 0.5 {
+        green = (255.0 * 2.0 * norm).floor() as u8;
+    } else {
+        red = (255.0 * 2.0 * (1.0 - norm)).floor() as u8;
+    }
+    [red, green, 0]
+}
+
+/// The summed up records of multiple cars.
+#[derive(Debug, Default, Clone)]
+pub struct CarRecords {
+    pub cars: u64,
+    pub rounds: u64,
+    pub distance: u64,
+    pub accelerations: u64,
+    pub deaccelerations: u64,
+    pub stops: u64,
+    pub lane_changes: u64,
+    pub congested_rounds: u64,
+}
+
+impl CarRecords {
+    /// Adds the records of a car.
+    pub fn add(&mut self, car: &Car) {
+        self.cars += 1;
+        self.rounds += car.rounds() as u64;
+        self.distance += car.distance() as u64;
+        self.accelerations += car.accelerations() as u64;
+        self.deaccelerations += car.deaccelerations() as u64;
+        self.stops += car.stops() as u64;
+        self.lane_changes += car.lane_changes() as u64;
+        self.congested_rounds += car.congested_rounds() as u64;
+    }
+
+    /// Adds the records of other cars.
+    pub fn merge(&mut self, other: &CarRecords) {
+        self.cars += other.cars;
+        self.rounds += other.rounds;
+        self.distance += other.distance;
+        self.accelerations += other.accelerations;
+        self.deaccelerations += other.deaccelerations;
+        self.stops += other.stops;
+        self.lane_changes += other.lane_changes;
+        self.congested_rounds += other.congested_rounds;
+    }
+}
+
+/// What kind of vehicle a vehicle type represents.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum VehicleClass {
+    #[default]
+    Regular,
+    /// Emergency vehicles can preempt traffic lights and use all reserved lanes.
+    Emergency,
+    /// Buses can use bus lanes.
+    Bus,
+    /// High-occupancy vehicles can use HOV lanes.
+    Hov,
+    /// Automated vehicles with adaptive cruise control never dilly-dally, accelerate to their
+    /// maximum speed at once and follow the car ahead closely by anticipating how far it drives.
+    Automated,
+}
+
+impl VehicleClass {
+    /// Returns whether vehicles of the class may drive in cells reserved for the given class.
+    pub fn may_use_reserved(&self, reserved_for: VehicleClass) -> bool {
+        *self == reserved_for || *self == VehicleClass::Emergency
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseVehicleClassError;
+
+impl FromStr for VehicleClass {
+    type Err = ParseVehicleClassError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "regular" => Ok(VehicleClass::Regular),
+            "emergency" => Ok(VehicleClass::Emergency),
+            "bus" => Ok(VehicleClass::Bus),
+            "hov" => Ok(VehicleClass::Hov),
+            "automated" => Ok(VehicleClass::Automated),
+            _ => Err(ParseVehicleClassError),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct VehicleBlueprint {
     max_speed: u8,
     acceleration_time: u8,
     traffic_density: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lane_densities: Option<Vec<f32>>,
+    vehicle_class: VehicleClass,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lane_weights: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_lanes: Option<Vec<usize>>,
+    reaction_rounds: u8,
 }
 
 impl VehicleBlueprint {
@@ -140,9 +498,117 @@ impl VehicleBlueprint {
         self.acceleration_time
     }
 
+    /// Returns the traffic density of the vehicle type on the whole road, which is the mean of its
+    /// lane densities if it has them.
     pub fn traffic_density(&self) -> f32 {
         self.traffic_density
     }
+
+    /// Returns the traffic densities of the vehicle type in each lane, from the left lane to the
+    /// right lane, with which the road is filled. Without lane densities, every lane gets the
+    /// traffic density.
+    pub fn lane_densities(&self) -> Option<&Vec<f32>> {
+        self.lane_densities.as_ref()
+    }
+
+    pub fn vehicle_class(&self) -> VehicleClass {
+        self.vehicle_class
+    }
+
+    /// Returns the number of rounds after which the cars of the vehicle type respond to their
+    /// leader driving off or speeding up.
+    pub fn reaction_rounds(&self) -> u8 {
+        self.reaction_rounds
+    }
+
+    /// Returns the relative weights, from the left lane to the right lane, with which the cars of
+    /// the vehicle type are spread over the lanes when the road is filled. Without lane weights,
+    /// the cars are spread evenly.
+    pub fn lane_weights(&self) -> Option<&Vec<f32>> {
+        self.lane_weights.as_ref()
+    }
+
+    /// Returns the lanes, from the left lane on, that the cars of the vehicle type may use, e.g. to
+    /// ban trucks from the left lane. Without allowed lanes, they may use all lanes.
+    pub fn allowed_lanes(&self) -> Option<&Vec<usize>> {
+        self.allowed_lanes.as_ref()
+    }
+
+    /// Returns whether the cars of the vehicle type may use the lane.
+    pub fn allows_lane(&self, lane_i: usize) -> bool {
+        self.allowed_lanes.as_ref().is_none_or(|allowed_lanes| allowed_lanes.contains(&lane_i))
+    }
+
+    /// Returns whether the cars of the vehicle type are not spread evenly over the lanes, because
+    /// of lane densities, lane weights or allowed lanes.
+    pub fn prefers_lanes(&self) -> bool {
+        self.lane_densities.is_some() || self.lane_weights.is_some() || self.allowed_lanes.is_some()
+    }
+
+    /// Returns the weight of the lane when the cars of the vehicle type are spread over the lanes,
+    /// which is its lane density if it has them and 0 for lanes they may not use.
+    pub fn lane_weight(&self, lane_i: usize) -> f64 {
+        if !self.allows_lane(lane_i) {
+            return 0.0;
+        }
+        match (&self.lane_densities, &self.lane_weights) {
+            (Some(densities), _) => densities[lane_i] as f64,
+            (None, Some(weights)) => weights[lane_i] as f64,
+            (None, None) => 1.0,
+        }
+    }
+
+    /// Returns the fraction of the cars of the vehicle type that are placed in each lane, which is
+    /// proportional to the lane weight times the number of cells available in the lane.
+    pub fn lane_shares(&self, cells_per_lane: &[u32]) -> Vec<f64> {
+        let weighted: Vec<f64> = cells_per_lane
+            .iter()
+            .enumerate()
+            .map(|(lane_i, cells)| *cells as f64 * self.lane_weight(lane_i))
+            .collect();
+        let total: f64 = weighted.iter().sum();
+        weighted.iter().map(|weighted| if total > 0.0 { weighted / total } else { 0.0 }).collect()
+    }
+
+    /// Turns the share of the cars of the vehicle type into automated vehicles with the same
+    /// parameters and returns their vehicle type, lowering the traffic density of this one
+    /// accordingly.
+    pub fn split_off_automated(&mut self, share: f32) -> VehicleBlueprint {
+        let automated = VehicleBlueprint {
+            traffic_density: self.traffic_density * share,
+            lane_densities: self.lane_densities.as_ref().map(|densities| densities.iter().map(|density| density * share).collect()),
+            vehicle_class: VehicleClass::Automated,
+            ..self.clone()
+        };
+        self.traffic_density *= 1.0 - share;
+        if let Some(densities) = &mut self.lane_densities {
+            densities.iter_mut().for_each(|density| *density *= 1.0 - share);
+        }
+        automated
+    }
+
+    /// Picks the index of a vehicle blueprint, weighted by the traffic densities.
+    pub fn pick_by_density<R: Rng>(rng: &mut R, vehicle_blueprints: &[VehicleBlueprint]) -> usize {
+        let densities: Vec<f64> = vehicle_blueprints.iter().map(|vb| vb.traffic_density() as f64).collect();
+        pick_weighted(rng, &densities)
+    }
+}
+
+/// Picks an index with a probability proportional to its weight. Indexes are picked uniformly if
+/// all weights are zero.
+pub fn pick_weighted<R: Rng>(rng: &mut R, weights: &[f64]) -> usize {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return rng.gen_range(0..weights.len());
+    }
+    let mut pick = rng.gen::<f64>() * total;
+    for (index, weight) in weights.iter().enumerate() {
+        if pick < *weight {
+            return index;
+        }
+        pick -= weight;
+    }
+    weights.len() - 1
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -159,17 +625,69 @@ impl FromStr for VehicleBlueprint {
             .ok_or(ParseVehicleBlueprintError)?;
 
         let split: Vec<&str> = inner.split(',').collect();
-        let (max_speed, acceleration_time, traffic_density) = if split.len() == 3 {
+        let (max_speed, acceleration_time) = if (3..=7).contains(&split.len()) {
             (
                 split[0].parse::<u8>().map_err(|_| ParseVehicleBlueprintError)?,
                 split[1].parse::<u8>().map_err(|_| ParseVehicleBlueprintError)?,
-                split[2].parse::<f32>().map_err(|_| ParseVehicleBlueprintError)?
             )
         } else {
             return Err(ParseVehicleBlueprintError);
         };
+        // The traffic density may be given for each lane as `density_lane_1:density_lane_2:...`.
+        let lane_densities = split[2]
+            .contains(':')
+            .then(|| split[2]
+                .split(':')
+                .map(|density| density.parse::<f32>().ok().filter(|density| (0.0..=1.0).contains(density)))
+                .collect::<Option<Vec<f32>>>()
+                .ok_or(ParseVehicleBlueprintError))
+            .transpose()?;
+        let traffic_density = match &lane_densities {
+            Some(densities) => densities.iter().sum::<f32>() / densities.len() as f32,
+            None => split[2].parse::<f32>().map_err(|_| ParseVehicleBlueprintError)?,
+        };
+        // The optional vehicle class, lane weights (`weight_lane_1:weight_lane_2:...`), allowed
+        // lanes (`lanes=lane_1:lane_2:...`) and reaction delay (`reaction=rounds`, always last) may
+        // each be left out.
+        let mut vehicle_class = VehicleClass::Regular;
+        let mut lane_weights = None;
+        let mut allowed_lanes = None;
+        let mut reaction_rounds = 0;
+        let mut options = &split[3..];
+        if let Some(rounds) = options.last().and_then(|option| option.strip_prefix("reaction=")) {
+            reaction_rounds = rounds.parse::<u8>().map_err(|_| ParseVehicleBlueprintError)?;
+            options = &options[..options.len() - 1];
+        }
+        if let Some(lanes) = options.last().and_then(|option| option.strip_prefix("lanes=")) {
+            let lanes = lanes
+                .split(':')
+                .map(|lane| lane.parse::<usize>().ok())
+                .collect::<Option<Vec<usize>>>()
+                .ok_or(ParseVehicleBlueprintError)?;
+            allowed_lanes = Some(lanes);
+            options = &options[..options.len() - 1];
+        }
+        for (i, option) in options.iter().enumerate() {
+            if i == 0 && !option.contains(':') {
+                vehicle_class = option.parse::<VehicleClass>().map_err(|_| ParseVehicleBlueprintError)?;
+            } else if lane_weights.is_none() {
+                let weights = option
+                    .split(':')
+                    .map(|weight| weight.parse::<f32>().ok().filter(|weight| *weight >= 0.0))
+                    .collect::<Option<Vec<f32>>>()
+                    .ok_or(ParseVehicleBlueprintError)?;
+                lane_weights = Some(weights);
+            } else {
+                return Err(ParseVehicleBlueprintError);
+            }
+        }
+
+        if lane_densities.is_some() && lane_weights.is_some() {
+            // Lane densities already spread the cars over the lanes.
+            return Err(ParseVehicleBlueprintError);
+        }
 
-        Ok(VehicleBlueprint { max_speed, acceleration_time, traffic_density })
+        Ok(VehicleBlueprint { max_speed, acceleration_time, traffic_density, lane_densities, vehicle_class, lane_weights, allowed_lanes, reaction_rounds })
     }
 }
 
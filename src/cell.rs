@@ -1,20 +1,46 @@
-use std::{ops::Range, str::FromStr};
+use std::{fmt, ops::Range, str::FromStr};
 use serde::Serialize;
 
-use crate::car::Car;
+use crate::car::{Car, VehicleClass};
+
+/// The occupancy of a cell without a car, as exposed to JavaScript and C. (See: `Cell::occupancy_code`)
+pub const FREE: u8 = 0;
+/// The occupancy of a cell with a car.
+pub const OCCUPIED: u8 = 1;
+/// The occupancy of a blocked cell.
+pub const BLOCKED: u8 = 2;
+/// The occupancy of a cell with a red traffic light and without a car.
+pub const RED_LIGHT: u8 = 3;
 
 #[derive(Debug)]
 pub struct PutCarErrorInformation {
     pub cell_blocked: bool,
-    pub new_car: Car,
+    pub new_car: Box<Car>,
 }
 
+/// A cell of a lane. The car is kept on the heap, so that the many empty cells of long roads stay
+/// small.
 #[derive(Debug)]
 pub struct Cell {
-    car: Option<Car>,
+    car: Option<Box<Car>>,
     cars_passed: i32,
+    passed_speeds: u64,
+    occupied_rounds: u32,
+    occupied_since: u32,
     blocked: bool,
     traffic_light: bool,
+    red: bool,
+    preempted: bool,
+    failed_rounds: u32,
+    speed_limit: Option<u8>,
+    rubbernecking: bool,
+    reserved_for: Option<VehicleClass>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cell {
@@ -22,14 +48,28 @@ impl Cell {
         Self {
             car: None,
             cars_passed: 0,
+            passed_speeds: 0,
+            occupied_rounds: 0,
+            occupied_since: 0,
             blocked: false,
             traffic_light: false,
+            red: false,
+            preempted: false,
+            failed_rounds: 0,
+            speed_limit: None,
+            rubbernecking: false,
+            reserved_for: None,
         }
     }
 
     /// Returns a read-only refrence to the car contained in the cell if there is one.
-    pub fn car(&self) -> &Option<Car> {
-        &self.car
+    pub fn car(&self) -> Option<&Car> {
+        self.car.as_deref()
+    }
+
+    /// Returns a mutable reference to the car contained in the cell if there is one.
+    pub fn car_mut(&mut self) -> Option<&mut Car> {
+        self.car.as_deref_mut()
     }
 
     /// Blocks the cell. Cars will not be able to use this cell anymore.
@@ -37,15 +77,102 @@ impl Cell {
         self.blocked = true;
     }
 
-    /// Designates the cell as a traffic light. It will turn in sync with the other traffic lights.
+    /// Designates the cell as a traffic light. It turns red and green according to its timing.
     pub fn make_traffic_light(&mut self) {
         self.traffic_light = true;
     }
 
-    /// Returns whether the cell represents a red light. The condition is met when the traffic
-    /// lights are red and the cell is a traffic light.
-    pub fn is_red_light(&self, light_red: bool) -> bool {
-        self.traffic_light && light_red
+    /// Limits the speed of cars driving off from this cell.
+    pub fn limit_speed(&mut self, limit: u8) {
+        self.speed_limit = Some(limit);
+    }
+
+    /// Returns the speed limit of the cell if there is one.
+    pub fn speed_limit(&self) -> Option<u8> {
+        self.speed_limit
+    }
+
+    /// Reserves the cell for vehicles of the class, e.g. for a bus lane.
+    pub fn reserve(&mut self, vehicle_class: VehicleClass) {
+        self.reserved_for = Some(vehicle_class);
+    }
+
+    /// Returns the class of vehicles that the cell is reserved for if there is one.
+    pub fn reserved_for(&self) -> Option<VehicleClass> {
+        self.reserved_for
+    }
+
+    /// Returns whether vehicles of the class may not drive in the cell because it is reserved for
+    /// other vehicles.
+    pub fn reserved_against(&self, vehicle_class: VehicleClass) -> bool {
+        self.reserved_for.is_some_and(|reserved_for| !vehicle_class.may_use_reserved(reserved_for))
+    }
+
+    /// Makes drivers in the cell gawk at a blocked cell next to it (or stops them from doing so).
+    pub fn set_rubbernecking(&mut self, rubbernecking: bool) {
+        self.rubbernecking = rubbernecking;
+    }
+
+    /// Returns whether drivers in the cell gawk at a blocked cell next to it.
+    pub fn rubbernecking(&self) -> bool {
+        self.rubbernecking
+    }
+
+    /// Returns whether the cell is a traffic light.
+    pub fn traffic_light(&self) -> bool {
+        self.traffic_light
+    }
+
+    /// Turns the traffic light of the cell red or green.
+    pub fn set_red(&mut self, red: bool) {
+        self.red = red;
+    }
+
+    /// Returns whether the traffic light of the cell is in its red phase, regardless of whether it
+    /// is forced green or has failed.
+    pub fn red(&self) -> bool {
+        self.traffic_light() && self.red
+    }
+
+    /// Forces the traffic light of the cell green (or stops forcing it).
+    pub fn preempt(&mut self, preempted: bool) {
+        self.preempted = preempted;
+    }
+
+    /// Returns whether the traffic light of the cell is forced green.
+    pub fn preempted(&self) -> bool {
+        self.preempted
+    }
+
+    /// Lets the traffic light of the cell fail for the given number of rounds.
+    pub fn fail(&mut self, rounds: u32) {
+        self.failed_rounds = rounds;
+    }
+
+    /// Counts down the remaining rounds of a failure of the traffic light of the cell.
+    pub fn pass_failed_round(&mut self) {
+        self.failed_rounds = self.failed_rounds.saturating_sub(1);
+    }
+
+    /// Returns whether the traffic light of the cell has failed.
+    pub fn failed(&self) -> bool {
+        self.failed_rounds > 0
+    }
+
+    /// Returns whether the cell represents a red light. The condition is met when the cell is a
+    /// working traffic light in its red phase and it is not forced green.
+    pub fn is_red_light(&self) -> bool {
+        self.red() && !self.failed() && !self.preempted()
+    }
+
+    /// Returns whether the cell is a failed traffic light, which drivers treat as a stop sign.
+    pub fn is_stop_sign(&self) -> bool {
+        self.traffic_light() && self.failed()
+    }
+
+    /// Clears the block of the cell.
+    pub fn unblock(&mut self) {
+        self.blocked = false;
     }
 
     /// Returns whether the cell is blocked.
@@ -54,44 +181,114 @@ impl Cell {
     }
 
     /// Returns whether the cell is free, meaning it contains no car and is not blocked, hence
-    /// theoretically driveable.
-    pub fn free(&self, light_red: bool) -> bool {
-         !(self.blocked() || self.car().is_some() || self.traffic_light && light_red)
+    /// theoretically driveable. Stop signs are only driveable for the car that stopped in front of
+    /// them, so they are not free.
+    pub fn free(&self) -> bool {
+         !(self.blocked() || self.car().is_some() || self.is_red_light() || self.is_stop_sign())
     }
 
-    /// Takes the car from the cell if there is one.
-    pub fn take_car(&mut self) -> Option<Car> {
-        self.car.take()
+    /// Returns the occupancy of the cell as one byte: `FREE`, `OCCUPIED`, `BLOCKED` or `RED_LIGHT`.
+    pub fn occupancy_code(&self) -> u8 {
+        match self.car() {
+            Some(_) => OCCUPIED,
+            None if self.blocked() => BLOCKED,
+            None if self.is_red_light() => RED_LIGHT,
+            None => FREE,
+        }
     }
 
-    /// Tries to put a car into the cell. Fails if the cell is blocked or already contains another
-    /// car.
-    pub fn put_car(&mut self, car: Car) -> Result<(), PutCarErrorInformation> {
+    /// Takes the car from the cell in the given round if there is one.
+    pub fn take_car(&mut self, round: u32) -> Option<Box<Car>> {
+        let car = self.car.take();
+        if car.is_some() {
+            self.occupied_rounds += round.saturating_sub(self.occupied_since);
+        }
+        car
+    }
+
+    /// Tries to put a car into the cell in the given round. Fails if the cell is blocked or already
+    /// contains another car.
+    pub fn put_car(&mut self, car: Box<Car>, round: u32) -> Result<(), PutCarErrorInformation> {
         if self.blocked() || self.car().is_some() {
             return Err(PutCarErrorInformation { cell_blocked: self.blocked(), new_car: car });
         }
         self.car = Some(car);
+        // Cars put into the cell before the first round count from the end of the first round on.
+        self.occupied_since = round.max(1);
         Ok(())
     }
 
-    /// Records that a car has passed the cell.
-    pub fn pass(&mut self) {
+    /// Forgets the cars that have come by and the rounds that the cell has been occupied, so that
+    /// they are counted from the end of the given round on.
+    pub fn reset_records(&mut self, round: u32) {
+        self.cars_passed = 0;
+        self.passed_speeds = 0;
+        self.occupied_rounds = 0;
+        if self.car.is_some() {
+            self.occupied_since = round + 1;
+        }
+    }
+
+    /// Records that a car has passed the cell at the given speed.
+    pub fn pass(&mut self, speed: u8) {
         self.cars_passed += 1;
+        self.passed_speeds += speed as u64;
+    }
+
+    /// Returns the number of cars that have come by this cell.
+    pub fn cars_passed(&self) -> i32 {
+        self.cars_passed
+    }
+
+    /// Returns whether the cell, used as a detector, detects a car: one has come by since the cell
+    /// had been passed by `cars_passed` cars or one is standing in it.
+    pub fn detects(&self, cars_passed: i32) -> bool {
+        self.cars_passed > cars_passed || self.car.is_some()
+    }
+
+    /// Returns the summed up speeds of the cars that have come by this cell.
+    pub fn passed_speeds(&self) -> u64 {
+        self.passed_speeds
+    }
+
+    /// Returns the number of rounds at whose end the cell contained a car after the given number of
+    /// rounds. The rounds are counted whenever a car leaves the cell rather than at the end of
+    /// every round, so that long roads need not be swept an extra time per round.
+    pub fn occupied_rounds(&self, rounds: u32) -> u32 {
+        match self.car {
+            Some(_) => self.occupied_rounds + (rounds + 1).saturating_sub(self.occupied_since),
+            None => self.occupied_rounds,
+        }
+    }
+
+    /// Returns the fraction of the last `measured_rounds` rounds up to the given round at whose end
+    /// the cell contained a car.
+    pub fn occupancy(&self, rounds: u32, measured_rounds: u32) -> f64 {
+        self.occupied_rounds(rounds) as f64 / measured_rounds as f64
     }
 
     /// Returns the cars per round that have come by this cell.
     pub fn flow(&self, rounds: u32) -> f64 {
         Into::<f64>::into(self.cars_passed) / Into::<f64>::into(rounds)
     }
+
+    /// Returns the mean speed in cells per round of the cars that have come by this cell.
+    pub fn mean_speed(&self) -> f64 {
+        self.passed_speeds as f64 / Into::<f64>::into(self.cars_passed)
+    }
 }
 
-#[derive(Serialize, Debug, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct CellLocation {
     lane: usize,
     index: usize
 }
 
 impl CellLocation {
+    pub fn new(lane: usize, index: usize) -> Self {
+        Self { lane, index }
+    }
+
     pub fn lane(&self) -> usize {
         self.lane
     }
@@ -99,6 +296,23 @@ impl CellLocation {
     pub fn index(&self) -> usize {
         self.index
     }
+
+    /// Parses a cell location whose lane may also be `*` or `all`, which stands for the same cell
+    /// index in each of the `lanes` lanes.
+    pub fn parse_in_lanes(s: &str, lanes: u32) -> Result<Vec<Self>, ParseCellLocationError> {
+        let s: String = s.replace(' ', "");
+        match s.strip_prefix("(*,").or_else(|| s.strip_prefix("(all,")) {
+            Some(index) => {
+                let index = index
+                    .strip_suffix(')')
+                    .ok_or(ParseCellLocationError)?
+                    .parse::<usize>()
+                    .map_err(|_| ParseCellLocationError)?;
+                Ok((0..lanes as usize).map(|lane| CellLocation { lane, index }).collect())
+            },
+            None => Ok(vec![s.parse::<CellLocation>()?]),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -121,14 +335,37 @@ impl FromStr for CellLocation {
     }
 }
 
-#[derive(Serialize, Debug, PartialEq)]
+impl fmt::Display for CellLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.lane, self.index)
+    }
+}
+
+/// A range of cells in a lane, used for blocks. A block may only be in place from round `from` on
+/// and until round `until`, exclusively.
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct CellLocationRange {
     lane: usize,
     start: usize,
     end: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    until: Option<u32>,
 }
 
 impl CellLocationRange {
+    pub fn new(lane: usize, start: usize, end: usize) -> Self {
+        Self { lane, start, end, from: None, until: None }
+    }
+
+    /// Restricts the block to the rounds from `from` on and before `until`.
+    pub fn with_rounds(mut self, from: Option<u32>, until: Option<u32>) -> Self {
+        self.from = from;
+        self.until = until;
+        self
+    }
+
     pub fn lane(&self) -> usize {
         self.lane
     }
@@ -136,6 +373,22 @@ impl CellLocationRange {
     pub fn indexes(&self) -> Range<usize> {
         Range { start: self.start, end: self.end }
     }
+
+    /// Returns whether the block is only in place for some of the rounds.
+    pub fn scheduled(&self) -> bool {
+        self.from.is_some() || self.until.is_some()
+    }
+
+    /// Returns whether the block is in place in the given round. Round 0 is the state before the
+    /// first round.
+    pub fn active_in(&self, round: u32) -> bool {
+        self.from.is_none_or(|from| round >= from) && self.until.is_none_or(|until| round < until)
+    }
+
+    /// Returns whether the block has been cleared for good by the given round.
+    pub fn ended_by(&self, round: u32) -> bool {
+        self.until.is_some_and(|until| round >= until)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -144,13 +397,18 @@ pub struct ParseCellLocationRangeError;
 impl FromStr for CellLocationRange {
     type Err = ParseCellLocationRangeError;
 
+    /// Parses a block in the format `(lane, start-end[, from=round][, until=round])` or
+    /// `(lane, index[, from=round][, until=round])`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s: String = s.replace(' ', "");
-        let (lane, indexes_str) = s
+        let inner = s
             .strip_prefix('(')
             .and_then(|s| s.strip_suffix(')'))
-            .and_then(|s| s.split_once(','))
             .ok_or(ParseCellLocationRangeError)?;
+        let mut split = inner.split(',');
+        let (Some(lane), Some(indexes_str)) = (split.next(), split.next()) else {
+            return Err(ParseCellLocationRangeError);
+        };
 
         let lane = lane.parse::<usize>().map_err(|_| ParseCellLocationRangeError)?;
 
@@ -166,7 +424,127 @@ impl FromStr for CellLocationRange {
             }
         };
 
-        Ok(CellLocationRange { lane, start, end })
+        let (mut from, mut until) = (None, None);
+        for option in split {
+            let (name, round) = option.split_once('=').ok_or(ParseCellLocationRangeError)?;
+            let round = round.parse::<u32>().map_err(|_| ParseCellLocationRangeError)?;
+            match name {
+                "from" => from = Some(round),
+                "until" => until = Some(round),
+                _ => return Err(ParseCellLocationRangeError),
+            }
+        }
+        if from.zip(until).is_some_and(|(from, until)| from >= until) {
+            return Err(ParseCellLocationRangeError);
+        }
+
+        Ok(CellLocationRange { lane, start, end, from, until })
+    }
+}
+
+
+/// A range of cells in a lane from which cars may drive off at most `limit` cells per round, e.g.
+/// in a work zone or in town.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SpeedLimitZone {
+    range: CellLocationRange,
+    limit: u8,
+}
+
+impl SpeedLimitZone {
+    pub fn new(range: CellLocationRange, limit: u8) -> Self {
+        Self { range, limit }
+    }
+
+    pub fn range(&self) -> &CellLocationRange {
+        &self.range
+    }
+
+    pub fn limit(&self) -> u8 {
+        self.limit
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseSpeedLimitZoneError;
+
+impl FromStr for SpeedLimitZone {
+    type Err = ParseSpeedLimitZoneError;
+
+    /// Parses a speed limit zone in the format `(lane, start-end, limit)` or `(lane, index, limit)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let (range, limit) = s
+            .strip_suffix(')')
+            .and_then(|s| s.rsplit_once(','))
+            .ok_or(ParseSpeedLimitZoneError)?;
+
+        let range = format!("{})", range).parse::<CellLocationRange>().map_err(|_| ParseSpeedLimitZoneError)?;
+        let limit = limit.parse::<u8>().map_err(|_| ParseSpeedLimitZoneError)?;
+        if range.scheduled() {
+            return Err(ParseSpeedLimitZoneError);
+        }
+
+        Ok(SpeedLimitZone { range, limit })
+    }
+}
+
+/// A lane or a range of cells in a lane that is reserved for a class of vehicles, e.g. a bus
+/// lane. Other vehicles treat the cells as blocked.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReservedZone {
+    lane: usize,
+    indexes: Option<Range<usize>>,
+    vehicle_class: VehicleClass,
+}
+
+impl ReservedZone {
+    /// Reserves the whole lane or, with indexes, the cells of the lane.
+    pub fn new(lane: usize, indexes: Option<Range<usize>>, vehicle_class: VehicleClass) -> Self {
+        Self { lane, indexes, vehicle_class }
+    }
+
+    pub fn lane(&self) -> usize {
+        self.lane
+    }
+
+    /// Returns the indexes of the reserved cells in a lane of the given length.
+    pub fn indexes(&self, length: usize) -> Range<usize> {
+        self.indexes.clone().unwrap_or(0..length)
+    }
+
+    pub fn vehicle_class(&self) -> VehicleClass {
+        self.vehicle_class
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseReservedZoneError;
+
+impl FromStr for ReservedZone {
+    type Err = ParseReservedZoneError;
+
+    /// Parses a reserved zone in the format `(lane, class)` for a whole lane or `(lane, start-end,
+    /// class)` or `(lane, index, class)` for cells of a lane.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let (cells, vehicle_class) = s
+            .strip_suffix(')')
+            .and_then(|s| s.rsplit_once(','))
+            .ok_or(ParseReservedZoneError)?;
+
+        let vehicle_class = vehicle_class.parse::<VehicleClass>().map_err(|_| ParseReservedZoneError)?;
+        let (lane, indexes) = match cells.strip_prefix('(').and_then(|lane| lane.parse::<usize>().ok()) {
+            Some(lane) => (lane, None),
+            None => {
+                let range = format!("{})", cells).parse::<CellLocationRange>().map_err(|_| ParseReservedZoneError)?;
+                if range.scheduled() {
+                    return Err(ParseReservedZoneError);
+                }
+                (range.lane(), Some(range.indexes()))
+            },
+        };
+
+        Ok(ReservedZone { lane, indexes, vehicle_class })
+    }
+}
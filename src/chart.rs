@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use plotters::prelude::*;
+use crate::cell::CellLocation;
+use crate::observer::Observer;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// The number of rounds over which the flow at a monitored cell is averaged, as a single round
+/// only ever sees zero or one car pass.
+const FLOW_WINDOW_ROUNDS: usize = 60;
+
+/// Renders series of values over the rounds as a line chart to an SVG file.
+pub fn render_line_chart(path: &Path, caption: &str, y_desc: &str, series: &[(String, Vec<f64>)]) -> Result<(), Box<dyn Error>> {
+    let root = SVGBackend::new(path, (1000, 500)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let rounds = series.iter().map(|(_, values)| values.len()).max().unwrap_or(0);
+    let y_max = series.iter().flat_map(|(_, values)| values).copied().filter(|value| !value.is_nan()).fold(1.0, f64::max) * 1.1;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(1.0..(rounds as f64).max(2.0), 0.0..y_max)?;
+    chart.configure_mesh().x_desc("Round").y_desc(y_desc).draw()?;
+    for (series_i, (label, values)) in series.iter().enumerate() {
+        let color = Palette99::pick(series_i);
+        // Rounds without a value, e.g. the mean speed of an empty road, are left out.
+        let points = values.iter().enumerate().filter(|(_, value)| !value.is_nan()).map(|(round_i, value)| ((round_i + 1) as f64, *value));
+        chart
+            .draw_series(LineSeries::new(points, color.stroke_width(2)))?
+            .label(label.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    if series.len() > 1 {
+        chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    }
+    root.present()?;
+    Ok(())
+}
+
+/// Records the mean speed of all cars and the flow at the monitored cells of every round and
+/// renders them as line charts at the end of the simulation. The flow of a round is averaged over
+/// the last `FLOW_WINDOW_ROUNDS` rounds.
+#[derive(Debug)]
+pub struct TimeSeriesCharts {
+    speed_path: PathBuf,
+    flow_path: PathBuf,
+    monitors: Vec<(usize, usize)>,
+    mean_speeds: Vec<f64>,
+    cars_passed: Vec<Vec<i32>>,
+}
+
+impl TimeSeriesCharts {
+    /// Creates the charts that are written to the given paths. Without monitored cells, there is no
+    /// flow chart.
+    pub fn new(speed_path: PathBuf, flow_path: PathBuf, monitors: &[CellLocation]) -> Self {
+        Self {
+            speed_path,
+            flow_path,
+            monitors: monitors.iter().map(|monitor| (monitor.lane(), monitor.index())).collect(),
+            mean_speeds: Vec::new(),
+            cars_passed: vec![Vec::new(); monitors.len()],
+        }
+    }
+
+    fn record_cars_passed(&mut self, road: &Road) {
+        for (monitor_i, (lane, index)) in self.monitors.iter().enumerate() {
+            let cars_passed = road.cells().get(*lane).and_then(|lane| lane.get(*index)).map_or(0, |cell| cell.cars_passed());
+            self.cars_passed[monitor_i].push(cars_passed);
+        }
+    }
+
+    /// Returns the flow in cars per minute at every monitored cell after every round.
+    fn flows(&self) -> Vec<Vec<f64>> {
+        self.cars_passed
+            .iter()
+            .map(|cars_passed| {
+                (1..cars_passed.len())
+                    .map(|round_i| {
+                        let window_start = round_i.saturating_sub(FLOW_WINDOW_ROUNDS);
+                        let passed = cars_passed[round_i] - cars_passed[window_start];
+                        passed as f64 / (round_i - window_start) as f64 / ROUND_S * 60.0
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Observer for TimeSeriesCharts {
+    fn start(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        // Only the passes of the initial state are needed to count the cars passing in round one.
+        self.record_cars_passed(road);
+        Ok(())
+    }
+
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        let (speed_sum, cars) = road.iter_cars().fold((0u64, 0u64), |(speed_sum, cars), (_, _, _, car)| (speed_sum + car.speed() as u64, cars + 1));
+        self.mean_speeds.push(speed_sum as f64 / cars as f64 * CELL_M / ROUND_S * 3.6);
+        self.record_cars_passed(road);
+        Ok(())
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        render_line_chart(&self.speed_path, "Mean Speed", "Mean Speed (km/h)", &[("all cars".to_string(), self.mean_speeds.clone())])?;
+        if self.monitors.is_empty() {
+            return Ok(());
+        }
+        let flow_series: Vec<(String, Vec<f64>)> = self.monitors
+            .iter()
+            .zip(self.flows())
+            .map(|((lane, index), flows)| (format!("({}, {})", lane, index), flows))
+            .collect();
+        render_line_chart(&self.flow_path, "Flow at Monitored Cells", "Flow (car/min)", &flow_series)
+    }
+
+    fn buffer_bytes(&self) -> usize {
+        self.mean_speeds.len() * size_of::<f64>() + self.cars_passed.iter().map(Vec::len).sum::<usize>() * size_of::<i32>()
+    }
+}
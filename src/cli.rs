@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use crate::SimulationConfig;
+
+/// The command line of the simulator: the scenario, followed by the options that pick what is done
+/// with it instead of running it once.
+#[derive(Parser, Clone, Debug)]
+#[command(author, version, about = None, long_about = None)]
+pub struct Cli {
+    #[command(flatten)]
+    pub scenario: SimulationConfig,
+
+    /// Optionally replay the run recorded in this event log instead of simulating it, rendering it
+    /// with the image, frame, GIF, ASCII and terminal outputs of the scenario. The road and the
+    /// number of rounds are taken from the log.
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+
+    /// Optionally run the scenario once for each of this many evenly spaced traffic densities
+    /// instead of once and print the measured points of the fundamental diagram. The number of
+    /// cars of each run replaces `vehicles` densities, `cars` and `lane_densities`. Outputs are
+    /// not written.
+    #[arg(long)]
+    pub density_sweep: Option<u32>,
+
+    /// Optionally render the flow-density and speed-density diagrams of a density sweep to this
+    /// SVG file.
+    #[arg(long)]
+    pub fd_plot: Option<PathBuf>,
+
+    /// Optionally run the scenario once for every combination of the parameter values in this
+    /// JSON or yaml manifest instead of once, spread over all cores. The manifest maps settings to
+    /// lists of values, e.g. `{"lanes": [1, 2], "dilly_dally_probability": [0.1, 0.2]}`. Outputs
+    /// are not written.
+    #[arg(long)]
+    pub batch: Option<PathBuf>,
+
+    /// Optionally write the parameters and results of all runs of a batch to this JSON file
+    /// instead of printing them.
+    #[arg(long)]
+    pub batch_out: Option<PathBuf>,
+
+    /// Optionally run the scenario this many times instead of once, spread over all cores, and
+    /// print the mean, the standard deviation and the 95% confidence interval of every metric. The
+    /// runs use the seeds `seed`, `seed + 1`, ... or random seeds without a seed. Outputs are not
+    /// written.
+    #[arg(long)]
+    pub replications: Option<u32>,
+
+    /// Optionally write the full result of every replication to this file as JSON Lines, one run
+    /// per line in the order of the seeds, so that other statistics can be computed later on.
+    #[arg(long)]
+    pub raw_results: Option<PathBuf>,
+
+    /// Optionally simulate a signalized intersection where two roads cross instead of a single
+    /// road and print the queues and delays of its four approaches. The probabilities with which
+    /// cars turn left, go straight on and turn right are specified as `(left, straight, right)`.
+    /// Each approach is an empty open road with `lanes` lanes and `length` cells that ends at the
+    /// stop line and is fed by `demand`, or 10 cars per minute without one.
+    #[arg(long)]
+    pub intersection: Option<String>,
+
+    /// The signal plan of the intersection, specified as `(north_south_green, east_west_green[, clearance])`
+    /// in rounds. The north and south approaches are green together, then all approaches are red
+    /// for the clearance, then the east and west approaches are green, followed by another
+    /// clearance.
+    #[arg(long, default_value = "(40, 40, 3)")]
+    pub intersection_phases: String,
+
+    /// The length in rounds of the intervals over which the cars that turn left, go straight on and
+    /// turn right at each approach of the intersection are counted, 15 minutes by default.
+    #[arg(long, default_value_t = 900)]
+    pub turning_movement_interval: u32,
+
+    /// Optionally write the turning movement counts of the intersection to this CSV file, with one
+    /// row per interval and approach.
+    #[arg(long)]
+    pub turning_movements: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Optionally provide simulator settings as a yaml file to avoid using the command line for
+    /// detailed simulations. Note: All Options except `yaml` must be used!
+    #[arg(short, long, global = true)]
+    pub yaml: Option<PathBuf>,
+
+    /// Optionally provide the scenario as a JSON (`.json`), yaml (`.yaml`, `.yml`) or TOML
+    /// (`.toml`) file with the same settings as `yaml`. Unlike with `yaml`, settings that the file leaves out keep their
+    /// defaults.
+    #[arg(long, global = true, conflicts_with = "yaml")]
+    pub config: Option<PathBuf>,
+
+    /// Optionally run as a daemon that watches this directory for yaml scenario files and runs each
+    /// of them as it appears. The result of `scenario.yaml` is written to `scenario.result.json` (or
+    /// the reason it failed to `scenario.error.txt`) and relative paths in the scenario are resolved
+    /// against the directory, so that all artifacts end up next to it.
+    #[arg(long)]
+    pub watch: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Check that canonical scenarios play out as expected, e.g. after building on a new platform.
+    ///
+    /// The scenarios are a single car in free flow, a dissolving jam, a bottleneck and a corridor
+    /// with traffic lights. Prints whether each of them passed and exits with an error if one did
+    /// not.
+    Smoke,
+    /// Check the scenario without running it, e.g. before a long run.
+    ///
+    /// Looks for unparsable options, values out of range, locations off the road, overlapping
+    /// blocks, traffic lights on blocked cells and options that cannot be combined in the scenario
+    /// given by the options, `--yaml` or `--config`. Prints a report of the problems and exits with
+    /// an error if the scenario is invalid.
+    Validate,
+    /// Check that the seeded scenario plays out the same every time it runs.
+    ///
+    /// Runs the scenario given by the options, `--yaml` or `--config` three times, the last time on
+    /// another number of threads, and compares the state hashes of every round. Prints the first
+    /// diverging round of each run and exits with an error if any run diverged. Without `--seed`,
+    /// a random seed is drawn and printed.
+    Selftest,
+}
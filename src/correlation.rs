@@ -0,0 +1,146 @@
+use std::cmp::Ordering;
+use serde::Serialize;
+use crate::cell::CellLocation;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// Records the flow and speed series of the monitored cells and estimates the delays with which
+/// changes in traffic propagate from one detector to another.
+#[derive(Debug)]
+pub struct DetectorCorrelator {
+    detectors: Vec<Option<(usize, usize)>>,
+    bin_rounds: u32,
+    /// The number of cars that have passed each detector and their summed up speeds by round.
+    passes: Vec<Vec<(i32, u64)>>,
+}
+
+/// The time-lagged cross-correlation of two detectors. A positive delay means that the traffic at
+/// detector `to` follows the traffic at detector `from`. Detectors are numbered in the order of the
+/// monitored cells.
+#[derive(Serialize, Debug)]
+pub struct DetectorCorrelation {
+    pub from: usize,
+    pub to: usize,
+    pub flow_delay_s: f64,
+    pub flow_correlation: f64,
+    pub speed_delay_s: f64,
+    pub speed_correlation: f64,
+}
+
+impl DetectorCorrelator {
+    /// Creates the correlator. Invalid detector locations are recorded as detectors that no car
+    /// ever passes.
+    pub fn new(road: &Road, monitors: &[CellLocation], bin_rounds: u32) -> Self {
+        if bin_rounds == 0 {
+            panic!("Detector correlation bins must span at least one round.");
+        }
+        let detectors = monitors
+            .iter()
+            .map(|cl| (cl.lane() < road.lanes() as usize && cl.index() < road.length() as usize).then(|| (cl.lane(), cl.index())))
+            .collect();
+        Self {
+            detectors,
+            bin_rounds,
+            passes: Vec::new(),
+        }
+    }
+
+    pub fn take_snapshot(&mut self, road: &Road) {
+        self.passes.push(
+            self.detectors
+                .iter()
+                .map(|detector| match detector {
+                    Some((lane, index)) => {
+                        let cell = &road.cells()[*lane][*index];
+                        (cell.cars_passed(), cell.passed_speeds())
+                    },
+                    None => (0, 0),
+                })
+                .collect()
+        );
+    }
+
+    /// Returns the number of bytes of the recorded series.
+    pub fn buffer_bytes(&self) -> usize {
+        self.passes.iter().map(Vec::len).sum::<usize>() * size_of::<(i32, u64)>()
+    }
+
+    /// Returns the flow in cars per minute and the mean speed in km/h of the passing cars of a
+    /// detector for each bin. Bins without passing cars keep the speed of the previous bin.
+    fn series(&self, detector: usize) -> (Vec<f64>, Vec<f64>) {
+        let mut flows = Vec::new();
+        let mut speeds = Vec::new();
+        let mut speed = 0.0;
+        // The first snapshot is taken before the first round.
+        let bins = self.passes.len().saturating_sub(1) / self.bin_rounds as usize;
+        for bin in 0..bins {
+            let (start_passed, start_speeds) = self.passes[bin * self.bin_rounds as usize][detector];
+            let (end_passed, end_speeds) = self.passes[(bin + 1) * self.bin_rounds as usize][detector];
+            let passed = end_passed - start_passed;
+            flows.push(passed as f64 / (self.bin_rounds as f64 * ROUND_S) * 60.0);
+            if passed > 0 {
+                speed = (end_speeds - start_speeds) as f64 / passed as f64 * (CELL_M / ROUND_S) * 3.6;
+            }
+            speeds.push(speed);
+        }
+        (flows, speeds)
+    }
+
+    /// Correlates every pair of detectors. Lags of up to a quarter of the series are considered.
+    pub fn correlate(&self) -> Vec<DetectorCorrelation> {
+        let series: Vec<(Vec<f64>, Vec<f64>)> = (0..self.detectors.len()).map(|detector| self.series(detector)).collect();
+        let bin_s = self.bin_rounds as f64 * ROUND_S;
+        let mut correlations = Vec::new();
+        for from in 0..series.len() {
+            for to in (from + 1)..series.len() {
+                let (flow_lag, flow_correlation) = best_lag(&series[from].0, &series[to].0);
+                let (speed_lag, speed_correlation) = best_lag(&series[from].1, &series[to].1);
+                correlations.push(DetectorCorrelation {
+                    from,
+                    to,
+                    flow_delay_s: flow_lag as f64 * bin_s,
+                    flow_correlation,
+                    speed_delay_s: speed_lag as f64 * bin_s,
+                    speed_correlation,
+                });
+            }
+        }
+        correlations
+    }
+}
+
+/// Returns the lag (in bins) at which `b` correlates best with `a` and that correlation. Ties are
+/// resolved in favor of the shorter lag. Returns a correlation of `NaN` if either series is
+/// constant.
+fn best_lag(a: &[f64], b: &[f64]) -> (isize, f64) {
+    let max_lag = (a.len() / 4) as isize;
+    (-max_lag..=max_lag)
+        .map(|lag| (lag, lagged_correlation(a, b, lag)))
+        .filter(|(_, correlation)| !correlation.is_nan())
+        .max_by(|(lag_a, correlation_a), (lag_b, correlation_b)| {
+            correlation_a.total_cmp(correlation_b).then_with(|| match lag_a.abs().cmp(&lag_b.abs()) {
+                Ordering::Equal => lag_b.cmp(lag_a),
+                ordering => ordering.reverse(),
+            })
+        })
+        .unwrap_or((0, f64::NAN))
+}
+
+/// Returns the Pearson correlation of `a[t]` and `b[t + lag]`.
+fn lagged_correlation(a: &[f64], b: &[f64], lag: isize) -> f64 {
+    let pairs: Vec<(f64, f64)> = (0..a.len() as isize)
+        .filter(|t| t + lag >= 0 && ((t + lag) as usize) < b.len())
+        .map(|t| (a[t as usize], b[(t + lag) as usize]))
+        .collect();
+    let n = pairs.len() as f64;
+    let mean_a = pairs.iter().map(|(a, _)| a).sum::<f64>() / n;
+    let mean_b = pairs.iter().map(|(_, b)| b).sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for (a, b) in &pairs {
+        covariance += (a - mean_a) * (b - mean_b);
+        variance_a += (a - mean_a).powi(2);
+        variance_b += (b - mean_b).powi(2);
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
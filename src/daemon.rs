@@ -0,0 +1,96 @@
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use crate::{run_sim, SimulationConfig};
+
+/// How often the watched directory is checked for new scenarios.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a scenario file must stay unmodified before it is run, so that files that are still
+/// being written or copied are not picked up half-finished.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Watches a directory for YAML scenario files and runs each of them once, forever. The result of
+/// `scenario.yaml` is written to `scenario.result.json` next to it or, if the scenario could not be
+/// run, the reason to `scenario.error.txt`. Scenarios that already have either file are skipped, so
+/// a restarted daemon picks up where it left off.
+pub fn watch(dir: &Path) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not a directory.", dir.display())));
+    }
+    eprintln!("Watching {} for scenarios.", dir.display());
+    loop {
+        for scenario in pending_scenarios(dir, SETTLE_TIME)? {
+            eprintln!("Running {}.", scenario.display());
+            match run_scenario(&scenario)? {
+                true => eprintln!("Finished {}.", scenario.display()),
+                false => eprintln!("Failed {}.", scenario.display()),
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Returns the YAML files in the directory that have neither a result nor an error file yet and
+/// have not been modified for `settle_time`, sorted by name.
+pub fn pending_scenarios(dir: &Path, settle_time: Duration) -> io::Result<Vec<PathBuf>> {
+    let mut scenarios = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_yaml = path.extension().is_some_and(|extension| extension == "yaml" || extension == "yml");
+        if !is_yaml || !entry.file_type()?.is_file() {
+            continue;
+        }
+        if result_path(&path).exists() || error_path(&path).exists() {
+            continue;
+        }
+        let age = SystemTime::now().duration_since(entry.metadata()?.modified()?).unwrap_or_default();
+        if age >= settle_time {
+            scenarios.push(path);
+        }
+    }
+    scenarios.sort();
+    Ok(scenarios)
+}
+
+/// Runs a scenario file and writes its result or error file. Relative paths in the scenario are
+/// resolved against the directory of the file, so that artifacts such as images end up next to
+/// it. Returns whether the scenario ran successfully.
+pub fn run_scenario(path: &Path) -> io::Result<bool> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let outcome = fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| SimulationConfig::from_yaml(&contents).map_err(|error| error.to_string()))
+        .and_then(|mut args| {
+            args.resolve_paths(dir);
+            // Invalid settings make the simulator panic, which must not take down the daemon.
+            panic::catch_unwind(AssertUnwindSafe(|| run_sim(args))).map_err(|payload| {
+                payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| payload.downcast_ref::<&str>().map(|message| message.to_string()))
+                    .unwrap_or_else(|| "The simulation panicked.".to_string())
+            })
+        });
+    match outcome {
+        Ok(result) => {
+            fs::write(result_path(path), result.json())?;
+            Ok(true)
+        },
+        Err(error) => {
+            fs::write(error_path(path), error + "\n")?;
+            Ok(false)
+        },
+    }
+}
+
+fn result_path(scenario: &Path) -> PathBuf {
+    scenario.with_extension("result.json")
+}
+
+fn error_path(scenario: &Path) -> PathBuf {
+    scenario.with_extension("error.txt")
+}
@@ -0,0 +1,97 @@
+use std::str::FromStr;
+use rand::prelude::*;
+use crate::car::{pick_weighted, VehicleBlueprint};
+use crate::ROUND_S;
+
+/// The demand for a period of time starting at `start_round`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemandBin {
+    start_round: u32,
+    cars_per_minute: f64,
+    shares: Vec<f64>,
+}
+
+impl DemandBin {
+    /// Returns the number of cars arriving per round on the whole road.
+    pub fn cars_per_round(&self) -> f64 {
+        self.cars_per_minute / 60.0 * ROUND_S
+    }
+
+    /// Returns the shares of the vehicle blueprints. Empty if none were specified.
+    pub fn shares(&self) -> &Vec<f64> {
+        &self.shares
+    }
+
+    /// Picks the index of the vehicle blueprint of an arriving car. The blueprints are weighted by
+    /// the shares of this bin or by their traffic densities if no shares were specified.
+    pub fn pick_blueprint<R: Rng>(&self, rng: &mut R, vehicle_blueprints: &[VehicleBlueprint]) -> usize {
+        if self.shares.is_empty() {
+            VehicleBlueprint::pick_by_density(rng, vehicle_blueprints)
+        } else {
+            pick_weighted(rng, &self.shares)
+        }
+    }
+}
+
+/// A profile of time-binned arrival rates that drives the inflow of an open road.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemandProfile {
+    bins: Vec<DemandBin>,
+}
+
+impl DemandProfile {
+    /// Returns the bin that is active during the given round, if any.
+    pub fn bin_at(&self, round: u32) -> Option<&DemandBin> {
+        self.bins.iter().rev().find(|bin| bin.start_round <= round)
+    }
+
+    /// Returns all bins ordered by their start round.
+    pub fn bins(&self) -> &Vec<DemandBin> {
+        &self.bins
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseDemandProfileError {
+    pub line: usize,
+}
+
+impl FromStr for DemandProfile {
+    type Err = ParseDemandProfileError;
+
+    /// Parses CSV rows of the format `start_round, cars_per_minute[, share_0, share_1, ...]`.
+    /// Empty lines, lines starting with `#` and a header row are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bins = Vec::new();
+        let mut first_row = true;
+        for (line_i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let is_first_row = first_row;
+            first_row = false;
+
+            let err = ParseDemandProfileError { line: line_i + 1 };
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let start_round = match fields[0].parse::<u32>() {
+                Ok(start_round) => start_round,
+                Err(_) if is_first_row => continue, // header
+                Err(_) => return Err(err),
+            };
+            let cars_per_minute = fields.get(1).ok_or(err)?.parse::<f64>().map_err(|_| err)?;
+            let shares = fields[2..]
+                .iter()
+                .map(|share| share.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| err)?;
+
+            if cars_per_minute < 0.0 || shares.iter().any(|share| *share < 0.0) {
+                return Err(err);
+            }
+            bins.push(DemandBin { start_round, cars_per_minute, shares });
+        }
+        bins.sort_by_key(|bin| bin.start_round);
+        Ok(DemandProfile { bins })
+    }
+}
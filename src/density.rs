@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+/// A target traffic density at a round.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DensityKeyframe {
+    round: u32,
+    density: f32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseDensityKeyframeError;
+
+impl FromStr for DensityKeyframe {
+    type Err = ParseDensityKeyframeError;
+
+    /// Parses `(round, density)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let (round, density) = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|s| s.split_once(','))
+            .ok_or(ParseDensityKeyframeError)?;
+
+        let round = round.parse::<u32>().map_err(|_| ParseDensityKeyframeError)?;
+        let density = density.parse::<f32>().map_err(|_| ParseDensityKeyframeError)?;
+        if !(0.0..=1.0).contains(&density) {
+            return Err(ParseDensityKeyframeError);
+        }
+
+        Ok(DensityKeyframe { round, density })
+    }
+}
+
+/// The traffic density of a lane, either in total or for each vehicle type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LaneDensity {
+    Total(f32),
+    PerVehicleType(Vec<f32>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseLaneDensityError;
+
+impl FromStr for LaneDensity {
+    type Err = ParseLaneDensityError;
+
+    /// Parses `density` or `(density_vehicle_type_1, density_vehicle_type_2, ...)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let parse_density = |density: &str| {
+            let density = density.parse::<f32>().map_err(|_| ParseLaneDensityError)?;
+            if !(0.0..=1.0).contains(&density) {
+                return Err(ParseLaneDensityError);
+            }
+            Ok(density)
+        };
+        match s.strip_prefix('(') {
+            Some(densities) => {
+                let densities = densities
+                    .strip_suffix(')')
+                    .ok_or(ParseLaneDensityError)?
+                    .split(',')
+                    .map(parse_density)
+                    .collect::<Result<Vec<f32>, _>>()?;
+                Ok(LaneDensity::PerVehicleType(densities))
+            },
+            None => Ok(LaneDensity::Total(parse_density(&s)?)),
+        }
+    }
+}
+
+/// A trajectory of the target traffic density over the rounds. Between keyframes, the density is
+/// interpolated linearly. Before the first and after the last keyframe, it stays constant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensitySchedule {
+    keyframes: Vec<DensityKeyframe>,
+}
+
+impl DensitySchedule {
+    pub fn new(mut keyframes: Vec<DensityKeyframe>) -> Self {
+        if keyframes.is_empty() {
+            panic!("A density schedule needs at least one keyframe.");
+        }
+        keyframes.sort_by_key(|keyframe| keyframe.round);
+        Self { keyframes }
+    }
+
+    /// Returns the target density of a round.
+    pub fn target_density(&self, round: u32) -> f32 {
+        let next_i = self.keyframes.partition_point(|keyframe| keyframe.round <= round);
+        if next_i == 0 {
+            return self.keyframes[0].density;
+        }
+        let previous = self.keyframes[next_i - 1];
+        let Some(next) = self.keyframes.get(next_i) else {
+            return previous.density;
+        };
+        let progress = (round - previous.round) as f32 / (next.round - previous.round) as f32;
+        previous.density + (next.density - previous.density) * progress
+    }
+}
@@ -0,0 +1,73 @@
+use std::f32::consts::PI;
+use std::str::FromStr;
+use rand::Rng;
+use crate::car::Car;
+
+/// How a probability of the driver behavior, like the dilly-dally probability, varies among the
+/// drivers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbabilityDistribution {
+    /// Every probability from `min` to `max` is equally likely.
+    Uniform { min: f32, max: f32 },
+    /// Normally distributed around `mean` with the standard deviation `sd`. Probabilities outside
+    /// of 0 to 1 are clamped.
+    Normal { mean: f32, sd: f32 },
+}
+
+impl ProbabilityDistribution {
+    /// Draws a probability from the distribution.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f32 {
+        match *self {
+            ProbabilityDistribution::Uniform { min, max } => min + rng.gen::<f32>() * (max - min),
+            ProbabilityDistribution::Normal { mean, sd } => {
+                // Box-Muller transform
+                let (u1, u2) = (1.0 - rng.gen::<f32>(), rng.gen::<f32>());
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+                (mean + sd * z).clamp(0.0, 1.0)
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseProbabilityDistributionError;
+
+impl FromStr for ProbabilityDistribution {
+    type Err = ParseProbabilityDistributionError;
+
+    /// Parses `uniform(min, max)` with `0 <= min <= max <= 1` or `normal(mean, sd)` with a mean
+    /// from 0 to 1 and a standard deviation of at least 0.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let (name, parameters) = s
+            .strip_suffix(')')
+            .and_then(|s| s.split_once('('))
+            .ok_or(ParseProbabilityDistributionError)?;
+        let (a, b) = parameters.split_once(',').ok_or(ParseProbabilityDistributionError)?;
+        let a = a.parse::<f32>().map_err(|_| ParseProbabilityDistributionError)?;
+        let b = b.parse::<f32>().map_err(|_| ParseProbabilityDistributionError)?;
+
+        match name {
+            "uniform" if 0.0 <= a && a <= b && b <= 1.0 => Ok(ProbabilityDistribution::Uniform { min: a, max: b }),
+            "normal" if (0.0..=1.0).contains(&a) && b >= 0.0 => Ok(ProbabilityDistribution::Normal { mean: a, sd: b }),
+            _ => Err(ParseProbabilityDistributionError),
+        }
+    }
+}
+
+/// The distributions from which the drivers draw their own probabilities when their car is put on
+/// the road. Probabilities without a distribution are shared by all drivers.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DriverDistributions {
+    pub dilly_dally: Option<ProbabilityDistribution>,
+    pub stay_in_lane: Option<ProbabilityDistribution>,
+}
+
+impl DriverDistributions {
+    /// Lets the driver of the car draw their own probabilities.
+    pub fn draw<R: Rng>(&self, rng: &mut R, car: &mut Car) {
+        let dilly_dally = self.dilly_dally.map(|distribution| distribution.sample(rng));
+        let stay_in_lane = self.stay_in_lane.map(|distribution| distribution.sample(rng));
+        car.set_driver_probabilities(dilly_dally, stay_in_lane);
+    }
+}
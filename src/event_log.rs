@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use crate::car::{Car, CarId, VehicleBlueprint};
+use crate::cell::{CellLocation, CellLocationRange};
+use crate::observer::Observer;
+use crate::traffic_light::{LightTiming, TrafficLight};
+use crate::simulation::create_observers;
+use crate::{ImageMode, Road, SimulationConfig};
+
+const MAGIC: &[u8; 8] = b"CATSEVT1";
+
+const ROUND: u8 = 0;
+const ENTER: u8 = 1;
+const MOVE: u8 = 2;
+const LANE_CHANGE: u8 = 3;
+const EXIT: u8 = 4;
+const LIGHT: u8 = 5;
+
+/// An event of the log. Every round starts with a `Round` event, followed by the events that led
+/// to the state of the road at its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Round { round: u32 },
+    /// A car appeared on the road, or its maximum speed changed.
+    Enter { car: CarId, vehicle_type: u16, max_speed: u8, lane: u16, index: u32, speed: u8 },
+    /// A car drove to another cell in its lane or changed its speed.
+    Move { car: CarId, index: u32, speed: u8 },
+    LaneChange { car: CarId, lane: u16, index: u32, speed: u8 },
+    Exit { car: CarId },
+    /// A traffic light, numbered in the order of the header, turned red or green.
+    Light { light: u32, red: bool },
+}
+
+/// The state of a car as far as the log records it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CarState {
+    vehicle_type: u16,
+    max_speed: u8,
+    lane: u16,
+    index: u32,
+    speed: u8,
+}
+
+/// Writes every car movement, lane change and traffic light switch to a compact binary log, from
+/// which the simulation can be replayed without simulating it again. (See: `replay`)
+///
+/// The log is little endian. The header holds the magic `CATSEVT1`, the lanes and the length of the
+/// road, the blocked cells and the traffic light cells, each as a `u32` count followed by `u32`
+/// lane and index pairs. Each event is a tag byte followed by its fields: `0` round (`u32`), `1`
+/// enter (car `u32`, vehicle type `u16`, maximum speed `u8`, lane `u16`, index `u32`, speed `u8`),
+/// `2` move (car `u32`, index `u32`, speed `u8`), `3` lane change (car `u32`, lane `u16`, index
+/// `u32`, speed `u8`), `4` exit (car `u32`) and `5` light (light `u32`, red `u8`).
+#[derive(Debug)]
+pub struct EventLogWriter {
+    writer: BufWriter<File>,
+    cars: HashMap<CarId, CarState>,
+    red_lights: Vec<bool>,
+}
+
+impl EventLogWriter {
+    pub fn new(filepath: &Path, road: &Road) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&road.lanes().to_le_bytes())?;
+        writer.write_all(&road.length().to_le_bytes())?;
+        let blocked: Vec<(usize, usize)> = road
+            .cells()
+            .iter()
+            .enumerate()
+            .flat_map(|(lane_i, lane)| lane.iter().enumerate().filter(|(_, cell)| cell.blocked()).map(move |(cell_i, _)| (lane_i, cell_i)))
+            .collect();
+        let lights: Vec<(usize, usize)> = road.traffic_lights().iter().map(|light| (light.location().lane(), light.location().index())).collect();
+        for cells in [blocked, lights] {
+            writer.write_all(&(cells.len() as u32).to_le_bytes())?;
+            for (lane, index) in cells {
+                writer.write_all(&(lane as u32).to_le_bytes())?;
+                writer.write_all(&(index as u32).to_le_bytes())?;
+            }
+        }
+        Ok(Self { writer, cars: HashMap::new(), red_lights: vec![false; road.traffic_lights().len()] })
+    }
+
+    fn write(&mut self, event: Event) -> io::Result<()> {
+        let w = &mut self.writer;
+        match event {
+            Event::Round { round } => {
+                w.write_all(&[ROUND])?;
+                w.write_all(&round.to_le_bytes())
+            },
+            Event::Enter { car, vehicle_type, max_speed, lane, index, speed } => {
+                w.write_all(&[ENTER])?;
+                w.write_all(&car.to_le_bytes())?;
+                w.write_all(&vehicle_type.to_le_bytes())?;
+                w.write_all(&[max_speed])?;
+                w.write_all(&lane.to_le_bytes())?;
+                w.write_all(&index.to_le_bytes())?;
+                w.write_all(&[speed])
+            },
+            Event::Move { car, index, speed } => {
+                w.write_all(&[MOVE])?;
+                w.write_all(&car.to_le_bytes())?;
+                w.write_all(&index.to_le_bytes())?;
+                w.write_all(&[speed])
+            },
+            Event::LaneChange { car, lane, index, speed } => {
+                w.write_all(&[LANE_CHANGE])?;
+                w.write_all(&car.to_le_bytes())?;
+                w.write_all(&lane.to_le_bytes())?;
+                w.write_all(&index.to_le_bytes())?;
+                w.write_all(&[speed])
+            },
+            Event::Exit { car } => {
+                w.write_all(&[EXIT])?;
+                w.write_all(&car.to_le_bytes())
+            },
+            Event::Light { light, red } => {
+                w.write_all(&[LIGHT])?;
+                w.write_all(&light.to_le_bytes())?;
+                w.write_all(&[red as u8])
+            },
+        }
+    }
+}
+
+impl Observer for EventLogWriter {
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        self.write(Event::Round { round: road.rounds() })?;
+        let mut cars = HashMap::with_capacity(self.cars.len());
+        for (id, lane, index, car) in road.iter_cars() {
+            let state = CarState { vehicle_type: car.vehicle_type() as u16, max_speed: car.max_speed(), lane: lane as u16, index: index as u32, speed: car.speed() };
+            match self.cars.get(&id) {
+                Some(before) if *before == state => (),
+                Some(before) if before.max_speed == state.max_speed && before.lane == state.lane => {
+                    self.write(Event::Move { car: id, index: state.index, speed: state.speed })?;
+                },
+                Some(before) if before.max_speed == state.max_speed => {
+                    self.write(Event::LaneChange { car: id, lane: state.lane, index: state.index, speed: state.speed })?;
+                },
+                _ => self.write(Event::Enter { car: id, vehicle_type: state.vehicle_type, max_speed: state.max_speed, lane: state.lane, index: state.index, speed: state.speed })?,
+            }
+            cars.insert(id, state);
+        }
+        let mut exited: Vec<CarId> = self.cars.keys().filter(|id| !cars.contains_key(id)).copied().collect();
+        exited.sort_unstable();
+        for car in exited {
+            self.write(Event::Exit { car })?;
+        }
+        self.cars = cars;
+        for light_i in 0..self.red_lights.len() {
+            let location = road.traffic_lights()[light_i].location();
+            let red = road.cells()[location.lane()][location.index()].is_red_light();
+            if red != self.red_lights[light_i] {
+                self.write(Event::Light { light: light_i as u32, red })?;
+                self.red_lights[light_i] = red;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// A log read back from a file. (See: `EventLogWriter`)
+#[derive(Debug)]
+pub struct EventLog {
+    pub lanes: u32,
+    pub length: u32,
+    pub blocked: Vec<CellLocation>,
+    pub traffic_lights: Vec<CellLocation>,
+    /// The events of every round, starting with the state before the first round.
+    pub rounds: Vec<Vec<Event>>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn take<const N: usize>(bytes: &[u8], at: &mut usize) -> io::Result<[u8; N]> {
+    let taken = bytes.get(*at..*at + N).ok_or_else(|| invalid("The event log ends in the middle of an entry."))?;
+    *at += N;
+    Ok(taken.try_into().unwrap())
+}
+
+fn take_u32(bytes: &[u8], at: &mut usize) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(take(bytes, at)?))
+}
+
+fn take_u16(bytes: &[u8], at: &mut usize) -> io::Result<u16> {
+    Ok(u16::from_le_bytes(take(bytes, at)?))
+}
+
+fn take_u8(bytes: &[u8], at: &mut usize) -> io::Result<u8> {
+    Ok(take::<1>(bytes, at)?[0])
+}
+
+impl EventLog {
+    pub fn read(filepath: &Path) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        BufReader::new(File::open(filepath)?).read_to_end(&mut bytes)?;
+        let at = &mut 0;
+        if &take::<8>(&bytes, at)? != MAGIC {
+            return Err(invalid("The file is not an event log."));
+        }
+        let lanes = take_u32(&bytes, at)?;
+        let length = take_u32(&bytes, at)?;
+        let mut cells = Vec::new();
+        for _ in 0..2 {
+            let count = take_u32(&bytes, at)?;
+            cells.push((0..count)
+                .map(|_| Ok(CellLocation::new(take_u32(&bytes, at)? as usize, take_u32(&bytes, at)? as usize)))
+                .collect::<io::Result<Vec<CellLocation>>>()?);
+        }
+        let traffic_lights = cells.pop().unwrap();
+        let blocked = cells.pop().unwrap();
+
+        let mut rounds: Vec<Vec<Event>> = Vec::new();
+        while *at < bytes.len() {
+            let event = match take_u8(&bytes, at)? {
+                ROUND => Event::Round { round: take_u32(&bytes, at)? },
+                ENTER => Event::Enter {
+                    car: take_u32(&bytes, at)?,
+                    vehicle_type: take_u16(&bytes, at)?,
+                    max_speed: take_u8(&bytes, at)?,
+                    lane: take_u16(&bytes, at)?,
+                    index: take_u32(&bytes, at)?,
+                    speed: take_u8(&bytes, at)?,
+                },
+                MOVE => Event::Move { car: take_u32(&bytes, at)?, index: take_u32(&bytes, at)?, speed: take_u8(&bytes, at)? },
+                LANE_CHANGE => Event::LaneChange {
+                    car: take_u32(&bytes, at)?,
+                    lane: take_u16(&bytes, at)?,
+                    index: take_u32(&bytes, at)?,
+                    speed: take_u8(&bytes, at)?,
+                },
+                EXIT => Event::Exit { car: take_u32(&bytes, at)? },
+                LIGHT => Event::Light { light: take_u32(&bytes, at)?, red: take_u8(&bytes, at)? != 0 },
+                _ => return Err(invalid("The event log contains an unknown event.")),
+            };
+            match event {
+                Event::Round { .. } => rounds.push(vec![event]),
+                _ => rounds.last_mut().ok_or_else(|| invalid("The event log does not start with a round."))?.push(event),
+            }
+        }
+        Ok(Self { lanes, length, blocked, traffic_lights, rounds })
+    }
+}
+
+/// Replays a simulation from its event log and renders it with the image, frame, GIF, ASCII and
+/// terminal outputs of the scenario, without simulating it again. Heatmap images need the statistics of the
+/// cells, which the log does not hold, so the image is always drawn as a space-time image.
+pub fn replay(args: &SimulationConfig, filepath: &Path) -> Result<(), Box<dyn Error>> {
+    let log = EventLog::read(filepath)?;
+    let blocks: Vec<CellLocationRange> = log.blocked.iter().map(|cell| CellLocationRange::new(cell.lane(), cell.index(), cell.index() + 1)).collect();
+    let traffic_lights: Vec<TrafficLight> = log.traffic_lights.iter().map(|cell| TrafficLight::new(cell.clone(), LightTiming::default())).collect();
+    let mut road = Road::new(log.lanes, log.length, &[], 0.0, 0.0, &blocks, &traffic_lights, None);
+    let replay_args = SimulationConfig {
+        rounds: log.rounds.len().saturating_sub(1) as u32,
+        lanes: log.lanes,
+        length: log.length,
+        image_mode: ImageMode::SpaceTime,
+        matsim_events: None,
+        trips: None,
+        timeseries: None,
+        arrow_stream: None,
+        charts: false,
+        debug_gaps: false,
+        event_log: None,
+        report: None,
+        ..args.clone()
+    };
+    let blueprint: VehicleBlueprint = "(1, 1, 0)".parse().unwrap();
+    let mut cars: HashMap<CarId, CarState> = HashMap::new();
+    let mut red_lights = vec![false; traffic_lights.len()];
+    let mut observers = create_observers(&replay_args, &road);
+    for (round_i, events) in log.rounds.iter().enumerate() {
+        let mut round = 0;
+        for event in events {
+            match *event {
+                Event::Round { round: event_round } => round = event_round,
+                Event::Enter { car, vehicle_type, max_speed, lane, index, speed } => {
+                    cars.insert(car, CarState { vehicle_type, max_speed, lane, index, speed });
+                },
+                Event::Move { car, index, speed } | Event::LaneChange { car, index, speed, .. } => {
+                    let state = cars.get_mut(&car).ok_or_else(|| invalid("The event log moves a car that is not on the road."))?;
+                    if let Event::LaneChange { lane, .. } = event {
+                        state.lane = *lane;
+                    }
+                    state.index = index;
+                    state.speed = speed;
+                },
+                Event::Exit { car } => {
+                    cars.remove(&car);
+                },
+                Event::Light { light, red } => {
+                    *red_lights.get_mut(light as usize).ok_or_else(|| invalid("The event log switches a traffic light that does not exist."))? = red;
+                },
+            }
+        }
+        let round_cars = cars
+            .iter()
+            .map(|(id, state)| {
+                let mut car = Car::new(*id, state.vehicle_type as usize, &blueprint);
+                car.set_max_speed(state.max_speed);
+                car.set_speed(state.speed);
+                (state.lane as usize, state.index as usize, car)
+            })
+            .collect();
+        road.restore(round, round_cars, &red_lights);
+        for observer in observers.iter_mut() {
+            if round_i == 0 {
+                observer.start(&road)?;
+            } else {
+                observer.observe(&road)?;
+            }
+        }
+    }
+    for observer in observers.iter_mut() {
+        observer.finish(&road)?;
+    }
+    Ok(())
+}
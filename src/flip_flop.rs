@@ -4,6 +4,12 @@ pub struct FlipFlop {
     state: bool
 }
 
+impl Default for FlipFlop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FlipFlop {
     /// Create a flip from with a state of `true`.
     pub fn new() -> Self {
@@ -25,6 +31,11 @@ impl FlipFlop {
         false
     }
 
+    /// Sets the state to the state of the other flip flop.
+    pub fn sync(&mut self, other: &Self) {
+        self.state = other.state();
+    }
+
     /// Returns the state of the flip flow.
     pub fn state(&self) -> bool {
         self.state
@@ -0,0 +1,80 @@
+#[cfg(feature = "plot")]
+use std::error::Error;
+#[cfg(feature = "plot")]
+use std::path::Path;
+use serde::Serialize;
+use crate::{run_sim, SimulationConfig, SimulationResult, CELL_M};
+
+/// A point of the fundamental diagram, measured in one run of a density sweep.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct FundamentalDiagramPoint {
+    pub density: f64,
+    pub flow_cars_per_minute_per_lane: f64,
+    pub speed_kilometers_per_hour: f64,
+}
+
+impl FundamentalDiagramPoint {
+    /// Derives the point from the result of a run on a closed road. The flow follows from the
+    /// density and the average speed. (`q = ρ * v`)
+    pub fn new(result: &SimulationResult) -> Self {
+        let density = result.cars as f64 / (result.lanes * result.length) as f64;
+        let speed_cells_per_second = result.average_speed_kilometers_per_hour / 3.6 / CELL_M;
+        Self {
+            density,
+            flow_cars_per_minute_per_lane: density * speed_cells_per_second * 60.0,
+            speed_kilometers_per_hour: result.average_speed_kilometers_per_hour,
+        }
+    }
+}
+
+/// Runs the scenario once for each of `n_points` evenly spaced traffic densities between `0` and
+/// `1` (exclusive) and measures the points of the fundamental diagram. The number of cars of each
+/// run replaces the traffic densities and car counts of the scenario and is split among the
+/// vehicle types by their traffic densities. Outputs of the scenario are not written.
+pub fn sweep(args: &SimulationConfig, n_points: u32) -> Vec<FundamentalDiagramPoint> {
+    if n_points == 0 {
+        panic!("A density sweep needs at least one point.");
+    }
+    if args.demand.is_some() {
+        panic!("A density sweep needs a closed road, so it cannot be combined with `demand`.");
+    }
+    let cells = (args.lanes * args.length) as f64;
+    (1..=n_points)
+        .map(|point| {
+            let density = point as f64 / (n_points + 1) as f64;
+            let mut point_args = args.clone().without_outputs();
+            point_args.cars = vec![((density * cells).round() as u32).to_string()];
+            point_args.lane_densities = vec![];
+            FundamentalDiagramPoint::new(&run_sim(point_args))
+        })
+        .collect()
+}
+
+/// Renders the flow-density and the speed-density diagram of a density sweep side by side as
+/// scatter plots to an SVG file.
+#[cfg(feature = "plot")]
+pub fn render(points: &[FundamentalDiagramPoint], path: &Path) -> Result<(), Box<dyn Error>> {
+    use plotters::prelude::*;
+
+    let root = SVGBackend::new(path, (1200, 500)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let (flow_area, speed_area) = root.split_horizontally(600);
+    let flows: Vec<(f64, f64)> = points.iter().map(|point| (point.density, point.flow_cars_per_minute_per_lane)).collect();
+    let speeds: Vec<(f64, f64)> = points.iter().map(|point| (point.density, point.speed_kilometers_per_hour)).collect();
+    for (area, caption, y_desc, values) in [
+        (flow_area, "Flow-Density", "Flow (car/min/lane)", flows),
+        (speed_area, "Speed-Density", "Average Speed (km/h)", speeds),
+    ] {
+        let y_max = values.iter().map(|(_, y)| *y).fold(1.0, f64::max) * 1.1;
+        let mut chart = ChartBuilder::on(&area)
+            .caption(caption, ("sans-serif", 24))
+            .margin(20)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(0.0..1.0, 0.0..y_max)?;
+        chart.configure_mesh().x_desc("Traffic Density").y_desc(y_desc).draw()?;
+        chart.draw_series(values.into_iter().map(|value| Circle::new(value, 4, BLUE.filled())))?;
+    }
+    root.present()?;
+    Ok(())
+}
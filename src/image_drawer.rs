@@ -1,10 +1,32 @@
-use std::path::PathBuf;
-use crate::Road;
-use image::{ImageError, Rgb, RgbImage};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use crate::car::norm_rgb;
+use crate::cell::Cell;
+use crate::observer::Observer;
+use crate::{ImageMode, Road};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Delay, Frame, ImageError, ImageResult, Rgb, RgbImage};
 
 const SEPERATOR_COLOR: Rgb<u8> = Rgb([0, 60, 180]); // Rgb([255, 255, 255]);
 const BLOCK_COLOR: Rgb<u8> = Rgb([180, 0, 180]);
 const RED_TRAFFIC_LIGHT_COLOR: Rgb<u8> = Rgb([50, 0, 180]);
+const LIGHT_STRIP_RED_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+const LIGHT_STRIP_GREEN_COLOR: Rgb<u8> = Rgb([0, 255, 0]);
+const LIGHT_STRIP_FAILED_COLOR: Rgb<u8> = Rgb([255, 200, 0]);
+const AXES_BACKGROUND_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+const AXES_COLOR: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// The factor by which the glyphs of the axes are enlarged, so that a glyph is 6×10 pixels.
+const TEXT_SCALE: u32 = 2;
+/// The space around the labels of the axes in pixels.
+const AXES_PADDING: u32 = 6;
+/// The length of the ticks of the axes in pixels.
+const TICK_LENGTH: u32 = 4;
+/// The width and the height of the speed legend in pixels.
+const LEGEND_SIZE: (u32, u32) = (12, 100);
 
 #[derive(Debug)]
 pub struct ImageDrawer {
@@ -12,28 +34,59 @@ pub struct ImageDrawer {
     current_row: u32,
     road_lanes: u32,
     seperator: bool,
+    light_strip: bool,
+    /// The color of the marker below the cell axis at every cell index if axes are drawn.
+    axes: Option<Vec<Option<Rgb<u8>>>>,
+    pixel_scale: u32,
+    out_path: PathBuf,
 }
 
 impl ImageDrawer {
-    pub fn new(road: &Road, rounds: u32) -> Self {
+    /// Creates the drawer. With `light_strip`, a row showing the state of the traffic lights at
+    /// each cell index is added above the lanes of every round.
+    pub fn new(road: &Road, rounds: u32, light_strip: bool, out_path: PathBuf) -> Self {
         let seperator = road.lanes() > 1;
-        let round_height = road.lanes() + if seperator { 1 } else { 0 };
+        let round_height = road.lanes() + if seperator { 1 } else { 0 } + if light_strip { 1 } else { 0 };
         let height = round_height * rounds;
         Self {
             image: RgbImage::new(road.length(), height),
             current_row: height,
             road_lanes: road.lanes(),
-            seperator
+            seperator,
+            light_strip,
+            axes: None,
+            pixel_scale: 1,
+            out_path,
         }
     }
 
-    pub fn placeholder() -> Self {
-        Self {
-            image: RgbImage::new(0, 0),
-            current_row: 0,
-            road_lanes: 0,
-            seperator: false
+    /// Frames the image with axes when saving: the rounds upwards along the left, the cell indexes
+    /// along the bottom with markers below the cells with traffic lights and blocked cells, and a
+    /// legend of the speed colors on the right.
+    pub fn with_axes(mut self, road: &Road) -> Self {
+        let markers = (0..road.length() as usize)
+            .map(|x| {
+                let cells = road.cells().iter().map(|lane| &lane[x]);
+                if cells.clone().any(|cell| cell.traffic_light()) {
+                    Some(RED_TRAFFIC_LIGHT_COLOR)
+                } else if cells.clone().any(|cell| cell.blocked()) {
+                    Some(BLOCK_COLOR)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        self.axes = Some(markers);
+        self
+    }
+
+    /// Draws every pixel as a `pixel_scale`×`pixel_scale` block when saving.
+    pub fn with_pixel_scale(mut self, pixel_scale: u32) -> Self {
+        if pixel_scale == 0 {
+            panic!("Pixel scale must be at least 1.");
         }
+        self.pixel_scale = pixel_scale;
+        self
     }
 
     pub fn take_snapshot(&mut self, road: &Road) {
@@ -45,27 +98,32 @@ impl ImageDrawer {
         self.current_row -= self.road_lanes;
         for (y, lane) in road.cells().iter().enumerate() {
             for (x, cell) in lane.iter().enumerate() {
-                if cell.blocked() {
-                    self.image.put_pixel(
-                        TryInto::<u32>::try_into(x).unwrap(),
-                        last_row + y as u32,
-                        BLOCK_COLOR
-                    );
-                } else if cell.is_red_light(road.traffic_lights_red()) {
+                if let Some(color) = cell_color(cell) {
                     self.image.put_pixel(
                         TryInto::<u32>::try_into(x).unwrap(),
                         last_row + y as u32,
-                        RED_TRAFFIC_LIGHT_COLOR
-                    );
-                } else if let Some(car) = cell.car() {
-                    self.image.put_pixel(
-                        TryInto::<u32>::try_into(x).unwrap(),
-                        last_row + y as u32,
-                        Rgb(car.speed_rgb())
+                        color
                     );
                 }
             }
         }
+        if self.light_strip {
+            self.current_row -= 1;
+            for x in 0..road.length() as usize {
+                let lights: Vec<&Cell> = road.cells().iter().map(|lane| &lane[x]).filter(|cell| cell.traffic_light()).collect();
+                if lights.is_empty() {
+                    continue;
+                }
+                let color = if lights.iter().any(|light| light.failed()) {
+                    LIGHT_STRIP_FAILED_COLOR
+                } else if lights.iter().any(|light| light.is_red_light()) {
+                    LIGHT_STRIP_RED_COLOR
+                } else {
+                    LIGHT_STRIP_GREEN_COLOR
+                };
+                self.image.put_pixel(x as u32, self.current_row, color);
+            }
+        }
         if self.seperator {
             self.current_row -= 1;
             for x in 0..self.image.width() {
@@ -74,8 +132,395 @@ impl ImageDrawer {
         }
     }
 
-    pub fn save(&self, filepath: PathBuf) -> Result<(), ImageError> {
-        self.image.save(filepath)
+    pub fn save(&self) -> Result<(), ImageError> {
+        let image = scale(&self.image, self.pixel_scale);
+        match &self.axes {
+            Some(markers) => self.draw_axes(&image, markers).save(&self.out_path),
+            None => image.save(&self.out_path),
+        }
+    }
+
+    /// Returns the number of rows that each round takes up in the unscaled image.
+    fn round_height(&self) -> u32 {
+        self.road_lanes + self.seperator as u32 + self.light_strip as u32
+    }
+
+    /// Returns the margins around the scaled image as `(left, top, right, bottom)` in pixels.
+    fn axes_margins(&self) -> (u32, u32, u32, u32) {
+        let rounds = self.image.height() / self.round_height();
+        let round_label_width = text_width(&rounds.saturating_sub(1).to_string()).max(text_width("round"));
+        let legend_width = (LEGEND_SIZE.0 + AXES_PADDING + text_width("max")).max(text_width("speed"));
+        (
+            round_label_width + TICK_LENGTH + 2 * AXES_PADDING,
+            GLYPH_HEIGHT + 2 * AXES_PADDING,
+            legend_width + 2 * AXES_PADDING,
+            2 * TICK_LENGTH + 2 * GLYPH_HEIGHT + 3 * AXES_PADDING,
+        )
+    }
+
+    /// Frames the scaled image with axes, tick labels, markers and a legend.
+    fn draw_axes(&self, image: &RgbImage, markers: &[Option<Rgb<u8>>]) -> RgbImage {
+        let (left, top, right, bottom) = self.axes_margins();
+        let (width, height) = image.dimensions();
+        let mut framed = RgbImage::from_pixel(left + width + right, top + height + bottom, AXES_BACKGROUND_COLOR);
+        imageops::replace(&mut framed, image, left as i64, top as i64);
+        let (x_axis, y_axis) = (top + height, left - 1);
+        fill(&mut framed, y_axis, top, 1, height + 1, AXES_COLOR);
+        fill(&mut framed, left, x_axis, width, 1, AXES_COLOR);
+
+        // rounds, upwards from the first round at the bottom
+        let round_height = self.round_height() * self.pixel_scale;
+        let rounds = self.image.height() / self.round_height();
+        let step = tick_step(round_height, GLYPH_HEIGHT + AXES_PADDING);
+        for round in (0..rounds).step_by(step as usize) {
+            let y = top + height - round * round_height - self.road_lanes * self.pixel_scale / 2 - 1;
+            fill(&mut framed, y_axis - TICK_LENGTH, y, TICK_LENGTH, 1, AXES_COLOR);
+            let label = round.to_string();
+            draw_text(&mut framed, y_axis - TICK_LENGTH - AXES_PADDING / 2 - text_width(&label), y - GLYPH_HEIGHT / 2, &label, AXES_COLOR);
+        }
+        draw_text(&mut framed, AXES_PADDING, AXES_PADDING, "round", AXES_COLOR);
+
+        // cell indexes with the markers of traffic lights and blocked cells
+        for (x, marker) in markers.iter().enumerate() {
+            if let Some(color) = marker {
+                fill(&mut framed, left + x as u32 * self.pixel_scale, x_axis + 1, self.pixel_scale, TICK_LENGTH, *color);
+            }
+        }
+        let label_width = text_width(&markers.len().saturating_sub(1).to_string());
+        let step = tick_step(self.pixel_scale, label_width + AXES_PADDING);
+        for index in (0..markers.len() as u32).step_by(step as usize) {
+            let x = left + index * self.pixel_scale + self.pixel_scale / 2;
+            fill(&mut framed, x, x_axis + 1 + TICK_LENGTH, 1, TICK_LENGTH, AXES_COLOR);
+            let label = index.to_string();
+            let label_x = x.saturating_sub(text_width(&label) / 2).min(framed.width() - text_width(&label));
+            draw_text(&mut framed, label_x, x_axis + 1 + 2 * TICK_LENGTH + AXES_PADDING / 2, &label, AXES_COLOR);
+        }
+        draw_text(&mut framed, left + width.saturating_sub(text_width("cell")) / 2, top + height + bottom - AXES_PADDING - GLYPH_HEIGHT, "cell", AXES_COLOR);
+
+        // speed legend, from stopped at the bottom to the maximum speed at the top
+        let legend_x = left + width + AXES_PADDING;
+        let legend_height = LEGEND_SIZE.1.min(height.max(2 * GLYPH_HEIGHT));
+        draw_text(&mut framed, legend_x, AXES_PADDING, "speed", AXES_COLOR);
+        for y in 0..legend_height {
+            let norm = 1.0 - y as f32 / (legend_height - 1) as f32;
+            fill(&mut framed, legend_x, top + y, LEGEND_SIZE.0, 1, Rgb(norm_rgb(norm)));
+        }
+        let legend_label_x = legend_x + LEGEND_SIZE.0 + AXES_PADDING;
+        draw_text(&mut framed, legend_label_x, top, "max", AXES_COLOR);
+        draw_text(&mut framed, legend_label_x, top + legend_height - GLYPH_HEIGHT, "0", AXES_COLOR);
+        framed
+    }
+}
+
+impl Observer for ImageDrawer {
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        self.take_snapshot(road);
+        Ok(())
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(self.save()?)
+    }
+
+    fn buffer_bytes(&self) -> usize {
+        let axes_bytes = match self.axes {
+            Some(_) => {
+                let (left, top, right, bottom) = self.axes_margins();
+                (left + self.image.width() * self.pixel_scale + right) as usize
+                    * (top + self.image.height() * self.pixel_scale + bottom) as usize
+                    * 3
+            },
+            None => 0,
+        };
+        scaled_image_bytes(self.image.width(), self.image.height(), self.pixel_scale) + axes_bytes
+    }
+}
+
+/// Saves the state of the road after every round as its own image, so that other tools can
+/// assemble them into animations. Each row of a frame is a lane and each column a cell. Frames are
+/// numbered by round, starting with `round_000000.png` for the state before the first round.
+#[derive(Debug)]
+pub struct FrameDrawer {
+    pixel_scale: u32,
+    out_dir: PathBuf,
+}
+
+impl FrameDrawer {
+    /// Creates the drawer and the directory the frames are saved to.
+    pub fn new(out_dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&out_dir)?;
+        Ok(Self {
+            pixel_scale: 1,
+            out_dir,
+        })
+    }
+
+    /// Draws every pixel as a `pixel_scale`×`pixel_scale` block.
+    pub fn with_pixel_scale(mut self, pixel_scale: u32) -> Self {
+        if pixel_scale == 0 {
+            panic!("Pixel scale must be at least 1.");
+        }
+        self.pixel_scale = pixel_scale;
+        self
+    }
+
+    pub fn draw(&self, road: &Road) -> RgbImage {
+        draw_road(road)
+    }
+}
+
+impl Observer for FrameDrawer {
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        let path = self.out_dir.join(format!("round_{:06}.png", road.rounds()));
+        Ok(scale(&self.draw(road), self.pixel_scale).save(path)?)
+    }
+}
+
+/// Saves the state of the road after every round as a frame of a looping animated GIF. Each row of
+/// a frame is a lane and each column a cell, like the images of the `FrameDrawer`. The frames are
+/// encoded as they come, so long runs do not have to be kept in memory.
+pub struct AnimationDrawer {
+    encoder: Option<GifEncoder<BufWriter<File>>>,
+    frame_delay: Delay,
+    pixel_scale: u32,
+}
+
+impl AnimationDrawer {
+    /// Creates the animation file. Each frame is shown for `frame_delay_ms` milliseconds, which GIF
+    /// viewers round to tens of milliseconds.
+    pub fn new(out_path: &Path, frame_delay_ms: u64) -> ImageResult<Self> {
+        let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(out_path)?), 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(Self {
+            encoder: Some(encoder),
+            frame_delay: Delay::from_numer_denom_ms(frame_delay_ms.try_into().unwrap_or(u32::MAX), 1),
+            pixel_scale: 1,
+        })
+    }
+
+    /// Draws every pixel as a `pixel_scale`×`pixel_scale` block.
+    pub fn with_pixel_scale(mut self, pixel_scale: u32) -> Self {
+        if pixel_scale == 0 {
+            panic!("Pixel scale must be at least 1.");
+        }
+        self.pixel_scale = pixel_scale;
+        self
+    }
+}
+
+impl Observer for AnimationDrawer {
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        let Some(encoder) = &mut self.encoder else { return Ok(()); };
+        let image = DynamicImage::ImageRgb8(scale(&draw_road(road), self.pixel_scale)).into_rgba8();
+        Ok(encoder.encode_frame(Frame::from_parts(image, 0, 0, self.frame_delay))?)
+    }
+
+    /// Ends the animation. The encoder writes the end of the file when it is dropped.
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        self.encoder.take();
+        Ok(())
     }
 }
 
+/// Draws a heatmap of the time-averaged occupancy or speed of every cell. Each row of the image is
+/// a lane and each column a cell.
+#[derive(Debug)]
+pub struct HeatmapDrawer {
+    mode: ImageMode,
+    snapshots: u32,
+    occupied: Vec<Vec<u32>>,
+    speed_norms: Vec<Vec<f32>>,
+    pixel_scale: u32,
+    out_path: PathBuf,
+}
+
+impl HeatmapDrawer {
+    pub fn new(road: &Road, mode: ImageMode, out_path: PathBuf) -> Self {
+        Self {
+            mode,
+            snapshots: 0,
+            occupied: vec![vec![0; road.length() as usize]; road.lanes() as usize],
+            speed_norms: vec![vec![0.0; road.length() as usize]; road.lanes() as usize],
+            pixel_scale: 1,
+            out_path,
+        }
+    }
+
+    /// Draws every pixel as a `pixel_scale`×`pixel_scale` block when saving.
+    pub fn with_pixel_scale(mut self, pixel_scale: u32) -> Self {
+        if pixel_scale == 0 {
+            panic!("Pixel scale must be at least 1.");
+        }
+        self.pixel_scale = pixel_scale;
+        self
+    }
+
+    pub fn take_snapshot(&mut self, road: &Road) {
+        self.snapshots += 1;
+        for (y, lane) in road.cells().iter().enumerate() {
+            for (x, cell) in lane.iter().enumerate() {
+                if let Some(car) = cell.car() {
+                    self.occupied[y][x] += 1;
+                    self.speed_norms[y][x] += car.speed() as f32 / car.max_speed() as f32;
+                }
+            }
+        }
+    }
+
+    /// Draws the heatmap. Occupancy ranges from green (always free) to red (always occupied) and
+    /// speed from red (stopped) to green (max_speed). Cells that were never occupied are left black
+    /// in speed mode.
+    pub fn draw(&self, road: &Road) -> RgbImage {
+        let mut image = RgbImage::new(road.length(), road.lanes());
+        for (y, lane) in road.cells().iter().enumerate() {
+            for (x, cell) in lane.iter().enumerate() {
+                let occupied = self.occupied[y][x];
+                let color = if cell.blocked() {
+                    BLOCK_COLOR
+                } else if self.mode == ImageMode::Speed {
+                    if occupied == 0 { continue; }
+                    Rgb(norm_rgb(self.speed_norms[y][x] / occupied as f32))
+                } else {
+                    Rgb(norm_rgb(1.0 - occupied as f32 / self.snapshots as f32))
+                };
+                image.put_pixel(x as u32, y as u32, color);
+            }
+        }
+        image
+    }
+}
+
+impl Observer for HeatmapDrawer {
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        self.take_snapshot(road);
+        Ok(())
+    }
+
+    fn finish(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(scale(&self.draw(road), self.pixel_scale).save(&self.out_path)?)
+    }
+
+    fn buffer_bytes(&self) -> usize {
+        let lanes = self.occupied.len();
+        let length = self.occupied.first().map_or(0, Vec::len);
+        lanes * length * (size_of::<u32>() + size_of::<f32>())
+            + scaled_image_bytes(length as u32, lanes as u32, self.pixel_scale)
+    }
+}
+
+/// Draws the state of the road with one row per lane and one column per cell.
+fn draw_road(road: &Road) -> RgbImage {
+    let mut image = RgbImage::new(road.length(), road.lanes());
+    for (y, lane) in road.cells().iter().enumerate() {
+        for (x, cell) in lane.iter().enumerate() {
+            if let Some(color) = cell_color(cell) {
+                image.put_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+    image
+}
+
+/// Returns the color of a cell in the space-time image, the frames and the animation or `None` if the cell is
+/// empty.
+fn cell_color(cell: &Cell) -> Option<Rgb<u8>> {
+    if cell.blocked() {
+        Some(BLOCK_COLOR)
+    } else if cell.is_red_light() {
+        Some(RED_TRAFFIC_LIGHT_COLOR)
+    } else {
+        cell.car().map(|car| Rgb(car.speed_rgb()))
+    }
+}
+
+/// The height of a glyph of the axes in pixels.
+const GLYPH_HEIGHT: u32 = 5 * TEXT_SCALE;
+
+/// Returns the rows of the 3×5 glyph of a character as bits from left to right, or a blank glyph
+/// for characters that are not needed for the axes.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'a' => [0b000, 0b011, 0b101, 0b101, 0b011],
+        'c' => [0b000, 0b011, 0b100, 0b100, 0b011],
+        'd' => [0b001, 0b011, 0b101, 0b101, 0b011],
+        'e' => [0b000, 0b111, 0b111, 0b100, 0b011],
+        'l' => [0b110, 0b010, 0b010, 0b010, 0b111],
+        'm' => [0b000, 0b110, 0b111, 0b101, 0b101],
+        'n' => [0b000, 0b110, 0b101, 0b101, 0b101],
+        'o' => [0b000, 0b010, 0b101, 0b101, 0b010],
+        'p' => [0b000, 0b110, 0b101, 0b110, 0b100],
+        'r' => [0b000, 0b101, 0b110, 0b100, 0b100],
+        's' => [0b000, 0b011, 0b110, 0b011, 0b110],
+        'u' => [0b000, 0b101, 0b101, 0b101, 0b011],
+        'x' => [0b000, 0b101, 0b010, 0b010, 0b101],
+        _ => [0; 5],
+    }
+}
+
+/// Returns the width of a text drawn with `draw_text` in pixels.
+fn text_width(text: &str) -> u32 {
+    (text.chars().count() as u32 * 4).saturating_sub(1) * TEXT_SCALE
+}
+
+/// Draws a text with its top left corner at the given pixel. Pixels outside the image are left out.
+fn draw_text(image: &mut RgbImage, x: u32, y: u32, text: &str, color: Rgb<u8>) {
+    for (i, c) in text.chars().enumerate() {
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    let glyph_x = x + (i as u32 * 4 + column) * TEXT_SCALE;
+                    fill(image, glyph_x, y + row as u32 * TEXT_SCALE, TEXT_SCALE, TEXT_SCALE, color);
+                }
+            }
+        }
+    }
+}
+
+/// Fills a rectangle of pixels. Pixels outside the image are left out.
+fn fill(image: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>) {
+    for y in y..(y + height).min(image.height()) {
+        for x in x..(x + width).min(image.width()) {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+/// Returns the smallest step of 1, 2 or 5 times a power of ten between the ticks of an axis whose
+/// units are `unit_pixels` apart that keeps the ticks at least `min_spacing` pixels apart.
+fn tick_step(unit_pixels: u32, min_spacing: u32) -> u32 {
+    let mut magnitude = 1;
+    loop {
+        for step in [magnitude, 2 * magnitude, 5 * magnitude] {
+            if step * unit_pixels >= min_spacing {
+                return step;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+/// Returns the number of bytes of an image together with the enlarged copy that is made of it
+/// when saving.
+fn scaled_image_bytes(width: u32, height: u32, pixel_scale: u32) -> usize {
+    let pixels = width as usize * height as usize;
+    (pixels + pixels * (pixel_scale * pixel_scale) as usize) * 3
+}
+
+/// Enlarges an image so that every pixel becomes a `pixel_scale`×`pixel_scale` block of the same
+/// color.
+fn scale(image: &RgbImage, pixel_scale: u32) -> RgbImage {
+    if pixel_scale == 1 {
+        return image.clone();
+    }
+    imageops::resize(image, image.width() * pixel_scale, image.height() * pixel_scale, FilterType::Nearest)
+}
@@ -0,0 +1,18 @@
+use serde::Serialize;
+use crate::cell::CellLocationRange;
+
+/// A breakdown or accident that blocks a cell for a number of rounds, starting in `round`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub round: u32,
+    pub lane: usize,
+    pub index: usize,
+    pub rounds: u32,
+}
+
+impl Incident {
+    /// Returns the block that the incident puts in place.
+    pub fn block(&self) -> CellLocationRange {
+        CellLocationRange::new(self.lane, self.index, self.index + 1).with_rounds(Some(self.round), Some(self.round.saturating_add(self.rounds)))
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use rand::prelude::*;
+use serde::Serialize;
+use crate::car::CarId;
+use crate::cell::CellLocation;
+use crate::demand::DemandProfile;
+use crate::traffic_light::{LightTiming, TrafficLight};
+use crate::{Road, SimulationConfig, ROUND_S};
+
+/// The number of cells in front of its stop line within which an oncoming car makes a car that
+/// turns left give way.
+const LEFT_TURN_SIGHT_CELLS: usize = 10;
+
+/// An approach of the intersection, named after the direction its cars come from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Approach {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Approach {
+    pub const ALL: [Approach; 4] = [Approach::North, Approach::East, Approach::South, Approach::West];
+
+    /// Returns the approach whose cars come towards the cars of this one.
+    pub fn opposing(&self) -> Approach {
+        Self::ALL[(*self as usize + 2) % 4]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Approach::North => "north",
+            Approach::East => "east",
+            Approach::South => "south",
+            Approach::West => "west",
+        }
+    }
+}
+
+/// Where a car leaves the intersection relative to where it came from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Movement {
+    Left,
+    Straight,
+    Right,
+}
+
+/// The probabilities with which a car turns left, goes straight on and turns right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurnProbabilities {
+    left: f64,
+    straight: f64,
+    right: f64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseTurnProbabilitiesError;
+
+impl TurnProbabilities {
+    pub fn new(left: f64, straight: f64, right: f64) -> Self {
+        if [left, straight, right].iter().any(|p| !(0.0..=1.0).contains(p)) || (left + straight + right - 1.0).abs() > 1e-6 {
+            panic!("Turn probabilities must be numbers between 0 and 1 that add up to 1.");
+        }
+        Self { left, straight, right }
+    }
+
+    fn pick<R: Rng>(&self, rng: &mut R) -> Movement {
+        let r = rng.gen::<f64>();
+        if r < self.left {
+            Movement::Left
+        } else if r < self.left + self.straight {
+            Movement::Straight
+        } else {
+            Movement::Right
+        }
+    }
+}
+
+impl FromStr for TurnProbabilities {
+    type Err = ParseTurnProbabilitiesError;
+
+    /// Parses turn probabilities in the format `(left, straight, right)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let probabilities = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or(ParseTurnProbabilitiesError)?
+            .split(',')
+            .map(|p| p.parse::<f64>().map_err(|_| ParseTurnProbabilitiesError))
+            .collect::<Result<Vec<f64>, _>>()?;
+        let [left, straight, right] = probabilities[..] else {
+            return Err(ParseTurnProbabilitiesError);
+        };
+        if [left, straight, right].iter().any(|p| !(0.0..=1.0).contains(p)) || (left + straight + right - 1.0).abs() > 1e-6 {
+            return Err(ParseTurnProbabilitiesError);
+        }
+        Ok(Self::new(left, straight, right))
+    }
+}
+
+/// The fixed-time signal plan of the intersection. The north and south approaches are green
+/// together, then all approaches are red for `clearance` rounds, then the east and west approaches
+/// are green together, followed by another clearance.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalPhases {
+    north_south_green: u32,
+    east_west_green: u32,
+    clearance: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseSignalPhasesError;
+
+impl SignalPhases {
+    pub fn new(north_south_green: u32, east_west_green: u32, clearance: u32) -> Self {
+        if north_south_green == 0 || east_west_green == 0 {
+            panic!("Both phases of an intersection must be green for at least one round.");
+        }
+        Self { north_south_green, east_west_green, clearance }
+    }
+
+    pub fn cycle(&self) -> u32 {
+        self.north_south_green + self.east_west_green + 2 * self.clearance
+    }
+
+    /// Returns the timing of the traffic lights of an approach.
+    pub fn timing(&self, approach: Approach) -> LightTiming {
+        match approach {
+            Approach::North | Approach::South => LightTiming::new(self.cycle(), self.north_south_green, 0),
+            Approach::East | Approach::West => LightTiming::new(self.cycle(), self.east_west_green, self.north_south_green + self.clearance),
+        }
+    }
+}
+
+impl FromStr for SignalPhases {
+    type Err = ParseSignalPhasesError;
+
+    /// Parses signal phases in the format `(north_south_green, east_west_green[, clearance])`, all in
+    /// rounds. The clearance defaults to 0 rounds.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let rounds = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or(ParseSignalPhasesError)?
+            .split(',')
+            .map(|rounds| rounds.parse::<u32>().map_err(|_| ParseSignalPhasesError))
+            .collect::<Result<Vec<u32>, _>>()?;
+        let (north_south_green, east_west_green, clearance) = match rounds[..] {
+            [north_south_green, east_west_green] => (north_south_green, east_west_green, 0),
+            [north_south_green, east_west_green, clearance] => (north_south_green, east_west_green, clearance),
+            _ => return Err(ParseSignalPhasesError),
+        };
+        if north_south_green == 0 || east_west_green == 0 {
+            return Err(ParseSignalPhasesError);
+        }
+        Ok(Self::new(north_south_green, east_west_green, clearance))
+    }
+}
+
+/// The metrics of one approach of the intersection.
+#[derive(Serialize, Debug)]
+pub struct ApproachResult {
+    pub approach: Approach,
+    pub served_cars: u32,
+    pub left_turns: u32,
+    pub straight_movements: u32,
+    pub right_turns: u32,
+    /// The cars waiting in front of the approach for a free cell to enter it at the end.
+    pub waiting_cars: u32,
+    /// The number of stopped cars queued back from the stop line, summed up over all lanes.
+    pub mean_queue_cars: f64,
+    pub max_queue_cars: u32,
+    /// The extra time a served car took compared to driving its distance at its maximum speed.
+    pub mean_delay_s: f64,
+    /// The rounds in which a car turning left had to give way to oncoming traffic.
+    pub left_turn_yield_rounds: u64,
+}
+
+/// The cars of an approach that turned left, went straight on and turned right at the stop line
+/// in the rounds after `start_round` up to and including `end_round`.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct TurningMovementCount {
+    pub start_round: u32,
+    pub end_round: u32,
+    pub approach: Approach,
+    pub left: u32,
+    pub straight: u32,
+    pub right: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IntersectionResult {
+    pub rounds: u32,
+    pub cycle_s: f64,
+    pub approaches: Vec<ApproachResult>,
+    /// The turning movement counts of every approach in every interval, interval by interval.
+    pub turning_movements: Vec<TurningMovementCount>,
+}
+
+/// The statistics collected for one approach while the simulation runs.
+#[derive(Debug, Default)]
+struct ApproachRecords {
+    movements: HashMap<CarId, Movement>,
+    served: [u32; 3],
+    /// The served cars by movement in each interval.
+    interval_served: Vec<[u32; 3]>,
+    queue_sum: u64,
+    max_queue: u32,
+    delay_rounds_sum: f64,
+    delayed_cars: u32,
+    left_turn_yield_rounds: u64,
+}
+
+/// A signalized intersection where two roads cross. Each of the four approaches is an open road
+/// that ends at the stop line of the intersection, where a traffic light in every lane lets its
+/// cars go. Cars pick whether to turn left, go straight on or turn right when they enter, and a
+/// car turning left gives way while an oncoming car that does not turn left is close to its stop
+/// line. Cars leave the model once they pass the stop line. Crossing streams never meet, since the
+/// signal plan never lets them go at the same time.
+#[derive(Debug)]
+pub struct Intersection {
+    approaches: Vec<Road>,
+    records: Vec<ApproachRecords>,
+    turns: TurnProbabilities,
+    phases: SignalPhases,
+    /// The length of the intervals over which the turning movements are counted in rounds.
+    interval: u32,
+    rng: StdRng,
+}
+
+impl Intersection {
+    /// Creates an intersection whose approaches are empty roads with the lanes, length, vehicles,
+    /// probabilities and arrival process of the scenario. Cars arrive according to its demand
+    /// profile, or at 10 cars per minute without one, at each approach. The turning movements are
+    /// counted over intervals of `interval` rounds.
+    pub fn new(args: &SimulationConfig, turns: TurnProbabilities, phases: SignalPhases, interval: u32) -> Self {
+        if interval == 0 {
+            panic!("Turning movements must be counted over intervals of at least one round.");
+        }
+        let vehicles = args.vehicles();
+        let demand = args.demand().unwrap_or_else(|| "0, 10".parse::<DemandProfile>().unwrap());
+        let approaches = Approach::ALL
+            .iter()
+            .map(|approach| {
+                let traffic_lights: Vec<TrafficLight> = (0..args.lanes as usize)
+                    .map(|lane| TrafficLight::new(CellLocation::new(lane, args.length as usize - 1), phases.timing(*approach)))
+                    .collect();
+                Road::new(
+                    args.lanes,
+                    args.length,
+                    &vehicles,
+                    args.dilly_dally_probability,
+                    args.stay_in_lane_probability,
+                    &Vec::new(),
+                    &traffic_lights,
+                    args.seed.map(|seed| seed.wrapping_add(*approach as u64 + 1)),
+                )
+                .with_car_counts(&vec![0; vehicles.len()])
+                .with_demand(demand.clone())
+                .with_arrival_process(args.arrival_process, args.arrival_batch_size)
+                .with_trip_recording()
+            })
+            .collect();
+        let rng = match args.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            approaches,
+            records: Approach::ALL.iter().map(|_| ApproachRecords::default()).collect(),
+            turns,
+            phases,
+            interval,
+            rng,
+        }
+    }
+
+    /// Returns the road of an approach.
+    pub fn approach(&self, approach: Approach) -> &Road {
+        &self.approaches[approach as usize]
+    }
+
+    /// Returns the movement that the car on an approach has picked, if it is on the approach.
+    pub fn movement(&self, approach: Approach, car: CarId) -> Option<Movement> {
+        self.records[approach as usize].movements.get(&car).copied()
+    }
+
+    /// Simulates one round on all approaches.
+    pub fn round(&mut self) {
+        for approach_i in 0..self.approaches.len() {
+            let road = &self.approaches[approach_i];
+            let records = &mut self.records[approach_i];
+            for (id, _, _, _) in road.iter_cars() {
+                records.movements.entry(id).or_insert_with(|| self.turns.pick(&mut self.rng));
+            }
+        }
+        for approach in Approach::ALL {
+            self.yield_left_turns(approach);
+        }
+        for (road, records) in self.approaches.iter_mut().zip(self.records.iter_mut()) {
+            road.round();
+            let interval_i = ((road.rounds() - 1) / self.interval) as usize;
+            if records.interval_served.len() <= interval_i {
+                records.interval_served.resize(interval_i + 1, [0; 3]);
+            }
+            for trip in road.arrived_trips() {
+                let movement = records.movements.remove(&trip.car).unwrap_or_else(|| self.turns.pick(&mut self.rng));
+                records.served[movement as usize] += 1;
+                records.interval_served[interval_i][movement as usize] += 1;
+                if let Some(travel_rounds) = trip.travel_rounds() {
+                    let max_speed = road.vehicle_blueprints()[trip.vehicle_type].max_speed();
+                    records.delay_rounds_sum += (travel_rounds as f64 - trip.distance as f64 / max_speed as f64).max(0.0);
+                    records.delayed_cars += 1;
+                }
+            }
+            let queue = Self::queue(road);
+            records.queue_sum += queue as u64;
+            records.max_queue = records.max_queue.max(queue);
+        }
+    }
+
+    /// Holds the green lights in the lanes of an approach whose first car turns left and could reach
+    /// the stop line in the next round while an oncoming car that goes straight on or turns right is
+    /// close to its stop line.
+    fn yield_left_turns(&mut self, approach: Approach) {
+        let opposing = &self.approaches[approach.opposing() as usize];
+        let opposing_records = &self.records[approach.opposing() as usize];
+        let length = opposing.length() as usize;
+        let oncoming = opposing.iter_cars().any(|(id, lane, index, _)| {
+            index + LEFT_TURN_SIGHT_CELLS >= length
+                && !opposing.cells()[lane][length - 1].is_red_light()
+                && opposing_records.movements.get(&id) != Some(&Movement::Left)
+        });
+        if !oncoming {
+            return;
+        }
+        let road = &self.approaches[approach as usize];
+        let records = &self.records[approach as usize];
+        let yielding_lanes: Vec<usize> = road
+            .cells()
+            .iter()
+            .enumerate()
+            .filter_map(|(lane_i, lane)| {
+                let (index, car) = lane.iter().enumerate().rev().find_map(|(index, cell)| Some((index, cell.car()?)))?;
+                let turning_left = records.movements.get(&car.id()) == Some(&Movement::Left);
+                let reaches_stop_line = index < length - 1 && index + car.max_speed() as usize >= length - 1;
+                (turning_left && reaches_stop_line && !lane[length - 1].is_red_light()).then_some(lane_i)
+            })
+            .collect();
+        let road = &mut self.approaches[approach as usize];
+        for lane_i in &yielding_lanes {
+            road.hold_traffic_light(*lane_i, length - 1);
+        }
+        self.records[approach as usize].left_turn_yield_rounds += yielding_lanes.len() as u64;
+    }
+
+    /// Returns the number of stopped cars queued back from the stop line, summed up over all lanes.
+    fn queue(road: &Road) -> u32 {
+        road.cells()
+            .iter()
+            .map(|lane| {
+                lane[..lane.len() - 1]
+                    .iter()
+                    .rev()
+                    .take_while(|cell| cell.car().is_some_and(|car| car.speed() == 0))
+                    .count() as u32
+            })
+            .sum()
+    }
+
+    /// Returns the turning movement counts of every approach in every interval so far. The last
+    /// interval ends with the last round, even if it is shorter than the others.
+    pub fn turning_movements(&self) -> Vec<TurningMovementCount> {
+        let rounds = self.approaches[0].rounds();
+        let intervals = rounds.div_ceil(self.interval);
+        (0..intervals)
+            .flat_map(|interval_i| {
+                Approach::ALL.iter().zip(&self.records).map(move |(approach, records)| {
+                    let [left, straight, right] = records.interval_served.get(interval_i as usize).copied().unwrap_or_default();
+                    TurningMovementCount {
+                        start_round: interval_i * self.interval,
+                        end_round: ((interval_i + 1) * self.interval).min(rounds),
+                        approach: *approach,
+                        left,
+                        straight,
+                        right,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    pub fn result(&self) -> IntersectionResult {
+        let rounds = self.approaches[0].rounds();
+        IntersectionResult {
+            rounds,
+            cycle_s: self.phases.cycle() as f64 * ROUND_S,
+            approaches: Approach::ALL
+                .iter()
+                .zip(self.approaches.iter().zip(&self.records))
+                .map(|(approach, (road, records))| ApproachResult {
+                    approach: *approach,
+                    served_cars: records.served.iter().sum(),
+                    left_turns: records.served[Movement::Left as usize],
+                    straight_movements: records.served[Movement::Straight as usize],
+                    right_turns: records.served[Movement::Right as usize],
+                    waiting_cars: road.waiting_cars(),
+                    mean_queue_cars: records.queue_sum as f64 / rounds as f64,
+                    max_queue_cars: records.max_queue,
+                    mean_delay_s: records.delay_rounds_sum / records.delayed_cars as f64 * ROUND_S,
+                    left_turn_yield_rounds: records.left_turn_yield_rounds,
+                })
+                .collect(),
+            turning_movements: self.turning_movements(),
+        }
+    }
+}
+
+/// Writes turning movement counts to a CSV file, one row per interval and approach.
+pub fn write_turning_movements(filepath: &Path, counts: &[TurningMovementCount]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filepath)?);
+    writeln!(writer, "start_round,end_round,start_s,end_s,approach,left,straight,right")?;
+    for count in counts {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{}",
+            count.start_round,
+            count.end_round,
+            count.start_round as f64 * ROUND_S,
+            count.end_round as f64 * ROUND_S,
+            count.approach.name(),
+            count.left,
+            count.straight,
+            count.right
+        )?;
+    }
+    writer.flush()
+}
+
+/// Simulates the intersection for the rounds of the scenario, counting the turning movements over
+/// intervals of `interval` rounds. (See: `Intersection`)
+pub fn run(args: &SimulationConfig, turns: TurnProbabilities, phases: SignalPhases, interval: u32) -> IntersectionResult {
+    let mut intersection = Intersection::new(args, turns, phases, interval);
+    for _ in 0..args.rounds {
+        intersection.round();
+    }
+    intersection.result()
+}
@@ -0,0 +1,100 @@
+use std::str::FromStr;
+use crate::cell::{CellLocation, CellLocationRange};
+
+/// A road drawn as ASCII art. Every line is a lane, starting with lane 0, and every character a
+/// cell:
+///
+/// - `_` or `.`: free cell
+/// - `x`: blocked cell
+/// - `#`: traffic light
+/// - `1` to `9`: slow zone, cars may not drive off from this cell faster than the digit
+/// - `o`: spawn point, a car starts in this cell
+///
+/// Empty lines are ignored. All lanes must be equally long.
+#[derive(Debug, PartialEq)]
+pub struct RoadLayout {
+    lanes: u32,
+    length: u32,
+    block: Vec<CellLocationRange>,
+    traffic_lights: Vec<CellLocation>,
+    speed_limits: Vec<(CellLocation, u8)>,
+    spawn_points: Vec<CellLocation>,
+}
+
+impl RoadLayout {
+    pub fn lanes(&self) -> u32 {
+        self.lanes
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn block(&self) -> &Vec<CellLocationRange> {
+        &self.block
+    }
+
+    pub fn traffic_lights(&self) -> &Vec<CellLocation> {
+        &self.traffic_lights
+    }
+
+    pub fn speed_limits(&self) -> &Vec<(CellLocation, u8)> {
+        &self.speed_limits
+    }
+
+    pub fn spawn_points(&self) -> &Vec<CellLocation> {
+        &self.spawn_points
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseRoadLayoutError {
+    pub lane: usize,
+    pub index: usize,
+}
+
+impl FromStr for RoadLayout {
+    type Err = ParseRoadLayoutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut layout = RoadLayout {
+            lanes: 0,
+            length: 0,
+            block: Vec::new(),
+            traffic_lights: Vec::new(),
+            speed_limits: Vec::new(),
+            spawn_points: Vec::new(),
+        };
+        let rows: Vec<&str> = s.lines().map(|row| row.trim_end()).filter(|row| !row.is_empty()).collect();
+        for (lane, row) in rows.iter().enumerate() {
+            let mut block_start: Option<usize> = None;
+            let mut length = 0;
+            for (index, c) in row.chars().enumerate() {
+                length = index + 1;
+                if c == 'x' {
+                    block_start.get_or_insert(index);
+                    continue;
+                }
+                if let Some(start) = block_start.take() {
+                    layout.block.push(CellLocationRange::new(lane, start, index));
+                }
+                match c {
+                    '_' | '.' => {},
+                    '#' => layout.traffic_lights.push(CellLocation::new(lane, index)),
+                    'o' => layout.spawn_points.push(CellLocation::new(lane, index)),
+                    '1'..='9' => layout.speed_limits.push((CellLocation::new(lane, index), c as u8 - b'0')),
+                    _ => return Err(ParseRoadLayoutError { lane, index }),
+                }
+            }
+            if let Some(start) = block_start {
+                layout.block.push(CellLocationRange::new(lane, start, length));
+            }
+            if lane > 0 && length != layout.length as usize {
+                return Err(ParseRoadLayoutError { lane, index: length });
+            }
+            layout.length = length as u32;
+        }
+        layout.lanes = rows.len() as u32;
+        Ok(layout)
+    }
+}
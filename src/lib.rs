@@ -0,0 +1,3762 @@
+//! A cellular automaton for traffic simulation based on the Nagel-Schreckenberg model.
+//!
+//! The command line simulator is a thin wrapper around this library. To run a simulation from
+//! code, change the defaults of a `SimulationConfig` as needed and pass it to `run_sim`:
+//!
+//! ```
+//! use cellular_automaton_traffic_simulation::{run_sim, SimulationConfig};
+//!
+//! let mut config = SimulationConfig::default();
+//! config.rounds = 100;
+//! config.lanes = 2;
+//! config.length = 500;
+//! config.vehicles = vec!["(5, 1, 0.1)".to_string()];
+//! let result = run_sim(config);
+//! println!("{}", result.json());
+//! ```
+//!
+//! For finer control, a `Road` can also be built and run round by round.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use car::VehicleClass;
+use cell::{CellLocationRange, ReservedZone, SpeedLimitZone};
+use demand::DemandProfile;
+use driver::{DriverDistributions, ProbabilityDistribution};
+use density::{DensityKeyframe, DensitySchedule, LaneDensity};
+use profile::ParameterProfile;
+use terminal::{FollowTarget, View};
+use correlation::DetectorCorrelation;
+use state_hash::StateHashComparison;
+use shockwave::ShockwaveEstimate;
+use spillback::Spillback;
+use breakdown::Breakdown;
+use steady_state::SteadyState;
+use queue::QueueStatistics;
+use section::{Section, SectionResult};
+use safety::SafetyMetrics;
+use speed_histogram::SpeedHistogramBin;
+use incident::Incident;
+use weather::{WeatherChange, WeatherSchedule};
+use clap::{Args, FromArgMatches, ValueEnum};
+use serde::{Deserialize, Serialize};
+use crate::cell::CellLocation;
+use crate::traffic_light::{GreenWave, TrafficLight};
+
+pub mod road;
+pub mod cell;
+pub mod car;
+#[cfg(feature = "image")]
+pub mod image_drawer;
+pub mod flip_flop;
+pub mod observer;
+pub mod terminal;
+pub mod matsim;
+pub mod trip;
+pub mod timeseries;
+pub mod cli;
+pub mod simulation;
+pub mod batch;
+pub mod replication;
+pub mod event_log;
+pub mod intersection;
+pub mod traffic_light;
+pub mod fundamental_diagram;
+pub mod report;
+pub mod validation;
+#[cfg(feature = "plot")]
+pub mod chart;
+pub mod demand;
+pub mod driver;
+pub mod density;
+pub mod profile;
+pub mod layout;
+pub mod shockwave;
+pub mod correlation;
+pub mod state_hash;
+pub mod spillback;
+pub mod breakdown;
+pub mod steady_state;
+pub mod queue;
+pub mod section;
+pub mod safety;
+pub mod speed_histogram;
+pub mod round_series;
+pub mod incident;
+pub mod weather;
+pub mod smoke;
+pub mod selftest;
+pub mod daemon;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "cabi")]
+pub mod cabi;
+#[cfg(feature = "arrow")]
+pub mod arrow_stream;
+
+pub use car::{Car, VehicleBlueprint};
+pub use cell::Cell;
+#[cfg(feature = "image")]
+pub use image_drawer::ImageDrawer;
+pub use road::Road;
+pub use simulation::run_sim;
+use road::LaneChanges;
+
+/// The length of a cell in meters.
+pub const CELL_M: f64 = 7.5;
+/// The duration of a round in seconds.
+pub const ROUND_S: f64 = 1.0;
+
+/// The setup of a simulation run. It is parsed from the command line (see `cli::Cli`) or a
+/// scenario file, or created with `SimulationConfig::default()` and changed as needed to run
+/// simulations from code. New settings are added over time, so it cannot be built from a struct
+/// expression outside of this crate.
+#[derive(Args, Clone, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SimulationConfig {
+    /// The number of rounds to run the simulation for.
+    #[arg(short, long, default_value_t = 4096)]
+    pub rounds: u32,
+
+    /// The number of rounds at the start of the simulation that let the traffic settle and are
+    /// left out of the metrics. They count towards the rounds.
+    #[arg(long, default_value_t = 0)]
+    #[serde(default)]
+    pub warmup: u32,
+
+    /// The number of lanes that make up the road.
+    #[arg(long, default_value_t = 1)]
+    pub lanes: u32,
+
+    /// The number of cells in each lane that make up the road.
+    #[arg(short, long, default_value_t = 1000)]
+    pub length: u32,
+
+    /// Allows specifying different vehicle types and with which density they occur.
+    /// Format: `(max_speed, acceleration_time, traffic_density[, class][, lane_weights][, lanes=allowed_lanes][, reaction=rounds]); ...`
+    /// where the optional class is `regular` (default), `emergency`, `bus`, `hov` or `automated`, the optional lane weights
+    /// `weight_lane_1:weight_lane_2:...` spread the cars of the vehicle type over the lanes in that
+    /// proportion (from left to right) instead of evenly, the optional allowed lanes
+    /// `lane_index_1:lane_index_2:...` keep the cars out of all other lanes, e.g. `lanes=1:2` bans
+    /// trucks from the left lane of three, and the optional reaction delay lets the cars respond to
+    /// their leader driving off or speeding up only that many rounds later. The traffic density
+    /// may also be given for each lane as `density_lane_1:density_lane_2:...` instead of lane weights.
+    /// Corresponding model with units: `(x * 7.5m/s, (1 / x) * 7.5m/s^2, x * 100% of road on lane-by-lane
+    /// basis)`
+    #[arg(long, value_delimiter = ';', default_value = "(5, 1, 0.2)")]
+    pub vehicles: Vec<String>,
+
+    /// The penetration rate of automated vehicles: the share of the cars of every regular vehicle
+    /// type that are automated instead. The automated vehicle types are added after the given ones,
+    /// so that car counts and demand shares have to include them.
+    #[arg(long, default_value_t = 0.0)]
+    #[serde(default)]
+    pub automated_share: f32,
+
+    /// The probability with which cars dilly-dally. (slow down randomly)
+    #[arg(short, long, default_value_t = 0.2)]
+    pub dilly_dally_probability: f32,
+
+    /// The probability with which cars stay in their lane, even when it would be best to switch lanes.
+    #[arg(short, long, default_value_t = 0.2)]
+    pub stay_in_lane_probability: f32,
+
+    /// Optionally let every driver draw their own dilly-dally probability from a distribution when
+    /// their car is put on the road, instead of sharing `dilly_dally_probability`. Either
+    /// `uniform(min, max)` or `normal(mean, standard_deviation)`, which is clamped to between 0
+    /// and 1.
+    #[arg(long)]
+    #[serde(default)]
+    pub dilly_dally_distribution: Option<String>,
+
+    /// Optionally let every driver draw their own stay-in-lane probability from a distribution when
+    /// their car is put on the road, instead of sharing `stay_in_lane_probability`. Specified like
+    /// `dilly_dally_distribution`.
+    #[arg(long)]
+    #[serde(default)]
+    pub stay_in_lane_distribution: Option<String>,
+
+    /// The locations, specified as `(lane_index, cell_index); ...`, of the cells that are to be monitored.
+    /// (Note: all cells are passively monitored but only those specified will be added to the simulation
+    /// result.
+    #[arg(long, value_delimiter = ';', default_value = "(0,0)")]
+    pub monitor: Vec<String>,
+
+    /// The number of cells on either side of a monitored cell within which the cars count towards
+    /// its local density.
+    #[arg(long, default_value_t = 5)]
+    #[serde(default = "default_density_window")]
+    pub density_window: u32,
+
+    /// Measurement sections, specified as `(start_cell_index, end_cell_index); ...`, that stretch
+    /// over all lanes. The mean, median and 95th percentile of the times that cars took to drive
+    /// from the start to the end of each section and of their delays are added to the simulation
+    /// result. On a closed road, a section may wrap around the end.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub sections: Vec<String>,
+
+    /// Whether to add the flow, the mean speed of the passing cars and the occupancy of every cell
+    /// to the simulation result. All are listed lane by lane.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub flow_map: bool,
+
+    /// The fraction of its max speed below which a car counts as congested.
+    #[arg(long, default_value_t = 0.5)]
+    #[serde(default = "default_congestion_threshold")]
+    pub congestion_threshold: f32,
+
+    /// Whether to add the fraction of congested cars of every round to the simulation result.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub congestion_series: bool,
+
+    /// Whether to add the number of cars in each lane after every round to the simulation result.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub cars_per_lane_series: bool,
+
+    /// Whether to add the mean speed and the density of the cars and the flow at every monitored
+    /// cell of every round to the simulation result.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub detailed: bool,
+
+    /// Whether to add the peak memory taken up by the cells, the image buffers and the recorded
+    /// series to the simulation result. Run a short simulation with this to estimate the memory a
+    /// long one will need.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub memory_report: bool,
+
+    /// Optionally save the result of the rounds run so far every this many rounds and flush all
+    /// output files, so that a crash late in a long simulation does not lose everything. The result
+    /// is written to `<out_path stem>-partial.json` and lacks the series and analyses, which are
+    /// only added at the end.
+    #[arg(long)]
+    #[serde(default)]
+    pub flush_every: Option<u32>,
+
+    /// Whether to detect the fronts of jams in the space-time data and add the estimated speeds
+    /// with which they propagate to the simulation result.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub shockwaves: bool,
+
+    /// Optionally detect the breakdown of traffic flow: the first round in which a jam of at least
+    /// this many stopped cars in a row forms that persists for `breakdown_rounds` rounds. Queues in
+    /// front of red lights and blocked cells are not jams. Whether and when traffic broke down is
+    /// added to the simulation result.
+    #[arg(long)]
+    #[serde(default)]
+    pub breakdown_jam_cars: Option<u32>,
+
+    /// The number of rounds in a row for which there must be a jam somewhere on the road for
+    /// traffic to count as broken down.
+    #[arg(long, default_value_t = 30)]
+    #[serde(default = "default_breakdown_rounds")]
+    pub breakdown_rounds: u32,
+
+    /// Optionally stop the simulation early once traffic has settled into a steady state: when the
+    /// mean speed of the cars over the last this many rounds differs from that over the window
+    /// before by no more than `steady_state_tolerance`. The rounds actually run and the round in
+    /// which the steady state was reached are reported in the simulation result.
+    #[arg(long)]
+    #[serde(default)]
+    pub steady_state_window: Option<u32>,
+
+    /// The largest change of the mean speed between two windows, relative to the earlier one, at
+    /// which traffic counts as steady.
+    #[arg(long, default_value_t = 0.01)]
+    #[serde(default = "default_steady_state_tolerance")]
+    pub steady_state_tolerance: f32,
+
+    /// Optionally detect jammed cars, which have driven at most one cell per round for this many
+    /// rounds in a row, and measure the queues of jammed cars upstream of blocked cells and traffic
+    /// lights. The jammed cars and the queues are added to the simulation result.
+    #[arg(long)]
+    #[serde(default)]
+    pub jam_rounds: Option<u32>,
+
+    /// Optionally record surrogate safety metrics: the time that cars spend with a time-to-collision
+    /// with the car ahead below this many seconds, the smallest time-to-collision and the hard
+    /// brakings of at least `hard_braking` cells per round. They are added to the simulation result.
+    #[arg(long)]
+    #[serde(default)]
+    pub ttc_threshold: Option<f32>,
+
+    /// The cells per round by which a car has to slow down in a round for it to count as a hard
+    /// braking.
+    #[arg(long, default_value_t = 2)]
+    #[serde(default = "default_hard_braking")]
+    pub hard_braking: u8,
+
+    /// Whether to add a histogram of the speeds that the cars drove at in every round to the
+    /// simulation result.
+    #[arg(long)]
+    #[serde(default)]
+    pub speed_histogram: bool,
+
+    /// Optionally cross-correlate the flow and speed series of every pair of monitored cells to
+    /// estimate the delays with which traffic propagates between them. The series are binned into
+    /// intervals of this many rounds.
+    #[arg(long)]
+    #[serde(default)]
+    pub detector_correlation: Option<u32>,
+
+    /// Optionally write a hash of the full state of the road of every round to this file, one
+    /// hexadecimal hash per line. Runs are only reproducible if they do not depend on chance.
+    #[arg(long)]
+    #[serde(default)]
+    pub dump_state_hashes: Option<PathBuf>,
+
+    /// Optionally compare the state hash of every round to the hashes in this file, as written by
+    /// `dump_state_hashes`, and add the first round whose state differs to the simulation result.
+    #[arg(long)]
+    #[serde(default)]
+    pub compare_state_hashes: Option<PathBuf>,
+
+    /// The locations, specified as `(lane_index, cell_index); ...`, of the cells that represent
+    /// traffic lights. Use `(*, cell_index)` or `(all, cell_index)` to place a light in every lane.
+    /// Traffic lights will be green for 100 rounds and then be red for 100 rounds. The timing of a
+    /// light can be changed by appending `cycle=rounds`, `green=rounds` and `offset=rounds`, e.g.
+    /// `(0, 50, cycle=60, green=40, offset=20)`. Every `cycle` rounds the light is green for
+    /// `green` rounds and then red for the rest of the cycle, which starts `offset` rounds late.
+    /// Adding `actuated` makes a light extend its green phase while cars keep arriving at a
+    /// detector `detector=cells` (10) in front of it: after at least `min_green=rounds` (10) it
+    /// turns red once no car has been detected for `gap=rounds` (3), and after at most
+    /// `max_green=rounds` (100). Its red phase lasts `cycle - green` rounds.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    pub traffic_lights: Vec<String>,
+
+    /// Corridors of traffic lights, specified as `(start-end, speed[, cycle=rounds][, green=rounds]); ...`,
+    /// that are coordinated into green waves. The lights at the cell indexes from `start` to `end`
+    /// share a cycle and turn green one after another, so that cars driving at `speed` cells per
+    /// round meet green lights all along the corridor. The cycle defaults to 200 rounds and the
+    /// green phase to half of the cycle. The timings given in `traffic_lights` are replaced.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub green_waves: Vec<String>,
+
+    /// The locations, specified as `(lane_index, cell_index); ...`, of protection points that are
+    /// watched for queues growing back from a red traffic light or blocked cell, e.g. `(*, 0)` for
+    /// the start of every lane. The first round and the total duration of spillback at each point
+    /// are added to the simulation result.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub spillback_points: Vec<String>,
+
+    /// Optionally let emergency vehicles force traffic lights in their lane green while they are
+    /// at most this many cells in front of them.
+    #[arg(long)]
+    #[serde(default)]
+    pub preemption_distance: Option<u32>,
+
+    /// Optionally let cars clear the way for emergency vehicles that are at most this many cells
+    /// behind them in their lane. They switch to the lane to their right if they can and slow
+    /// down otherwise.
+    #[arg(long)]
+    #[serde(default)]
+    pub emergency_yield_distance: Option<u32>,
+
+    /// Let emergency vehicles drive through red traffic lights and the stop signs of failed ones.
+    #[arg(long)]
+    #[serde(default)]
+    pub emergency_runs_red_lights: bool,
+
+    /// The probability with which the traffic lights at a cell index fail in a round, going dark
+    /// or flashing. Drivers treat a failed light as a stop sign: they stop right in front of it
+    /// and then drive on.
+    #[arg(long, default_value_t = 0.0)]
+    #[serde(default)]
+    pub light_failure_probability: f32,
+
+    /// The mean number of rounds that a traffic light failure lasts. The durations are drawn from
+    /// an exponential distribution.
+    #[arg(long, default_value_t = 100)]
+    #[serde(default = "default_light_failure_rounds")]
+    pub light_failure_rounds: u32,
+
+    /// The probability with which an incident, like a breakdown, blocks a random cell in a round.
+    /// The times and locations of all incidents are added to the simulation result.
+    #[arg(long, default_value_t = 0.0)]
+    #[serde(default)]
+    pub incident_probability: f32,
+
+    /// The mean number of rounds for which an incident blocks its cell. The durations are drawn
+    /// from an exponential distribution.
+    #[arg(long, default_value_t = 100)]
+    #[serde(default = "default_incident_rounds")]
+    pub incident_rounds: u32,
+
+    /// Optionally let drivers that pass a blocked cell in the lane next to them gawk at it and
+    /// drive at most this many cells per round.
+    #[arg(long)]
+    #[serde(default)]
+    pub rubbernecking_speed: Option<u8>,
+
+    /// Optionally let drivers that pass a blocked cell in the lane next to them gawk at it and
+    /// dilly-dally with at least this probability.
+    #[arg(long)]
+    #[serde(default)]
+    pub rubbernecking_dilly_dally_probability: Option<f32>,
+
+    /// A car that has to slow down by more than this many cells per round because another car
+    /// switched into its lane right in front of it counts as a near miss.
+    #[arg(long, default_value_t = 2)]
+    #[serde(default = "default_near_miss_braking")]
+    pub near_miss_braking: u8,
+
+    /// Optionally limit how many cells per round cars can brake in a round. A car that would have
+    /// to brake harder overruns the limit as set by `braking_overrun`.
+    #[arg(long)]
+    #[serde(default)]
+    pub max_deceleration: Option<u8>,
+
+    /// What happens when a car has to brake harder than the maximum deceleration. With `collision`,
+    /// it runs into the obstacle ahead and the wreck blocks its cell for `collision_rounds` rounds.
+    /// With `emergency`, it brakes up to `emergency_braking_factor` times as hard, which counts as
+    /// an emergency braking, and only collides if that is not enough either.
+    #[arg(long, value_enum, default_value_t = BrakingOverrun::Collision)]
+    #[serde(default)]
+    pub braking_overrun: BrakingOverrun,
+
+    /// How many times the maximum deceleration cars can brake in an emergency.
+    #[arg(long, default_value_t = 2.0)]
+    #[serde(default = "default_emergency_braking_factor")]
+    pub emergency_braking_factor: f32,
+
+    /// The number of rounds for which a car that collided stands still and blocks its cell.
+    #[arg(long, default_value_t = 10)]
+    #[serde(default = "default_collision_rounds")]
+    pub collision_rounds: u32,
+
+    /// Whether cars announce lane changes a round ahead. Cars behind a signalling car in the lane
+    /// it wants to switch into hold back to open a gap for it.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub turn_signals: bool,
+
+    /// The probability with which a car holds back to let a car merge in front of it whose lane is
+    /// blocked ahead. This is independent of the stay-in-lane probability of the merging car.
+    #[arg(long, default_value_t = 0.0)]
+    #[serde(default)]
+    pub courtesy_probability: f32,
+
+    /// Whether cars whose lane is blocked ahead make mandatory lane changes. These take any gap in
+    /// the lane next to the car and override the stay-in-lane probability the more, the closer the
+    /// blocked cell gets.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub mandatory_lane_changes: bool,
+
+    /// Optionally make cars aware of blocked cells in their lane up to this many cells ahead, even
+    /// behind other cars. Cars then start mandatory lane changes this early, which allows comparing
+    /// early and late merging. Implies `--mandatory-lane-changes`.
+    #[arg(long)]
+    #[serde(default)]
+    pub blockage_look_ahead: Option<u32>,
+
+    /// The locations, specified as `(lane_index, cell_index_start - cell_index_end_exclusive); ...`
+    /// or `(lane_index, cell_index); ...`, of the cells that may not be driven over. This simulates
+    /// blockages as they occur when construction work is being done. Appending `from=round` and
+    /// `until=round` puts a block in place only from the one round until the other, e.g. for an
+    /// incident.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    pub block: Vec<String>,
+
+    /// Speed limits, specified as `(lane_index, cell_index_start - cell_index_end_exclusive,
+    /// limit); ...` or `(lane_index, cell_index, limit); ...`, that cap the speed of cars driving
+    /// off from the cells below the maximum speeds of their vehicle types, e.g. in work zones or
+    /// in town.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub speed_limit: Vec<String>,
+
+    /// Lanes or cells reserved for a class of vehicles, specified as `(lane_index, class); ...`
+    /// for whole lanes or `(lane_index, cell_index_start - cell_index_end_exclusive, class); ...`,
+    /// e.g. `(2, bus)` for a bus lane. Vehicles of other classes treat the cells as blocked, except
+    /// for emergency vehicles.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub reserve: Vec<String>,
+
+    /// The order in which the cars are updated each round. `sequential` sweeps over the cells from
+    /// the end of the road to its start and over the lanes from left to right. `random-lanes`
+    /// shuffles the lanes every round, `random-lanes-per-cell` shuffles them anew for every cell
+    /// and `alternating-lanes` reverses them every other round. `parallel` lets all cars switch
+    /// lanes at once and then drive at once.
+    #[arg(long, value_enum, default_value_t = UpdateOrder::Sequential)]
+    #[serde(default)]
+    pub update_order: UpdateOrder,
+
+    /// The rule by which cars keep their distance. `nasch` lets cars drive up to the free cells in
+    /// front of them. `anticipation` also lets them count on the car ahead driving on by its speed
+    /// in the same round, unless its brake lights are on because it slowed down, which dampens
+    /// stop-and-go waves.
+    #[arg(long, value_enum, default_value_t = DrivingModel::Nasch)]
+    #[serde(default)]
+    pub model: DrivingModel,
+
+    /// The rules by which cars switch lanes. `symmetric` lets cars switch to either side to gain
+    /// space. `keep-right` makes cars return to the lane to their right as soon as they can drive
+    /// on there at full speed and only lets them switch to the left to overtake.
+    #[arg(long, value_enum, default_value_t = LaneRules::Symmetric)]
+    #[serde(default)]
+    pub lane_rules: LaneRules,
+
+    /// The seed of the random number generator. Runs with the same seed and settings make the same
+    /// random decisions and give the same results, apart from the runtime. Without a seed, every run
+    /// is different.
+    #[arg(long)]
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// How the cars are placed on the road before the first round. `lanes` puts
+    /// `traffic_density * unblocked cells` cars (rounded) of each vehicle type into every lane,
+    /// walking along the lane and taking each free cell with the traffic density as probability.
+    /// `exact` puts exactly `traffic_density * unblocked cells` cars (rounded) of each vehicle type
+    /// onto the whole road, picking the cells uniformly at random. This also replaces the cars on
+    /// the spawn points of a layout.
+    #[arg(long, value_enum, default_value_t = SpawnMode::Lanes)]
+    #[serde(default)]
+    pub spawn_mode: SpawnMode,
+
+    /// Optionally place exactly this many cars instead of using the traffic densities, specified as
+    /// `cars` or `cars_vehicle_type_1; cars_vehicle_type_2; ...`. A single number is split among the
+    /// vehicle types in proportion to their traffic densities. The cars are placed in cells picked
+    /// uniformly at random from the whole road.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub cars: Vec<String>,
+
+    /// Optionally place the cars by a traffic density for each lane instead of using the traffic
+    /// densities of the vehicle types, specified as `density_lane_1; density_lane_2; ...`. A lane
+    /// density is split among the vehicle types in proportion to their traffic densities. Use
+    /// `(density_vehicle_type_1, density_vehicle_type_2, ...)` to set the density of each vehicle
+    /// type in a lane.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub lane_densities: Vec<String>,
+
+    /// Optionally draw the road in this text file instead of using `lanes`, `length`, `block` and
+    /// `traffic_lights`. Every line is a lane and every character a cell: `_` free, `x` blocked,
+    /// `#` traffic light, `1`-`9` slow zone with that speed limit and `o` spawn point. If there are
+    /// spawn points, exactly one car starts on each of them.
+    #[arg(long)]
+    #[serde(default)]
+    pub layout: Option<PathBuf>,
+
+    /// Optionally open the road and let cars arrive according to the demand profile in this CSV
+    /// file. Each row is formatted as `start_round, cars_per_minute[, share_0, share_1, ...]`, where
+    /// the optional shares weight the vehicle types in the order of `vehicles`. Without shares,
+    /// vehicle types are weighted by their traffic density. Cars leave the open road at its end.
+    #[arg(long)]
+    #[serde(default)]
+    pub demand: Option<PathBuf>,
+
+    /// How cars arrive at the open road. `bernoulli` lets a car arrive in front of each lane with
+    /// a probability of `cars_per_minute / 60 / lanes` every round. `poisson` draws the number of
+    /// arriving cars of the whole road every round from a Poisson distribution and `uniform` lets
+    /// them arrive at evenly spaced times, taking turns between the lanes. `batch` lets bursts of
+    /// `arrival_batch_size` cars arrive at once as a Poisson process. The shares of the demand
+    /// split the arrival rate among the vehicle types.
+    #[arg(long, value_enum, default_value_t = ArrivalProcess::Bernoulli)]
+    #[serde(default)]
+    pub arrival_process: ArrivalProcess,
+
+    /// The number of cars that arrive together with the `batch` arrival process.
+    #[arg(long, default_value_t = 5)]
+    #[serde(default = "default_arrival_batch_size")]
+    pub arrival_batch_size: u32,
+
+    /// Keyframes of a target traffic density, specified as `(round, density); ...`, that the
+    /// number of cars on the closed road follows by adding and removing cars at the end of every
+    /// round. Between keyframes, the density is interpolated linearly, e.g. `(0, 0.05); (1000,
+    /// 0.35)` ramps the density up over 1000 rounds.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub target_density: Vec<String>,
+
+    /// The path to a CSV file with a time-of-day profile of model parameters. Each row contains
+    /// the round from which on it applies, the dilly-dally probability, the stay-in-lane
+    /// probability and the maximum speed of each vehicle type. Fields left empty keep the values
+    /// given by the other options.
+    #[arg(long)]
+    #[serde(default)]
+    pub parameter_profile: Option<PathBuf>,
+
+    /// The weather, which makes drivers dilly-dally more and lowers the maximum speeds of all
+    /// vehicle types: `rain` slightly, `fog` more and `snow` the most.
+    #[arg(long, value_enum, default_value_t = Weather::Dry)]
+    #[serde(default)]
+    pub weather: Weather,
+
+    /// Changes of the weather over time, specified as `(round, weather); ...`, e.g. `(500, rain);
+    /// (1500, dry)`. Until the first change, the weather is `weather`.
+    #[arg(long, value_delimiter = ';', default_value = "")]
+    #[serde(default)]
+    pub weather_schedule: Vec<String>,
+
+    /// Whether to print the states of the road to stdout.
+    #[arg(short, long, default_value_t = false)]
+    pub verbose: bool,
+
+    /// Whether to print the states of the road to stdout using color and overwriting for greater
+    /// viewing pleasure. This option trumps the `verbose` option.
+    #[arg(short, long, default_value_t = false)]
+    pub animate: bool,
+
+    /// How long each frame of the animation in the terminal or of the animated GIF (`gif`) is shown
+    /// in milliseconds. Use 0 to animate as fast as possible.
+    #[arg(long, default_value_t = 50)]
+    #[serde(default = "default_frame_delay_ms")]
+    pub frame_delay_ms: u64,
+
+    /// The window of the road to print or animate as `(lane_start-lane_end, cell_start-cell_end)`
+    /// with exclusive ends, e.g. `(0-2, 100-200)`. A single number selects a single lane or cell.
+    #[arg(long)]
+    #[serde(default)]
+    pub view: Option<String>,
+
+    /// A car to keep in the center of the printed or animated output, given by its ID or by the
+    /// cell `(lane, index)` it starts in. Without a `view`, 80 cells of all lanes are shown.
+    #[arg(long)]
+    #[serde(default)]
+    pub follow: Option<String>,
+
+    /// Whether to show the internal gap counters (cells to the next car and to the next obstacle)
+    /// that the last update saw in each cell below each lane of the printed or animated output.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub debug_gaps: bool,
+ 
+    /// Whether to create a visualization image of the simulation.
+    #[arg(short, long, default_value_t = false)]
+    pub image: bool,
+
+    /// What the visualization image shows. `space-time` stacks the road of every round, while
+    /// `occupancy` and `speed` draw a single heatmap of the time-averaged occupancy or speed of
+    /// every cell.
+    #[arg(long, value_enum, default_value_t = ImageMode::SpaceTime)]
+    #[serde(default)]
+    pub image_mode: ImageMode,
+
+    /// Whether to add a row showing the state of the traffic lights (red or green) above the lanes
+    /// of every round in the `space-time` image.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub image_light_strip: bool,
+
+    /// Whether to frame the `space-time` image with axes: the rounds upwards along the left, the
+    /// cell indexes along the bottom with markers below traffic lights (blue) and blocked cells
+    /// (magenta), and a legend of the speed colors from stopped (red) to the maximum speed (green).
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub image_axes: bool,
+
+    /// The size of the square of pixels that each cell is drawn as in the visualization image, the
+    /// frames and the animated GIF.
+    #[arg(long, default_value_t = 1)]
+    #[serde(default = "default_pixel_scale")]
+    pub pixel_scale: u32,
+
+    /// Where to save the visualization image.
+    #[arg(short, long, default_value = "traffic.png")]
+    pub out_path: PathBuf,
+
+    /// Whether to render the mean speed of the cars and the flow at the monitored cells over the
+    /// rounds as line charts to SVG files next to the image, e.g. `traffic-speed.svg` and
+    /// `traffic-flow.svg` for `traffic.png`.
+    #[arg(long, default_value_t = false)]
+    #[serde(default)]
+    pub charts: bool,
+
+    /// A directory to save the state of the road after every round to as a numbered image
+    /// (`round_000000.png`, `round_000001.png`, ...), e.g. for assembling animations with ffmpeg.
+    #[arg(long)]
+    #[serde(default)]
+    pub frames_dir: Option<PathBuf>,
+
+    /// Where to save the states of the road after every round as the frames of a looping animated
+    /// GIF. Each frame is shown for `frame_delay_ms` and scaled by `pixel_scale`.
+    #[arg(long)]
+    #[serde(default)]
+    pub gif: Option<PathBuf>,
+
+    /// A text file to write the uncolored state of the road of every round to, as printed by the
+    /// `verbose` option.
+    #[arg(long)]
+    #[serde(default)]
+    pub ascii_out: Option<PathBuf>,
+
+    /// Optionally write the movements of all cars as MATSim events to this file. The events are
+    /// written as XML unless the file ends in `.csv`.
+    #[arg(long)]
+    #[serde(default)]
+    pub matsim_events: Option<PathBuf>,
+
+    /// The number of cells that make up one MATSim link. Links span all lanes.
+    #[arg(long, default_value_t = 100)]
+    #[serde(default = "default_matsim_link_length")]
+    pub matsim_link_length: u32,
+
+    /// Optionally write the trip of every car that leaves the open road to this CSV file, with
+    /// its departure and arrival rounds, travel time, entry and exit lanes, lane changes, distance
+    /// and stops. Requires `demand`.
+    #[arg(long)]
+    #[serde(default)]
+    pub trips: Option<PathBuf>,
+
+    /// Optionally write the state of the road after every round to this CSV file, with the cars on
+    /// the road, their density and mean speed, the lane changes of the round and the flow at every
+    /// monitored cell.
+    #[arg(long)]
+    #[serde(default)]
+    pub timeseries: Option<PathBuf>,
+
+    /// Optionally stream the state of every round as Apache Arrow record batches with one row per
+    /// car. Use `-` for stdout, `tcp://host:port` for a socket or a file path. When streaming to
+    /// stdout, the simulation result is printed to stderr.
+    #[arg(long)]
+    #[serde(default)]
+    pub arrow_stream: Option<String>,
+
+    /// Optionally record every car movement, lane change and traffic light switch to this compact
+    /// binary event log, from which the run can be replayed with `replay`.
+    #[arg(long)]
+    #[serde(default)]
+    pub event_log: Option<PathBuf>,
+
+    /// Optionally write a standalone HTML report of the run with its settings, metrics, warnings
+    /// and the image and charts it created embedded to this file.
+    #[arg(long)]
+    #[serde(default)]
+    pub report: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageMode {
+    #[default]
+    SpaceTime,
+    Occupancy,
+    Speed,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateOrder {
+    #[default]
+    Sequential,
+    RandomLanes,
+    RandomLanesPerCell,
+    AlternatingLanes,
+    Parallel,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DrivingModel {
+    #[default]
+    Nasch,
+    Anticipation,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LaneRules {
+    #[default]
+    Symmetric,
+    KeepRight,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Weather {
+    #[default]
+    Dry,
+    Rain,
+    Snow,
+    Fog,
+}
+
+impl Weather {
+    /// Returns the factor by which the weather raises the dilly-dally probability.
+    pub fn dilly_dally_factor(&self) -> f32 {
+        match self {
+            Weather::Dry => 1.0,
+            Weather::Rain => 1.5,
+            Weather::Snow => 2.5,
+            Weather::Fog => 2.0,
+        }
+    }
+
+    /// Returns the factor by which the weather lowers the maximum speeds.
+    pub fn max_speed_factor(&self) -> f32 {
+        match self {
+            Weather::Dry => 1.0,
+            Weather::Rain => 0.8,
+            Weather::Snow => 0.5,
+            Weather::Fog => 0.6,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BrakingOverrun {
+    #[default]
+    Collision,
+    Emergency,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArrivalProcess {
+    #[default]
+    Bernoulli,
+    Poisson,
+    Uniform,
+    Batch,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpawnMode {
+    #[default]
+    Lanes,
+    Exact,
+}
+
+fn default_arrival_batch_size() -> u32 {
+    5
+}
+
+fn default_frame_delay_ms() -> u64 {
+    50
+}
+
+fn default_pixel_scale() -> u32 {
+    1
+}
+
+fn default_hard_braking() -> u8 {
+    2
+}
+
+fn default_density_window() -> u32 {
+    5
+}
+
+fn default_breakdown_rounds() -> u32 {
+    30
+}
+
+fn default_steady_state_tolerance() -> f32 {
+    0.01
+}
+
+fn default_emergency_braking_factor() -> f32 {
+    2.0
+}
+
+fn default_collision_rounds() -> u32 {
+    10
+}
+
+fn default_near_miss_braking() -> u8 {
+    2
+}
+
+fn default_congestion_threshold() -> f32 {
+    0.5
+}
+
+fn default_incident_rounds() -> u32 {
+    100
+}
+
+fn default_light_failure_rounds() -> u32 {
+    100
+}
+
+fn default_matsim_link_length() -> u32 {
+    100
+}
+
+impl Default for SimulationConfig {
+    /// Returns the defaults of the command line options.
+    fn default() -> Self {
+        let command = Self::augment_args(clap::Command::new(env!("CARGO_PKG_NAME")));
+        Self::from_arg_matches(&command.get_matches_from([env!("CARGO_PKG_NAME")])).unwrap()
+    }
+}
+
+/// Reads a map of settings from a JSON, yaml or TOML file, picking the format by the file
+/// extension.
+pub(crate) fn read_settings_file(path: &Path) -> Result<serde_json::Map<String, serde_json::Value>, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let settings: serde_json::Value = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_str(&contents)?,
+        Some("yaml" | "yml") => serde_yaml::from_str(&contents)?,
+        Some("toml") => toml::from_str(&contents)?,
+        _ => return Err(format!("The format of the file `{}` is unknown, use `.json`, `.yaml` or `.toml`.", path.display()).into()),
+    };
+    match settings {
+        serde_json::Value::Object(settings) => Ok(settings),
+        _ => Err(format!("The file `{}` must contain a map of settings.", path.display()).into()),
+    }
+}
+
+impl SimulationConfig {
+    /// Returns the path of a chart next to the image, e.g. `traffic-speed.svg` for `traffic.png`.
+    pub fn chart_path(&self, name: &str) -> PathBuf {
+        let stem = self.out_path.file_stem().map_or("traffic".into(), |stem| stem.to_string_lossy());
+        self.out_path.with_file_name(format!("{}-{}.svg", stem, name))
+    }
+
+    pub fn partial_result_path(&self) -> PathBuf {
+        let stem = self.out_path.file_stem().map_or("traffic".into(), |stem| stem.to_string_lossy());
+        self.out_path.with_file_name(format!("{}-partial.json", stem))
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let deserialized: SimulationConfig = serde_yaml::from_str(yaml)?;
+        Ok(deserialized)
+    }
+
+    /// Reads a scenario from a JSON, yaml or TOML file, depending on its extension. Settings that the file
+    /// leaves out keep the defaults of the command line options.
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let settings = read_settings_file(path)?;
+        let mut config = serde_json::to_value(Self::default())?;
+        for (name, value) in settings {
+            config[name] = value;
+        }
+        Ok(serde_json::from_value(config)?)
+    }
+
+    /// Returns the scenario without any of its outputs, for runs whose results are only collected.
+    pub fn without_outputs(self) -> Self {
+        Self {
+            verbose: false,
+            animate: false,
+            debug_gaps: false,
+            image: false,
+            frames_dir: None,
+            gif: None,
+            charts: false,
+            ascii_out: None,
+            matsim_events: None,
+            trips: None,
+            timeseries: None,
+            arrow_stream: None,
+            event_log: None,
+            dump_state_hashes: None,
+            compare_state_hashes: None,
+            flush_every: None,
+            report: None,
+            ..self
+        }
+    }
+
+    /// Deserializes stringified_tuples that were provided as arguments.
+    /// Note: This method assumes that the parenthesis are each one byte long. Beware of UTF-8
+    /// characters in those positions.
+    pub fn deserialize_tuple_type<D: FromStr>(stringified_tuples: &Vec<String>) -> Vec<D> where <D as FromStr>::Err: Debug {
+        let mut tuples = Vec::new();
+        for string in stringified_tuples {
+            if string.is_empty() { continue; }
+            tuples.push(string.parse::<D>().unwrap());
+        }
+        tuples
+    }
+
+    pub fn vehicles(&self) -> Vec<VehicleBlueprint> {
+        let mut vehicles = Self::deserialize_tuple_type(&self.vehicles);
+        self.add_automated_vehicles(&mut vehicles);
+        vehicles
+    }
+
+    /// Turns the automated share of the cars of every regular vehicle type into automated vehicle
+    /// types, which are added after the given ones.
+    pub fn add_automated_vehicles(&self, vehicles: &mut Vec<VehicleBlueprint>) {
+        if self.automated_share <= 0.0 {
+            return;
+        }
+        let automated: Vec<VehicleBlueprint> = vehicles
+            .iter_mut()
+            .filter(|vehicle| vehicle.vehicle_class() == VehicleClass::Regular)
+            .map(|vehicle| vehicle.split_off_automated(self.automated_share))
+            .collect();
+        vehicles.extend(automated);
+    }
+
+    /// Returns the distributions of the driver probabilities or `None` if all drivers share them.
+    pub fn driver_distributions(&self) -> Option<DriverDistributions> {
+        let parse = |distribution: &Option<String>| distribution
+            .as_ref()
+            .map(|distribution| distribution.parse::<ProbabilityDistribution>().expect("Unable to parse the driver distribution."));
+        let driver_distributions = DriverDistributions {
+            dilly_dally: parse(&self.dilly_dally_distribution),
+            stay_in_lane: parse(&self.stay_in_lane_distribution),
+        };
+        (driver_distributions != DriverDistributions::default()).then_some(driver_distributions)
+    }
+
+    pub fn cars(&self) -> Vec<u32> {
+        self.cars
+            .iter()
+            .filter(|cars| !cars.trim().is_empty())
+            .map(|cars| cars.trim().parse::<u32>().expect("Car counts must be whole numbers."))
+            .collect()
+    }
+
+    pub fn lane_densities(&self) -> Vec<LaneDensity> {
+        Self::deserialize_tuple_type(&self.lane_densities)
+    }
+
+    pub fn monitor(&self) -> Vec<CellLocation> {
+        Self::deserialize_tuple_type(&self.monitor)
+    }
+
+    pub fn sections(&self) -> Vec<Section> {
+        Self::deserialize_tuple_type(&self.sections)
+    }
+
+    pub fn block(&self) -> Vec<CellLocationRange> {
+        Self::deserialize_tuple_type(&self.block)
+    }
+
+    pub fn speed_limit(&self) -> Vec<SpeedLimitZone> {
+        Self::deserialize_tuple_type(&self.speed_limit)
+    }
+
+    pub fn reserve(&self) -> Vec<ReservedZone> {
+        Self::deserialize_tuple_type(&self.reserve)
+    }
+
+    pub fn traffic_lights(&self) -> Vec<TrafficLight> {
+        let mut traffic_lights = Vec::new();
+        for string in &self.traffic_lights {
+            if string.is_empty() { continue; }
+            traffic_lights.append(&mut TrafficLight::parse_in_lanes(string, self.lanes).unwrap());
+        }
+        traffic_lights
+    }
+
+    pub fn green_waves(&self) -> Vec<GreenWave> {
+        Self::deserialize_tuple_type(&self.green_waves)
+    }
+
+    pub fn spillback_points(&self) -> Vec<CellLocation> {
+        let mut spillback_points = Vec::new();
+        for string in &self.spillback_points {
+            if string.is_empty() { continue; }
+            spillback_points.append(&mut CellLocation::parse_in_lanes(string, self.lanes).unwrap());
+        }
+        spillback_points
+    }
+
+    pub fn view(&self) -> Option<View> {
+        self.view.as_ref().map(|view| view.parse::<View>().unwrap())
+    }
+
+    pub fn follow(&self) -> Option<FollowTarget> {
+        self.follow.as_ref().map(|follow| follow.parse::<FollowTarget>().unwrap())
+    }
+
+    pub fn layout(&self) -> Option<String> {
+        self.layout.as_ref().map(|path| {
+            std::fs::read_to_string(path).expect("Unable to read layout file at provided path.")
+        })
+    }
+
+    pub fn demand(&self) -> Option<DemandProfile> {
+        self.demand.as_ref().map(|path| {
+            let contents = std::fs::read_to_string(path).expect("Unable to read demand CSV file at provided path.");
+            contents.parse::<DemandProfile>().unwrap()
+        })
+    }
+
+    /// Returns the weather over time, or `None` if it is dry throughout.
+    pub fn weather_schedule(&self) -> Option<WeatherSchedule> {
+        let changes: Vec<WeatherChange> = Self::deserialize_tuple_type(&self.weather_schedule);
+        (self.weather != Weather::Dry || !changes.is_empty()).then(|| WeatherSchedule::new(self.weather, changes))
+    }
+
+    pub fn target_density(&self) -> Option<DensitySchedule> {
+        let keyframes: Vec<DensityKeyframe> = Self::deserialize_tuple_type(&self.target_density);
+        (!keyframes.is_empty()).then(|| DensitySchedule::new(keyframes))
+    }
+
+    /// Resolves the relative paths of the input and output files against a directory.
+    pub fn resolve_paths(&mut self, dir: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        };
+        resolve(&mut self.out_path);
+        for path in [
+            &mut self.dump_state_hashes,
+            &mut self.compare_state_hashes,
+            &mut self.layout,
+            &mut self.demand,
+            &mut self.parameter_profile,
+            &mut self.frames_dir,
+            &mut self.gif,
+            &mut self.ascii_out,
+            &mut self.matsim_events,
+            &mut self.trips,
+            &mut self.timeseries,
+            &mut self.event_log,
+            &mut self.report,
+        ].into_iter().flatten() {
+            resolve(path);
+        }
+        if let Some(arrow_stream) = &mut self.arrow_stream {
+            if arrow_stream != "-" && !arrow_stream.starts_with("tcp://") && Path::new(arrow_stream).is_relative() {
+                *arrow_stream = dir.join(&*arrow_stream).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    pub fn parameter_profile(&self) -> Option<ParameterProfile> {
+        self.parameter_profile.as_ref().map(|path| {
+            let contents = std::fs::read_to_string(path).expect("Unable to read parameter profile CSV file at provided path.");
+            contents.parse::<ParameterProfile>().unwrap()
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SimulationResult {
+    // Settings
+    pub rounds: u32,
+    pub warmup: u32,
+    pub lanes: u32,
+    pub length: u32,
+    pub cars: u32,
+    pub open: bool,
+    pub dilly_dally_probability: f32,
+    pub stay_in_lane_probability: f32,
+    pub update_order: UpdateOrder,
+    pub model: DrivingModel,
+    pub lane_rules: LaneRules,
+    pub seed: Option<u64>,
+    // Metrics
+    pub runtime_s: f64,
+    pub average_speed_kilometers_per_hour: f64,
+    pub monitor_cells_flow_cars_per_minute: Vec<f64>,
+    pub monitor_cells_occupancy: Vec<f64>,
+    pub monitor_cells_density_cars_per_km: Vec<f64>,
+    pub average_accelerations_n_per_car_per_round: f64,
+    pub average_deaccelerations_n_per_car_per_round: f64,
+    pub exited_cars: u32,
+    pub removed_cars: u32,
+    pub waiting_cars: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_map_cars_per_minute: Option<Vec<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_map_kilometers_per_hour: Option<Vec<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occupancy_map: Option<Vec<Vec<f64>>>,
+    pub initial_cars_per_lane: Vec<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cars_per_lane_per_round: Option<Vec<Vec<u32>>>,
+    pub vehicle_types: Vec<VehicleTypeResult>,
+    pub average_speed_kilometers_per_hour_by_vehicle_class: BTreeMap<VehicleClass, f64>,
+    pub laps: u64,
+    pub lap_times_s: LapTimeDistribution,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emergency_travel_times_s: Option<LapTimeDistribution>,
+    pub congested_time_s: f64,
+    pub average_congested_time_s_per_car: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub congested_fraction_per_round: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_speed_kilometers_per_hour_per_round: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub density_cars_per_km_per_lane_per_round: Option<Vec<f64>>,
+    /// The flows at the monitored cells, round by round.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor_cells_flow_cars_per_minute_per_round: Option<Vec<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shockwaves: Option<ShockwaveEstimate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detector_correlations: Option<Vec<DetectorCorrelation>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_hashes: Option<StateHashComparison>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spillbacks: Option<Vec<Spillback>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<Breakdown>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steady_state: Option<SteadyState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queues: Option<QueueStatistics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sections: Option<Vec<SectionResult>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety: Option<SafetyMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_histogram: Option<Vec<SpeedHistogramBin>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal_plan: Option<Vec<TrafficLight>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incidents: Option<Vec<Incident>>,
+    pub preempted_light_rounds: u64,
+    pub light_failures: u64,
+    pub failed_light_rounds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryReport>,
+    pub near_misses: u64,
+    pub near_misses_per_lane_change: f64,
+    pub yields: u64,
+    pub mandatory_lane_changes: u64,
+    pub lane_changes: LaneChangeStatistics,
+    pub collisions: u64,
+    pub emergency_brakings: u64,
+}
+
+/// The distribution of the durations of all full laps on a closed road. Without any full laps, all
+/// durations are `NaN`.
+#[derive(Serialize, Debug)]
+pub struct LapTimeDistribution {
+    pub full_laps: usize,
+    pub mean: f64,
+    pub min: f64,
+    pub percentile_10: f64,
+    pub median: f64,
+    pub percentile_90: f64,
+    pub max: f64,
+}
+
+impl LapTimeDistribution {
+    fn new(lap_times_rounds: &[u32]) -> Self {
+        let mut lap_times: Vec<f64> = lap_times_rounds.iter().map(|rounds| *rounds as f64 * ROUND_S).collect();
+        lap_times.sort_by(f64::total_cmp);
+        let percentile = |p: f64| {
+            if lap_times.is_empty() {
+                f64::NAN
+            } else {
+                lap_times[((lap_times.len() - 1) as f64 * p).round() as usize]
+            }
+        };
+        Self {
+            full_laps: lap_times.len(),
+            mean: lap_times.iter().sum::<f64>() / lap_times.len() as f64,
+            min: percentile(0.0),
+            percentile_10: percentile(0.1),
+            median: percentile(0.5),
+            percentile_90: percentile(0.9),
+            max: percentile(1.0),
+        }
+    }
+}
+
+/// The lane changes of all cars to the left and to the right, by the lane that the cars left and
+/// by vehicle class, and how often cars change lanes per round.
+#[derive(Serialize, Debug)]
+pub struct LaneChangeStatistics {
+    pub left: u64,
+    pub right: u64,
+    pub n_per_car_per_round: f64,
+    pub by_lane: Vec<LaneChanges>,
+    pub by_vehicle_class: BTreeMap<VehicleClass, LaneChanges>,
+    pub n_per_car_per_round_by_vehicle_class: BTreeMap<VehicleClass, f64>,
+}
+
+impl LaneChangeStatistics {
+    fn new(road: &Road) -> Self {
+        let mut total = LaneChanges::default();
+        let mut by_lane = Vec::new();
+        let mut by_vehicle_class: BTreeMap<VehicleClass, LaneChanges> = BTreeMap::new();
+        for lane in road.lane_changes_by_lane_and_vehicle_type() {
+            let mut lane_total = LaneChanges::default();
+            for (vehicle, lane_changes) in road.vehicle_blueprints().iter().zip(lane) {
+                lane_total.merge(lane_changes);
+                by_vehicle_class.entry(vehicle.vehicle_class()).or_default().merge(lane_changes);
+            }
+            total.merge(&lane_total);
+            by_lane.push(lane_total);
+        }
+        let mut car_rounds_by_vehicle_class: BTreeMap<VehicleClass, u64> = BTreeMap::new();
+        for (vehicle, records) in road.vehicle_blueprints().iter().zip(road.car_records_by_vehicle_type()) {
+            *car_rounds_by_vehicle_class.entry(vehicle.vehicle_class()).or_default() += records.rounds;
+        }
+        let n_per_car_per_round_by_vehicle_class = by_vehicle_class
+            .iter()
+            .map(|(vehicle_class, lane_changes)| (*vehicle_class, lane_changes.total() as f64 / car_rounds_by_vehicle_class[vehicle_class] as f64))
+            .collect();
+        Self {
+            left: total.left,
+            right: total.right,
+            n_per_car_per_round: total.total() as f64 / road.car_rounds() as f64,
+            by_lane,
+            by_vehicle_class,
+            n_per_car_per_round_by_vehicle_class,
+        }
+    }
+}
+
+/// The memory in bytes taken up by the simulation at its peak, which is reached at the end since
+/// all buffers only grow. `output_buffer_bytes` covers the images of the image outputs (including
+/// the enlarged copies made when saving) and `series_buffer_bytes` the data recorded every round
+/// for later analysis.
+#[derive(Serialize, Debug)]
+pub struct MemoryReport {
+    pub cell_grid_bytes: usize,
+    pub output_buffer_bytes: usize,
+    pub series_buffer_bytes: usize,
+    pub total_bytes: usize,
+}
+
+impl MemoryReport {
+    fn new(cell_grid_bytes: usize, output_buffer_bytes: usize, series_buffer_bytes: usize) -> Self {
+        Self {
+            cell_grid_bytes,
+            output_buffer_bytes,
+            series_buffer_bytes,
+            total_bytes: cell_grid_bytes + output_buffer_bytes + series_buffer_bytes,
+        }
+    }
+}
+
+/// The metrics of all cars built from one vehicle blueprint.
+#[derive(Serialize, Debug)]
+pub struct VehicleTypeResult {
+    pub vehicle: VehicleBlueprint,
+    pub cars: u64,
+    pub initial_cars_per_lane: Vec<u32>,
+    pub average_speed_kilometers_per_hour: f64,
+    pub average_accelerations_n_per_car_per_round: f64,
+    pub average_deaccelerations_n_per_car_per_round: f64,
+    pub average_stops_n_per_car_per_round: f64,
+    pub average_lane_changes_n_per_car_per_round: f64,
+}
+
+impl SimulationResult {
+    pub fn json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Writes the result to a file. The file is replaced in one step, so it never holds half a
+    /// result.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let temporary_path = path.with_extension("json.tmp");
+        std::fs::write(&temporary_path, self.json())?;
+        std::fs::rename(temporary_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr, time::Duration};
+    use clap::Parser;
+
+    use crate::cell::{Cell, CellLocation, CellLocationRange, SpeedLimitZone};
+    use crate::terminal::{render, Camera, View};
+    #[cfg(feature = "terminal")]
+    use crate::terminal::frame_changes;
+    use crate::car::{Car, VehicleBlueprint, VehicleClass};
+    use crate::breakdown::Breakdown;
+    use crate::steady_state::SteadyState;
+    use crate::section::Section;
+    use crate::safety::SafetyMonitor;
+    use crate::driver::{DriverDistributions, ProbabilityDistribution};
+    use crate::weather::WeatherSchedule;
+    use crate::traffic_light::TrafficLight;
+    use crate::intersection::{self, SignalPhases, TurningMovementCount, TurnProbabilities};
+    use crate::cli::{Cli, Command};
+    use crate::{batch, daemon, event_log, fundamental_diagram, replication, run_sim, selftest, smoke, validation, ArrivalProcess, LaneChangeStatistics, LaneChanges, BrakingOverrun, DrivingModel, ImageMode, LaneRules, Road, SimulationConfig, SpawnMode, UpdateOrder, Weather, CELL_M, ROUND_S};
+
+    // -- simple simulation --
+
+    #[test]
+    fn no_road() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            lanes: 0,
+            length: 0,
+            vehicles: vec!["(5, 1, 0.5)".to_string()],
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert!(result.average_speed_kilometers_per_hour.is_nan());
+        assert!(result.average_accelerations_n_per_car_per_round.is_nan());
+        assert!(result.average_deaccelerations_n_per_car_per_round.is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "The monitored cell `(2, 10)` is not on the road, which has 2 lanes of 100 cells.")]
+    fn monitor_off_the_road() {
+        run_sim(SimulationConfig { rounds: 10, lanes: 2, length: 100, monitor: vec!["(2, 10)".to_string()], ..SimulationConfig::default() });
+    }
+
+    #[test]
+    #[should_panic(expected = "The block `(0, 90-110)` is not on the road, which has 2 lanes of 100 cells.")]
+    fn block_off_the_road() {
+        let _ = Road::new(2, 100, &[], 0.0, 0.0, &["(0, 90-110)".parse().unwrap()], &[], None);
+    }
+
+    #[test]
+    fn default_simulation() {
+        let result = run_sim(SimulationConfig {
+            vehicles: vec!["(5, 1, 0.5)".to_string()],
+            stay_in_lane_probability: 0.0,
+            monitor: vec!["(0,0)".to_string(), "(0,500)".to_string(), "(0,999)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.cars, 500);
+        assert!(result.monitor_cells_flow_cars_per_minute[0].is_finite());
+    }
+
+    #[test]
+    fn one_car() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.cars, 1);
+        assert_eq!(
+            result.average_speed_kilometers_per_hour,
+            (1+2+3+4+5+(10-5)*5) as f64 / 10.0 * (CELL_M / ROUND_S) * 3.6
+        );
+        assert_eq!(
+            result.average_accelerations_n_per_car_per_round,
+            5.0 / 10.0
+        );
+        assert_eq!(
+            result.average_deaccelerations_n_per_car_per_round,
+            0.0
+        );
+    }
+
+    #[test]
+    fn flow_map_one_car() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            flow_map: true,
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        let flow_map = result.flow_map_cars_per_minute.unwrap();
+        let speed_map = result.speed_map_kilometers_per_hour.unwrap();
+        assert_eq!(flow_map.len(), 1);
+        assert_eq!(flow_map[0].len(), 10);
+        assert_eq!(flow_map[0][0], result.monitor_cells_flow_cars_per_minute[0]);
+        // The car drives 40 cells, so it passes every cell four times.
+        assert!(flow_map[0].iter().all(|flow| *flow == 4.0 / 10.0 * 60.0));
+        assert!(speed_map[0].iter().all(|speed| *speed > 0.0 && *speed <= 5.0 * (CELL_M / ROUND_S) * 3.6));
+        // The car occupies exactly one cell at the end of every round.
+        let occupancy_map = result.occupancy_map.unwrap();
+        assert_eq!(occupancy_map[0][0], result.monitor_cells_occupancy[0]);
+        assert!((occupancy_map[0].iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn local_density_at_monitor_cells() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.5)".parse().unwrap()];
+        let monitor = [CellLocation::new(0, 3)];
+        let mut road = Road::new(1, 20, &vehicles, 0.2, 0.0, &[], &[], Some(4)).with_density_monitors(&monitor, 10);
+        let mut narrow = Road::new(1, 20, &vehicles, 0.2, 0.0, &[], &[], Some(4)).with_density_monitors(&monitor, 0);
+        for _ in 0..50 {
+            road.round();
+            narrow.round();
+        }
+        // A window that spans the whole closed road always holds all of its cars.
+        assert_eq!(road.monitor_densities(), vec![0.5]);
+        // A window of just the cell itself is the occupancy.
+        assert_eq!(narrow.monitor_densities(), vec![narrow.cells()[0][3].occupancy(narrow.rounds(), narrow.measured_rounds())]);
+
+        let result = run_sim(SimulationConfig {
+            rounds: 20,
+            length: 50,
+            vehicles: vec!["(5, 1, 1.0)".to_string()],
+            monitor: vec!["(0, 10)".to_string()],
+            ..SimulationConfig::default()
+        });
+        assert_eq!(result.monitor_cells_flow_cars_per_minute, vec![0.0]);
+        assert_eq!(result.monitor_cells_occupancy, vec![1.0]);
+        assert_eq!(result.monitor_cells_density_cars_per_km, vec![1000.0 / CELL_M]);
+    }
+
+    #[test]
+    fn lap_times_one_car() {
+        let result = run_sim(SimulationConfig {
+            rounds: 40,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // The car drives 190 cells, so it wraps around 18 or 19 times depending on where it starts.
+        assert!(result.laps == 18 || result.laps == 19);
+        assert_eq!(result.lap_times_s.full_laps as u64, result.laps - 1);
+        assert_eq!(result.lap_times_s.min, 2.0 * ROUND_S);
+        assert_eq!(result.lap_times_s.median, 2.0 * ROUND_S);
+    }
+
+    #[test]
+    fn parameter_profile_one_car() {
+        let path = std::env::temp_dir().join("traffic-parameter_profile_one_car.csv");
+        std::fs::write(&path, "start_round,dilly_dally_probability,stay_in_lane_probability,max_speed_car\n0,,,2\n50,,,\n").unwrap();
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.01)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            parameter_profile: Some(path),
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // The car drives at up to 2 cells per round in rounds 1 to 49 and at up to 5 from round 50 on.
+        let cells = 1 + 2 * 48 + 3 + 4 + 5 * 49;
+        assert_eq!(result.average_speed_kilometers_per_hour, cells as f64 / 100.0 * (CELL_M / ROUND_S) * 3.6);
+    }
+
+    #[test]
+    fn congestion_one_car() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            congestion_series: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // The car is congested while accelerating to speeds 1 and 2.
+        assert_eq!(result.congested_time_s, 2.0 * ROUND_S);
+        assert_eq!(result.average_congested_time_s_per_car, 2.0 * ROUND_S);
+        assert_eq!(
+            result.congested_fraction_per_round.unwrap(),
+            vec![1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn single_lane_matches_multilane_update() {
+        // A second lane that is blocked entirely forces the general update without any lane changes.
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let lane = "oo_o___o__#____oo_o__o__o__5__oo_";
+        let blocked_lane = "x".repeat(lane.len());
+        let mut single_lane = Road::from_layout(&format!("{}\n", lane), &vehicles, 0.0, 0.0, None).unwrap();
+        let mut two_lanes = Road::from_layout(&format!("{}\n{}\n", lane, blocked_lane), &vehicles, 0.0, 0.0, None).unwrap();
+        for _ in 0..250 {
+            single_lane.round();
+            two_lanes.round();
+            let cars = |road: &Road| road.iter_cars().map(|(id, _, cell_i, car)| (id, cell_i, car.speed())).collect::<Vec<_>>();
+            assert_eq!(cars(&single_lane), cars(&two_lanes));
+        }
+        assert_eq!(single_lane.laps(), two_lanes.laps());
+    }
+
+    #[test]
+    fn occupancy_counted_when_cars_leave() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let mut road = Road::from_layout("oo_o___o__#____oo_o__o__o__5__oo_\noooooo___x__oo___o_o____oo___o___\n", &vehicles, 0.3, 0.5, None).unwrap();
+        let mut occupied_rounds = vec![vec![0; 33]; 2];
+        for _ in 0..100 {
+            road.round();
+            for (_, lane, cell_i, _) in road.iter_cars() {
+                occupied_rounds[lane][cell_i] += 1;
+            }
+            for (lane, cells) in road.cells().iter().enumerate() {
+                for (cell, occupied) in cells.iter().zip(&occupied_rounds[lane]) {
+                    assert_eq!(cell.occupied_rounds(road.rounds()), *occupied);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_result() {
+        let run = || run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.2)".to_string(), "(3, 2, 0.05)".to_string()],
+            dilly_dally_probability: 0.3,
+            stay_in_lane_probability: 0.3,
+            monitor: vec!["(0,0)".to_string(), "(1,0)".to_string(), "(2,0)".to_string()],
+            flow_map: true,
+            seed: Some(42),
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        let mut first = serde_json::to_value(run()).unwrap();
+        let mut second = serde_json::to_value(run()).unwrap();
+        first["runtime_s"] = 0.into();
+        second["runtime_s"] = 0.into();
+        assert_eq!(first, second);
+    }
+
+    // -- multilane extension --
+
+    #[test]
+    fn three_cars_three_lanes_no_switches() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            lanes: 3,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 1.0,
+            monitor: vec!["(0,0)".to_string(), "(1,0)".to_string(), "(2,0)".to_string()],
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.cars, 3);
+        assert!(result.average_speed_kilometers_per_hour <= 108.0);
+        assert!(result.average_speed_kilometers_per_hour >= 50.0);
+        assert!(result.average_accelerations_n_per_car_per_round >= 0.5);
+        assert_eq!(result.stay_in_lane_probability, 1.0);
+        assert_eq!(result.near_misses, 0);
+    }
+
+    #[test]
+    fn lane_changes_cause_near_misses() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            near_miss_braking: 1,
+            block: vec!["(1,50-60)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // Cars in the middle lane have to merge in front of the cars in the outer lanes.
+        assert!(result.near_misses > 0);
+        assert!(result.near_misses_per_lane_change > 0.0);
+        assert!(result.near_misses_per_lane_change <= 1.0);
+        assert_eq!(result.mandatory_lane_changes, 0);
+    }
+
+    #[test]
+    fn blocked_lanes_force_mandatory_lane_changes() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            stay_in_lane_probability: 0.5,
+            monitor: vec![],
+            near_miss_braking: 1,
+            mandatory_lane_changes: true,
+            block: vec!["(1,50-60)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+
+        println!("{:?}", result);
+
+        // Cars in the middle lane have to leave it before the blocked cells.
+        assert!(result.mandatory_lane_changes > 0);
+    }
+
+    #[test]
+    fn blockage_look_ahead() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            stay_in_lane_probability: 0.5,
+            monitor: vec![],
+            near_miss_braking: 1,
+            blockage_look_ahead: Some(30),
+            block: vec!["(1,50-60)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+
+        println!("{:?}", result);
+
+        // Cars in the middle lane start leaving it up to 30 cells before the blocked cells.
+        assert!(result.mandatory_lane_changes > 0);
+    }
+
+    #[test]
+    fn turn_signals_open_gaps() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            turn_signals: true,
+            block: vec!["(1,50-60)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // Cars in the middle lane announce merging in front of the cars in the outer lanes, which
+        // hold back for them.
+        assert!(result.yields > 0);
+    }
+
+    #[test]
+    fn courteous_cars_let_blocked_cars_merge() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            courtesy_probability: 1.0,
+            block: vec!["(1,50-60)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+
+        println!("{:?}", result);
+
+        // Cars in the outer lanes always let the cars in the blocked middle lane merge.
+        assert!(result.yields > 0);
+    }
+
+    #[test]
+    fn density_sweep() {
+        let points = fundamental_diagram::sweep(&SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            ..SimulationConfig::default()
+        }, 3);
+
+        println!("{:?}", points);
+
+        let densities: Vec<f64> = points.iter().map(|point| point.density).collect();
+        assert_eq!(densities, vec![0.25, 0.5, 0.75]);
+        // Traffic gets slower the denser it is.
+        assert!(points[0].speed_kilometers_per_hour > points[1].speed_kilometers_per_hour);
+        assert!(points[1].speed_kilometers_per_hour > points[2].speed_kilometers_per_hour);
+        assert!(points.iter().all(|point| point.flow_cars_per_minute_per_lane > 0.0));
+
+        #[cfg(feature = "plot")]
+        {
+            let path = std::env::temp_dir().join("traffic-density_sweep.svg");
+            fundamental_diagram::render(&points, &path).unwrap();
+            assert!(std::fs::read_to_string(&path).unwrap().starts_with("<svg"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "plot")]
+    fn time_series_charts() {
+        run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            monitor: vec!["(0,10)".to_string(), "(2,90)".to_string()],
+            out_path: std::env::temp_dir().join("traffic-time_series_charts.png"),
+            charts: true,
+            ..SimulationConfig::default()
+        });
+
+        for chart in ["traffic-time_series_charts-speed.svg", "traffic-time_series_charts-flow.svg"] {
+            let svg = std::fs::read_to_string(std::env::temp_dir().join(chart)).unwrap();
+            assert!(svg.starts_with("<svg"));
+            assert!(svg.contains("<polyline"));
+        }
+    }
+
+    #[test]
+    fn html_report() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            monitor: vec!["(0,10)".to_string()],
+            image: cfg!(feature = "image"),
+            out_path: std::env::temp_dir().join("traffic-html_report.png"),
+            report: Some(std::env::temp_dir().join("traffic-html_report.html")),
+            ..SimulationConfig::default()
+        });
+
+        let report = std::fs::read_to_string(std::env::temp_dir().join("traffic-html_report.html")).unwrap();
+        assert!(report.starts_with("<!DOCTYPE html>"));
+        assert!(report.contains(&format!("<tr><td>near_misses</td><td>{}</td></tr>", result.near_misses)));
+        assert!(report.contains("<h2>Warnings</h2>\n<p>None</p>"));
+        assert!(report.contains("<pre>rounds: 200\n"));
+        #[cfg(feature = "image")]
+        assert!(report.contains("<img src=\"data:image/png;base64,iVBORw0KGgo"));
+    }
+
+    #[test]
+    fn batch_runs_every_combination() {
+        let manifest = std::env::temp_dir().join("traffic-batch-manifest.json");
+        std::fs::write(&manifest, r#"{"lanes": [1, 2], "dilly_dally_probability": [0.0, 0.2], "rounds": 20}"#).unwrap();
+        let config = SimulationConfig { length: 100, vehicles: vec!["(5, 1, 0.2)".to_string()], ..SimulationConfig::default() };
+        let runs = batch::run(&config, &manifest).unwrap();
+        let settings: Vec<(f32, u32, u32)> = runs.iter().map(|run| (run.result.dilly_dally_probability, run.result.lanes, run.result.rounds)).collect();
+        assert_eq!(settings, vec![(0.0, 1, 20), (0.0, 2, 20), (0.2, 1, 20), (0.2, 2, 20)]);
+        assert_eq!(runs[3].parameters["lanes"], 2);
+
+        std::fs::write(&manifest, r#"{"lane_count": [1, 2]}"#).unwrap();
+        assert!(batch::run(&config, &manifest).is_err());
+        std::fs::remove_file(manifest).unwrap();
+    }
+
+    #[test]
+    fn replications_report_confidence_intervals() {
+        let config = SimulationConfig { rounds: 100, length: 100, vehicles: vec!["(5, 1, 0.2)".to_string()], seed: Some(7), ..SimulationConfig::default() };
+        let path = std::env::temp_dir().join("traffic-replications.jsonl");
+        let replications = replication::run(&config, 5, Some(&path)).unwrap();
+        assert_eq!(replications.seeds, vec![7, 8, 9, 10, 11]);
+        let speed = &replications.metrics["average_speed_kilometers_per_hour"];
+        assert_eq!(speed.n, 5);
+        assert!(speed.sd > 0.0);
+        assert!(speed.ci95_low < speed.mean && speed.mean < speed.ci95_high);
+        assert!((speed.ci95_high - speed.mean - 2.776 * speed.sd / 5f64.sqrt()).abs() < 1e-9);
+        // The runs are those of the seeds, so single runs can be reproduced.
+        let mean: f64 = (7..12).map(|seed| run_sim(SimulationConfig { seed: Some(seed), ..config.clone() }).average_speed_kilometers_per_hour).sum::<f64>() / 5.0;
+        assert!((speed.mean - mean).abs() < 1e-9);
+        assert_eq!(replications.metrics["monitor_cells_flow_cars_per_minute.0"].n, 5);
+        assert_eq!(replications.metrics["lanes"], replication::MetricStatistics { n: 5, mean: 1.0, sd: 0.0, ci95_low: 1.0, ci95_high: 1.0 });
+        assert!(!replications.metrics.contains_key("seed"));
+        // Every run is kept in full, one per line.
+        let raw_results = std::fs::read_to_string(&path).unwrap();
+        let runs: Vec<serde_json::Value> = raw_results.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(runs.len(), 5);
+        assert_eq!(runs.iter().map(|run| run["seed"].as_u64().unwrap()).collect::<Vec<_>>(), replications.seeds);
+        assert_eq!(runs[0]["lanes"], 1);
+    }
+
+    #[test]
+    fn event_log_replays_run() {
+        let dir = std::env::temp_dir();
+        let (log, recorded, replayed) = (dir.join("traffic-events.bin"), dir.join("traffic-events-run.txt"), dir.join("traffic-events-replay.txt"));
+        let config = SimulationConfig {
+            rounds: 60,
+            warmup: 0,
+            lanes: 2,
+            length: 80,
+            vehicles: vec!["(5, 1, 0.2)".to_string(), "(3, 2, 0.1)".to_string()],
+            automated_share: 0.0,
+            traffic_lights: vec!["(*, 40, cycle=20, green=10)".to_string()],
+            seed: Some(7),
+            ..SimulationConfig::default()
+        };
+        run_sim(SimulationConfig { event_log: Some(log.clone()), ascii_out: Some(recorded.clone()), ..config.clone() });
+        let events = event_log::EventLog::read(&log).unwrap();
+        assert_eq!((events.lanes, events.length, events.rounds.len(), events.traffic_lights.len()), (2, 80, 61, 2));
+        assert!(events.rounds[1..].iter().flatten().any(|event| matches!(event, event_log::Event::LaneChange { .. })));
+
+        event_log::replay(&SimulationConfig { ascii_out: Some(replayed.clone()), ..SimulationConfig::default() }, &log).unwrap();
+        assert_eq!(std::fs::read_to_string(&recorded).unwrap(), std::fs::read_to_string(&replayed).unwrap());
+        for path in [log, recorded, replayed] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "wasm")]
+    fn wasm_simulation_steps() {
+        use crate::wasm::{Simulation, FREE, OCCUPIED};
+        assert!(Simulation::new(1, 100, vec!["(5, 1)".to_string()], 0.2, 0.2, None).is_err());
+        let mut simulation = Simulation::new(2, 100, vec!["(5, 1, 0.2)".to_string()], 0.2, 0.2, Some(3)).unwrap();
+        simulation.step(10);
+        assert_eq!((simulation.rounds(), simulation.cells()), (10, 200));
+        let occupancy = simulation.occupancy();
+        assert_eq!(occupancy.iter().filter(|cell| **cell == OCCUPIED).count(), 40);
+        assert!(occupancy.iter().zip(simulation.speeds()).all(|(cell, speed)| *cell != FREE || speed == 0));
+        let road_speeds: Vec<u8> = simulation.road().cells().iter().flatten().map(|cell| cell.car().map_or(0, |car| car.speed())).collect();
+        assert_eq!(simulation.speeds(), road_speeds);
+    }
+
+    #[test]
+    #[cfg(feature = "cabi")]
+    fn c_api_steps() {
+        use crate::cabi::*;
+        unsafe {
+            assert!(sim_create(1, 100, c"(5, 1)".as_ptr(), 0.2, 0.2, std::ptr::null()).is_null());
+            let seed = 3;
+            let sim = sim_create(2, 100, c"(5, 1, 0.2); (3, 2, 0.1)".as_ptr(), 0.2, 0.2, &seed);
+            sim_step(sim, 10);
+            assert_eq!((sim_lanes(sim), sim_length(sim), sim_rounds(sim)), (2, 100, 10));
+            let (mut occupancy, mut speeds) = (vec![SIM_FREE; 200], vec![0; 200]);
+            assert_eq!(sim_get_cells(sim, occupancy.as_mut_ptr(), speeds.as_mut_ptr(), 200), 200);
+            assert_eq!(occupancy.iter().filter(|cell| **cell == SIM_OCCUPIED).count(), 60);
+            assert!(occupancy.iter().zip(&speeds).all(|(cell, speed)| *cell == SIM_OCCUPIED || *speed == 0));
+            assert_eq!(sim_get_cells(sim, std::ptr::null_mut(), std::ptr::null_mut(), 0), 200);
+            sim_destroy(sim);
+        }
+    }
+
+    #[test]
+    fn default_config_runs() {
+        let config = SimulationConfig::default();
+        assert_eq!((config.rounds, config.lanes, config.length, config.update_order), (4096, 1, 1000, UpdateOrder::Sequential));
+        let result = run_sim(SimulationConfig {
+            rounds: 50,
+            warmup: 0,
+            lanes: 2,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.2)".to_string()],
+            automated_share: 0.0,
+            ..config
+        });
+        assert_eq!((result.rounds, result.lanes, result.length, result.cars), (50, 2, 100, 40));
+    }
+
+    #[test]
+    fn smoke_scenarios_play_out_as_expected() {
+        assert_eq!(Cli::parse_from(["traffic", "smoke"]).command, Some(Command::Smoke));
+        let checks = smoke::run();
+        assert_eq!(checks.len(), 4);
+        for check in checks {
+            assert!(check.passed, "{}: {:?}", check.scenario, check.deviations);
+        }
+    }
+
+    #[test]
+    fn validate_scenario() {
+        let report = validation::validate(&SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string(), "(5, 1)".to_string()],
+            dilly_dally_probability: 1.5,
+            stay_in_lane_probability: 0.0,
+            monitor: vec!["(1,60)".to_string(), "(5,10)".to_string()],
+            near_miss_braking: 1,
+            block: vec!["(1,50-60)".to_string(), "(1,55-65)".to_string(), "(2,90-110)".to_string()],
+            traffic_lights: vec!["(1,52)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        assert!(!report.is_valid());
+        assert_eq!(report.errors, vec![
+            "`(5, 1)` of `vehicles` cannot be parsed.",
+            "`dilly_dally_probability` must be a number between 0 and 1, but is 1.5.",
+            "The block of cells 55..65 in lane 1 overlaps another block.",
+            "The block of cells 90..110 in lane 2 is not on the road.",
+            "The traffic light (1, 52) is on a blocked cell.",
+            "The monitored cell (5, 10) is not on the road.",
+        ]);
+        assert_eq!(report.warnings, vec!["The monitored cell (1, 60) is blocked, so no car will pass it."]);
+
+        let valid = validation::validate(&Cli::parse_from(["traffic", "--lanes", "2", "--block", "(1, 10-20)", "--traffic-lights", "(*, 30)"]).scenario);
+        assert_eq!(valid, validation::ValidationReport::default());
+
+        let cli = Cli::parse_from(["traffic", "--lanes", "2", "validate"]);
+        assert_eq!((cli.command, cli.scenario.lanes), (Some(Command::Validate), 2));
+        let report = validation::validate_command_line(&Cli::parse_from(["traffic", "--replications", "1", "--intersection", "(0.2, 0.6, 0.2)"]));
+        assert_eq!(report.errors, vec![
+            "At least two replications are needed to estimate the spread of the metrics, but there are 1.",
+            "Replications cannot be combined with a batch, a density sweep or an intersection.",
+        ]);
+    }
+
+    #[test]
+    fn selftest_reproduces_seeded_run() {
+        let cli = Cli::parse_from(["traffic", "--rounds", "50", "--lanes", "2", "--length", "100", "--seed", "3", "selftest"]);
+        assert_eq!(cli.command, Some(Command::Selftest));
+        let report = selftest::run(&cli.scenario);
+        assert!(report.passed);
+        assert_eq!((report.seed, report.rounds), (3, 51));
+        assert_eq!(report.checks.len(), 2);
+        assert_ne!(report.checks[0].threads, report.checks[1].threads);
+        assert!(report.checks.iter().all(|check| check.compared_rounds == 51 && check.first_diverging_round.is_none()));
+    }
+
+    #[test]
+    fn exact_spawning() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            lanes: 3,
+            length: 6,
+            vehicles: vec!["(5, 1, 0.25)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 1.0,
+            monitor: vec!["(0,0)".to_string(), "(1,0)".to_string(), "(2,0)".to_string()],
+            spawn_mode: SpawnMode::Exact,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // Spawning lane by lane would put round(0.25 * 6) = 2 cars into each lane.
+        assert_eq!(result.cars, 5);
+        assert_eq!(result.initial_cars_per_lane.iter().sum::<u32>(), 5);
+    }
+
+    #[test]
+    fn total_cars() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.2)".to_string(), "(3, 2, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 1.0,
+            monitor: vec!["(0,0)".to_string(), "(1,0)".to_string(), "(2,0)".to_string()],
+            cars: vec!["50".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // 50 cars split 0.2 : 0.1 are 33.3 and 16.7 cars. The leftover car goes to the larger
+        // remainder.
+        assert_eq!(result.cars, 50);
+        assert_eq!(result.vehicle_types[0].cars, 33);
+        assert_eq!(result.vehicle_types[1].cars, 17);
+    }
+
+    #[test]
+    fn lane_densities() {
+        let result = run_sim(SimulationConfig {
+            rounds: 1,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.2)".to_string(), "(3, 2, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 1.0,
+            monitor: vec!["(0,0)".to_string(), "(1,0)".to_string(), "(2,0)".to_string()],
+            lane_densities: vec!["0.3".to_string(), "0.2".to_string(), "(0.05, 0.05)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.initial_cars_per_lane, vec![30, 20, 10]);
+        assert_eq!(result.vehicle_types[0].initial_cars_per_lane, vec![20, 13, 5]);
+        assert_eq!(result.vehicle_types[1].initial_cars_per_lane, vec![10, 7, 5]);
+    }
+
+    #[test]
+    fn lane_densities_of_vehicle_types() {
+        let truck = "(4, 6, 0.2:0:0.1, lanes=0:2)".parse::<VehicleBlueprint>().unwrap();
+        assert_eq!((truck.lane_densities(), truck.traffic_density()), (Some(&vec![0.2, 0.0, 0.1]), 0.1));
+        assert!("(4, 6, 0.2:0:0, 1:1:1)".parse::<VehicleBlueprint>().is_err());
+        assert!("(4, 6, 0.2:2)".parse::<VehicleBlueprint>().is_err());
+
+        let vehicles = vec![truck, "(5, 1, 0.1)".parse().unwrap()];
+        let road = Road::new(3, 100, &vehicles, 0.0, 0.0, &[], &[], Some(2));
+        let cars = |vehicle_type: usize, lane_i: usize| road.iter_cars().filter(|(_, lane, _, car)| *lane == lane_i && car.vehicle_type() == vehicle_type).count();
+        assert_eq!([cars(0, 0), cars(0, 1), cars(0, 2)], [20, 0, 10]);
+        assert_eq!([cars(1, 0), cars(1, 1), cars(1, 2)], [10, 10, 10]);
+    }
+
+    #[test]
+    fn lane_weights() {
+        let result = run_sim(SimulationConfig {
+            rounds: 1,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(4, 6, 0.1, 0:0:1)".to_string(), "(9, 1, 0.05, regular, 1:0:0)".to_string(), "(5, 2, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 1.0,
+            monitor: vec!["(0,0)".to_string(), "(1,0)".to_string(), "(2,0)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // All trucks start in the right lane and all sports cars in the left lane.
+        assert_eq!(result.vehicle_types[0].initial_cars_per_lane, vec![0, 0, 30]);
+        assert_eq!(result.vehicle_types[1].initial_cars_per_lane, vec![15, 0, 0]);
+        assert_eq!(result.vehicle_types[2].initial_cars_per_lane, vec![10, 10, 10]);
+    }
+
+    #[test]
+    fn slow_all_moving_over() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            lanes: 10,
+            length: 20,
+            vehicles: vec!["(2, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.1,
+            stay_in_lane_probability: 0.0,
+            monitor: {
+                let mut mon = Vec::new();
+                for lane in 4..=8 {
+                    mon.push(format!("({},0)", lane));
+                }
+                mon
+            },
+            seed: Some(1),
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        let mut last = 0.0;
+        for val in result.monitor_cells_flow_cars_per_minute {
+            assert!(last <= val);
+            last = val;
+        }
+    }
+
+    #[test]
+    fn all_update_orders() {
+        for update_order in [UpdateOrder::Sequential, UpdateOrder::RandomLanes, UpdateOrder::RandomLanesPerCell, UpdateOrder::AlternatingLanes, UpdateOrder::Parallel] {
+            let result = run_sim(SimulationConfig {
+                rounds: 300,
+                lanes: 3,
+                length: 200,
+                vehicles: vec!["(5, 1, 0.2)".to_string(), "(3, 2, 0.05)".to_string()],
+                block: vec!["(1,50-60)".to_string()],
+                traffic_lights: vec!["(0,100)".to_string()],
+                update_order,
+                ..SimulationConfig::default()
+            });
+
+            println!("{:?}: {:?}", update_order, result);
+
+            assert_eq!(result.update_order, update_order);
+            assert_eq!(result.vehicle_types.iter().map(|vehicle_type| vehicle_type.cars).sum::<u64>(), result.cars as u64);
+            assert!(result.average_speed_kilometers_per_hour > 0.0);
+        }
+    }
+
+    // -- multilane extension with blockages --
+
+    #[test]
+    fn scheduled_block() {
+        let block: CellLocationRange = "(0, 10-12, from=3, until=5)".parse().unwrap();
+        assert_eq!(block, CellLocationRange::new(0, 10, 12).with_rounds(Some(3), Some(5)));
+        assert!("(0, 10-12, from=5, until=5)".parse::<CellLocationRange>().is_err());
+        assert!("(0, 10-12, at=5)".parse::<CellLocationRange>().is_err());
+        let mut road = Road::new(1, 20, &[], 0.0, 0.0, &[block, "(0, 15, until=2)".parse().unwrap()], &[], None);
+        let blocked = |road: &Road| (road.cells()[0][11].blocked(), road.cells()[0][15].blocked());
+        assert_eq!(blocked(&road), (false, true));
+        for expected in [(false, true), (false, false), (true, false), (true, false), (false, false)] {
+            road.round();
+            assert_eq!(blocked(&road), expected);
+        }
+    }
+
+    #[test]
+    fn scheduled_block_overlapping_permanent_block() {
+        let block: Vec<CellLocationRange> = ["(0, 10-12)", "(0, 11-14, from=3, until=5)", "(0, 16)", "(0, 15-17, until=2)"]
+            .iter()
+            .map(|block| block.parse().unwrap())
+            .collect();
+        let mut road = Road::new(1, 20, &[], 0.0, 0.0, &block, &[], None);
+        let blocked = |road: &Road| [11, 13, 15, 16].map(|cell_i| road.cells()[0][cell_i].blocked());
+        assert_eq!(blocked(&road), [true, false, true, true]);
+        // The permanently blocked cells stay blocked before, while and after the scheduled blocks
+        // are in place.
+        for expected in [[true, false, true, true], [true, false, false, true], [true, true, false, true], [true, true, false, true], [true, false, false, true]] {
+            road.round();
+            assert_eq!(blocked(&road), expected);
+        }
+    }
+
+    #[test]
+    fn random_incidents() {
+        let mut road = Road::new(2, 50, &[], 0.0, 0.0, &[], &[], Some(4)).with_incidents(1.0, 5);
+        for _ in 0..20 {
+            road.round();
+        }
+        let incidents = road.incidents();
+        assert!(incidents.len() > 10 && incidents.len() <= 20);
+        for incident in incidents {
+            assert!(incident.round >= 1 && incident.rounds >= 1);
+            let ongoing = incident.round + incident.rounds > 20;
+            assert_eq!(road.cells()[incident.lane][incident.index].blocked(), ongoing);
+        }
+    }
+
+    #[test]
+    fn rubbernecking_slows_cars_passing_block() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let layout = format!("{}{}{}\no{}\n", "_".repeat(20), "x".repeat(10), "_".repeat(20), "_".repeat(49));
+        let mut road = Road::from_layout(&layout, &vehicles, 0.0, 1.0, None).unwrap().with_rubbernecking(Some(1), None);
+        assert!(road.cells()[1][20].rubbernecking() && road.cells()[1][29].rubbernecking());
+        assert!(!road.cells()[1][19].rubbernecking() && !road.cells()[1][30].rubbernecking() && !road.cells()[0][25].rubbernecking());
+        let mut index = 0;
+        for _ in 0..40 {
+            road.round();
+            let (_, _, new_index, car) = road.iter_cars().next().unwrap();
+            if (20..30).contains(&index) {
+                assert_eq!(car.speed(), 1);
+            }
+            index = new_index;
+        }
+    }
+
+    #[test]
+    fn weather_schedule() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let changes = vec!["(10, snow)".parse().unwrap(), "(20, dry)".parse().unwrap()];
+        let mut road = Road::new(1, 100, &vehicles, 0.2, 1.0, &[], &[], Some(0))
+            .with_weather_schedule(WeatherSchedule::new(Weather::Rain, changes));
+        let mut max_speeds = vec![];
+        for _ in 0..30 {
+            road.round();
+            let (_, _, _, car) = road.iter_cars().next().unwrap();
+            max_speeds.push(car.max_speed());
+        }
+        assert_eq!((max_speeds[5], max_speeds[15], max_speeds[25]), (4, 3, 5));
+    }
+
+    #[test]
+    fn speed_limit_zone() {
+        assert!("(0, 5, 2, from=3)".parse::<SpeedLimitZone>().is_err());
+        assert!("(0, 5)".parse::<SpeedLimitZone>().is_err());
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let zones = ["(0, 30-60, 2)".parse().unwrap(), "(0, 40, 1)".parse().unwrap()];
+        let layout = format!("o{}\n", "_".repeat(99));
+        let mut road = Road::from_layout(&layout, &vehicles, 0.0, 1.0, None).unwrap().with_speed_limits(&zones);
+        assert_eq!((road.cells()[0][39].speed_limit(), road.cells()[0][40].speed_limit()), (Some(2), Some(1)));
+        let mut index = 0;
+        for _ in 0..100 {
+            road.round();
+            let (_, _, new_index, car) = road.iter_cars().next().unwrap();
+            if (30..60).contains(&index) {
+                assert!(car.speed() <= if index == 40 { 1 } else { 2 });
+            }
+            index = new_index;
+        }
+    }
+
+    #[test]
+    fn keep_right_lane_rules() {
+        // The right lane opens up just before the left lane is blocked, but not enough to drive on
+        // at full speed.
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let layout = format!("o{}x{}\n{}{}{}\n", "_".repeat(33), "_".repeat(5), "x".repeat(30), "_".repeat(5), "x".repeat(5));
+        let lanes = |lane_rules: LaneRules| {
+            let mut road = Road::from_layout(&layout, &vehicles, 0.0, 0.0, None).unwrap().with_lane_rules(lane_rules);
+            (0..12).map(|_| {
+                road.round();
+                road.iter_cars().next().unwrap().1
+            }).collect::<Vec<usize>>()
+        };
+        // Symmetric rules let the car switch to the right for more space, while the keep-right
+        // rules only let it return once it has slowed down in front of the block.
+        assert_eq!(lanes(LaneRules::Symmetric)[8], 1);
+        let keep_right = lanes(LaneRules::KeepRight);
+        assert_eq!((keep_right[8], keep_right[11]), (0, 1));
+    }
+
+    #[test]
+    fn lane_restrictions() {
+        let truck = "(3, 2, 0.2, 1:1:2, lanes=1:2, reaction=1)".parse::<VehicleBlueprint>().unwrap();
+        assert_eq!(truck.allowed_lanes(), Some(&vec![1, 2]));
+        let vehicles = vec!["(5, 1, 0.1)".parse().unwrap(), "(3, 2, 0.2, lanes=1:2)".parse().unwrap()];
+        let mut road = Road::new(3, 200, &vehicles, 0.1, 0.0, &[], &[], Some(3));
+        for _ in 0..100 {
+            assert!(road.iter_cars().all(|(_, lane, _, car)| lane > 0 || car.vehicle_type() == 0));
+            road.round();
+        }
+        assert!(road.lane_changes() > 0);
+        road = road.with_car_counts(&[0, 100]);
+        assert!(road.iter_cars().all(|(_, lane, _, _)| lane > 0));
+    }
+
+    #[test]
+    fn reserved_lanes() {
+        let vehicles = vec!["(5, 1, 0.1)".parse().unwrap(), "(4, 2, 0.05, bus)".parse().unwrap(), "(5, 1, 0.05, hov)".parse().unwrap()];
+        let zones = vec!["(2, bus)".parse().unwrap(), "(0, 100-150, hov)".parse().unwrap()];
+        for update_order in [UpdateOrder::Sequential, UpdateOrder::Parallel] {
+            let mut road = Road::new(3, 300, &vehicles, 0.1, 0.0, &[], &[], Some(5))
+                .with_update_order(update_order)
+                .with_reserved_zones(&zones);
+            assert_eq!((road.cells()[2][0].reserved_for(), road.cells()[0][149].reserved_for()), (Some(VehicleClass::Bus), Some(VehicleClass::Hov)));
+            let mut hov_in_zone = false;
+            for _ in 0..200 {
+                assert!(road.iter_cars().all(|(_, lane, cell, car)| !road.cells()[lane][cell].reserved_against(car.vehicle_class())));
+                hov_in_zone |= road.iter_cars().any(|(_, lane, cell, car)| lane == 0 && (100..150).contains(&cell) && car.vehicle_class() == VehicleClass::Hov);
+                road.round();
+            }
+            assert!(hov_in_zone);
+        }
+    }
+
+    #[test]
+    fn automated_vehicles() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.0, automated)".parse().unwrap(), "(5, 1, 0.0)".parse().unwrap()];
+        let mut road = Road::new(1, 100, &vehicles, 1.0, 0.0, &[], &[], Some(1));
+        road.restore(0, vec![(0, 10, Car::new(0, 0, &vehicles[0])), (0, 9, Car::new(1, 0, &vehicles[0])), (0, 50, Car::new(2, 1, &vehicles[1]))], &[]);
+        road.round();
+        // The automated vehicles drive off at full speed as a platoon, the dilly-dallying driver does not.
+        let positions: Vec<(usize, u8)> = (0..3).map(|id| road.find_car(id).map(|(_, cell_i, car)| (cell_i, car.speed())).unwrap()).collect();
+        assert_eq!(positions, vec![(15, 5), (14, 5), (50, 0)]);
+
+        let args = Cli::parse_from(["traffic", "--vehicles", "(5, 1, 0.2); (7, 1, 0.1, emergency)", "--automated-share", "0.25"]).scenario;
+        let vehicles: Vec<(f32, VehicleClass)> = args.vehicles().iter().map(|vb| (vb.traffic_density(), vb.vehicle_class())).collect();
+        assert_eq!(vehicles, vec![(0.15, VehicleClass::Regular), (0.1, VehicleClass::Emergency), (0.05, VehicleClass::Automated)]);
+    }
+
+    #[test]
+    fn warmup_is_left_out_of_the_metrics() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.02)".parse().unwrap()];
+        let mut road = Road::new(1, 50, &vehicles, 0.0, 0.0, &[], &[], Some(1));
+        for _ in 0..10 {
+            road.round();
+        }
+        assert!(road.average_speed() < 5.0);
+        road.reset_records();
+        for _ in 0..100 {
+            road.round();
+        }
+        // Having accelerated during the warm-up, the car drives at full speed for all of the measured rounds.
+        assert_eq!(road.measured_rounds(), 100);
+        assert_eq!(road.average_speed(), 5.0);
+        assert_eq!(road.cells()[0][0].flow(road.measured_rounds()), 0.1);
+
+        assert!(!validation::validate(&Cli::parse_from(["traffic", "--rounds", "100", "--warmup", "100"]).scenario).is_valid());
+    }
+
+    #[test]
+    fn lane_change_statistics() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.2)".parse().unwrap(), "(3, 1, 0.1, bus)".parse().unwrap()];
+        for update_order in [UpdateOrder::Sequential, UpdateOrder::Parallel] {
+            let mut road = Road::new(3, 200, &vehicles, 0.2, 0.0, &[], &[], Some(5)).with_update_order(update_order);
+            for _ in 0..100 {
+                road.round();
+            }
+            let lane_changes = LaneChangeStatistics::new(&road);
+            assert!(lane_changes.left > 0 && lane_changes.right > 0);
+            assert_eq!(lane_changes.left + lane_changes.right, road.lane_changes());
+            // Nobody leaves the leftmost lane to the left or the rightmost lane to the right.
+            assert_eq!((lane_changes.by_lane[0].left, lane_changes.by_lane[2].right), (0, 0));
+            assert_eq!(lane_changes.by_lane.iter().map(LaneChanges::total).sum::<u64>(), road.lane_changes());
+            assert_eq!(lane_changes.by_vehicle_class.values().map(LaneChanges::total).sum::<u64>(), road.lane_changes());
+            assert_eq!(lane_changes.n_per_car_per_round, road.lane_changes() as f64 / road.car_rounds() as f64);
+            assert_eq!(lane_changes.n_per_car_per_round_by_vehicle_class.keys().copied().collect::<Vec<_>>(), vec![VehicleClass::Regular, VehicleClass::Bus]);
+        }
+    }
+
+    #[test]
+    fn speed_histogram() {
+        let result = run_sim(Cli::parse_from(["traffic", "--rounds", "300", "--length", "200", "--vehicles", "(5, 1, 0.3)", "--speed-histogram", "--seed", "2"]).scenario);
+        let histogram = result.speed_histogram.unwrap();
+        assert_eq!(histogram.len(), 6);
+        assert_eq!(histogram.iter().map(|bin| bin.car_rounds).sum::<u64>(), result.cars as u64 * 300);
+        assert!((histogram.iter().map(|bin| bin.fraction).sum::<f64>() - 1.0).abs() < 1e-9);
+        // Cars stand in jams as well as drive freely.
+        assert!(histogram[0].fraction > 0.0 && histogram[5].fraction > 0.0);
+        let mean_speed: f64 = histogram.iter().map(|bin| bin.speed_kilometers_per_hour * bin.fraction).sum();
+        assert!((mean_speed - result.average_speed_kilometers_per_hour).abs() < 1e-9);
+    }
+
+    #[test]
+    fn detailed_per_round_metrics() {
+        let result = run_sim(Cli::parse_from(["traffic", "--rounds", "200", "--length", "100", "--vehicles", "(5, 1, 0.2)", "--monitor", "(0, 50)", "--detailed", "--seed", "3"]).scenario);
+        let speeds = result.average_speed_kilometers_per_hour_per_round.unwrap();
+        let densities = result.density_cars_per_km_per_lane_per_round.unwrap();
+        let flows = result.monitor_cells_flow_cars_per_minute_per_round.unwrap();
+        assert_eq!((speeds.len(), densities.len(), flows.len()), (200, 200, 200));
+        // No car enters or leaves the closed road.
+        assert!(densities.iter().all(|density| *density == densities[0]));
+        let mean_speed = speeds.iter().sum::<f64>() / 200.0;
+        assert!((mean_speed - result.average_speed_kilometers_per_hour).abs() < 1e-9);
+        let mean_flow = flows.iter().map(|flows| flows[0]).sum::<f64>() / 200.0;
+        assert!((mean_flow - result.monitor_cells_flow_cars_per_minute[0]).abs() < 1e-9);
+
+        let result = run_sim(Cli::parse_from(["traffic", "--rounds", "10"]).scenario);
+        assert!(result.average_speed_kilometers_per_hour_per_round.is_none());
+    }
+
+    #[test]
+    fn safety_surrogate_metrics() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.0)".parse().unwrap()];
+        let mut road = Road::new(1, 30, &vehicles, 0.0, 0.0, &[CellLocationRange::new(0, 20, 21)], &[], Some(1));
+        road.restore(0, vec![(0, 19, Car::new(0, 0, &vehicles[0])), (0, 0, Car::new(1, 0, &vehicles[0]))], &[]);
+        let mut monitor = SafetyMonitor::new(1.5, 2);
+        for _ in 0..10 {
+            road.round();
+            monitor.take_snapshot(&road);
+        }
+        // The second car speeds up towards the first one, which waits at the block, until it has to
+        // brake from 5 to 3 cells per round with a TTC of 0.6s and then stop right behind it.
+        let metrics = monitor.metrics();
+        assert_eq!(metrics.time_exposed_ttc_s, 2.0);
+        assert_eq!(metrics.time_exposed_ttc_s_per_car_per_round, 0.1);
+        assert_eq!(metrics.min_ttc_s, 0.0);
+        assert_eq!(metrics.hard_brakings, 2);
+    }
+
+    #[test]
+    fn section_travel_times() {
+        let args = Cli::parse_from(["traffic", "--rounds", "200", "--warmup", "20", "--length", "100", "--vehicles", "(5, 1, 0.01)", "--dilly-dally-probability", "0", "--sections", "(10, 60); (90, 10)", "--seed", "1"]).scenario;
+        let sections = run_sim(args.clone()).sections.unwrap();
+        // The car drives through at full speed, so it takes a fifth of a round per cell.
+        assert_eq!((sections[0].start, sections[0].end), (10, 60));
+        assert_eq!(sections[0].cars, 9);
+        assert!((sections[0].mean_travel_time_s - 10.0).abs() < 1e-9 && (sections[0].percentile_95_travel_time_s - 10.0).abs() < 1e-9);
+        assert!(sections[0].mean_delay_s.abs() < 1e-9);
+        // The second section wraps around the end of the road.
+        assert!((sections[1].median_travel_time_s - 4.0).abs() < 1e-9 && sections[1].median_delay_s.abs() < 1e-9);
+
+        assert!("(10, 10)".parse::<Section>().is_err());
+        let report = validation::validate(&SimulationConfig { sections: vec!["(90, 100)".to_string()], ..args });
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn queues_behind_obstacles() {
+        let args = Cli::parse_from(["traffic", "--rounds", "200", "--length", "50", "--vehicles", "(5, 1, 0.2)", "--dilly-dally-probability", "0", "--block", "(0, 40)", "--jam-rounds", "3", "--seed", "1"]).scenario;
+        let result = run_sim(args.clone());
+        let queues = result.queues.unwrap();
+        // All cars end up waiting behind the block.
+        assert_eq!(queues.max_jammed_cars, result.cars);
+        assert_eq!(queues.max_queue_cars, result.cars);
+        assert_eq!(queues.max_queue_m, result.cars as f64 * CELL_M);
+        assert_eq!(queues.queues.len(), 1);
+        assert_eq!((queues.queues[0].lane, queues.queues[0].cell), (0, 40));
+        assert!(queues.queues[0].average_queue_cars > 0.0 && queues.queues[0].average_queue_cars < result.cars as f64);
+
+        let free = run_sim(SimulationConfig { block: vec![], ..args }).queues.unwrap();
+        assert!(free.queues.is_empty());
+        assert_eq!(free.max_queue_cars, 0);
+    }
+
+    #[test]
+    fn steady_state_stops_the_simulation_early() {
+        let args = Cli::parse_from(["traffic", "--rounds", "10000", "--length", "200", "--vehicles", "(5, 1, 0.1)", "--steady-state-window", "50", "--seed", "1"]).scenario;
+        let result = run_sim(args.clone());
+        let steady_state = result.steady_state.unwrap();
+        assert!(steady_state.reached);
+        assert!(result.rounds < 10000);
+        assert_eq!(steady_state.round, Some(result.rounds));
+
+        // Without a tolerance, a speed that keeps fluctuating never counts as steady.
+        let result = run_sim(SimulationConfig { rounds: 500, steady_state_tolerance: 0.0, ..args });
+        assert_eq!(result.rounds, 500);
+        assert_eq!(result.steady_state.unwrap(), SteadyState { reached: false, round: None, mean_speed_kilometers_per_hour: None });
+    }
+
+    #[test]
+    fn heterogeneous_drivers() {
+        assert!("uniform(0.5, 0.4)".parse::<ProbabilityDistribution>().is_err());
+        assert!("normal(0.2, -0.1)".parse::<ProbabilityDistribution>().is_err());
+        assert!("beta(1, 2)".parse::<ProbabilityDistribution>().is_err());
+        let driver_distributions = DriverDistributions {
+            dilly_dally: Some("uniform(0.1, 0.3)".parse().unwrap()),
+            stay_in_lane: Some("normal(0.5, 1.0)".parse().unwrap()),
+        };
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.2)".parse().unwrap()];
+        let mut road = Road::new(2, 200, &vehicles, 0.0, 0.0, &[], &[], Some(3)).with_driver_distributions(driver_distributions);
+        road.add_car(0);
+        let dilly_dally_probabilities: Vec<f32> = road.iter_cars().map(|(_, _, _, car)| car.dilly_dally_probability().unwrap()).collect();
+        assert!(dilly_dally_probabilities.iter().all(|probability| (0.1..=0.3).contains(probability)));
+        assert!(dilly_dally_probabilities.iter().any(|probability| *probability != dilly_dally_probabilities[0]));
+        assert!(road.iter_cars().all(|(_, _, _, car)| car.stay_in_lane_probability().is_some_and(|probability| (0.0..=1.0).contains(&probability))));
+
+        // The drivers always dilly-dally although the road itself does not.
+        let mut road = Road::new(1, 100, &vehicles, 0.0, 0.0, &[], &[], Some(3))
+            .with_driver_distributions(DriverDistributions { dilly_dally: Some("uniform(1, 1)".parse().unwrap()), stay_in_lane: None });
+        for _ in 0..5 {
+            road.round();
+        }
+        assert!(road.iter_cars().all(|(_, _, _, car)| car.speed() == 0 && car.stay_in_lane_probability().is_none()));
+    }
+
+    #[test]
+    fn single_lane_full_blockage() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            block: vec!["(0,0)".to_string()],
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.cars, 1);
+        assert_eq!(result.monitor_cells_flow_cars_per_minute[0], 0.0);
+    }
+
+    #[test]
+    fn left_lane_full_blockage() {
+        // This test is the same as `one_car`, except that there is a second lane
+        // that is totally blocked.
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            lanes: 2,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            block: vec!["(0,0-10)".to_string()],
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.cars, 1);
+        assert_eq!(
+            result.average_speed_kilometers_per_hour,
+            (1+2+3+4+5+(10-5)*5) as f64 / 10.0 * (CELL_M / ROUND_S) * 3.6
+        );
+        assert_eq!(
+            result.average_accelerations_n_per_car_per_round,
+            5.0 / 10.0
+        );
+        assert_eq!(
+            result.average_deaccelerations_n_per_car_per_round,
+            0.0
+        );
+    }
+
+    #[test]
+    fn ultra_bottleneck() {
+        let _result = run_sim(SimulationConfig {
+            rounds: 100,
+            lanes: 10,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            block: {
+                let mut blk = Vec::new();
+                for lane in 0..=7 {
+                    blk.push(format!("({},{}-100)", lane, 90 + lane));
+                }
+                for lane in 2..=9 {
+                    blk.push(format!("({},{}-30)", lane, 20 + (9 - lane)));
+                }
+                for lane in 4..=6 {
+                    blk.push(format!("({},50-60)", lane));
+                }
+                blk
+            },
+            verbose: true,
+            out_path: PathBuf::from_str("traffic-ultra_bottleneck.png").unwrap(),
+            ..SimulationConfig::default()
+        });
+
+        // This test is too confusing to write comprehensive tests for. It's enough for me if
+        // nothing in the simulator itself panics.
+        // Just uncomment the following explicit `panic!` and set `SimulationConfig.image` to `true` and witness
+        // the chaos unfold:
+        // panic!();
+    }
+
+    // -- different vehicle types extension --
+
+    #[test]
+    fn slow_truck_causing_traffic_jam() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            length: 100,
+            vehicles: vec!["(4, 6, 0.01)".to_string(), "(5, 1, 0.2)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            verbose: true,
+            out_path: PathBuf::from_str("traffic-slow_truck.png").unwrap(),
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert!(result.monitor_cells_flow_cars_per_minute[0] > 0.0);
+    }
+    
+    #[test]
+    fn bunch_of_trucks() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            length: 100,
+            vehicles: vec!["(4, 6, 0.3)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            verbose: true,
+            out_path: PathBuf::from_str("traffic-bunch_of_truck.png").unwrap(),
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert!(result.monitor_cells_flow_cars_per_minute[0] > 0.0);
+    }
+
+    #[test]
+    fn metrics_by_vehicle_type() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(2, 1, 0.03)".to_string(), "(5, 1, 0.03)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        let trucks = &result.vehicle_types[0];
+        let cars = &result.vehicle_types[1];
+        assert_eq!(trucks.cars + cars.cars, result.cars as u64);
+        assert!(trucks.average_speed_kilometers_per_hour <= 2.0 * CELL_M / ROUND_S * 3.6);
+        assert!(cars.average_speed_kilometers_per_hour > trucks.average_speed_kilometers_per_hour);
+    }
+
+    #[test]
+    fn cars_per_lane() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(2, 1, 0.05)".to_string(), "(5, 1, 0.1)".to_string()],
+            monitor: vec![],
+            cars_per_lane_series: true,
+            ..SimulationConfig::default()
+        });
+
+        assert_eq!(result.initial_cars_per_lane, vec![15, 15, 15]);
+        assert_eq!(result.vehicle_types[0].initial_cars_per_lane, vec![5, 5, 5]);
+        assert_eq!(result.vehicle_types[1].initial_cars_per_lane, vec![10, 10, 10]);
+        let series = result.cars_per_lane_per_round.unwrap();
+        assert_eq!(series.len(), 100);
+        assert!(series.iter().all(|cars| cars.len() == 3 && cars.iter().sum::<u32>() == 45));
+    }
+
+    #[test]
+    fn memory_report() {
+        let result = run_sim(SimulationConfig {
+            rounds: 10,
+            lanes: 2,
+            length: 10,
+            monitor: vec![],
+            congestion_series: true,
+            cars_per_lane_series: true,
+            memory_report: true,
+            shockwaves: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        let memory = result.memory.unwrap();
+        assert_eq!(memory.cell_grid_bytes, 2 * size_of::<Vec<Cell>>() + 2 * 10 * size_of::<Cell>() + 4 * size_of::<Car>());
+        assert_eq!(memory.output_buffer_bytes, 0);
+        // The congestion series, the cars per lane series and the jam heads of the initial state
+        // and every round, of which there are at most as many as there are cars in a lane.
+        let series_bytes = 10 * 8 + 10 * 2 * 4;
+        assert!((series_bytes..=series_bytes + 11 * 2 * 2 * 4).contains(&memory.series_buffer_bytes));
+        assert_eq!(memory.total_bytes, memory.cell_grid_bytes + memory.series_buffer_bytes);
+    }
+
+    #[test]
+    fn flush_every() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            lanes: 2,
+            length: 10,
+            monitor: vec![],
+            congestion_series: true,
+            flush_every: Some(30),
+            out_path: std::env::temp_dir().join("traffic-flush_every.png"),
+            ..SimulationConfig::default()
+        });
+
+        // The last partial result was saved after round 90 and holds the metrics up to then, but not
+        // the series.
+        let partial: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(std::env::temp_dir().join("traffic-flush_every-partial.json")).unwrap()).unwrap();
+        assert_eq!(partial["rounds"], 90);
+        assert_eq!(partial["cars"], result.cars);
+        assert!(partial["congested_fraction_per_round"].is_null());
+    }
+
+    #[test]
+    fn target_density_ramp() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.05)".to_string()],
+            monitor: vec![],
+            cars_per_lane_series: true,
+            target_density: vec!["(0, 0.05)".to_string(), "(100, 0.35)".to_string(), "(200,0.05)".to_string()],
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        let series = result.cars_per_lane_per_round.unwrap();
+        assert_eq!(series[49], vec![20]);
+        assert_eq!(series[99], vec![35]);
+        assert_eq!(series[199], vec![5]);
+        assert_eq!(result.cars, 35);
+        assert_eq!(result.removed_cars, 30);
+    }
+
+    #[test]
+    fn reaction_delay() {
+        let speeds = |vehicle: &str| {
+            let mut car = Car::new(0, 0, &vehicle.parse::<VehicleBlueprint>().unwrap());
+            [0, 0, 3, 3, 3, 3].iter().map(|cells_to_next_car| {
+                car.increase_speed();
+                car.finish(*cells_to_next_car, false);
+                car.speed()
+            }).collect::<Vec<u8>>()
+        };
+        // The leader drives off in the third round, which the car only notices two rounds later.
+        assert_eq!(speeds("(5, 1, 0.1)"), vec![0, 0, 1, 2, 3, 3]);
+        assert_eq!(speeds("(5, 1, 0.1, reaction=2)"), vec![0, 0, 0, 0, 1, 2]);
+        let truck = "(4, 6, 0.1, regular, 1:2, reaction=3)".parse::<VehicleBlueprint>().unwrap();
+        assert_eq!((truck.reaction_rounds(), truck.lane_weights()), (3, Some(&vec![1.0, 2.0])));
+        assert!("(5, 1, 0.1, reaction=2, emergency)".parse::<VehicleBlueprint>().is_err());
+    }
+
+    #[test]
+    fn anticipation_lets_platoon_drive_off() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let speeds = |layout: &str, update_order: UpdateOrder, driving_model: DrivingModel| {
+            let mut road = Road::from_layout(layout, &vehicles, 0.0, 1.0, None)
+                .unwrap()
+                .with_update_order(update_order)
+                .with_driving_model(driving_model);
+            road.round();
+            road.iter_cars().filter(|(_, lane, _, _)| *lane == 0).map(|(_, _, _, car)| car.speed()).collect::<Vec<u8>>()
+        };
+        for (layout, update_order) in [("oooo____________\n", UpdateOrder::Sequential), ("oooo____________\n________________\n", UpdateOrder::Sequential), ("oooo____________\n", UpdateOrder::Parallel)] {
+            // Only the car at the front can drive off, unless the cars behind count on it driving.
+            assert_eq!(speeds(layout, update_order, DrivingModel::Nasch), vec![0, 0, 0, 1]);
+            assert_eq!(speeds(layout, update_order, DrivingModel::Anticipation), vec![1, 1, 1, 1]);
+        }
+    }
+
+    #[test]
+    fn bounded_deceleration() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let layout = format!("o{}x\n", "_".repeat(29));
+        let road = |braking_overrun: BrakingOverrun| {
+            let mut road = Road::from_layout(&layout, &vehicles, 0.0, 1.0, None)
+                .unwrap()
+                .with_max_deceleration(1, braking_overrun, 4.0)
+                .with_collision_rounds(3);
+            // The car speeds up to 5 cells per round and has to stop in front of the blocked cell
+            // in the ninth round.
+            for _ in 0..9 {
+                road.round();
+            }
+            road
+        };
+        let mut collided = road(BrakingOverrun::Collision);
+        assert_eq!((collided.collisions(), collided.emergency_brakings()), (1, 0));
+        for wrecked in [true, true, true, false] {
+            assert_eq!(collided.car_at(0, 29).unwrap().wrecked(), wrecked);
+            collided.round();
+        }
+        let braked = road(BrakingOverrun::Emergency);
+        assert_eq!((braked.collisions(), braked.emergency_brakings()), (0, 1));
+        assert!(!braked.car_at(0, 29).unwrap().wrecked());
+    }
+
+    #[test]
+    #[should_panic]
+    fn sum_of_densities_cannot_be_greater_than_1() {
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            length: 100,
+            vehicles: vec!["(4, 6, 0.3)".to_string(), "(5, 1, 0.8)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+    }
+
+    // -- traffic lights --
+
+    #[test]
+    fn single_lane_traffic_light() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            traffic_lights: vec!["(0, 9)".to_string()],
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.cars, 1);
+        assert!(
+            result.average_speed_kilometers_per_hour -
+            (1+2+3+4+5+(100-5)*5) as f64 / 200.0 * (CELL_M / ROUND_S) * 3.6
+            < 2.0
+        );
+    }
+
+    #[test]
+    fn green_wave_lets_cars_pass() {
+        let result = run_sim(SimulationConfig {
+            rounds: 600,
+            length: 300,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            traffic_lights: vec!["(0, 50, cycle=60, green=30)".to_string(), "(0, 150, cycle=60, green=30)".to_string(), "(0, 250, cycle=60, green=30)".to_string()],
+            green_waves: vec!["(0-300, 5, cycle=60, green=30)".to_string()],
+            seed: Some(1),
+            cars: vec!["1".to_string()],
+            ..SimulationConfig::default()
+        });
+
+
+        let offsets: Vec<u32> = result.signal_plan.unwrap().iter().map(|light| light.timing().offset()).collect();
+        assert_eq!(offsets, vec![0, 20, 40]);
+        // Once the car has caught the wave, it never has to brake again.
+        assert!(result.average_deaccelerations_n_per_car_per_round < 0.01);
+    }
+
+    #[test]
+    fn traffic_lights_in_all_lanes() {
+        let args = SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            monitor: vec![],
+            traffic_lights: vec!["(*, 50)".to_string(), "(1,20)".to_string(), "(all,80)".to_string()],
+            ..SimulationConfig::default()
+        };
+
+        assert_eq!(args.traffic_lights().iter().map(|light| light.location().clone()).collect::<Vec<CellLocation>>(), vec![
+            CellLocation::new(0, 50), CellLocation::new(1, 50), CellLocation::new(2, 50),
+            CellLocation::new(1, 20),
+            CellLocation::new(0, 80), CellLocation::new(1, 80), CellLocation::new(2, 80),
+        ]);
+        let result = run_sim(args);
+        println!("{:?}", result);
+    }
+
+    #[test]
+    fn traffic_lights_with_own_timings() {
+        assert!(TrafficLight::parse_in_lanes("(0, 10, cycle=0)", 1).is_err());
+        assert!(TrafficLight::parse_in_lanes("(0, 10, cycle=20, green=30)", 1).is_err());
+        assert!(TrafficLight::parse_in_lanes("(0, 10, yellow=3)", 1).is_err());
+        let mut traffic_lights = TrafficLight::parse_in_lanes("(0, 10, cycle=20, green=15)", 1).unwrap();
+        traffic_lights.append(&mut TrafficLight::parse_in_lanes("(0, 30, cycle=20, green=15, offset=10)", 1).unwrap());
+        let mut road = Road::new(1, 50, &[], 0.0, 0.0, &[], &traffic_lights, None);
+        let mut red_rounds = [0, 0];
+        for round in 1..=40 {
+            road.round();
+            let red = [road.cells()[0][10].is_red_light(), road.cells()[0][30].is_red_light()];
+            assert_eq!(red[0], round % 20 >= 15);
+            assert_eq!(red[1], (round + 10) % 20 >= 15);
+            red_rounds[0] += red[0] as u32;
+            red_rounds[1] += red[1] as u32;
+        }
+        assert_eq!(red_rounds, [10, 10]);
+    }
+
+    #[test]
+    fn actuated_traffic_light_extends_green() {
+        let first_red_round = |vehicles: &[VehicleBlueprint]| {
+            let traffic_lights = TrafficLight::parse_in_lanes("(0, 40, actuated, min_green=5, max_green=30, gap=2, cycle=30, green=20)", 1).unwrap();
+            let mut road = Road::new(1, 50, vehicles, 0.0, 0.0, &[], &traffic_lights, Some(1));
+            (1..=100).find(|_| {
+                road.round();
+                road.cells()[0][40].is_red_light()
+            })
+        };
+        // Without cars the light turns red once the minimum green phase is over.
+        assert_eq!(first_red_round(&[]), Some(5));
+        // A dense stream of cars keeps it green up to the maximum.
+        assert_eq!(first_red_round(&["(5, 1, 0.5)".parse().unwrap()]), Some(30));
+        assert!(TrafficLight::parse_in_lanes("(0, 40, min_green=20, max_green=10)", 1).is_err());
+    }
+
+    #[test]
+    fn intersection_turning_movements() {
+        let config = SimulationConfig {
+            rounds: 1000,
+            warmup: 0,
+            lanes: 1,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            automated_share: 0.0,
+            seed: Some(3),
+            ..SimulationConfig::default()
+        };
+        let phases: SignalPhases = "(40, 40, 3)".parse().unwrap();
+        let result = intersection::run(&config, "(0.2, 0.6, 0.2)".parse().unwrap(), phases, 300);
+        assert_eq!(result.cycle_s, 86.0);
+        for approach in &result.approaches {
+            assert!(approach.served_cars > 0);
+            assert_eq!(approach.left_turns + approach.straight_movements + approach.right_turns, approach.served_cars);
+            assert!(approach.mean_queue_cars > 0.0 && approach.mean_delay_s > 0.0);
+            // The counts of the intervals add up to the counts of the whole run.
+            let counts: Vec<&TurningMovementCount> = result.turning_movements.iter().filter(|count| count.approach == approach.approach).collect();
+            assert_eq!(counts.iter().map(|count| (count.start_round, count.end_round)).collect::<Vec<_>>(), vec![(0, 300), (300, 600), (600, 900), (900, 1000)]);
+            assert_eq!(counts.iter().map(|count| count.left).sum::<u32>(), approach.left_turns);
+            assert_eq!(counts.iter().map(|count| count.straight).sum::<u32>(), approach.straight_movements);
+            assert_eq!(counts.iter().map(|count| count.right).sum::<u32>(), approach.right_turns);
+        }
+        let path = std::env::temp_dir().join("traffic-turning_movements.csv");
+        intersection::write_turning_movements(&path, &result.turning_movements).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + 4 * 4);
+        assert_eq!(lines[0], "start_round,end_round,start_s,end_s,approach,left,straight,right");
+        let first = &result.turning_movements[0];
+        assert_eq!(lines[1], format!("0,300,0,300,north,{},{},{}", first.left, first.straight, first.right));
+        assert!(result.approaches.iter().any(|approach| approach.left_turn_yield_rounds > 0));
+
+        // Cars only give way to oncoming cars that do not turn left themselves.
+        for turns in ["(0, 0.5, 0.5)", "(1, 0, 0)"] {
+            let result = intersection::run(&config, turns.parse().unwrap(), phases, 300);
+            assert!(result.approaches.iter().all(|approach| approach.left_turn_yield_rounds == 0));
+        }
+        assert!("(0.5, 0.5, 0.5)".parse::<TurnProbabilities>().is_err());
+        assert!("(0, 40)".parse::<SignalPhases>().is_err());
+    }
+
+    #[test]
+    fn queue_spills_back_from_red_light() {
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            length: 50,
+            vehicles: vec!["(5, 1, 0.3)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            traffic_lights: vec!["(0,40)".to_string()],
+            spillback_points: vec!["(*,30)".to_string(), "(0,10)".to_string()],
+            matsim_link_length: 50,
+            ..SimulationConfig::default()
+        });
+
+
+        println!("{:?}", result);
+
+        // All 15 cars queue up in the 15 cells in front of the red light (rounds 100 to 199).
+        let spillbacks = result.spillbacks.unwrap();
+        assert_eq!(spillbacks[0].location, CellLocation::new(0, 30));
+        assert!(spillbacks[0].first_round.is_some_and(|round| round > 100 && round < 200));
+        assert!(spillbacks[0].duration_s > 0.0 && spillbacks[0].duration_s < 100.0);
+        assert_eq!(spillbacks[1].first_round, None);
+        assert_eq!(spillbacks[1].duration_s, 0.0);
+    }
+
+    #[test]
+    fn emergency_vehicle_preempts_traffic_light() {
+        let run = |preemption_distance: Option<u32>| run_sim(SimulationConfig {
+            rounds: 300,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.01, emergency)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            preemption_distance,
+            traffic_lights: vec!["(0,50)".to_string()],
+            seed: Some(1),
+            ..SimulationConfig::default()
+        });
+
+        let without_preemption = run(None);
+        assert_eq!(without_preemption.preempted_light_rounds, 0);
+        assert!(without_preemption.vehicle_types[0].average_stops_n_per_car_per_round > 0.0);
+        let with_preemption = run(Some(10));
+        assert!(with_preemption.preempted_light_rounds > 0);
+        assert_eq!(with_preemption.vehicle_types[0].average_stops_n_per_car_per_round, 0.0);
+    }
+
+    #[test]
+    fn cars_clear_the_way_for_emergency_vehicles() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(3, 1, 0.0)".parse().unwrap(), "(5, 1, 0.0, emergency)".parse().unwrap()];
+        let cars = || vec![(0, 20, Car::new(0, 0, &vehicles[0])), (0, 15, Car::new(1, 1, &vehicles[1]))];
+        let mut road = Road::new(2, 100, &vehicles, 0.0, 1.0, &[], &[], Some(1));
+        road.restore(0, cars(), &[]);
+        road.round();
+        // Cars that always stay in their lane do not make way by themselves.
+        assert_eq!(road.find_car(0).unwrap().0, 0);
+        let mut road = Road::new(2, 100, &vehicles, 0.0, 1.0, &[], &[], Some(1)).with_emergency_yielding(10);
+        road.restore(0, cars(), &[]);
+        road.round();
+        assert_eq!(road.find_car(0).unwrap().0, 1);
+    }
+
+    #[test]
+    fn emergency_vehicle_runs_red_lights() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.0, emergency)".parse().unwrap()];
+        let traffic_lights = TrafficLight::parse_in_lanes("(0, 20, cycle=1000, green=1)", 1).unwrap();
+        let run = |red_light_running: bool| {
+            let mut road = Road::new(1, 100, &vehicles, 0.0, 0.0, &[], &traffic_lights, Some(1));
+            if red_light_running {
+                road = road.with_emergency_red_light_running();
+            }
+            road.restore(0, vec![(0, 0, Car::new(0, 0, &vehicles[0]))], &[false]);
+            for _ in 0..80 {
+                road.round();
+            }
+            road
+        };
+
+        let stopped = run(false);
+        assert!(stopped.find_car(0).unwrap().1 < 20);
+        assert!(stopped.emergency_travel_times().is_empty());
+        let running = run(true);
+        assert!(!running.emergency_travel_times().is_empty());
+    }
+
+    #[test]
+    fn failed_traffic_light_acts_as_stop_sign() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let mut road = Road::from_layout("o___#_____\n", &vehicles, 0.0, 0.0, None).unwrap().with_light_failures(1.0, 1_000_000);
+        let mut positions = Vec::new();
+        for _ in 0..6 {
+            road.round();
+            let (_, _, cell_i, car) = road.iter_cars().next().unwrap();
+            positions.push((cell_i, car.speed()));
+        }
+        // The car stops right in front of the dark light although it is green, and then drives on.
+        assert_eq!(positions, vec![(1, 1), (3, 2), (3, 0), (4, 1), (6, 2), (9, 3)]);
+        assert_eq!(road.light_failures(), 1);
+        assert_eq!(road.failed_light_rounds(), 6);
+    }
+
+    // -- road layouts --
+
+    #[test]
+    fn layout_with_spawn_points() {
+        let path = std::env::temp_dir().join("traffic-layout_with_spawn_points.txt");
+        std::fs::write(&path, "\no_________xxxx______\n__o____#__2222____o_\n").unwrap();
+        let result = run_sim(SimulationConfig {
+            rounds: 50,
+            vehicles: vec!["(5, 1, 0.5)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec!["(1,12)".to_string()],
+            layout: Some(path),
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.lanes, 2);
+        assert_eq!(result.length, 20);
+        assert_eq!(result.cars, 3);
+    }
+
+    #[test]
+    fn layout_slow_zone() {
+        let path = std::env::temp_dir().join("traffic-layout_slow_zone.txt");
+        std::fs::write(&path, "o1111111111111111111").unwrap();
+        let result = run_sim(SimulationConfig {
+            rounds: 100,
+            vehicles: vec!["(5, 1, 0.5)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            layout: Some(path),
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert_eq!(result.cars, 1);
+        // Only the spawn point has no speed limit, so the car can only speed up once per lap.
+        assert!(result.average_speed_kilometers_per_hour < 1.1 * (CELL_M / ROUND_S) * 3.6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn layout_lanes_must_be_equally_long() {
+        let _ = Road::from_layout("____\n___\n", &[], 0.0, 0.0, None).unwrap();
+    }
+
+    // -- viewport --
+
+    #[test]
+    fn view_selects_window_of_road() {
+        let road = Road::from_layout("x___\n__x_\n___x\n", &[], 0.0, 0.0, None).unwrap();
+        let text = render(&road, Some(&"(1-3, 1-3)".parse::<View>().unwrap()));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with(" _x"));
+        assert!(lines[2].ends_with(" __"));
+        // Views reaching past the road only show the part on the road.
+        let text = render(&road, Some(&"(2-9, 3-9)".parse::<View>().unwrap()));
+        assert!(text.ends_with(" x"));
+        assert_eq!(text.lines().count(), 2);
+    }
+
+    #[test]
+    fn camera_follows_car() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.005)".parse().unwrap()];
+        let mut road = Road::new(1, 200, &vehicles, 0.2, 0.2, &[], &[], None);
+        let position = road.cells()[0].iter().position(|cell| cell.car().is_some()).unwrap();
+        let mut camera = Camera::new(None).with_follow(format!("(0, {})", position).parse().unwrap());
+        camera.start(&road);
+        for _ in 0..50 {
+            let index = road.cells()[0].iter().position(|cell| cell.car().is_some()).unwrap();
+            let start = index.saturating_sub(40).min(120);
+            assert_eq!(camera.view(&road), Some(format!("(0, {}-{})", start, start + 80).parse::<View>().unwrap()));
+            road.round();
+        }
+    }
+
+    #[test]
+    fn car_queries() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.2)".parse().unwrap()];
+        let mut road = Road::new(2, 50, &vehicles, 0.2, 0.2, &[], &[], None);
+        for _ in 0..3 {
+            assert_eq!(road.iter_cars().count() as u32, road.cars());
+            for (id, lane, cell, car) in road.iter_cars() {
+                assert_eq!(car.id(), id);
+                assert_eq!(road.car_at(lane, cell).unwrap().id(), id);
+                let (found_lane, found_cell, _) = road.find_car(id).unwrap();
+                assert_eq!((found_lane, found_cell), (lane, cell));
+            }
+            road.round();
+        }
+        assert!(road.find_car(road.cars()).is_none());
+        assert!(road.car_at(2, 0).is_none());
+        assert!(road.car_at(0, 50).is_none());
+    }
+
+    #[test]
+    fn gap_overlay_shows_counters() {
+        let mut road = Road::from_layout("____x___\n", &[], 0.0, 0.0, None).unwrap().with_gap_recording();
+        let camera = Camera::new(None).with_gap_overlay();
+        for _ in 0..2 {
+            let text = camera.render(&road);
+            let lines: Vec<&str> = text.lines().collect();
+            assert_eq!(lines[2], "c ++++++++");
+            assert_eq!(lines[3], "o 32107654");
+            road.round();
+        }
+    }
+
+    #[test]
+    fn gaps_beyond_255_cells() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let layout = format!("o{}\n", "_".repeat(399));
+        let mut road = Road::from_layout(&layout, &vehicles, 0.0, 0.0, None).unwrap().with_gap_recording();
+        assert_eq!(road.gap_counters().unwrap()[0][0], (399, 399));
+        road.round();
+        assert_eq!(road.gap_counters().unwrap()[0][0], (399, 399));
+        assert_eq!(road.gap_counters().unwrap()[0][100], (299, 299));
+    }
+
+    #[test]
+    #[cfg(feature = "terminal")]
+    fn animation_rewrites_changed_columns() {
+        let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+        let mut road = Road::from_layout("o_______\n________\n", &vehicles, 0.0, 1.0, None).unwrap();
+        let camera = Camera::new(None);
+        let shown_frame = camera.frame(&road);
+        road.round();
+        let frame = camera.frame(&road);
+        // The car drives from cell 0 to cell 1, which are the third and fourth columns.
+        assert_eq!(frame_changes(&shown_frame, &frame), Some(vec![(1, 2, frame[1][2..4].concat())]));
+        assert_eq!(frame_changes(&frame, &frame), Some(vec![]));
+        let smaller_frame = Camera::new(Some("(0, 0-4)".parse().unwrap())).frame(&road);
+        assert_eq!(frame_changes(&frame, &smaller_frame), None);
+    }
+
+    // -- open road with demand --
+
+    #[test]
+    fn open_road_with_demand() {
+        let path = std::env::temp_dir().join("traffic-open_road_with_demand.csv");
+        std::fs::write(&path, "start_round,cars_per_minute,share_car,share_truck\n0,20,1,0\n100,0,1,0\n").unwrap();
+        let result = run_sim(SimulationConfig {
+            rounds: 300,
+            lanes: 2,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.0)".to_string(), "(4, 6, 0.0)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec!["(0,99)".to_string(), "(1,99)".to_string()],
+            demand: Some(path),
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        assert!(result.open);
+        assert!(result.cars > 0);
+        // Nothing arrives after round 100, so every car has left the road by the end.
+        assert_eq!(result.exited_cars, result.cars);
+        assert_eq!(result.waiting_cars, 0);
+        assert!(result.average_speed_kilometers_per_hour > 4.0 * (CELL_M / ROUND_S) * 3.6);
+        assert!(result.monitor_cells_flow_cars_per_minute.iter().sum::<f64>() > 0.0);
+    }
+
+    #[test]
+    fn trip_records() {
+        let path = std::env::temp_dir().join("traffic-trip_records.csv");
+        let trips_path = std::env::temp_dir().join("traffic-trip_records-trips.csv");
+        std::fs::write(&path, "start_round,cars_per_minute,share_car,share_truck\n0,20,1,0\n100,0,1,0\n").unwrap();
+        let result = run_sim(SimulationConfig {
+            rounds: 300,
+            lanes: 2,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.0)".to_string(), "(4, 6, 0.0)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec!["(0,99)".to_string(), "(1,99)".to_string()],
+            demand: Some(path),
+            verbose: true,
+            trips: Some(trips_path.clone()),
+            ..SimulationConfig::default()
+        });
+
+
+        println!("{:?}", result);
+
+        let trips = std::fs::read_to_string(&trips_path).unwrap();
+        let rows: Vec<Vec<&str>> = trips.lines().skip(1).map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len() as u32, result.exited_cars);
+        for row in rows {
+            let departure_round: u32 = row[2].parse().unwrap();
+            let arrival_round: u32 = row[3].parse().unwrap();
+            assert!(departure_round < 100);
+            // Even the fastest cars need 20 rounds to cross the 100 cells.
+            assert!(arrival_round - departure_round >= 20);
+            assert_eq!(row[4].parse::<f64>().unwrap(), (arrival_round - departure_round) as f64 * ROUND_S);
+            assert!(row[8].parse::<f64>().unwrap() >= 100.0 * CELL_M);
+        }
+    }
+
+    #[test]
+    fn timeseries_csv() {
+        let path = std::env::temp_dir().join("traffic-timeseries_csv.csv");
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            lanes: 3,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            monitor: vec!["(1,0)".to_string()],
+            verbose: true,
+            timeseries: Some(path.clone()),
+            ..SimulationConfig::default()
+        });
+
+
+        let timeseries = std::fs::read_to_string(&path).unwrap();
+        let mut lines = timeseries.lines();
+        assert_eq!(lines.next().unwrap(), "round,time_s,cars,density_cars_per_km_per_lane,mean_speed_km_per_h,lane_changes,flow_1_0_cars_per_minute");
+        let rows: Vec<Vec<f64>> = lines.map(|line| line.split(',').map(|value| value.parse().unwrap()).collect()).collect();
+        assert_eq!(rows.len(), 200);
+        for (round_i, row) in rows.iter().enumerate() {
+            assert_eq!(row[0], (round_i + 1) as f64);
+            assert_eq!(row[2], 30.0);
+            assert!((row[3] - 30.0 / (3.0 * 100.0 * CELL_M / 1000.0)).abs() < 1e-9);
+            assert_eq!(row[6] % 60.0, 0.0);
+        }
+        let mean_speed = rows.iter().map(|row| row[4]).sum::<f64>() / 200.0;
+        assert!((mean_speed - result.average_speed_kilometers_per_hour).abs() < 1e-6);
+        let lane_changes: f64 = rows.iter().map(|row| row[5]).sum();
+        assert!(lane_changes > 0.0);
+        assert!((lane_changes - result.vehicle_types[0].average_lane_changes_n_per_car_per_round * 30.0 * 200.0).abs() < 1e-6);
+        let flow = rows.iter().map(|row| row[6]).sum::<f64>() / 200.0;
+        assert!((flow - result.monitor_cells_flow_cars_per_minute[0]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn uniform_arrivals() {
+        let path = std::env::temp_dir().join("traffic-uniform_arrivals.csv");
+        std::fs::write(&path, "start_round,cars_per_minute,share_car,share_truck\n0,30,1,0\n100,0,1,0\n").unwrap();
+        let result = run_sim(SimulationConfig {
+            rounds: 300,
+            lanes: 2,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.0)".to_string(), "(4, 6, 0.0)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec!["(0,99)".to_string(), "(1,99)".to_string()],
+            demand: Some(path),
+            arrival_process: ArrivalProcess::Uniform,
+            verbose: true,
+            ..SimulationConfig::default()
+        });
+
+        println!("{:?}", result);
+
+        // Rounds 1 to 99 let half a car arrive each.
+        assert_eq!(result.cars, 49);
+        assert_eq!(result.exited_cars, 49);
+    }
+
+    // -- shockwaves --
+
+    #[test]
+    fn shockwaves_move_upstream() {
+        let result = run_sim(SimulationConfig {
+            rounds: 500,
+            length: 500,
+            vehicles: vec!["(5, 1, 0.35)".to_string()],
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            shockwaves: true,
+            ..SimulationConfig::default()
+        });
+
+        let shockwaves = result.shockwaves.unwrap();
+        println!("{:?}", shockwaves);
+
+        assert!(shockwaves.jam_fronts > 0);
+        assert!(shockwaves.average_speed_kilometers_per_hour < -5.0);
+        assert!(shockwaves.average_speed_kilometers_per_hour > -40.0);
+    }
+
+    // -- traffic breakdown --
+
+
+    #[test]
+    fn persistent_jam_breaks_traffic_down() {
+        let run = |density: f32, dilly_dally_probability: f32| run_sim(SimulationConfig {
+            rounds: 300,
+            length: 200,
+            vehicles: vec![format!("(5, 1, {})", density)],
+            dilly_dally_probability,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            breakdown_jam_cars: Some(5),
+            ..SimulationConfig::default()
+        });
+
+        // Without dawdling, dense traffic may settle into moving slowly in lockstep instead of jamming.
+        let breakdown = |density: f32, dilly_dally_probability: f32| run(density, dilly_dally_probability).breakdown.unwrap();
+        let jammed = breakdown(0.5, 0.2);
+        assert!(jammed.occurred);
+        assert_eq!(jammed.time_to_breakdown_s, jammed.first_round.map(|round| round as f64 * ROUND_S));
+        assert!(jammed.largest_jam_cars >= 5);
+        let free = breakdown(0.05, 0.0);
+        assert_eq!(free, Breakdown { occurred: false, first_round: None, time_to_breakdown_s: None, largest_jam_cars: free.largest_jam_cars });
+        assert!(free.largest_jam_cars < 5);
+    }
+
+    // -- detector correlation --
+
+    #[test]
+    fn detector_correlation_one_car() {
+        let path = std::env::temp_dir().join("traffic-detector_correlation_one_car.txt");
+        std::fs::write(&path, format!("o{}", "_".repeat(99))).unwrap();
+        let result = run_sim(SimulationConfig {
+            rounds: 200,
+            length: 100,
+            vehicles: vec!["(5, 1, 0.01)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec!["(0,50)".to_string(), "(0,75)".to_string()],
+            detector_correlation: Some(1),
+            layout: Some(path),
+            ..SimulationConfig::default()
+        });
+
+        let correlations = result.detector_correlations.unwrap();
+        println!("{:?}", correlations);
+
+        // At full speed the car needs 5 rounds to get from the first detector to the second.
+        assert_eq!(correlations.len(), 1);
+        assert_eq!(correlations[0].flow_delay_s, 5.0 * ROUND_S);
+        assert!(correlations[0].flow_correlation > 0.9);
+    }
+
+    // -- state hashes --
+
+    #[test]
+    fn state_hashes_reproduce_deterministic_run() {
+        let layout_path = std::env::temp_dir().join("traffic-state_hashes.txt");
+        let hashes_path = std::env::temp_dir().join("traffic-state_hashes.hashes");
+        std::fs::write(&layout_path, "o___o_____xx___o____\n__o____#__2222____o_\n").unwrap();
+        let run = |dilly_dally_probability: f32, dump: bool| run_sim(SimulationConfig {
+            rounds: 50,
+            vehicles: vec!["(5, 1, 0.5)".to_string()],
+            dilly_dally_probability,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            dump_state_hashes: dump.then(|| hashes_path.clone()),
+            compare_state_hashes: (!dump).then(|| hashes_path.clone()),
+            layout: Some(layout_path.clone()),
+            ..SimulationConfig::default()
+        });
+
+        // Without dilly-dallying (or with certain dilly-dallying) the layout leaves nothing to chance.
+        assert!(run(0.0, true).state_hashes.is_none());
+        let same = run(0.0, false).state_hashes.unwrap();
+        assert_eq!(same.compared_rounds, 51);
+        assert_eq!(same.first_diverging_round, None);
+        let different = run(1.0, false).state_hashes.unwrap();
+        assert_eq!(different.first_diverging_round, Some(1));
+    }
+
+    // -- images --
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn occupancy_heatmap() {
+        let path = std::env::temp_dir().join("traffic-occupancy_heatmap.png");
+        let _result = run_sim(SimulationConfig {
+            rounds: 100,
+            lanes: 3,
+            length: 50,
+            monitor: vec![],
+            block: vec!["(0,10-20)".to_string()],
+            image: true,
+            image_mode: ImageMode::Occupancy,
+            out_path: path.clone(),
+            ..SimulationConfig::default()
+        });
+
+        let heatmap = image::open(path).unwrap().to_rgb8();
+        assert_eq!(heatmap.dimensions(), (50, 3));
+        assert_eq!(heatmap.get_pixel(15, 0).0, [180, 0, 180]);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn image_light_strip() {
+        let path = std::env::temp_dir().join("traffic-image_light_strip.png");
+        let _result = run_sim(SimulationConfig {
+            rounds: 150,
+            length: 20,
+            monitor: vec![],
+            traffic_lights: vec!["(0,10)".to_string()],
+            image: true,
+            image_light_strip: true,
+            out_path: path.clone(),
+            ..SimulationConfig::default()
+        });
+
+        let image = image::open(path).unwrap().to_rgb8();
+        // Every round takes up one row for the lane and one for the lights, read from the bottom up.
+        assert_eq!(image.dimensions(), (20, 2 * 151));
+        let strip_row = |round: u32| image.height() - 2 - 2 * round;
+        assert_eq!(image.get_pixel(10, strip_row(0)).0, [0, 255, 0]);
+        assert_eq!(image.get_pixel(10, strip_row(120)).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(9, strip_row(120)).0, [0, 0, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn image_axes() {
+        let path = std::env::temp_dir().join("traffic-image_axes.png");
+        run_sim(SimulationConfig {
+            rounds: 99,
+            warmup: 0,
+            lanes: 1,
+            length: 50,
+            vehicles: vec!["(5, 1, 0.2)".to_string()],
+            automated_share: 0.0,
+            block: vec!["(0, 10-12)".to_string()],
+            traffic_lights: vec!["(0, 30)".to_string()],
+            image: true,
+            image_axes: true,
+            pixel_scale: 2,
+            out_path: path.clone(),
+            ..SimulationConfig::default()
+        });
+
+        let image = image::open(&path).unwrap().to_rgb8();
+        let (width, height) = image.dimensions();
+        // The road is framed by a white margin on every side.
+        assert!(width > 2 * 50 && height > 2 * 100);
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(image.get_pixel(width - 1, height - 1).0, [255, 255, 255]);
+        // The markers of the blocked cells and the traffic light are right below the cell axis.
+        let black = |x: u32, y: u32| image.get_pixel(x, y).0 == [0, 0, 0];
+        let y_axis = (0..width).find(|x| (height / 4..height / 2).all(|y| black(*x, y))).unwrap();
+        let x_axis = (0..height).find(|y| (y_axis..y_axis + 100).all(|x| black(x, *y))).unwrap();
+        let left = y_axis + 1;
+        assert_eq!(image.get_pixel(left + 2 * 10, x_axis + 1).0, [180, 0, 180]);
+        assert_eq!(image.get_pixel(left + 2 * 30, x_axis + 1).0, [50, 0, 180]);
+        assert_eq!(image.get_pixel(left + 2 * 20, x_axis + 1).0, [255, 255, 255]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn animated_gif() {
+        use image::AnimationDecoder;
+        let path = std::env::temp_dir().join("traffic-animated_gif.gif");
+        run_sim(SimulationConfig {
+            rounds: 30,
+            warmup: 0,
+            lanes: 2,
+            length: 40,
+            vehicles: vec!["(5, 1, 0.2)".to_string()],
+            automated_share: 0.0,
+            block: vec!["(1, 20)".to_string()],
+            frame_delay_ms: 100,
+            image_axes: false,
+            pixel_scale: 2,
+            gif: Some(path.clone()),
+            ..SimulationConfig::default()
+        });
+
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::BufReader::new(std::fs::File::open(&path).unwrap())).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 31);
+        assert_eq!(frames[0].buffer().dimensions(), (2 * 40, 2 * 2));
+        assert_eq!(frames[0].delay().numer_denom_ms(), (100, 1));
+        assert_eq!(frames[30].buffer().get_pixel(2 * 20 + 1, 2 + 1).0, [180, 0, 180, 255]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn image_pixel_scale() {
+        let path = std::env::temp_dir().join("traffic-image_pixel_scale.png");
+        let result = run_sim(SimulationConfig {
+            rounds: 150,
+            length: 20,
+            monitor: vec![],
+            memory_report: true,
+            traffic_lights: vec!["(0,10)".to_string()],
+            image: true,
+            image_light_strip: true,
+            pixel_scale: 3,
+            out_path: path.clone(),
+            ..SimulationConfig::default()
+        });
+
+        let image = image::open(path).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (3 * 20, 3 * 2 * 151));
+        // The light strip of the first round is drawn as a green block of 3×3 pixels.
+        for x in 30..33 {
+            for y in (image.height() - 6)..(image.height() - 3) {
+                assert_eq!(image.get_pixel(x, y).0, [0, 255, 0]);
+            }
+        }
+        // The image and its enlarged copy.
+        assert_eq!(result.memory.unwrap().output_buffer_bytes, (20 * 2 * 151 + 3 * 20 * 3 * 2 * 151) * 3);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn frames_dir() {
+        let dir = std::env::temp_dir().join("traffic-frames_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        let _result = run_sim(SimulationConfig {
+            rounds: 10,
+            length: 20,
+            monitor: vec![],
+            traffic_lights: vec!["(0,10)".to_string()],
+            pixel_scale: 2,
+            frames_dir: Some(dir.clone()),
+            ..SimulationConfig::default()
+        });
+
+        // One frame for the state before the first round and one for each round.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 11);
+        let frame = image::open(dir.join("round_000010.png")).unwrap().to_rgb8();
+        assert_eq!(frame.dimensions(), (2 * 20, 2));
+        // Each of the 4 cars is drawn as a block of 2×2 pixels.
+        assert_eq!(frame.pixels().filter(|pixel| pixel.0 != [0, 0, 0]).count(), 4 * 4);
+    }
+
+    // -- MATSim events --
+
+    #[test]
+    fn matsim_events_one_car() {
+        let path = std::env::temp_dir().join("traffic-matsim_events_one_car.xml");
+        let _result = run_sim(SimulationConfig {
+            rounds: 10,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            matsim_events: Some(path.clone()),
+            matsim_link_length: 5,
+            ..SimulationConfig::default()
+        });
+
+        let events = std::fs::read_to_string(path).unwrap();
+        assert!(events.starts_with("<?xml"));
+        assert!(events.trim_end().ends_with("</events>"));
+        assert_eq!(events.matches("type=\"vehicle enters traffic\"").count(), 1);
+        // The car drives 1+2+3+4+5+5*5 = 40 cells, which are 8 links of 5 cells each.
+        assert_eq!(events.matches("type=\"left link\"").count(), 8);
+        assert_eq!(events.matches("type=\"entered link\"").count(), 8);
+    }
+
+    // -- ASCII output --
+
+    #[test]
+    fn ascii_out_one_car() {
+        let path = std::env::temp_dir().join("traffic-ascii_out_one_car.txt");
+        let _result = run_sim(SimulationConfig {
+            rounds: 10,
+            length: 10,
+            vehicles: vec!["(5, 1, 0.1)".to_string()],
+            dilly_dally_probability: 0.0,
+            stay_in_lane_probability: 0.0,
+            monitor: vec![],
+            ascii_out: Some(path.clone()),
+            ..SimulationConfig::default()
+        });
+
+        let text = std::fs::read_to_string(path).unwrap();
+        let rounds: Vec<&str> = text.trim_end().split("\n\n").collect();
+        assert_eq!(rounds.len(), 11);
+        for round in rounds {
+            let lines: Vec<&str> = round.lines().collect();
+            assert_eq!(lines[0], "  0123456789");
+            assert_eq!(lines[1].len(), 12);
+            assert_eq!(lines[1].matches('_').count(), 9);
+        }
+    }
+
+    // -- Arrow stream --
+
+    #[test]
+    #[cfg(feature = "arrow")]
+    fn arrow_stream_one_batch_per_round() {
+        let path = std::env::temp_dir().join("traffic-arrow_stream_one_batch_per_round.arrows");
+        let result = run_sim(SimulationConfig {
+            rounds: 20,
+            lanes: 2,
+            length: 50,
+            monitor: vec![],
+            arrow_stream: Some(path.to_str().unwrap().to_string()),
+            ..SimulationConfig::default()
+        });
+
+        let reader = arrow_ipc::reader::StreamReader::try_new(std::fs::File::open(path).unwrap(), None).unwrap();
+        let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+        assert_eq!(batches.len(), 21);
+        assert!(batches.iter().all(|batch| batch.num_rows() == result.cars as usize));
+    }
+
+    // -- yaml reading --
+
+    #[test]
+    fn watch_folder_scenarios() {
+        let dir = std::env::temp_dir().join("traffic-watch_folder_scenarios");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(
+            dir.join("a.yaml"),
+            "rounds: 10\nlanes: 1\nlength: 10\nvehicles:\n  - '(5, 1, 0.1)'\ndilly_dally_probability: 0.0\nstay_in_lane_probability: 0.0\nmonitor: []\ntraffic_lights: []\nblock: []\nverbose: false\nanimate: false\nimage: false\nout_path: 'a.png'\nascii_out: 'a.txt'"
+        ).unwrap();
+        std::fs::write(dir.join("b.yml"), "rounds: 10").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a scenario").unwrap();
+
+        let pending = daemon::pending_scenarios(&dir, Duration::ZERO).unwrap();
+        assert_eq!(pending, vec![dir.join("a.yaml"), dir.join("b.yml")]);
+        assert!(daemon::pending_scenarios(&dir, Duration::from_secs(3600)).unwrap().is_empty());
+
+        assert!(daemon::run_scenario(&pending[0]).unwrap());
+        let result = std::fs::read_to_string(dir.join("a.result.json")).unwrap();
+        assert!(result.contains("\"cars\":1"));
+        // The relative output path is resolved against the watched directory.
+        assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap().trim_end().split("\n\n").count(), 11);
+
+        assert!(!daemon::run_scenario(&pending[1]).unwrap());
+        assert!(dir.join("b.error.txt").exists());
+
+        assert!(daemon::pending_scenarios(&dir, Duration::ZERO).unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_yaml() {
+        let _ = SimulationConfig::from_yaml("").expect("Unable to parse yaml file.");
+    }
+
+    #[test]
+    fn example_yaml() {
+        let args = SimulationConfig::from_yaml("rounds: 110\nlanes: 3\nlength: 300\nvehicles:\n  - '(7, 1, 0.01)'\n  - '(5, 2, 0.19)'\n  - '(4, 6, 0.05)'\ndilly_dally_probability: 0.15\nstay_in_lane_probability: 0.1\nmonitor:\n  - '(0, 0)'\n  - '(1, 0)'\n  - '(2, 0)'\ntraffic_lights:\n  - '(1, 150)'\nblock:\n  - '(0, 150-180)'\nverbose: false\nanimate: false\nimage: false\nout_path: 'example.png'")
+                .expect("Unable to parse yaml file.");
+
+        assert_eq!(args.lanes, 3);
+    }
+
+    #[test]
+    fn scenario_files() {
+        let dir = std::env::temp_dir().join("traffic-scenario_files");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("scenario.json"), r#"{"lanes": 3, "length": 300, "block": ["(0, 150-180)", "(2, 20-40)"], "seed": 7}"#).unwrap();
+        std::fs::write(dir.join("scenario.yaml"), "lanes: 3\nlength: 300\nblock:\n  - (0, 150-180)\n  - (2, 20-40)\nseed: 7\n").unwrap();
+        std::fs::write(dir.join("scenario.toml"), "lanes = 3\nlength = 300\nblock = [\"(0, 150-180)\", \"(2, 20-40)\"]\nseed = 7\n").unwrap();
+        for file in ["scenario.json", "scenario.yaml", "scenario.toml"] {
+            let config = SimulationConfig::from_file(&dir.join(file)).unwrap();
+            assert_eq!((config.lanes, config.length, config.seed), (3, 300, Some(7)));
+            assert_eq!(config.block(), vec![CellLocationRange::new(0, 150, 180), CellLocationRange::new(2, 20, 40)]);
+            // settings left out keep their defaults
+            assert_eq!(config.rounds, SimulationConfig::default().rounds);
+        }
+        // a scenario written as TOML reads back the same
+        let config = SimulationConfig::from_file(&dir.join("scenario.toml")).unwrap();
+        std::fs::write(dir.join("round-trip.toml"), toml::to_string(&config).unwrap()).unwrap();
+        let read_back = SimulationConfig::from_file(&dir.join("round-trip.toml")).unwrap();
+        assert_eq!(serde_json::to_value(read_back).unwrap(), serde_json::to_value(config).unwrap());
+        std::fs::write(dir.join("scenario.ini"), "lanes = 3\n").unwrap();
+        assert!(SimulationConfig::from_file(&dir.join("scenario.ini")).is_err());
+        std::fs::write(dir.join("invalid.json"), r#"{"lanes": "three"}"#).unwrap();
+        assert!(SimulationConfig::from_file(&dir.join("invalid.json")).is_err());
+    }
+}
+
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use crate::observer::Observer;
+use crate::{Road, ROUND_S};
+
+/// The state of a car as it was last seen by the `MatsimEventsWriter`.
+#[derive(Debug)]
+struct CarState {
+    link: u32,
+    halted: bool,
+    snapshot: u32,
+}
+
+/// Writes MATSim compatible events to a file. The road is split into links of `link_length` cells
+/// spanning all lanes. Link `n` covers the cells `n * link_length` up to `(n + 1) * link_length`.
+///
+/// The format is XML unless the file extension is `csv`, in which case every event is written as a
+/// `time;type;link;vehicle` row.
+#[derive(Debug)]
+pub struct MatsimEventsWriter {
+    writer: BufWriter<File>,
+    csv: bool,
+    link_length: u32,
+    n_links: u32,
+    snapshots: u32,
+    cars: HashMap<u32, CarState>,
+}
+
+impl MatsimEventsWriter {
+    pub fn new(road: &Road, link_length: u32, filepath: PathBuf) -> io::Result<Self> {
+        if link_length == 0 {
+            panic!("MATSim link length must be at least one cell.");
+        }
+        let csv = filepath.extension().is_some_and(|extension| extension == "csv");
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        if csv {
+            writeln!(writer, "time;type;link;vehicle")?;
+        } else {
+            writeln!(writer, "<?xml version=\"1.0\" encoding=\"utf-8\"?>")?;
+            writeln!(writer, "<events version=\"1.0\">")?;
+        }
+        Ok(Self {
+            writer,
+            csv,
+            link_length,
+            n_links: road.length().div_ceil(link_length),
+            snapshots: 0,
+            cars: HashMap::new(),
+        })
+    }
+
+    /// Compares the positions and speeds of all cars on the road to the last snapshot and writes
+    /// the resulting events. Cars that are seen for the first time enter traffic and cars that are
+    /// no longer on the road leave traffic.
+    pub fn take_snapshot(&mut self, road: &Road, time_s: f64) -> io::Result<()> {
+        self.snapshots += 1;
+        let snapshot = self.snapshots;
+        for lane in road.cells() {
+            for (cell_i, cell) in lane.iter().enumerate() {
+                let Some(car) = cell.car() else { continue; };
+                let link = cell_i as u32 / self.link_length;
+                let halted = car.speed() == 0;
+
+                let Some(last) = self.cars.insert(car.id(), CarState { link, halted, snapshot }) else {
+                    self.write_event(time_s, "vehicle enters traffic", link, car.id())?;
+                    continue;
+                };
+
+                // Links are passed in driving direction and wrap around at the end of the road.
+                let passed_links = (link + self.n_links - last.link) % self.n_links;
+                for passed in 0..passed_links {
+                    let left = (last.link + passed) % self.n_links;
+                    self.write_event(time_s, "left link", left, car.id())?;
+                    self.write_event(time_s, "entered link", (left + 1) % self.n_links, car.id())?;
+                }
+
+                if halted && !last.halted {
+                    self.write_event(time_s, "vehicle halts", link, car.id())?;
+                } else if !halted && last.halted {
+                    self.write_event(time_s, "vehicle resumes", link, car.id())?;
+                }
+            }
+        }
+
+        let mut exited: Vec<(u32, u32)> = self.cars
+            .iter()
+            .filter(|(_, state)| state.snapshot != snapshot)
+            .map(|(id, state)| (*id, state.link))
+            .collect();
+        exited.sort_unstable();
+        for (id, link) in exited {
+            self.cars.remove(&id);
+            self.write_event(time_s, "vehicle leaves traffic", link, id)?;
+        }
+        Ok(())
+    }
+
+    fn write_event(&mut self, time_s: f64, event_type: &str, link: u32, vehicle: u32) -> io::Result<()> {
+        if self.csv {
+            writeln!(self.writer, "{:.1};{};{};{}", time_s, event_type, link, vehicle)
+        } else {
+            writeln!(
+                self.writer,
+                "\t<event time=\"{:.1}\" type=\"{}\" link=\"{}\" vehicle=\"{}\" />",
+                time_s, event_type, link, vehicle
+            )
+        }
+    }
+
+    /// Closes the event list and flushes all buffered events to the file.
+    pub fn close(&mut self) -> io::Result<()> {
+        if !self.csv {
+            writeln!(self.writer, "</events>")?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl Observer for MatsimEventsWriter {
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(self.take_snapshot(road, road.rounds() as f64 * ROUND_S)?)
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(self.close()?)
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.writer.flush()?)
+    }
+}
@@ -0,0 +1,29 @@
+use std::error::Error;
+use crate::Road;
+
+/// Watches the road while the simulation runs. All output of the simulator (printing, animating,
+/// drawing and exporting) is done by observers, so that the road itself stays free of side effects.
+pub trait Observer {
+    /// Observes the road before the first round.
+    fn start(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        self.observe(road)
+    }
+
+    /// Observes the road after a round.
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>>;
+
+    /// Observes the road after the last round and completes the output.
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Writes buffered output to disk, so that it is kept if the simulation does not finish.
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Returns the number of bytes the observer keeps in memory for its output at most.
+    fn buffer_bytes(&self) -> usize {
+        0
+    }
+}
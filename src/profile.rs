@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+/// The parameters for a period of time starting at `start_round`. Parameters without a value keep
+/// the value the road was created with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterBin {
+    start_round: u32,
+    dilly_dally_probability: Option<f32>,
+    stay_in_lane_probability: Option<f32>,
+    max_speeds: Vec<Option<u8>>,
+}
+
+impl ParameterBin {
+    pub fn dilly_dally_probability(&self) -> Option<f32> {
+        self.dilly_dally_probability
+    }
+
+    pub fn stay_in_lane_probability(&self) -> Option<f32> {
+        self.stay_in_lane_probability
+    }
+
+    /// Returns the maximum speed of the vehicle type if the bin sets one.
+    pub fn max_speed(&self, vehicle_type: usize) -> Option<u8> {
+        self.max_speeds.get(vehicle_type).copied().flatten()
+    }
+
+    /// Returns the maximum speeds by vehicle type. Empty if none were specified.
+    pub fn max_speeds(&self) -> &Vec<Option<u8>> {
+        &self.max_speeds
+    }
+}
+
+/// A profile of time-binned model parameters, e.g. for switching between the behavior of drivers
+/// at night and during peak hours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterProfile {
+    bins: Vec<ParameterBin>,
+}
+
+impl ParameterProfile {
+    /// Returns the bin that is active during the given round, if any.
+    pub fn bin_at(&self, round: u32) -> Option<&ParameterBin> {
+        self.bins.iter().rev().find(|bin| bin.start_round <= round)
+    }
+
+    /// Returns all bins ordered by their start round.
+    pub fn bins(&self) -> &Vec<ParameterBin> {
+        &self.bins
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseParameterProfileError {
+    pub line: usize,
+}
+
+/// Parses an optional field. Empty fields are `None`.
+fn parse_optional<T: FromStr>(field: Option<&&str>, err: ParseParameterProfileError) -> Result<Option<T>, ParseParameterProfileError> {
+    match field {
+        Some(field) if !field.is_empty() => field.parse::<T>().map(Some).map_err(|_| err),
+        _ => Ok(None),
+    }
+}
+
+impl FromStr for ParameterProfile {
+    type Err = ParseParameterProfileError;
+
+    /// Parses CSV rows of the format
+    /// `start_round, dilly_dally_probability, stay_in_lane_probability[, max_speed_0, max_speed_1, ...]`.
+    /// Fields may be left empty to keep the value the road was created with. Empty lines, lines
+    /// starting with `#` and a header row are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bins = Vec::new();
+        let mut first_row = true;
+        for (line_i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let is_first_row = first_row;
+            first_row = false;
+
+            let err = ParseParameterProfileError { line: line_i + 1 };
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let start_round = match fields[0].parse::<u32>() {
+                Ok(start_round) => start_round,
+                Err(_) if is_first_row => continue, // header
+                Err(_) => return Err(err),
+            };
+            let dilly_dally_probability = parse_optional::<f32>(fields.get(1), err)?;
+            let stay_in_lane_probability = parse_optional::<f32>(fields.get(2), err)?;
+            let max_speeds = (3..fields.len())
+                .map(|field_i| parse_optional::<u8>(fields.get(field_i), err))
+                .collect::<Result<Vec<Option<u8>>, _>>()?;
+
+            let probability_valid = |probability: Option<f32>| probability.is_none_or(|probability| (0.0..=1.0).contains(&probability));
+            if !probability_valid(dilly_dally_probability) || !probability_valid(stay_in_lane_probability) || max_speeds.contains(&Some(0)) {
+                return Err(err);
+            }
+            bins.push(ParameterBin { start_round, dilly_dally_probability, stay_in_lane_probability, max_speeds });
+        }
+        bins.sort_by_key(|bin| bin.start_round);
+        Ok(ParameterProfile { bins })
+    }
+}
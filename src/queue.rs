@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::{Road, CELL_M};
+
+/// Detects jammed cars, which have driven at most one cell per round for at least `min_rounds`
+/// rounds in a row, and measures the queues of jammed cars upstream of blocked cells and traffic
+/// lights. A queue is the jammed cars in the consecutive cells right behind the obstacle.
+#[derive(Debug)]
+pub struct QueueDetector {
+    min_rounds: u32,
+    snapshots: u32,
+    jammed_cars: u64,
+    max_jammed_cars: u32,
+    /// The summed up and the longest queue in cars behind each obstacle, by lane and cell.
+    queues: BTreeMap<(usize, usize), (u64, u32)>,
+}
+
+/// The jammed cars and the queues of a run. The averages are taken over all rounds, including
+/// those without jammed cars or queues.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct QueueStatistics {
+    pub average_jammed_cars: f64,
+    pub max_jammed_cars: u32,
+    pub max_queue_cars: u32,
+    pub max_queue_m: f64,
+    pub queues: Vec<Queue>,
+}
+
+/// The queue behind a blocked cell or a traffic light.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Queue {
+    pub lane: usize,
+    pub cell: usize,
+    pub average_queue_cars: f64,
+    pub max_queue_cars: u32,
+}
+
+impl QueueDetector {
+    pub fn new(min_rounds: u32) -> Self {
+        if min_rounds == 0 {
+            panic!("Cars must be slow for at least one round to be jammed.");
+        }
+        Self {
+            min_rounds,
+            snapshots: 0,
+            jammed_cars: 0,
+            max_jammed_cars: 0,
+            queues: BTreeMap::new(),
+        }
+    }
+
+    /// Counts the jammed cars and measures the queues after a round.
+    pub fn take_snapshot(&mut self, road: &Road) {
+        self.snapshots += 1;
+        let jammed = |lane_i: usize, cell_i: usize| road.cells()[lane_i][cell_i].car().is_some_and(|car| car.slow_rounds() >= self.min_rounds);
+        let length = road.length() as usize;
+        let mut jammed_cars = 0;
+        for (lane_i, lane) in road.cells().iter().enumerate() {
+            jammed_cars += (0..length).filter(|cell_i| jammed(lane_i, *cell_i)).count() as u32;
+            for (cell_i, cell) in lane.iter().enumerate() {
+                if !cell.blocked() && !cell.traffic_light() {
+                    continue;
+                }
+                // Follow the queue upstream, around the end of a closed road, until a cell without
+                // a jammed car.
+                let queue = (1..length)
+                    .take_while(|distance| !road.open() || *distance <= cell_i)
+                    .take_while(|distance| jammed(lane_i, (cell_i + length - distance) % length))
+                    .count() as u32;
+                let (sum, max) = self.queues.entry((lane_i, cell_i)).or_default();
+                *sum += queue as u64;
+                *max = (*max).max(queue);
+            }
+        }
+        self.jammed_cars += jammed_cars as u64;
+        self.max_jammed_cars = self.max_jammed_cars.max(jammed_cars);
+    }
+
+    pub fn statistics(self) -> QueueStatistics {
+        let max_queue_cars = self.queues.values().map(|(_, max)| *max).max().unwrap_or(0);
+        QueueStatistics {
+            average_jammed_cars: self.jammed_cars as f64 / self.snapshots as f64,
+            max_jammed_cars: self.max_jammed_cars,
+            max_queue_cars,
+            max_queue_m: max_queue_cars as f64 * CELL_M,
+            queues: self.queues
+                .into_iter()
+                .map(|((lane, cell), (sum, max))| Queue {
+                    lane,
+                    cell,
+                    average_queue_cars: sum as f64 / self.snapshots as f64,
+                    max_queue_cars: max,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use crate::{run_sim, SimulationConfig};
+
+/// The statistics of a metric over the replications of a scenario. The confidence interval is the
+/// 95% confidence interval of the mean, based on the t-distribution.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MetricStatistics {
+    /// The number of replications that reported the metric.
+    pub n: u32,
+    pub mean: f64,
+    pub sd: f64,
+    pub ci95_low: f64,
+    pub ci95_high: f64,
+}
+
+impl MetricStatistics {
+    /// Returns the statistics of the values, which must not be empty.
+    fn new(values: &[f64]) -> Self {
+        let n = values.len();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let sd = match n {
+            1 => f64::NAN,
+            _ => (values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt(),
+        };
+        let half_width = t_quantile_975(n as u32 - 1) * sd / (n as f64).sqrt();
+        Self {
+            n: n as u32,
+            mean,
+            sd,
+            ci95_low: mean - half_width,
+            ci95_high: mean + half_width,
+        }
+    }
+}
+
+/// The aggregated result of running a scenario several times with different seeds.
+#[derive(Serialize, Debug)]
+pub struct ReplicationResult {
+    pub replications: u32,
+    pub seeds: Vec<u64>,
+    /// The statistics of every numeric metric of the simulation result. Metrics inside lists and
+    /// objects are named by their path, e.g. `monitor_cells_flow_cars_per_minute.0` or
+    /// `vehicle_types.1.average_speed_kilometers_per_hour`.
+    pub metrics: BTreeMap<String, MetricStatistics>,
+}
+
+/// Returns the 97.5% quantile of the t-distribution with the degrees of freedom.
+fn t_quantile_975(degrees_of_freedom: u32) -> f64 {
+    const QUANTILES: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+        2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+        2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+    ];
+    match degrees_of_freedom {
+        0 => f64::NAN,
+        1..=30 => QUANTILES[degrees_of_freedom as usize - 1],
+        // Cornish-Fisher expansion around the normal quantile
+        df => 1.96 + (1.96f64.powi(3) + 1.96) / (4.0 * df as f64),
+    }
+}
+
+/// Adds every number in the value to the metrics, named by its path.
+fn collect_metrics(path: String, value: &Value, metrics: &mut BTreeMap<String, Vec<f64>>) {
+    match value {
+        Value::Number(number) => metrics.entry(path).or_default().extend(number.as_f64()),
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                collect_metrics(format!("{}.{}", path, i), value, metrics);
+            }
+        },
+        Value::Object(values) => {
+            for (name, value) in values {
+                let path = match path.is_empty() {
+                    true => name.clone(),
+                    false => format!("{}.{}", path, name),
+                };
+                collect_metrics(path, value, metrics);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Runs the scenario the number of times with the seeds `seed`, `seed + 1`, ... or random seeds
+/// without a seed. The runs are spread over all cores and outputs of the scenario are not written.
+/// With `raw_results`, the result of every run is written to that file as one line of JSON.
+pub fn run(args: &SimulationConfig, replications: u32, raw_results: Option<&Path>) -> io::Result<ReplicationResult> {
+    let seeds: Vec<u64> = (0..replications)
+        .map(|i| args.seed.map_or_else(rand::random, |seed| seed.wrapping_add(i as u64)))
+        .collect();
+    let results: Vec<Value> = seeds
+        .par_iter()
+        .map(|seed| {
+            let config = SimulationConfig { seed: Some(*seed), ..args.clone().without_outputs() };
+            serde_json::to_value(run_sim(config)).expect("Unable to serialize simulation result.")
+        })
+        .collect();
+    if let Some(path) = raw_results {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for result in &results {
+            writeln!(writer, "{}", serde_json::to_string(result)?)?;
+        }
+        writer.flush()?;
+    }
+
+    let mut values = BTreeMap::new();
+    for mut result in results {
+        result.as_object_mut().unwrap().remove("seed");
+        collect_metrics(String::new(), &result, &mut values);
+    }
+    Ok(ReplicationResult {
+        replications,
+        seeds,
+        metrics: values.iter().map(|(name, values)| (name.clone(), MetricStatistics::new(values))).collect(),
+    })
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use crate::{SimulationConfig, SimulationResult};
+
+/// Writes a standalone HTML report of a simulation run with its settings, the scalar metrics of
+/// its result, warnings about suspicious results and the images and charts the run created. The
+/// images are embedded, so the report can be shared as a single file.
+pub fn write(path: &Path, args: &SimulationConfig, result: &SimulationResult) -> io::Result<()> {
+    let settings = serde_yaml::to_string(args).map_err(io::Error::other)?;
+    let metrics = match serde_json::to_value(result).map_err(io::Error::other)? {
+        Value::Object(fields) => fields
+            .into_iter()
+            .filter(|(_, value)| matches!(value, Value::Number(_) | Value::Bool(_)))
+            .map(|(name, value)| format!("<tr><td>{}</td><td>{}</td></tr>\n", name, value))
+            .collect::<String>(),
+        _ => String::new(),
+    };
+    let warnings = warnings(result)
+        .iter()
+        .map(|warning| format!("<li>{}</li>\n", escape(warning)))
+        .collect::<String>();
+
+    let mut figures = String::new();
+    if args.image && args.out_path.is_file() {
+        let png = fs::read(&args.out_path)?;
+        figures += &format!("<figure><img src=\"data:image/png;base64,{}\" alt=\"space-time diagram\"></figure>\n", base64(&png));
+    }
+    for chart in chart_paths(args) {
+        if chart.is_file() {
+            figures += &format!("<figure>{}</figure>\n", fs::read_to_string(chart)?);
+        }
+    }
+
+    fs::write(
+        path,
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Traffic Simulation Report</title>\n<style>{}</style>\n</head>\n<body>\n\
+            <h1>Traffic Simulation Report</h1>\n\
+            <h2>Warnings</h2>\n{}\n\
+            <h2>Metrics</h2>\n<table>\n{}</table>\n\
+            <h2>Images &amp; Charts</h2>\n{}\
+            <h2>Settings</h2>\n<pre>{}</pre>\n\
+            </body>\n</html>\n",
+            STYLE,
+            if warnings.is_empty() { "<p>None</p>".to_string() } else { format!("<ul>\n{}</ul>", warnings) },
+            metrics,
+            if figures.is_empty() { "<p>None</p>\n".to_string() } else { figures },
+            escape(&settings)
+        ),
+    )
+}
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 1200px; margin: auto; } \
+    td { padding: 2px 12px; } tr:nth-child(even) { background: #eee; } \
+    img, svg { max-width: 100%; height: auto; image-rendering: pixelated; } \
+    pre { background: #eee; padding: 8px; }";
+
+/// Returns the paths of the charts that the run may have rendered.
+fn chart_paths(args: &SimulationConfig) -> Vec<PathBuf> {
+    match args.charts {
+        true => vec![args.chart_path("speed"), args.chart_path("flow")],
+        false => vec![],
+    }
+}
+
+/// Returns warnings about results that hint at a mistake in the settings.
+fn warnings(result: &SimulationResult) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if result.cars == 0 {
+        warnings.push("There were no cars on the road.".to_string());
+    }
+    if result.waiting_cars > 0 {
+        warnings.push(format!("{} cars were still waiting to enter the road at the end, so the demand exceeded what the road could take.", result.waiting_cars));
+    }
+    warnings
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Encodes bytes as standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for sextet_i in 0..4 {
+            if sextet_i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * sextet_i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
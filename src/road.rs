@@ -1,21 +1,51 @@
-use std::{cmp, fmt, isize};
+use std::cmp;
+use std::collections::{HashSet, VecDeque};
 use rand::prelude::*;
-use crate::cell::{Cell, CellLocation, CellLocationRange, PutCarErrorInformation};
-use crate::car::{Car, VehicleBlueprint};
+use serde::Serialize;
+use crate::cell::{Cell, CellLocation, CellLocationRange, PutCarErrorInformation, ReservedZone, SpeedLimitZone};
+use crate::car::{pick_weighted, Car, CarId, CarRecords, VehicleBlueprint, VehicleClass};
+use crate::demand::DemandProfile;
+use crate::driver::DriverDistributions;
+use crate::density::{DensitySchedule, LaneDensity};
+use crate::profile::ParameterProfile;
+use crate::weather::WeatherSchedule;
+use crate::layout::{ParseRoadLayoutError, RoadLayout};
 use crate::flip_flop::FlipFlop;
-use colored::Colorize;
+use crate::incident::Incident;
+use crate::traffic_light::{GreenWave, LightState, LightTiming, TrafficLight};
+use crate::trip::Trip;
+use crate::section::Section;
+use crate::{ArrivalProcess, BrakingOverrun, DrivingModel, LaneRules, UpdateOrder};
+
+/// The number of lane changes to the left and to the right.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LaneChanges {
+    pub left: u64,
+    pub right: u64,
+}
+
+impl LaneChanges {
+    pub fn total(&self) -> u64 {
+        self.left + self.right
+    }
+
+    pub fn merge(&mut self, other: &LaneChanges) {
+        self.left += other.left;
+        self.right += other.right;
+    }
+}
 
 #[derive(Debug)]
 enum LaneSwitch {
-    Left(u8),
-    Right(u8),
-    Stay(u8),
+    Left(u32),
+    Right(u32),
+    Stay(u32),
 }
 
 impl LaneSwitch {
     /// Returns the number of fields that are driveable (allowed as well as enough speed to be used)
     /// based on the lane switch.
-    pub fn driveable(&self) -> u8 {
+    pub fn driveable(&self) -> u32 {
         match self {
             LaneSwitch::Left(cells) => *cells,
             LaneSwitch::Right(cells) => *cells,
@@ -41,57 +71,693 @@ impl LaneSwitch {
 /// Represents a road.
 #[derive(Debug)]
 pub struct Road {
-    rng: ThreadRng,
+    rng: StdRng,
     lanes: Vec<Vec<Cell>>,
     n_lanes: u32,
     length: u32,
-    cells_to_next_cars: Vec<u8>,
-    cells_to_next_obstacles: Vec<u8>,
+    cells_to_next_cars: Vec<u32>,
+    cells_to_next_obstacles: Vec<u32>,
+    /// The cells that the next car ahead in each lane drives in the current round, if the driving
+    /// model anticipates it.
+    next_car_speeds: Vec<u32>,
     rounds: u32,
+    /// The round after which the records have last been reset.
+    records_since: u32,
     n_cars: u32,
+    n_exited_cars: u32,
+    n_removed_cars: u32,
+    car_rounds: u64,
+    laps: u64,
+    lap_times: Vec<u32>,
+    congestion_threshold: f32,
+    congested_fraction: f64,
+    update_order: UpdateOrder,
+    driving_model: DrivingModel,
+    lane_rules: LaneRules,
+    preemption_distance: Option<u32>,
+    preempted_light_rounds: u64,
+    /// The cells behind them within which cars notice an emergency vehicle in their lane.
+    emergency_yield_distance: Option<u32>,
+    emergency_red_light_running: bool,
+    emergency_travel_times: Vec<u32>,
+    /// The probability with which a working traffic light fails in a round and the mean number of
+    /// rounds a failure lasts.
+    light_failures: Option<(f32, f64)>,
+    /// The indexes of the cells that have a traffic light in any lane.
+    traffic_light_indexes: Vec<usize>,
+    n_light_failures: u64,
+    failed_light_rounds: u64,
+    near_miss_braking: u8,
+    near_misses: u64,
+    turn_signals: bool,
+    signal_cells: Vec<Option<usize>>,
+    courtesy_probability: f32,
+    merge_cells: Vec<Option<usize>>,
+    yields: u64,
+    mandatory_lane_changes: bool,
+    blockage_look_ahead: Option<u32>,
+    n_mandatory_lane_changes: u64,
+    /// The blocks that are only in place for some of the rounds.
+    scheduled_blocks: Vec<CellLocationRange>,
+    /// The cells, by lane and index, that are blocked by a scheduled block and not by a permanent
+    /// one. Only these are unblocked again when their scheduled blocks are not in place.
+    scheduled_cells: HashSet<(usize, usize)>,
+    /// The probability with which an incident blocks a random cell in a round and the mean number
+    /// of rounds it lasts.
+    incident_rate: Option<(f32, f64)>,
+    incidents: Vec<Incident>,
+    /// The speed limit and the dilly-dally probability of drivers passing a blocked cell in the
+    /// lane next to them.
+    rubbernecking: Option<(Option<u8>, Option<f32>)>,
+    reserved_cells: bool,
+    /// The cells per round by which cars can brake at most in a round, what happens if they have
+    /// to brake harder and how many times as hard they can brake in an emergency.
+    max_deceleration: Option<(u8, BrakingOverrun, f32)>,
+    collision_rounds: u32,
+    n_collisions: u64,
+    n_emergency_brakings: u64,
+    gap_counters: Option<Vec<Vec<(u32, u32)>>>,
+    /// The cells whose local density is measured, the number of cells on either side of them that
+    /// count towards it and the cars that were within those cells at the end of each round, summed
+    /// up over the rounds.
+    density_monitors: Vec<CellLocation>,
+    density_window: u32,
+    density_monitor_cars: Vec<u64>,
+    sections: Vec<Section>,
+    /// The lane changes by the lane that the cars left and by vehicle type.
+    lane_change_counts: Vec<Vec<LaneChanges>>,
+    lanes_processed: Vec<bool>,
+    exited_car_records: Vec<CarRecords>,
+    trip_recording: bool,
+    arrived_trips: Vec<Trip>,
     overflow_flip_flop: FlipFlop,
     dilly_dally_probability: f32,
     stay_in_lane_probability: f32,
-    traffic_lights_red: bool,
+    parameter_profile: Option<ParameterProfile>,
+    driver_distributions: Option<DriverDistributions>,
+    weather_schedule: Option<WeatherSchedule>,
+    active_dilly_dally_probability: f32,
+    active_stay_in_lane_probability: f32,
+    active_max_speeds: Vec<u8>,
+    traffic_lights: Vec<TrafficLight>,
+    light_states: Vec<LightState>,
+    held_traffic_lights: Vec<(usize, usize)>,
+    vehicle_blueprints: Vec<VehicleBlueprint>,
+    demand: Option<DemandProfile>,
+    arrival_process: ArrivalProcess,
+    arrival_batch_size: u32,
+    /// The fraction of a car that has arrived but not yet been let in by the uniform process.
+    arrival_credit: f64,
+    next_arrival_lane: usize,
+    density_schedule: Option<DensitySchedule>,
+    waiting_cars: Vec<VecDeque<usize>>,
 }
 
 impl Road {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lanes: u32,
         length: u32,
-        vehicle_blueprints: &Vec<VehicleBlueprint>,
+        vehicle_blueprints: &[VehicleBlueprint],
         dilly_dally_probability: f32, 
         stay_in_lane_probability: f32,
-        block: &Vec<CellLocationRange>,
-        traffic_lights: &Vec<CellLocation>,
+        block: &[CellLocationRange],
+        traffic_lights: &[TrafficLight],
+        seed: Option<u64>,
     ) -> Self {
+        Self::check_locations(lanes, length, block, traffic_lights);
+        let mut rng = Self::seeded_rng(seed);
+        let mut lanes = Self::create_lanes_and_cells(lanes, length);
+        let unblocked_cells_per_lane = Self::block_cells(&mut lanes, length, block);
+        Self::add_traffic_lights(&mut lanes, traffic_lights);
+        let n_cars = Self::add_cars(&mut lanes, unblocked_cells_per_lane, &mut rng, vehicle_blueprints);
 
-        if !(0.0..=1.0).contains(&dilly_dally_probability) {
-            panic!("Dilly-dally probability must be a number between 0 and 1.");
+        let mut road = Self::from_lanes(lanes, length, n_cars, rng, vehicle_blueprints, dilly_dally_probability, stay_in_lane_probability);
+        road.set_traffic_lights(traffic_lights.to_vec());
+        road.scheduled_blocks = block.iter().filter(|blocked| blocked.scheduled()).cloned().collect();
+        let permanent_cells = Self::cells_of(block.iter().filter(|blocked| !blocked.scheduled()));
+        road.scheduled_cells = &Self::cells_of(road.scheduled_blocks.iter().filter(|blocked| blocked.active_in(0))) - &permanent_cells;
+        road
+    }
+
+    /// Creates a road from an ASCII layout. (See: `RoadLayout`) If the layout contains spawn
+    /// points, exactly one car is placed on each of them and the traffic densities only weight the
+    /// vehicle types. Otherwise cars are added according to the traffic densities.
+    pub fn from_layout(
+        layout: &str,
+        vehicle_blueprints: &[VehicleBlueprint],
+        dilly_dally_probability: f32,
+        stay_in_lane_probability: f32,
+        seed: Option<u64>,
+    ) -> Result<Self, ParseRoadLayoutError> {
+        let layout = layout.parse::<RoadLayout>()?;
+
+        let mut rng = Self::seeded_rng(seed);
+        let mut lanes = Self::create_lanes_and_cells(layout.lanes(), layout.length());
+        let unblocked_cells_per_lane = Self::block_cells(&mut lanes, layout.length(), layout.block());
+        let traffic_lights: Vec<TrafficLight> = layout
+            .traffic_lights()
+            .iter()
+            .map(|location| TrafficLight::new(location.clone(), LightTiming::default()))
+            .collect();
+        Self::add_traffic_lights(&mut lanes, &traffic_lights);
+        for (location, limit) in layout.speed_limits() {
+            lanes[location.lane()][location.index()].limit_speed(*limit);
         }
+        let n_cars = if layout.spawn_points().is_empty() {
+            Self::add_cars(&mut lanes, unblocked_cells_per_lane, &mut rng, vehicle_blueprints)
+        } else {
+            if vehicle_blueprints.is_empty() {
+                panic!("At least one vehicle type is required for the spawn points.");
+            }
+            for (id, location) in layout.spawn_points().iter().enumerate() {
+                let vehicle_type = VehicleBlueprint::pick_by_density(&mut rng, vehicle_blueprints);
+                lanes[location.lane()][location.index()].put_car(Box::new(Car::new(id as u32, vehicle_type, &vehicle_blueprints[vehicle_type])), 0).unwrap();
+            }
+            layout.spawn_points().len() as u32
+        };
 
-        let mut rng = thread_rng();
-        let n_lanes = lanes;
+        let mut road = Self::from_lanes(lanes, layout.length(), n_cars, rng, vehicle_blueprints, dilly_dally_probability, stay_in_lane_probability);
+        road.set_traffic_lights(traffic_lights);
+        Ok(road)
+    }
 
-        let mut lanes = Self::create_lanes_and_cells(n_lanes, length);
-        let unblocked_cells_per_lane = Self::block_cells(&mut lanes, length, block);
-        Self::add_traffic_lights(&mut lanes, traffic_lights);
-        let n_cars = Self::add_cars(&mut lanes, unblocked_cells_per_lane, &mut rng, vehicle_blueprints);
+    /// Creates the random number generator of a road. A road with a seed makes the same random
+    /// decisions on every run, one without a seed different ones.
+    fn seeded_rng(seed: Option<u64>) -> StdRng {
+        match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    fn from_lanes(
+        lanes: Vec<Vec<Cell>>,
+        length: u32,
+        n_cars: u32,
+        rng: StdRng,
+        vehicle_blueprints: &[VehicleBlueprint],
+        dilly_dally_probability: f32,
+        stay_in_lane_probability: f32,
+    ) -> Self {
+        if !(0.0..=1.0).contains(&dilly_dally_probability) {
+            panic!("Dilly-dally probability must be a number between 0 and 1.");
+        }
+        Self::check_lane_weights(lanes.len(), vehicle_blueprints);
 
+        let n_lanes = lanes.len() as u32;
+        let traffic_light_indexes = (0..length as usize).filter(|cell_i| lanes.iter().any(|lane| lane[*cell_i].traffic_light())).collect();
         Self {
             rng,
             lanes,
             n_lanes,
             length,
-            cells_to_next_cars: vec![255u8; n_lanes as usize],
-            cells_to_next_obstacles: vec![255u8; n_lanes as usize],
+            cells_to_next_cars: vec![u32::MAX; n_lanes as usize],
+            cells_to_next_obstacles: vec![u32::MAX; n_lanes as usize],
+            next_car_speeds: vec![0; n_lanes as usize],
             rounds: 0,
+            records_since: 0,
             n_cars,
+            n_exited_cars: 0,
+            n_removed_cars: 0,
+            car_rounds: 0,
+            laps: 0,
+            lap_times: Vec::new(),
+            congestion_threshold: 0.5,
+            congested_fraction: f64::NAN,
+            update_order: UpdateOrder::Sequential,
+            driving_model: DrivingModel::Nasch,
+            lane_rules: LaneRules::Symmetric,
+            preemption_distance: None,
+            preempted_light_rounds: 0,
+            emergency_yield_distance: None,
+            emergency_red_light_running: false,
+            emergency_travel_times: Vec::new(),
+            light_failures: None,
+            traffic_light_indexes,
+            n_light_failures: 0,
+            failed_light_rounds: 0,
+            near_miss_braking: 2,
+            near_misses: 0,
+            turn_signals: false,
+            signal_cells: vec![None; n_lanes as usize],
+            courtesy_probability: 0.0,
+            merge_cells: vec![None; n_lanes as usize],
+            yields: 0,
+            mandatory_lane_changes: false,
+            blockage_look_ahead: None,
+            n_mandatory_lane_changes: 0,
+            scheduled_blocks: Vec::new(),
+            scheduled_cells: HashSet::new(),
+            incident_rate: None,
+            incidents: Vec::new(),
+            rubbernecking: None,
+            reserved_cells: false,
+            max_deceleration: None,
+            collision_rounds: 10,
+            n_collisions: 0,
+            n_emergency_brakings: 0,
+            gap_counters: None,
+            density_monitors: Vec::new(),
+            density_window: 0,
+            density_monitor_cars: Vec::new(),
+            sections: Vec::new(),
+            lane_change_counts: vec![vec![LaneChanges::default(); vehicle_blueprints.len()]; n_lanes as usize],
+            lanes_processed: vec![false; n_lanes as usize],
+            exited_car_records: vec![CarRecords::default(); vehicle_blueprints.len()],
+            trip_recording: false,
+            arrived_trips: Vec::new(),
             overflow_flip_flop: FlipFlop::new(),
             dilly_dally_probability,
             stay_in_lane_probability,
-            traffic_lights_red: false,
+            parameter_profile: None,
+            driver_distributions: None,
+            weather_schedule: None,
+            active_dilly_dally_probability: dilly_dally_probability,
+            active_stay_in_lane_probability: stay_in_lane_probability,
+            active_max_speeds: vehicle_blueprints.iter().map(|vb| vb.max_speed()).collect(),
+            traffic_lights: Vec::new(),
+            light_states: Vec::new(),
+            held_traffic_lights: Vec::new(),
+            vehicle_blueprints: vehicle_blueprints.to_vec(),
+            demand: None,
+            arrival_process: ArrivalProcess::Bernoulli,
+            arrival_batch_size: 1,
+            arrival_credit: 0.0,
+            next_arrival_lane: 0,
+            density_schedule: None,
+            waiting_cars: vec![VecDeque::new(); n_lanes as usize],
+        }
+    }
+
+    /// Opens the road. Instead of driving in a loop, cars leave the road at its end and new cars
+    /// arrive at its start according to the demand profile. Arriving cars wait in a queue in front
+    /// of their lane until its first cell is free.
+    pub fn with_demand(mut self, demand: DemandProfile) -> Self {
+        if self.vehicle_blueprints.is_empty() {
+            panic!("At least one vehicle type is required for the demand.");
+        }
+        if demand.bins().iter().any(|bin| !bin.shares().is_empty() && bin.shares().len() != self.vehicle_blueprints.len()) {
+            panic!("The number of vehicle type shares in the demand must match the number of vehicle types.");
+        }
+        self.demand = Some(demand);
+        self
+    }
+
+    /// Replaces the cars placed by the traffic densities with exactly `traffic_density * unblocked
+    /// cells` cars (rounded) of each vehicle type, placed in cells picked uniformly at random from
+    /// the whole road. Spawning lane by lane rounds the number of cars in every lane and prefers
+    /// cells near the start of the lanes.
+    pub fn with_exact_spawning(mut self) -> Self {
+        let unblocked_cells = self.lanes.iter().flatten().filter(|cell| !cell.blocked()).count() as f32;
+        let counts: Vec<u32> = self.vehicle_blueprints
+            .iter()
+            .map(|vb| (vb.traffic_density() * unblocked_cells).round() as u32)
+            .collect();
+        self.respawn_cars(&counts);
+        self
+    }
+
+    /// Replaces the cars placed by the traffic densities with exactly `counts[vehicle_type]` cars of
+    /// each vehicle type, placed in cells picked uniformly at random from the whole road.
+    pub fn with_car_counts(mut self, counts: &[u32]) -> Self {
+        if counts.len() != self.vehicle_blueprints.len() {
+            panic!("The number of car counts must match the number of vehicle types.");
+        }
+        self.respawn_cars(counts);
+        self
+    }
+
+    /// Replaces the cars placed by the traffic densities with exactly `cars` cars, which are split
+    /// among the vehicle types in proportion to their traffic densities (largest remainders get the
+    /// leftover cars) and placed in cells picked uniformly at random from the whole road.
+    pub fn with_total_cars(self, cars: u32) -> Self {
+        if self.vehicle_blueprints.len() == 1 {
+            return self.with_car_counts(&[cars]);
+        }
+        let total_density: f64 = self.vehicle_blueprints.iter().map(|vb| vb.traffic_density() as f64).sum();
+        if total_density <= 0.0 {
+            panic!("At least one vehicle type must have a traffic density above 0 to split the cars.");
+        }
+        let shares: Vec<f64> = self.vehicle_blueprints
+            .iter()
+            .map(|vb| vb.traffic_density() as f64 / total_density * cars as f64)
+            .collect();
+        let mut counts: Vec<u32> = shares.iter().map(|share| share.floor() as u32).collect();
+        let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+        by_remainder.sort_by(|a, b| (shares[*b] - shares[*b].floor()).total_cmp(&(shares[*a] - shares[*a].floor())));
+        let leftover = cars - counts.iter().sum::<u32>();
+        for vehicle_type in by_remainder.into_iter().take(leftover as usize) {
+            counts[vehicle_type] += 1;
+        }
+        self.with_car_counts(&counts)
+    }
+
+    /// Replaces the cars placed by the traffic densities with `lane density * unblocked cells of the
+    /// lane` cars (rounded) of each vehicle type in each lane, placed in cells of the lane picked
+    /// uniformly at random. A total lane density is split among the vehicle types that may use the
+    /// lane in proportion to their traffic densities.
+    pub fn with_lane_densities(mut self, lane_densities: &[LaneDensity]) -> Self {
+        if lane_densities.len() != self.lanes.len() {
+            panic!("The number of lane densities must match the number of lanes.");
+        }
+        let counts_by_lane: Vec<Vec<u32>> = self.lanes
+            .iter()
+            .zip(lane_densities)
+            .enumerate()
+            .map(|(lane_i, (lane, lane_density))| {
+                let densities: Vec<f32> = match lane_density {
+                    LaneDensity::Total(density) => {
+                        let traffic_densities: Vec<f32> = self.vehicle_blueprints
+                            .iter()
+                            .map(|vb| if vb.allows_lane(lane_i) { vb.traffic_density() } else { 0.0 })
+                            .collect();
+                        let total_density: f32 = traffic_densities.iter().sum();
+                        if total_density <= 0.0 {
+                            panic!("At least one vehicle type must have a traffic density above 0 to split a lane density.");
+                        }
+                        traffic_densities.iter().map(|traffic_density| traffic_density / total_density * density).collect()
+                    },
+                    LaneDensity::PerVehicleType(densities) => {
+                        if densities.len() != self.vehicle_blueprints.len() {
+                            panic!("The number of densities of a lane must match the number of vehicle types.");
+                        }
+                        densities.clone()
+                    },
+                };
+                if !(0.0..=1.0).contains(&densities.iter().sum::<f32>()) {
+                    panic!("The traffic densities of each lane must add up to a number between 0 and 1.");
+                }
+                let unblocked_cells = lane.iter().filter(|cell| !cell.blocked()).count() as f32;
+                densities.iter().map(|density| (density * unblocked_cells).round() as u32).collect()
+            })
+            .collect();
+        self.respawn_cars_by_lane(&counts_by_lane);
+        self
+    }
+
+    /// Sets how the cars arrive at an open road. With `ArrivalProcess::Batch`, cars arrive in
+    /// batches of `batch_size` cars.
+    pub fn with_arrival_process(mut self, arrival_process: ArrivalProcess, batch_size: u32) -> Self {
+        if batch_size == 0 {
+            panic!("Arrival batches must contain at least one car.");
+        }
+        self.arrival_process = arrival_process;
+        self.arrival_batch_size = batch_size;
+        self
+    }
+
+    /// Sets how many cells per round a car must have to brake more than because of a car that
+    /// switched into its lane in front of it for this to count as a near miss.
+    pub fn with_near_miss_braking(mut self, near_miss_braking: u8) -> Self {
+        self.near_miss_braking = near_miss_braking;
+        self
+    }
+
+    /// Makes cars announce lane changes one round ahead. A car that wants to switch lanes first
+    /// signals for a round and only switches in a later round if it still wants to. Cars behind it
+    /// in the target lane hold back, so that they do not pull up alongside it.
+    pub fn with_turn_signals(mut self) -> Self {
+        self.turn_signals = true;
+        self
+    }
+
+    /// Makes cars tell lane changes that they have to make because their lane is blocked ahead
+    /// apart from those that only gain them speed. Such a mandatory lane change takes any gap in
+    /// the lane next to the car, and the closer the blocked cell gets, the less likely the car is
+    /// to stay in its lane. The urgency grows over the last cells that the car can drive in one
+    /// round at its maximum speed.
+    pub fn with_mandatory_lane_changes(mut self) -> Self {
+        self.mandatory_lane_changes = true;
+        self
+    }
+
+    /// Limits how many cells per round cars can brake in a round. A car that would have to brake
+    /// harder collides with the obstacle ahead or, with `BrakingOverrun::Emergency`, brakes up to
+    /// `emergency_braking_factor` times as hard first. (See: `with_collision_rounds`)
+    pub fn with_max_deceleration(mut self, max_deceleration: u8, braking_overrun: BrakingOverrun, emergency_braking_factor: f32) -> Self {
+        if max_deceleration == 0 {
+            panic!("Cars must be able to brake by at least one cell per round.");
+        }
+        if emergency_braking_factor < 1.0 {
+            panic!("Cars cannot brake less hard in an emergency.");
+        }
+        self.max_deceleration = Some((max_deceleration, braking_overrun, emergency_braking_factor));
+        self
+    }
+
+    /// Sets the number of rounds for which a car that collided stands still and blocks its cell.
+    pub fn with_collision_rounds(mut self, collision_rounds: u32) -> Self {
+        self.collision_rounds = collision_rounds;
+        self
+    }
+
+    /// Makes cars aware of blocked cells in their lane at most `look_ahead` cells in front of them,
+    /// even behind other cars, and makes them start a mandatory lane change right away. The
+    /// urgency then grows over the whole look-ahead distance. Without it, cars only notice blocked
+    /// cells that are their next obstacle.
+    pub fn with_blockage_look_ahead(mut self, look_ahead: u32) -> Self {
+        self.mandatory_lane_changes = true;
+        self.blockage_look_ahead = Some(look_ahead);
+        self
+    }
+
+    /// Sets the probability with which a car holds back to let a car in the lane next to it merge
+    /// in front of it, when that car's lane is blocked ahead. Unlike the stay-in-lane probability,
+    /// this describes how cooperative the drivers in the target lane are.
+    pub fn with_courtesy_probability(mut self, courtesy_probability: f32) -> Self {
+        if !(0.0..=1.0).contains(&courtesy_probability) {
+            panic!("Courtesy probability must be a number between 0 and 1.");
+        }
+        self.courtesy_probability = courtesy_probability;
+        self
+    }
+
+    /// Adds and removes cars at the end of every round, so that the traffic density of the closed
+    /// road follows the schedule. Added cars are picked by the traffic densities of the vehicle
+    /// types and placed in random free cells. Removed cars are picked at random.
+    pub fn with_density_schedule(mut self, density_schedule: DensitySchedule) -> Self {
+        if self.open() {
+            panic!("Density schedules are only supported on closed roads.");
+        }
+        if self.vehicle_blueprints.is_empty() {
+            panic!("At least one vehicle type is required for the density schedule.");
+        }
+        self.density_schedule = Some(density_schedule);
+        self
+    }
+
+    /// Changes the dilly-dally probability, the stay-in-lane probability and the maximum speeds of
+    /// the vehicle types over time according to the profile. Parameters that a bin leaves out keep
+    /// the value the road was created with.
+    pub fn with_parameter_profile(mut self, parameter_profile: ParameterProfile) -> Self {
+        if parameter_profile.bins().iter().any(|bin| bin.max_speeds().len() > self.vehicle_blueprints.len()) {
+            panic!("The parameter profile sets the maximum speeds of more vehicle types than there are.");
+        }
+        self.parameter_profile = Some(parameter_profile);
+        self.apply_parameter_profile();
+        self
+    }
+
+    /// Lets every driver draw their own dilly-dally and stay-in-lane probabilities from the
+    /// distributions, both the drivers already on the road and those of cars put on it later. Their
+    /// own probabilities take the place of those of the road, the parameter profile and the
+    /// weather.
+    pub fn with_driver_distributions(mut self, driver_distributions: DriverDistributions) -> Self {
+        for cell in self.lanes.iter_mut().flatten() {
+            if let Some(car) = cell.car_mut() {
+                driver_distributions.draw(&mut self.rng, car);
+            }
+        }
+        self.driver_distributions = Some(driver_distributions);
+        self
+    }
+
+    /// Changes the weather over time according to the schedule. The weather scales the
+    /// dilly-dally probability and the maximum speeds, including those of the parameter profile.
+    /// (See: `Weather`)
+    pub fn with_weather_schedule(mut self, weather_schedule: WeatherSchedule) -> Self {
+        self.weather_schedule = Some(weather_schedule);
+        self.apply_parameter_profile();
+        self
+    }
+
+    /// Sets the fraction of their max speed below which cars count as congested.
+    pub fn with_congestion_threshold(mut self, congestion_threshold: f32) -> Self {
+        if !(0.0..=1.0).contains(&congestion_threshold) {
+            panic!("Congestion threshold must be a number between 0 and 1.");
+        }
+        self.congestion_threshold = congestion_threshold;
+        self
+    }
+
+    /// Sets the order in which the cars are updated each round. (See: `UpdateOrder`)
+    pub fn with_update_order(mut self, update_order: UpdateOrder) -> Self {
+        self.update_order = update_order;
+        self
+    }
+
+    /// Sets the rules by which cars switch lanes. (See: `LaneRules`)
+    pub fn with_lane_rules(mut self, lane_rules: LaneRules) -> Self {
+        self.lane_rules = lane_rules;
+        self
+    }
+
+    /// Sets the rule by which cars keep their distance. (See: `DrivingModel`)
+    pub fn with_driving_model(mut self, driving_model: DrivingModel) -> Self {
+        self.driving_model = driving_model;
+        self
+    }
+
+    /// Coordinates the traffic lights in the corridor of the green wave. (See: `GreenWave`)
+    pub fn with_green_wave(mut self, green_wave: &GreenWave) -> Self {
+        green_wave.coordinate(&mut self.traffic_lights);
+        self.switch_traffic_lights();
+        self
+    }
+
+    /// Lets emergency vehicles force traffic lights in their lane green while they are at most
+    /// `preemption_distance` cells in front of them. The lights return to their plan afterwards.
+    pub fn with_preemption_distance(mut self, preemption_distance: u32) -> Self {
+        self.preemption_distance = Some(preemption_distance);
+        self
+    }
+
+    /// Makes cars clear the way for emergency vehicles that are at most `yield_distance` cells
+    /// behind them in their lane. Such a car switches to the lane to its right if it can and
+    /// slows down otherwise.
+    pub fn with_emergency_yielding(mut self, yield_distance: u32) -> Self {
+        self.emergency_yield_distance = Some(yield_distance);
+        self
+    }
+
+    /// Lets emergency vehicles drive through red traffic lights and the stop signs of failed ones,
+    /// up to the next car or blocked cell in their lane.
+    pub fn with_emergency_red_light_running(mut self) -> Self {
+        self.emergency_red_light_running = true;
+        self
+    }
+
+    /// Lets the traffic lights at each cell index fail with `probability` every round, going dark
+    /// or flashing for a number of rounds drawn from an exponential distribution with a mean of
+    /// `mean_rounds`. Drivers treat a failed light as a stop sign: it is an obstacle, except for a
+    /// car that stands still right in front of it.
+    pub fn with_light_failures(mut self, probability: f32, mean_rounds: u32) -> Self {
+        if !(0.0..=1.0).contains(&probability) {
+            panic!("Light failure probability must be a number between 0 and 1.");
+        }
+        if mean_rounds == 0 {
+            panic!("Light failures must last at least one round on average.");
+        }
+        self.light_failures = Some((probability, mean_rounds as f64));
+        self
+    }
+
+    /// Lets an incident block a random cell with `probability` every round, for a number of rounds
+    /// drawn from an exponential distribution with a mean of `mean_rounds`. Incidents do not
+    /// happen on cells that are blocked or traffic lights or that already have a block scheduled.
+    pub fn with_incidents(mut self, probability: f32, mean_rounds: u32) -> Self {
+        if !(0.0..=1.0).contains(&probability) {
+            panic!("Incident probability must be a number between 0 and 1.");
+        }
+        if mean_rounds == 0 {
+            panic!("Incidents must last at least one round on average.");
+        }
+        self.incident_rate = Some((probability, mean_rounds as f64));
+        self
+    }
+
+    /// Limits the speed of cars driving off from the cells of the zones. Where zones overlap or a
+    /// cell already has a speed limit, the lowest limit applies.
+    pub fn with_speed_limits(mut self, zones: &[SpeedLimitZone]) -> Self {
+        for zone in zones {
+            let range = zone.range();
+            if range.lane() >= self.lanes() as usize || range.indexes().end > self.length() as usize {
+                panic!("The speed limit zone of cells {:?} in lane {} is not on the road.", range.indexes(), range.lane());
+            }
+            if zone.limit() == 0 {
+                panic!("Speed limits must be at least 1.");
+            }
+            for cell in &mut self.lanes[range.lane()][range.indexes()] {
+                cell.limit_speed(cell.speed_limit().map_or(zone.limit(), |limit| cmp::min(limit, zone.limit())));
+            }
+        }
+        self
+    }
+
+    /// Reserves lanes or ranges of cells for classes of vehicles, e.g. bus lanes. Other vehicles
+    /// treat the cells as blocked: they do not switch into them and leave their lane in front of
+    /// them. Cars that are already in cells reserved for other vehicles are moved to random free
+    /// cells that they may use.
+    pub fn with_reserved_zones(mut self, zones: &[ReservedZone]) -> Self {
+        let length = self.length() as usize;
+        for zone in zones {
+            let indexes = zone.indexes(length);
+            if zone.lane() >= self.lanes() as usize || indexes.end > length {
+                panic!("The reserved cells {:?} in lane {} are not on the road.", indexes, zone.lane());
+            }
+            for cell in &mut self.lanes[zone.lane()][indexes] {
+                cell.reserve(zone.vehicle_class());
+            }
+            self.reserved_cells = true;
+        }
+        let misplaced: Vec<(usize, usize)> = self.iter_cars()
+            .filter(|(_, lane_i, cell_i, car)| self.lanes[*lane_i][*cell_i].reserved_against(car.vehicle_class()))
+            .map(|(_, lane_i, cell_i, _)| (lane_i, cell_i))
+            .collect();
+        for (lane_i, cell_i) in misplaced {
+            let car = self.lanes[lane_i][cell_i].take_car(self.rounds).unwrap();
+            let free_cells = self.free_cells_for(car.vehicle_type());
+            let Some((new_lane_i, new_cell_i)) = free_cells.choose(&mut self.rng) else {
+                panic!("There are not enough free cells outside of the reserved cells for all cars.");
+            };
+            self.lanes[*new_lane_i][*new_cell_i].put_car(car, self.rounds).unwrap();
         }
+        self
+    }
+
+    /// Lets drivers gawk at blocked cells in the lanes next to them. While they pass one, they drive
+    /// at most `speed_limit` cells per round and dilly-dally with at least `dilly_dally_probability`.
+    pub fn with_rubbernecking(mut self, speed_limit: Option<u8>, dilly_dally_probability: Option<f32>) -> Self {
+        if dilly_dally_probability.is_some_and(|probability| !(0.0..=1.0).contains(&probability)) {
+            panic!("Rubbernecking dilly-dally probability must be a number between 0 and 1.");
+        }
+        self.rubbernecking = Some((speed_limit, dilly_dally_probability));
+        self.mark_rubbernecking_cells();
+        self
+    }
+
+    /// Records the trip of every car that leaves the open road, so that the trips of each round can
+    /// be inspected after it.
+    pub fn with_trip_recording(mut self) -> Self {
+        if !self.open() {
+            panic!("Trips can only be recorded on open roads. Use `demand` to open the road.");
+        }
+        self.trip_recording = true;
+        self
+    }
+
+    /// Records the cells to the next car and to the next obstacle that the update saw in every
+    /// cell, so that they can be inspected after each round. Before the first round, the gaps of
+    /// the initial state are recorded.
+    pub fn with_gap_recording(mut self) -> Self {
+        self.gap_counters = Some(self.gaps());
+        self
+    }
+
+    /// Measures the local density around each of the cells: the cars within `window` cells on
+    /// either side of the cell at the end of every round.
+    pub fn with_density_monitors(mut self, monitors: &[CellLocation], window: u32) -> Self {
+        self.density_monitors = monitors.to_vec();
+        self.density_window = window;
+        self.density_monitor_cars = vec![0; monitors.len()];
+        self
+    }
+
+    /// Measures the travel times of the cars through the sections.
+    pub fn with_sections(mut self, sections: Vec<Section>) -> Self {
+        self.sections = sections;
+        self
     }
 
     /// Creates a vector of lanes, where each lane is a vector of cells.
@@ -107,145 +773,833 @@ impl Road {
         lanes
     }
 
+    /// Panics with the offending argument if a block or a traffic light is not on a road with the
+    /// given number of lanes and cells per lane.
+    fn check_locations(lanes: u32, length: u32, block: &[CellLocationRange], traffic_lights: &[TrafficLight]) {
+        for blocked in block {
+            let indexes = blocked.indexes();
+            if blocked.lane() >= lanes as usize || indexes.start > indexes.end || indexes.end > length as usize {
+                panic!(
+                    "The block `({}, {}-{})` is not on the road, which has {} lanes of {} cells.",
+                    blocked.lane(), indexes.start, indexes.end, lanes, length
+                );
+            }
+        }
+        for traffic_light in traffic_lights {
+            let location = traffic_light.location();
+            if location.lane() >= lanes as usize || location.index() >= length as usize {
+                panic!("The traffic light `{}` is not on the road, which has {} lanes of {} cells.", location, lanes, length);
+            }
+        }
+    }
+
     /// Blocks the certain cells for construction simulation. Returns the number of unblocked cells
     /// in each lane.
-    fn block_cells(lanes: &mut [Vec<Cell>], length: u32, block: &Vec<CellLocationRange>) -> Vec<u32> {
+    fn block_cells(lanes: &mut [Vec<Cell>], length: u32, block: &[CellLocationRange]) -> Vec<u32> {
         let mut unblocked_cells_per_lane = vec![length; lanes.len()];
-        for blocked in block {
+        for blocked in block.iter().filter(|blocked| blocked.active_in(0)) {
             let lane_i = blocked.lane();
             let lane = &mut lanes[blocked.lane()];
             let unblocked = &mut unblocked_cells_per_lane[lane_i];
             for cell_i in blocked.indexes() {
-                lane[cell_i].block();
-                *unblocked -= 1;
+                if !lane[cell_i].blocked() {
+                    lane[cell_i].block();
+                    *unblocked -= 1;
+                }
             }
         }
         unblocked_cells_per_lane
     }
 
-    fn add_traffic_lights(lanes: &mut [Vec<Cell>], traffic_lights: &Vec<CellLocation>) {
+    fn add_traffic_lights(lanes: &mut [Vec<Cell>], traffic_lights: &[TrafficLight]) {
         for traffic_light in traffic_lights {
-            lanes[traffic_light.lane()][traffic_light.index()].make_traffic_light();
+            lanes[traffic_light.location().lane()][traffic_light.location().index()].make_traffic_light();
         }
     }
 
-    /// Adds cars to the road. Formula for number of cars in each lane: `(traffic_density * unblocked_cells_in_lane).round()`.
-    fn add_cars(lanes: &mut [Vec<Cell>], unblocked_cells_per_lane: Vec<u32>, rng: &mut ThreadRng, vehicle_blueprints: &Vec<VehicleBlueprint>) -> u32 {
+    /// Adds cars to the road. Formula for number of cars in each lane: `(traffic_density * unblocked_cells_in_lane).round()`,
+    /// where vehicle types with lane densities use the density of the lane.
+    fn add_cars(lanes: &mut [Vec<Cell>], unblocked_cells_per_lane: Vec<u32>, rng: &mut StdRng, vehicle_blueprints: &[VehicleBlueprint]) -> u32 {
         if !(0.0..=1.0).contains(&vehicle_blueprints.iter().map(|vb| vb.traffic_density()).reduce(|acc, td| td + acc).unwrap_or(0.0)) {
             panic!("The sum of all traffic densities must be a number between 0 and 1.");
         }
+        Self::check_lane_weights(lanes.len(), vehicle_blueprints);
+        let unblocked_cells: u64 = unblocked_cells_per_lane.iter().map(|cells| *cells as u64).sum();
         let mut n_cars: u32 = 0;
-        for vehicle_blueprint in vehicle_blueprints {
-            for (lane, unblocked)in lanes.iter_mut().zip(unblocked_cells_per_lane.iter()) {
-                let n_cars_in_lane = (vehicle_blueprint.traffic_density() * *unblocked as f32).round() as u32;
+        for (vehicle_type, vehicle_blueprint) in vehicle_blueprints.iter().enumerate() {
+            let lane_shares = vehicle_blueprint.prefers_lanes().then(|| vehicle_blueprint.lane_shares(&unblocked_cells_per_lane));
+            for (lane_i, (lane, unblocked)) in lanes.iter_mut().zip(unblocked_cells_per_lane.iter()).enumerate() {
+                // With lane weights or allowed lanes, each lane gets its share of the cars of the
+                // whole road instead of the traffic density of its own cells.
+                let (n_cars_in_lane, probability) = match (vehicle_blueprint.lane_densities(), &lane_shares) {
+                    (Some(_), _) => {
+                        let density = vehicle_blueprint.lane_weight(lane_i) as f32;
+                        ((density * *unblocked as f32).round() as u32, density)
+                    },
+                    (None, Some(lane_shares)) => {
+                        let n_cars_in_lane = (vehicle_blueprint.traffic_density() as f64 * unblocked_cells as f64 * lane_shares[lane_i]).round() as u32;
+                        (n_cars_in_lane, n_cars_in_lane as f32 / *unblocked as f32)
+                    },
+                    (None, None) => ((vehicle_blueprint.traffic_density() * *unblocked as f32).round() as u32, vehicle_blueprint.traffic_density()),
+                };
+                if n_cars_in_lane as usize > lane.iter().filter(|cell| cell.free()).count() {
+                    panic!("There are not enough free cells in lane {} for the cars of vehicle type {}.", lane_i, vehicle_type);
+                }
                 let mut spawned_cars: u32 = 0;
                 let mut index: usize = 0;
                 while spawned_cars < n_cars_in_lane {
                     let cell = &mut lane[index];
-                    if Self::occurs(rng, vehicle_blueprint.traffic_density()) && cell.free(false) {
+                    if Self::occurs(rng, probability) && cell.free() {
+                        cell.put_car(Box::new(Car::new(n_cars + spawned_cars, vehicle_type, vehicle_blueprint)), 0).unwrap();
                         spawned_cars += 1;
-                        cell.put_car(Car::new(vehicle_blueprint)).unwrap();
                     }
                     index = (index + 1) % lane.len();
                 }
-                n_cars += n_cars_in_lane;
+                n_cars += n_cars_in_lane;
+            }
+        }
+        n_cars
+    }
+
+    fn check_lane_weights(n_lanes: usize, vehicle_blueprints: &[VehicleBlueprint]) {
+        if vehicle_blueprints.iter().any(|vb| vb.lane_weights().is_some_and(|weights| weights.len() != n_lanes)) {
+            panic!("The number of lane weights of each vehicle type must match the number of lanes.");
+        }
+        if vehicle_blueprints.iter().any(|vb| vb.lane_densities().is_some_and(|densities| densities.len() != n_lanes)) {
+            panic!("The number of lane densities of each vehicle type must match the number of lanes.");
+        }
+        if vehicle_blueprints.iter().any(|vb| vb.allowed_lanes().is_some_and(|lanes| lanes.is_empty() || lanes.iter().any(|lane_i| *lane_i >= n_lanes))) {
+            panic!("The allowed lanes of each vehicle type must be lanes of the road.");
+        }
+    }
+
+    /// Takes all cars off the road and puts `counts[vehicle_type]` new cars of each vehicle type
+    /// into free cells picked uniformly at random.
+    fn respawn_cars(&mut self, counts: &[u32]) {
+        self.clear_cars();
+        let lanes: Vec<usize> = (0..self.lanes.len()).collect();
+        self.spawn_cars(&lanes, counts);
+    }
+
+    /// Takes all cars off the road and puts `counts_by_lane[lane][vehicle_type]` new cars of each
+    /// vehicle type into free cells of each lane picked uniformly at random.
+    fn respawn_cars_by_lane(&mut self, counts_by_lane: &[Vec<u32>]) {
+        self.clear_cars();
+        for (lane_i, counts) in counts_by_lane.iter().enumerate() {
+            self.spawn_cars(&[lane_i], counts);
+        }
+    }
+
+    fn clear_cars(&mut self) {
+        for cell in self.lanes.iter_mut().flatten() {
+            cell.take_car(self.rounds);
+        }
+        self.n_cars = 0;
+    }
+
+    /// Puts `counts[vehicle_type]` new cars of each vehicle type into free cells of the lanes
+    /// picked uniformly at random, or with a probability proportional to the lane weight of the
+    /// vehicle type if it has lane weights and there are several lanes. Cars are never put into
+    /// lanes that their vehicle type may not use.
+    fn spawn_cars(&mut self, lanes: &[usize], counts: &[u32]) {
+        let mut free_cells: Vec<Vec<usize>> = lanes
+            .iter()
+            .map(|lane_i| (0..self.lanes[*lane_i].len()).filter(|cell_i| self.lanes[*lane_i][*cell_i].free()).collect())
+            .collect();
+        if counts.iter().sum::<u32>() as usize > free_cells.iter().map(Vec::len).sum() {
+            panic!("There are not enough free cells for all cars.");
+        }
+        for lane in free_cells.iter_mut() {
+            lane.shuffle(&mut self.rng);
+        }
+
+        for (vehicle_type, count) in counts.iter().enumerate() {
+            let vehicle_blueprint = &self.vehicle_blueprints[vehicle_type];
+            let lane_weights: Vec<f64> = lanes
+                .iter()
+                .map(|lane_i| match lanes.len() > 1 {
+                    true => vehicle_blueprint.lane_weight(*lane_i),
+                    false if vehicle_blueprint.allows_lane(*lane_i) => 1.0,
+                    false => 0.0,
+                })
+                .collect();
+            for _ in 0..*count {
+                let weights: Vec<f64> = free_cells.iter().zip(&lane_weights).map(|(cells, weight)| cells.len() as f64 * weight).collect();
+                if weights.iter().sum::<f64>() <= 0.0 {
+                    panic!("There are not enough free cells in the weighted lanes of vehicle type {}.", vehicle_type);
+                }
+                let lane = pick_weighted(&mut self.rng, &weights);
+                let (lane_i, cell_i) = (lanes[lane], free_cells[lane].pop().unwrap());
+                let mut car = Car::new(self.n_cars, vehicle_type, &self.vehicle_blueprints[vehicle_type]);
+                if let Some(driver_distributions) = self.driver_distributions {
+                    driver_distributions.draw(&mut self.rng, &mut car);
+                }
+                self.lanes[lane_i][cell_i].put_car(Box::new(car), self.rounds).unwrap();
+                self.n_cars += 1;
+            }
+        }
+    }
+
+    /// Returns `true` `probability * 100`% of the time. The draw lies in `[0, 1)`, so the strict
+    /// comparison never lets a probability of 0 occur and always lets a probability of 1 occur.
+    fn occurs(rng: &mut StdRng, probability: f32) -> bool {
+        rng.gen::<f32>() < probability
+    }
+
+    /// Returns the number of cars that have been on the road. On a closed road without added or
+    /// removed cars, this is the number of cars on the road.
+    pub fn cars(&self) -> u32 {
+        self.n_cars
+    }
+
+    /// Returns the number of times that a car had to brake harder than the near miss braking
+    /// because a car switched into its lane in front of it.
+    pub fn near_misses(&self) -> u64 {
+        self.near_misses
+    }
+
+    /// Returns the number of times that a car held back to open a gap for a car that announced a
+    /// lane change into its lane or, out of courtesy, for a car whose lane is blocked.
+    pub fn yields(&self) -> u64 {
+        self.yields
+    }
+
+    /// Returns the number of lane changes that cars had to make because their lane was blocked
+    /// ahead. These are only told apart with mandatory lane changes.
+    pub fn mandatory_lane_changes(&self) -> u64 {
+        self.n_mandatory_lane_changes
+    }
+
+    /// Returns the number of times that a car had to brake harder than the maximum deceleration
+    /// allows and collided with the obstacle ahead.
+    pub fn collisions(&self) -> u64 {
+        self.n_collisions
+    }
+
+    /// Returns the number of times that a car had to brake harder than the maximum deceleration
+    /// allows, but could still stop in time in an emergency.
+    pub fn emergency_brakings(&self) -> u64 {
+        self.n_emergency_brakings
+    }
+
+    /// Returns the number of lane changes of all cars so far, including the cars that have left
+    /// the road.
+    pub fn lane_changes(&self) -> u64 {
+        self.car_records().lane_changes
+    }
+
+    /// Returns the lane changes of all cars so far, by the lane that they left and by vehicle type.
+    /// (Indexes match `vehicle_blueprints` within each lane.)
+    pub fn lane_changes_by_lane_and_vehicle_type(&self) -> &Vec<Vec<LaneChanges>> {
+        &self.lane_change_counts
+    }
+
+    /// Returns the number of rounds that cars have been on the road, summed up over the cars and
+    /// the rounds recorded so far.
+    pub fn car_rounds(&self) -> u64 {
+        self.car_rounds
+    }
+
+    /// Returns the number of near misses per lane change.
+    pub fn near_misses_per_lane_change(&self) -> f64 {
+        self.near_misses as f64 / self.lane_changes() as f64
+    }
+
+    /// Returns the trips of the cars that left the open road in the last round. Trips are only
+    /// recorded with trip recording.
+    pub fn arrived_trips(&self) -> &[Trip] {
+        &self.arrived_trips
+    }
+
+    /// Returns the measurement sections with the travel times recorded in them.
+    pub fn sections(&self) -> &Vec<Section> {
+        &self.sections
+    }
+
+    /// Returns the number of cars that have been removed from the road.
+    pub fn removed_cars(&self) -> u32 {
+        self.n_removed_cars
+    }
+
+    /// Returns the lane and cell of every free cell that the cars of the vehicle type may use.
+    fn free_cells_for(&self, vehicle_type: usize) -> Vec<(usize, usize)> {
+        let vehicle_blueprint = &self.vehicle_blueprints[vehicle_type];
+        self.lanes
+            .iter()
+            .enumerate()
+            .filter(|(lane_i, _)| vehicle_blueprint.allows_lane(*lane_i))
+            .flat_map(|(lane_i, lane)| lane.iter().enumerate().map(move |(cell_i, cell)| (lane_i, cell_i, cell)))
+            .filter(|(_, _, cell)| cell.free() && !cell.reserved_against(vehicle_blueprint.vehicle_class()))
+            .map(|(lane_i, cell_i, _)| (lane_i, cell_i))
+            .collect()
+    }
+
+    /// Puts a new car of the vehicle type into a random free cell that the vehicle type may use.
+    /// Returns the ID of the car or `None` if there is no such cell.
+    pub fn add_car(&mut self, vehicle_type: usize) -> Option<CarId> {
+        let free_cells = self.free_cells_for(vehicle_type);
+        let (lane_i, cell_i) = *free_cells.choose(&mut self.rng)?;
+        let id = self.n_cars;
+        let mut car = Car::new(id, vehicle_type, &self.vehicle_blueprints[vehicle_type]);
+        car.set_max_speed(self.active_max_speeds[vehicle_type]);
+        if let Some(driver_distributions) = self.driver_distributions {
+            driver_distributions.draw(&mut self.rng, &mut car);
+        }
+        car.flip_flop_sync(&self.overflow_flip_flop);
+        self.lanes[lane_i][cell_i].put_car(Box::new(car), self.rounds).unwrap();
+        self.n_cars += 1;
+        Some(id)
+    }
+
+    /// Takes the car with the ID off the road. Its records are kept. Returns whether the car was
+    /// on the road.
+    pub fn remove_car(&mut self, id: CarId) -> bool {
+        let Some((lane_i, cell_i, _)) = self.find_car(id) else {
+            return false;
+        };
+        let car = self.lanes[lane_i][cell_i].take_car(self.rounds).unwrap();
+        self.exited_car_records[car.vehicle_type()].add(&car);
+        for section in self.sections.iter_mut() {
+            section.forget(car.id());
+        }
+        self.n_removed_cars += 1;
+        true
+    }
+
+    /// Adds or removes cars until the number of cars matches the target density of the schedule.
+    fn control_density(&mut self) {
+        let Some(density_schedule) = &self.density_schedule else { return; };
+        let unblocked_cells = self.lanes.iter().flatten().filter(|cell| !cell.blocked()).count();
+        let target_cars = (density_schedule.target_density(self.rounds) * unblocked_cells as f32).round() as u32;
+        let mut cars_on_road = self.n_cars - self.n_exited_cars - self.n_removed_cars;
+        while cars_on_road < target_cars {
+            let vehicle_type = VehicleBlueprint::pick_by_density(&mut self.rng, &self.vehicle_blueprints);
+            if self.add_car(vehicle_type).is_none() {
+                break;
+            }
+            cars_on_road += 1;
+        }
+        if cars_on_road > target_cars {
+            let ids: Vec<CarId> = self.iter_cars().map(|(id, ..)| id).collect();
+            let removed_ids: Vec<CarId> = ids.choose_multiple(&mut self.rng, (cars_on_road - target_cars) as usize).copied().collect();
+            for id in removed_ids {
+                self.remove_car(id);
+            }
+        }
+    }
+
+    /// Returns the number of cars that have left the road at its end.
+    pub fn exited_cars(&self) -> u32 {
+        self.n_exited_cars
+    }
+
+    /// Returns the number of cars that are waiting to enter the road.
+    pub fn waiting_cars(&self) -> u32 {
+        self.waiting_cars.iter().map(|waiting| waiting.len() as u32).sum()
+    }
+
+    /// Returns the number of times that cars have wrapped around the end of the closed road.
+    pub fn laps(&self) -> u64 {
+        self.laps
+    }
+
+    /// Returns the duration in rounds of every full lap that has been driven on the closed road.
+    pub fn lap_times(&self) -> &Vec<u32> {
+        &self.lap_times
+    }
+
+    /// Returns the duration in rounds of every full lap that an emergency vehicle has driven on the
+    /// closed road or of every trip of an emergency vehicle through the open road.
+    pub fn emergency_travel_times(&self) -> &Vec<u32> {
+        &self.emergency_travel_times
+    }
+
+    /// Returns the number of rounds that cars have been congested in total.
+    pub fn congested_rounds(&self) -> u64 {
+        self.car_records().congested_rounds
+    }
+
+    /// Returns the fraction of the cars on the road that were congested in the last round.
+    pub fn congested_fraction(&self) -> f64 {
+        self.congested_fraction
+    }
+
+    /// Returns whether the road is open, meaning cars leave at the end instead of looping around.
+    pub fn open(&self) -> bool {
+        self.demand.is_some()
+    }
+
+    /// Returns the number of lanes.
+    pub fn lanes(&self) -> u32 {
+        self.n_lanes
+    }
+
+    /// Returns the length of the road. (Equal to the number of cells in each lane.)
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Returns the number of rounds that the simulator has run so far.
+    pub fn rounds(&self) -> u32 {
+        self.rounds
+    }
+
+    /// Returns the number of rounds that have been recorded since the records were last reset.
+    pub fn measured_rounds(&self) -> u32 {
+        self.rounds - self.records_since
+    }
+
+    /// Forgets the records of the cells, the cars and the road itself, so that the metrics only
+    /// cover the rounds from now on, e.g. to leave out a warm-up period. The cars stay where they
+    /// are.
+    pub fn reset_records(&mut self) {
+        self.records_since = self.rounds;
+        self.car_rounds = 0;
+        self.laps = 0;
+        self.lap_times.clear();
+        self.emergency_travel_times.clear();
+        self.exited_car_records = vec![CarRecords::default(); self.vehicle_blueprints.len()];
+        for cell in self.lanes.iter_mut().flatten() {
+            cell.reset_records(self.rounds);
+            if let Some(car) = cell.car_mut() {
+                car.reset_records();
+            }
+        }
+        for light_state in self.light_states.iter_mut() {
+            light_state.reset_detector();
+        }
+        self.density_monitor_cars.fill(0);
+        self.lane_change_counts.iter_mut().flatten().for_each(|lane_changes| *lane_changes = LaneChanges::default());
+        for section in self.sections.iter_mut() {
+            section.reset_records();
+        }
+        self.near_misses = 0;
+        self.yields = 0;
+        self.n_mandatory_lane_changes = 0;
+        self.n_collisions = 0;
+        self.n_emergency_brakings = 0;
+        self.preempted_light_rounds = 0;
+        self.n_light_failures = 0;
+        self.failed_light_rounds = 0;
+    }
+
+    /// Returns the `dilly_dally_probability`.
+    pub fn dilly_dally_probability(&self) -> f32 {
+        self.dilly_dally_probability
+    }
+
+    pub fn update_order(&self) -> UpdateOrder {
+        self.update_order
+    }
+
+    pub fn driving_model(&self) -> DrivingModel {
+        self.driving_model
+    }
+
+    pub fn lane_rules(&self) -> LaneRules {
+        self.lane_rules
+    }
+
+    /// Returns the `stay_in_lane_probability`.
+    pub fn stay_in_lane_probability(&self) -> f32 {
+        self.stay_in_lane_probability
+    }
+
+    /// Provides read access to all cells. Outer vector holds lanes, inner vector holds cells.
+    pub fn cells(&self) -> &Vec<Vec<Cell>> {
+        &self.lanes
+    }
+
+    /// Returns the car in a cell or `None` if the cell is empty or not on the road.
+    pub fn car_at(&self, lane: usize, cell: usize) -> Option<&Car> {
+        self.lanes.get(lane)?.get(cell)?.car()
+    }
+
+    /// Iterates over all cars on the road as `(id, lane, cell, car)`, lane by lane and from the
+    /// start to the end of each lane.
+    pub fn iter_cars(&self) -> impl Iterator<Item = (CarId, usize, usize, &Car)> {
+        self.lanes.iter().enumerate().flat_map(|(lane_i, lane)| {
+            lane.iter()
+                .enumerate()
+                .filter_map(move |(cell_i, cell)| cell.car().map(|car| (car.id(), lane_i, cell_i, car)))
+        })
+    }
+
+    /// Returns the lane, cell and car with the ID or `None` if the car is not on the road.
+    pub fn find_car(&self, id: CarId) -> Option<(usize, usize, &Car)> {
+        self.iter_cars().find(|(car_id, ..)| *car_id == id).map(|(_, lane, cell, car)| (lane, cell, car))
+    }
+
+    /// Returns the vehicle blueprints that the cars are built from.
+    pub fn vehicle_blueprints(&self) -> &Vec<VehicleBlueprint> {
+        &self.vehicle_blueprints
+    }
+
+    /// Returns the summed up records of all cars that are or have been on the road, separately for
+    /// each vehicle type. (Indexes match `vehicle_blueprints`.)
+    pub fn car_records_by_vehicle_type(&self) -> Vec<CarRecords> {
+        let mut records = self.exited_car_records.clone();
+        for (_, _, _, car) in self.iter_cars() {
+            records[car.vehicle_type()].add(car);
+        }
+        records
+    }
+
+    /// Returns the number of cars of each vehicle type that are on the road, lane by lane.
+    /// (Indexes match `vehicle_blueprints` within each lane.)
+    pub fn cars_per_lane_by_vehicle_type(&self) -> Vec<Vec<u32>> {
+        let mut cars = vec![vec![0; self.vehicle_blueprints.len()]; self.lanes.len()];
+        for (_, lane_i, _, car) in self.iter_cars() {
+            cars[lane_i][car.vehicle_type()] += 1;
+        }
+        cars
+    }
+
+    /// Returns the number of cars on the road, lane by lane.
+    pub fn cars_per_lane(&self) -> Vec<u32> {
+        self.lanes.iter().map(|lane| lane.iter().filter(|cell| cell.car().is_some()).count() as u32).collect()
+    }
+
+    /// Returns the summed up records of all cars that are or have been on the road.
+    fn car_records(&self) -> CarRecords {
+        let mut records = CarRecords::default();
+        for vehicle_type_records in self.car_records_by_vehicle_type() {
+            records.merge(&vehicle_type_records);
+        }
+        records
+    }
+
+    /// Returns the average number of cells driven per car per round.
+    pub fn average_speed(&self) -> f64 {
+        self.car_records().distance as f64 / self.car_rounds as f64
+    }
+
+    /// Returns the average amount of accelerations per car per round.
+    pub fn average_accelerations(&self) -> f64 {
+        self.car_records().accelerations as f64 / self.car_rounds as f64
+    }
+
+    /// Returns the average amount of deaccelerations per car per round.
+    pub fn average_deaccelerations(&self) -> f64 {
+        self.car_records().deaccelerations as f64 / self.car_rounds as f64
+    }
+
+    fn set_traffic_lights(&mut self, traffic_lights: Vec<TrafficLight>) {
+        self.light_states = vec![LightState::default(); traffic_lights.len()];
+        self.traffic_lights = traffic_lights;
+        self.switch_traffic_lights();
+    }
+
+    /// Replaces the cars on the road and the phases of its traffic lights, in the order of
+    /// `traffic_lights`, with a recorded state of a round, e.g. for replaying an event log.
+    pub fn restore(&mut self, round: u32, cars: Vec<(usize, usize, Car)>, red_lights: &[bool]) {
+        for lane in self.lanes.iter_mut() {
+            for cell in lane.iter_mut() {
+                cell.take_car(round);
+            }
+        }
+        for (lane, index, car) in cars {
+            self.lanes[lane][index].put_car(Box::new(car), round).expect("Unable to put a recorded car into its cell.");
+        }
+        for (light, red) in self.traffic_lights.iter().zip(red_lights) {
+            self.lanes[light.location().lane()][light.location().index()].set_red(*red);
+        }
+        self.rounds = round;
+    }
+
+    /// Returns the cells of the blocks by lane and index.
+    fn cells_of<'a>(blocks: impl Iterator<Item = &'a CellLocationRange>) -> HashSet<(usize, usize)> {
+        blocks.flat_map(|blocked| blocked.indexes().map(|cell_i| (blocked.lane(), cell_i))).collect()
+    }
+
+    /// Puts the scheduled blocks in place and clears them again as their rounds come and go. A cell
+    /// that still holds a car is only blocked once the car has left it.
+    fn update_scheduled_blocks(&mut self) {
+        let mut changed = false;
+        let active = Self::cells_of(self.scheduled_blocks.iter().filter(|blocked| blocked.active_in(self.rounds)));
+        // Cells that were blocked before by anything but a scheduled block stay blocked.
+        for (lane_i, cell_i) in self.scheduled_cells.extract_if(|cell| !active.contains(cell)) {
+            self.lanes[lane_i][cell_i].unblock();
+            changed = true;
+        }
+        for (lane_i, cell_i) in active {
+            let cell = &mut self.lanes[lane_i][cell_i];
+            if !cell.blocked() && cell.car().is_none() {
+                cell.block();
+                self.scheduled_cells.insert((lane_i, cell_i));
+                changed = true;
+            }
+        }
+        let round = self.rounds;
+        self.scheduled_blocks.retain(|blocked| !blocked.ended_by(round));
+        if changed {
+            self.mark_rubbernecking_cells();
+        }
+    }
+
+    /// Marks the cells next to blocked cells, in which drivers gawk at them, if they do.
+    fn mark_rubbernecking_cells(&mut self) {
+        if self.rubbernecking.is_none() {
+            return;
+        }
+        for lane_i in 0..self.lanes.len() {
+            for cell_i in 0..self.lanes[lane_i].len() {
+                let beside_block = (lane_i > 0 && self.lanes[lane_i - 1][cell_i].blocked())
+                    || (lane_i + 1 < self.lanes.len() && self.lanes[lane_i + 1][cell_i].blocked());
+                let rubbernecking = beside_block && !self.lanes[lane_i][cell_i].blocked();
+                self.lanes[lane_i][cell_i].set_rubbernecking(rubbernecking);
+            }
+        }
+    }
+
+    /// Returns the speed limit for cars driving off from a cell, if there is one, which may be
+    /// lowered by rubbernecking.
+    fn speed_limit_at(&self, lane_i: usize, cell_i: usize) -> Option<u8> {
+        let cell = &self.lanes[lane_i][cell_i];
+        let rubbernecking_limit = self.rubbernecking.and_then(|(speed_limit, _)| speed_limit).filter(|_| cell.rubbernecking());
+        match (cell.speed_limit(), rubbernecking_limit) {
+            (Some(limit), Some(rubbernecking_limit)) => Some(cmp::min(limit, rubbernecking_limit)),
+            (limit, rubbernecking_limit) => limit.or(rubbernecking_limit),
+        }
+    }
+
+    /// Returns the dilly-dally probability of a car driving off from a cell, which is that of its
+    /// driver or else that of the road and may be raised by rubbernecking.
+    fn dilly_dally_probability_at(&self, car: &Car, lane_i: usize, cell_i: usize) -> f32 {
+        let dilly_dally_probability = car.dilly_dally_probability().unwrap_or(self.active_dilly_dally_probability);
+        match self.rubbernecking.and_then(|(_, probability)| probability) {
+            Some(probability) if self.lanes[lane_i][cell_i].rubbernecking() => probability.max(dilly_dally_probability),
+            _ => dilly_dally_probability,
+        }
+    }
+
+    /// Returns the stay-in-lane probability of a car, which is that of its driver or else that of
+    /// the road.
+    fn stay_in_lane_probability_of(&self, car: &Car) -> f32 {
+        car.stay_in_lane_probability().unwrap_or(self.active_stay_in_lane_probability)
+    }
+
+    /// Lets an incident block a random cell with the incident probability.
+    fn generate_incidents(&mut self) {
+        let Some((probability, mean_rounds)) = self.incident_rate else { return; };
+        if !Self::occurs(&mut self.rng, probability) {
+            return;
+        }
+        let lane = self.rng.gen_range(0..self.lanes.len());
+        let index = self.rng.gen_range(0..self.length() as usize);
+        // Exponentially distributed, rounded up to whole rounds.
+        let rounds = (-mean_rounds * (1.0 - self.rng.gen::<f64>()).ln()).ceil().max(1.0) as u32;
+        let cell = &self.lanes[lane][index];
+        if cell.blocked() || cell.traffic_light() || self.scheduled_blocks.iter().any(|blocked| blocked.lane() == lane && blocked.indexes().contains(&index)) {
+            return;
+        }
+        let incident = Incident { round: self.rounds, lane, index, rounds };
+        self.scheduled_blocks.push(incident.block());
+        self.incidents.push(incident);
+    }
+
+    /// Returns the incidents so far. (See: `with_incidents`)
+    pub fn incidents(&self) -> &[Incident] {
+        &self.incidents
+    }
+
+    fn update_traffic_lights(&mut self) {
+        self.actuate_traffic_lights();
+        self.switch_traffic_lights();
+        self.held_traffic_lights.clear();
+        self.fail_traffic_lights();
+    }
+
+    /// Keeps the traffic light at the cell red in the next round whatever its timing says, e.g.
+    /// while the car in front of it has to give way.
+    pub fn hold_traffic_light(&mut self, lane: usize, index: usize) {
+        self.held_traffic_lights.push((lane, index));
+    }
+
+    /// Advances the phases of the actuated traffic lights by reading their detectors.
+    fn actuate_traffic_lights(&mut self) {
+        let length = self.length() as usize;
+        let open = self.open();
+        let detections: Vec<Option<(bool, i32)>> = self
+            .traffic_lights
+            .iter()
+            .zip(&self.light_states)
+            .map(|(light, state)| {
+                let distance = light.actuation()?.detector() as usize;
+                let index = light.location().index();
+                let detector_i = if open { index.saturating_sub(distance) } else { (index + length - distance % length) % length };
+                let detector = &self.lanes[light.location().lane()][detector_i];
+                Some((detector.detects(state.detector_cars_passed()), detector.cars_passed()))
+            })
+            .collect();
+        for light_i in 0..self.traffic_lights.len() {
+            let Some((_, cars_passed)) = detections[light_i] else { continue; };
+            // The lights of a signal switch together, so a car detected in any lane counts.
+            let index = self.traffic_lights[light_i].location().index();
+            let detected = self
+                .traffic_lights
+                .iter()
+                .zip(&detections)
+                .any(|(light, detection)| light.location().index() == index && detection.is_some_and(|(detected, _)| detected));
+            let light = &self.traffic_lights[light_i];
+            let Some(actuation) = light.actuation() else { continue; };
+            self.light_states[light_i].advance(light.timing(), actuation, detected, cars_passed);
+        }
+    }
+
+    /// Turns each traffic light red or green according to its timing or, if it is actuated, its
+    /// phase.
+    fn switch_traffic_lights(&mut self) {
+        for (light, state) in self.traffic_lights.iter().zip(&self.light_states) {
+            let red = match light.actuation() {
+                Some(_) => state.red(),
+                None => light.timing().red(self.rounds),
+            };
+            self.lanes[light.location().lane()][light.location().index()].set_red(red);
+        }
+        for (lane, index) in &self.held_traffic_lights {
+            self.lanes[*lane][*index].set_red(true);
+        }
+    }
+
+    /// Lets the traffic lights at a cell index fail together and counts down running failures.
+    fn fail_traffic_lights(&mut self) {
+        let Some((probability, mean_rounds)) = self.light_failures else { return; };
+        for light_i in 0..self.traffic_light_indexes.len() {
+            let cell_i = self.traffic_light_indexes[light_i];
+            let failed = self.lanes.iter().any(|lane| lane[cell_i].failed());
+            if failed {
+                for lane in self.lanes.iter_mut() {
+                    lane[cell_i].pass_failed_round();
+                }
+            } else if Self::occurs(&mut self.rng, probability) {
+                // Exponentially distributed, rounded up to whole rounds.
+                let rounds = (-mean_rounds * (1.0 - self.rng.gen::<f64>()).ln()).ceil().max(1.0) as u32;
+                for lane in self.lanes.iter_mut().filter(|lane| lane[cell_i].traffic_light()) {
+                    lane[cell_i].fail(rounds);
+                }
+                self.n_light_failures += 1;
+            }
+            if self.lanes.iter().any(|lane| lane[cell_i].failed()) {
+                self.failed_light_rounds += 1;
             }
         }
-        n_cars
     }
 
-    /// Returns `true` `probability * 100`% of the time.
-    fn occurs(rng: &mut ThreadRng, probability: f32) -> bool {
-        rng.gen::<f32>() <= probability
+    /// Returns whether the stop sign of a failed traffic light lets the car in the cell right in
+    /// front of it go, which it does once the car stands still.
+    fn stop_sign_open(lane: &[Cell], cell_i: usize, open: bool) -> bool {
+        if open && cell_i == 0 {
+            return false;
+        }
+        let before_i = (cell_i + lane.len() - 1) % lane.len();
+        lane[before_i].car().is_some_and(|car| car.speed() == 0)
     }
 
-    /// Returns the number of cars on the road.
-    pub fn cars(&self) -> u32 {
-        self.n_cars
+    /// Returns whether a cell is free or a stop sign that lets the car right in front of it go.
+    fn passable(lane: &[Cell], cell_i: usize, open: bool) -> bool {
+        let cell = &lane[cell_i];
+        cell.free() || cell.is_stop_sign() && cell.car().is_none() && Self::stop_sign_open(lane, cell_i, open)
     }
 
-    /// Returns the number of lanes.
-    pub fn lanes(&self) -> u32 {
-        self.n_lanes
+    /// Makes the stop sign of a failed traffic light an obstacle for the cells in front of it once
+    /// the cell it is in has been updated, unless it lets the car right in front of it go. That car
+    /// must not take the cars behind it along.
+    fn note_stop_signs(&mut self, lane_i: usize, cell_i: usize) {
+        if self.light_failures.is_none() {
+            return;
+        }
+        let length = self.length() as usize;
+        let lane = &self.lanes[lane_i];
+        if (cell_i + 1 < length || !self.open()) && lane[(cell_i + 1) % length].is_stop_sign() {
+            self.cells_to_next_obstacles[lane_i] = cmp::min(self.cells_to_next_obstacles[lane_i], 1);
+            self.next_car_speeds[lane_i] = 0;
+        }
+        if lane[cell_i].is_stop_sign() && !Self::stop_sign_open(lane, cell_i, self.open()) {
+            self.cells_to_next_obstacles[lane_i] = 0;
+            self.next_car_speeds[lane_i] = 0;
+        }
     }
 
-    /// Returns the length of the road. (Equal to the number of cells in each lane.)
-    pub fn length(&self) -> u32 {
-        self.length
+    /// Returns the number of times the traffic lights at a cell index failed.
+    pub fn light_failures(&self) -> u64 {
+        self.n_light_failures
     }
 
-    /// Returns the number of rounds that the simulator has run so far.
-    pub fn rounds(&self) -> u32 {
-        self.rounds
+    /// Returns the number of rounds in which traffic lights were failed, summed up over all cell
+    /// indexes with traffic lights.
+    pub fn failed_light_rounds(&self) -> u64 {
+        self.failed_light_rounds
     }
 
-    /// Returns the `dilly_dally_probability`.
-    pub fn dilly_dally_probability(&self) -> f32 {
-        self.dilly_dally_probability
+    /// Returns the traffic lights of the road with their timings.
+    pub fn traffic_lights(&self) -> &Vec<TrafficLight> {
+        &self.traffic_lights
     }
 
-    /// Returns the `stay_in_lane_probability`.
-    pub fn stay_in_lane_probability(&self) -> f32 {
-        self.stay_in_lane_probability
+    /// Returns the number of rounds that traffic lights have been forced green while they would
+    /// have been red, summed up over all traffic lights.
+    pub fn preempted_light_rounds(&self) -> u64 {
+        self.preempted_light_rounds
     }
 
-    /// Provides read access to all cells. Outer vector holds lanes, inner vector holds cells.
-    pub fn cells(&self) -> &Vec<Vec<Cell>> {
-        &self.lanes
+    /// Returns the recorded cells to the next car and to the next obstacle by lane and cell if
+    /// gap recording is enabled.
+    pub fn gap_counters(&self) -> Option<&Vec<Vec<(u32, u32)>>> {
+        self.gap_counters.as_ref()
     }
 
-    /// Returns the average number of cells driven per car per round.
-    pub fn average_speed(&self) -> f64 {
-        let mut sum = 0;
-        for lane in &self.lanes {
-            for cell in lane {
-                if let Some(car) = cell.car() {
-                    sum += car.distance();
-                }
-            }
-        }
-        sum as f64 / self.cars() as f64 / self.rounds() as f64
+    /// Returns the number of bytes the cells of the road (including the cars in them) and the
+    /// recorded gaps take up.
+    pub fn cell_grid_bytes(&self) -> usize {
+        let cells: usize = self.lanes.iter().map(Vec::len).sum();
+        let gaps: usize = self.gap_counters.iter().flatten().map(Vec::len).sum();
+        self.lanes.len() * size_of::<Vec<Cell>>() + cells * size_of::<Cell>() + self.iter_cars().count() * size_of::<Car>() + gaps * size_of::<(u32, u32)>()
     }
 
-    /// Returns the average amount of accelerations per car per round.
-    pub fn average_accelerations(&self) -> f64 {
-        let mut sum = 0;
-        for lane in &self.lanes {
-            for cell in lane {
-                if let Some(car) = cell.car() {
-                    sum += car.accelerations();
-                }
+    /// Forces the traffic lights green that emergency vehicles are approaching in their lane.
+    fn preempt_traffic_lights(&mut self) {
+        let Some(preemption_distance) = self.preemption_distance else { return; };
+        let length = self.length() as usize;
+        let open = self.open();
+        for lane in self.lanes.iter_mut() {
+            for cell in lane.iter_mut() {
+                cell.preempt(false);
             }
-        }
-        sum as f64 / self.cars() as f64 / self.rounds() as f64
-    }
-
-    /// Returns the average amount of deaccelerations per car per round.
-    pub fn average_deaccelerations(&self) -> f64 {
-        let mut sum = 0;
-        for lane in &self.lanes {
-            for cell in lane {
-                if let Some(car) = cell.car() {
-                    sum += car.deaccelerations();
+            let emergency_vehicles: Vec<usize> = lane
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.car().is_some_and(|car| car.vehicle_class() == VehicleClass::Emergency))
+                .map(|(cell_i, _)| cell_i)
+                .collect();
+            for cell_i in emergency_vehicles {
+                for ahead in 0..=preemption_distance as usize {
+                    if open && cell_i + ahead >= length {
+                        break;
+                    }
+                    let cell = &mut lane[(cell_i + ahead) % length];
+                    if cell.traffic_light() {
+                        cell.preempt(true);
+                    }
                 }
             }
+            self.preempted_light_rounds += lane.iter().filter(|cell| cell.preempted() && cell.red()).count() as u64;
         }
-        sum as f64 / self.cars() as f64 / self.rounds() as f64
-    }
-
-    fn update_traffic_lights(&mut self) {
-        self.traffic_lights_red = self.rounds % 100 != self.rounds % 200;
-    }
-
-    pub fn traffic_lights_red(&self) -> bool {
-        self.traffic_lights_red
     }
 
     fn prepare_cells_to_next_obstacles_for_wrap_around(&mut self) {
+        // The cars at the start of the road have not driven yet.
+        self.next_car_speeds.fill(0);
+        if self.open() {
+            // There is nothing but free space behind the end of an open road.
+            self.cells_to_next_cars.fill(u32::MAX);
+            self.cells_to_next_obstacles.fill(u32::MAX);
+            return;
+        }
         for (lane_i, lane) in self.lanes.iter().enumerate() {
             let mut looking_for_first_obstacle = true;
-            'cells: for cell_i in 0u8..cmp::min(self.length(), 255) as u8 {
-                if looking_for_first_obstacle && !lane[cell_i as usize].free(self.traffic_lights_red) {
+            'cells: for cell_i in 0..self.length() {
+                if looking_for_first_obstacle && !Self::passable(lane, cell_i as usize, false) {
                     self.cells_to_next_obstacles[lane_i] = cell_i;
                     looking_for_first_obstacle = false;
                 }
@@ -260,89 +1614,457 @@ impl Road {
     fn check_sides_clear(&self, lane_index: usize, cell_index: usize) -> (bool, bool) {
         let not_in_leftmost_lane = lane_index > 0;
         let not_in_rightmost_lane = lane_index + 1 != self.lanes.len();
-        let left_clear = not_in_leftmost_lane && self.lanes[lane_index - 1][cell_index].free(self.traffic_lights_red);
-        let right_clear = not_in_rightmost_lane && self.lanes[lane_index + 1][cell_index].free(self.traffic_lights_red);
+        let left_clear = not_in_leftmost_lane && self.lanes[lane_index - 1][cell_index].free();
+        let right_clear = not_in_rightmost_lane && self.lanes[lane_index + 1][cell_index].free();
         (left_clear, right_clear)
     }
 
     /// Notes that there is a car in a certain lane a certain amount of cells away.
-    fn note_car_obstacle(&mut self, lane_index: usize, distance_away: u8) {
+    fn note_car_obstacle(&mut self, lane_index: usize, distance_away: u32) {
         self.cells_to_next_cars[lane_index] = distance_away;
         // a car is always an obstacles too
         self.cells_to_next_obstacles[lane_index] = distance_away;
+        self.next_car_speeds[lane_index] = 0;
+    }
+
+    /// Notes the cells that the car just noted in a lane drives in the current round, so that the
+    /// cars behind it can anticipate them. Cars with their brake lights on are not anticipated.
+    fn note_car_speed(&mut self, lane_index: usize, car: &Car) {
+        if !car.brake_light() {
+            self.next_car_speeds[lane_index] = car.speed() as u32;
+        }
+    }
+
+    /// Returns the cells that the next car ahead in a lane is anticipated to drive in the current
+    /// round, which is 0 unless the driving model or, for automated vehicles, their cruise control
+    /// anticipates it and no other obstacle is in front of it.
+    fn anticipated_cells(&self, vehicle_class: VehicleClass, lane_index: usize, cells_to_next_car: u32, cells_to_next_obstacle: u32) -> u32 {
+        let anticipates = self.driving_model == DrivingModel::Anticipation || vehicle_class == VehicleClass::Automated;
+        if !anticipates || cells_to_next_car != cells_to_next_obstacle {
+            return 0;
+        }
+        self.next_car_speeds[lane_index]
+    }
+
+    /// Checks whether a car can brake down to the cells it is about to drive within the maximum
+    /// deceleration. If it cannot, it brakes in an emergency or collides with the obstacle ahead,
+    /// which wrecks it. The car still stops behind the obstacle, as cells hold only one car.
+    fn limit_deceleration(&mut self, car: &mut Car, driveable: u32) {
+        let Some((max_deceleration, braking_overrun, emergency_braking_factor)) = self.max_deceleration else { return; };
+        let deceleration = (car.last_speed() as u32).saturating_sub(driveable);
+        if deceleration <= max_deceleration as u32 {
+            return;
+        }
+        if braking_overrun == BrakingOverrun::Emergency && deceleration as f32 <= max_deceleration as f32 * emergency_braking_factor {
+            self.n_emergency_brakings += 1;
+        } else {
+            car.crash(self.collision_rounds);
+            self.n_collisions += 1;
+        }
+    }
+
+    /// Returns the cells to the next obstacle in a lane plus the cells that the car ahead is
+    /// anticipated to drive by a car of the vehicle class. (See: `DrivingModel`)
+    fn cells_to_anticipated_obstacle(&self, vehicle_class: VehicleClass, lane_index: usize) -> u32 {
+        let cells_to_next_obstacle = self.cells_to_next_obstacles[lane_index];
+        cells_to_next_obstacle.saturating_add(self.anticipated_cells(vehicle_class, lane_index, self.cells_to_next_cars[lane_index], cells_to_next_obstacle))
     }
 
     fn note_car_free(&mut self, lane_index: usize, other_obstacle: bool) {
         let road_length = self.length();
         let cells_to_next_car = &mut self.cells_to_next_cars[lane_index];
-        if *cells_to_next_car < 255 && (*cells_to_next_car as u32) < road_length {
-            // Prevents from adding with overflow in cases where the
-            // next gap is very far away or there are no cars in the lane.
+        if *cells_to_next_car < road_length {
+            // Prevents from adding with overflow in cases where there are no cars in the lane.
             *cells_to_next_car += 1;
         }
         if other_obstacle {
             self.cells_to_next_obstacles[lane_index] = 0;
+            self.next_car_speeds[lane_index] = 0;
         } else {
             let cells_to_next_obstacle = &mut self.cells_to_next_obstacles[lane_index];
-            if *cells_to_next_obstacle < 255 && (*cells_to_next_obstacle as u32) < road_length {
+            if *cells_to_next_obstacle < road_length {
                 *cells_to_next_obstacle += 1;
             }
         }
     }
 
+    /// Returns whether the car `cells_to_next_car` free cells ahead of a cell switched into the lane
+    /// in the current round.
+    fn car_ahead_changed_lane(&self, lane_i: usize, cell_i: usize, cells_to_next_car: usize) -> bool {
+        let length = self.length() as usize;
+        let ahead_i = cell_i + cells_to_next_car + 1;
+        if ahead_i >= length && self.open() {
+            return false;
+        }
+        self.lanes[lane_i][ahead_i % length].car().is_some_and(|car| car.changed_lane_in(self.rounds))
+    }
+
+    /// Lets arriving cars join the queues in front of the lanes and moves the first waiting car of
+    /// each lane onto the road if the first cell is free. Cars enter as fast as the free cells in
+    /// front of them allow.
+    fn let_cars_enter(&mut self) {
+        let Some(demand) = &self.demand else { return; };
+        let n_lanes = self.lanes.len();
+        if n_lanes == 0 || self.length == 0 {
+            return;
+        }
+
+        if let Some(bin) = demand.bin_at(self.rounds) {
+            let cars_per_round = bin.cars_per_round();
+            match self.arrival_process {
+                ArrivalProcess::Bernoulli => {
+                    let arrival_probability = cars_per_round / n_lanes as f64;
+                    for lane_i in 0..n_lanes {
+                        if self.rng.gen::<f64>() < arrival_probability {
+                            let vehicle_type = bin.pick_blueprint(&mut self.rng, &self.vehicle_blueprints);
+                            Self::join_queue(&mut self.waiting_cars, &self.lanes, &self.vehicle_blueprints, lane_i, vehicle_type);
+                        }
+                    }
+                },
+                ArrivalProcess::Poisson => {
+                    for _ in 0..Self::poisson(&mut self.rng, cars_per_round) {
+                        let lane_i = self.rng.gen_range(0..n_lanes);
+                        let vehicle_type = bin.pick_blueprint(&mut self.rng, &self.vehicle_blueprints);
+                        Self::join_queue(&mut self.waiting_cars, &self.lanes, &self.vehicle_blueprints, lane_i, vehicle_type);
+                    }
+                },
+                ArrivalProcess::Uniform => {
+                    self.arrival_credit += cars_per_round;
+                    while self.arrival_credit >= 1.0 {
+                        self.arrival_credit -= 1.0;
+                        let lane_i = self.next_arrival_lane % n_lanes;
+                        self.next_arrival_lane = (lane_i + 1) % n_lanes;
+                        let vehicle_type = bin.pick_blueprint(&mut self.rng, &self.vehicle_blueprints);
+                        Self::join_queue(&mut self.waiting_cars, &self.lanes, &self.vehicle_blueprints, lane_i, vehicle_type);
+                    }
+                },
+                ArrivalProcess::Batch => {
+                    let batch_size = self.arrival_batch_size;
+                    for _ in 0..Self::poisson(&mut self.rng, cars_per_round / batch_size as f64) {
+                        for _ in 0..batch_size {
+                            let lane_i = self.rng.gen_range(0..n_lanes);
+                            let vehicle_type = bin.pick_blueprint(&mut self.rng, &self.vehicle_blueprints);
+                            Self::join_queue(&mut self.waiting_cars, &self.lanes, &self.vehicle_blueprints, lane_i, vehicle_type);
+                        }
+                    }
+                },
+            }
+        }
+
+        for lane_i in 0..n_lanes {
+            let lane = &mut self.lanes[lane_i];
+            if self.waiting_cars[lane_i].is_empty() || !lane[0].free() {
+                continue;
+            }
+            let vehicle_type = self.waiting_cars[lane_i].pop_front().unwrap();
+            let blueprint = &self.vehicle_blueprints[vehicle_type];
+            let max_speed = self.active_max_speeds[vehicle_type];
+            let free_cells = (1..lane.len())
+                .take(max_speed as usize)
+                .take_while(|cell_i| lane[*cell_i].free())
+                .count();
+            let mut car = Car::new(self.n_cars, vehicle_type, blueprint);
+            car.set_max_speed(max_speed);
+            car.set_speed(free_cells as u8);
+            if let Some(driver_distributions) = self.driver_distributions {
+                driver_distributions.draw(&mut self.rng, &mut car);
+            }
+            car.flip_flop_sync(&self.overflow_flip_flop);
+            car.depart(self.rounds, lane_i);
+            lane[0].put_car(Box::new(car), self.rounds).unwrap();
+            self.n_cars += 1;
+        }
+    }
+
+    /// Lets an arriving car join the queue in front of the lane or, if its vehicle type may not use
+    /// the lane or its first cell is reserved for other vehicles, in front of the nearest lane that
+    /// it may use, preferring the lane to the right in a tie.
+    fn join_queue(waiting_cars: &mut [VecDeque<usize>], lanes: &[Vec<Cell>], vehicle_blueprints: &[VehicleBlueprint], lane_i: usize, vehicle_type: usize) {
+        let vehicle_blueprint = &vehicle_blueprints[vehicle_type];
+        let usable = |lane_i: usize| vehicle_blueprint.allows_lane(lane_i) && !lanes[lane_i][0].reserved_against(vehicle_blueprint.vehicle_class());
+        let queue_i = (0..lanes.len())
+            .filter(|other_lane_i| usable(*other_lane_i))
+            .min_by_key(|other_lane_i| (other_lane_i.abs_diff(lane_i), cmp::Reverse(*other_lane_i)))
+            .unwrap_or(lane_i);
+        waiting_cars[queue_i].push_back(vehicle_type);
+    }
+
+    /// Draws the number of events in a round from a Poisson distribution with the given mean.
+    /// (Knuth's algorithm, which is fast for the small means of arrivals per round)
+    fn poisson(rng: &mut StdRng, mean: f64) -> u32 {
+        let limit = (-mean).exp();
+        let mut events = 0;
+        let mut product = rng.gen::<f64>();
+        while product > limit {
+            events += 1;
+            product *= rng.gen::<f64>();
+        }
+        events
+    }
+
+    /// Removes a car that has driven past the end of an open road.
+    fn exit_car(&mut self, car: Car, lane_i: usize) {
+        self.exited_car_records[car.vehicle_type()].add(&car);
+        for section in self.sections.iter_mut() {
+            section.forget(car.id());
+        }
+        self.n_exited_cars += 1;
+        if let Some((departure_round, _)) = car.departure().filter(|_| car.vehicle_class() == VehicleClass::Emergency) {
+            self.emergency_travel_times.push(self.rounds - departure_round);
+        }
+        if self.trip_recording {
+            self.arrived_trips.push(Trip::new(&car, self.rounds, lane_i));
+        }
+    }
+
+    /// Moves a car that drives off from a cell along the target lane by its speed and records the
+    /// passed cells and completed laps. Cars that drive past the end of an open road exit.
+    fn place_car(&mut self, mut car: Box<Car>, cell_i: usize, target_lane_i: usize) -> Result<(), PutCarErrorInformation> {
+        let length = self.length() as usize;
+        let open = self.open();
+        let target_i = cell_i + car.speed() as usize;
+        let passed_cells = match open {
+            true => cmp::min(car.speed() as usize, length - 1 - cell_i),
+            false => car.speed() as usize,
+        };
+        for section in self.sections.iter_mut() {
+            section.record_move(&car, cell_i, passed_cells, self.rounds, length);
+        }
+        if !open && target_i >= length {
+            self.laps += 1;
+            if let Some(lap_time) = car.complete_lap(self.rounds) {
+                self.lap_times.push(lap_time);
+                if car.vehicle_class() == VehicleClass::Emergency {
+                    self.emergency_travel_times.push(lap_time);
+                }
+            }
+        }
+        let target_lane = &mut self.lanes[target_lane_i];
+        if open && target_i >= length {
+            for passed_cell in &mut target_lane[(cell_i + 1)..] {
+                passed_cell.pass(car.speed());
+            }
+            self.exit_car(*car, target_lane_i);
+            return Ok(());
+        }
+        for passed_cell_i in (cell_i + 1)..=target_i {
+            target_lane[passed_cell_i % length].pass(car.speed());
+        }
+        target_lane[target_i % length].put_car(car, self.rounds)
+    }
+
+    /// Returns the order in which the lanes are updated in the current round.
+    fn lane_order(&mut self) -> Vec<usize> {
+        let mut lane_order: Vec<usize> = (0..self.lanes.len()).collect();
+        match self.update_order {
+            UpdateOrder::RandomLanes => lane_order.shuffle(&mut self.rng),
+            UpdateOrder::AlternatingLanes if self.rounds.is_multiple_of(2) => lane_order.reverse(),
+            _ => (),
+        }
+        lane_order
+    }
+
     /// Simulates one round of the cellular automaton.
     pub fn round(&mut self) {
         self.rounds += 1;
+        self.arrived_trips.clear();
+        self.apply_parameter_profile();
+        let cars_on_road = self.n_cars - self.n_exited_cars - self.n_removed_cars;
+        self.car_rounds += cars_on_road as u64;
+        self.generate_incidents();
+        self.update_scheduled_blocks();
         self.update_traffic_lights();
+        self.preempt_traffic_lights();
+
+        let congested_cars = if self.update_order == UpdateOrder::Parallel {
+            self.update_cars_in_parallel()
+        } else if self.lanes.len() == 1 {
+            self.update_cars_in_single_lane()
+        } else {
+            self.update_cars_sequentially()
+        };
+
+        self.congested_fraction = congested_cars as f64 / cars_on_road as f64;
+        // Flip the flop to keep track of which cars have already been moved in a round.
+        self.overflow_flip_flop.flip_flop();
+        self.let_cars_enter();
+        self.control_density();
+        self.record_density_monitors();
+    }
+
+    /// Returns the indexes of the cells within the density window around the cell. On a closed
+    /// road the window wraps around the end, on an open road it ends with the road.
+    fn density_window_cells(&self, cell_i: usize) -> Vec<usize> {
+        let (length, window) = (self.length as i64, self.density_window as i64);
+        if !self.open() && 2 * window + 1 >= length {
+            return (0..self.length as usize).collect();
+        }
+        (cell_i as i64 - window..=cell_i as i64 + window)
+            .filter(|i| !self.open() || (0..length).contains(i))
+            .map(|i| i.rem_euclid(length) as usize)
+            .collect()
+    }
+
+    /// Counts the cars within the density window of every density monitor.
+    fn record_density_monitors(&mut self) {
+        for monitor_i in 0..self.density_monitors.len() {
+            let monitor = &self.density_monitors[monitor_i];
+            let lane = &self.lanes[monitor.lane()];
+            let cars = self.density_window_cells(monitor.index())
+                .into_iter()
+                .filter(|cell_i| lane[*cell_i].car().is_some())
+                .count();
+            self.density_monitor_cars[monitor_i] += cars as u64;
+        }
+    }
+
+    /// Returns the mean fraction of the cells within the density window of each density monitor
+    /// that contained a car at the end of the rounds recorded so far. This is the local density
+    /// in cars per cell.
+    pub fn monitor_densities(&self) -> Vec<f64> {
+        self.density_monitors
+            .iter()
+            .zip(&self.density_monitor_cars)
+            .map(|(monitor, cars)| *cars as f64 / self.measured_rounds() as f64 / self.density_window_cells(monitor.index()).len() as f64)
+            .collect()
+    }
+
+    /// Activates the parameters of the parameter profile and the weather for the current round and
+    /// passes the maximum speeds on to the cars on the road if they changed.
+    fn apply_parameter_profile(&mut self) {
+        if self.parameter_profile.is_none() && self.weather_schedule.is_none() {
+            return;
+        }
+        let bin = self.parameter_profile.as_ref().and_then(|parameter_profile| parameter_profile.bin_at(self.rounds));
+        let weather = self.weather_schedule.as_ref().map(|weather_schedule| weather_schedule.weather_at(self.rounds)).unwrap_or_default();
+        let dilly_dally_probability = bin
+            .and_then(|bin| bin.dilly_dally_probability())
+            .unwrap_or(self.dilly_dally_probability);
+        self.active_dilly_dally_probability = (dilly_dally_probability * weather.dilly_dally_factor()).min(1.0);
+        self.active_stay_in_lane_probability = bin
+            .and_then(|bin| bin.stay_in_lane_probability())
+            .unwrap_or(self.stay_in_lane_probability);
+        let max_speeds: Vec<u8> = self.vehicle_blueprints
+            .iter()
+            .enumerate()
+            .map(|(vehicle_type, vb)| bin.and_then(|bin| bin.max_speed(vehicle_type)).unwrap_or(vb.max_speed()))
+            .map(|max_speed| ((max_speed as f32 * weather.max_speed_factor()).round() as u8).max(1))
+            .collect();
+        if max_speeds != self.active_max_speeds {
+            for cell in self.lanes.iter_mut().flatten() {
+                if let Some(car) = cell.car_mut() {
+                    car.set_max_speed(max_speeds[car.vehicle_type()]);
+                }
+            }
+            self.active_max_speeds = max_speeds;
+        }
+    }
 
+    /// Updates the cars one after another, sweeping over the cells in reverse and over the lanes in
+    /// the lane order. Returns the number of congested cars.
+    fn update_cars_sequentially(&mut self) -> u32 {
+        let mut congested_cars: u32 = 0;
         let length = self.length() as usize;
-        let n_lanes = self.lanes.len();
+        let mut lane_order = self.lane_order();
 
         self.prepare_cells_to_next_obstacles_for_wrap_around();
+        self.signal_cells.fill(None);
+        self.merge_cells.fill(None);
 
         // Iterate over cars in reverse to avoid having to look ahead each time.
         for cell_i in (0..length).rev() {
-            for lane_i in 0..n_lanes {
-                if self.lanes[lane_i][cell_i].blocked() || self.lanes[lane_i][cell_i].is_red_light(self.traffic_lights_red) {
+            if self.update_order == UpdateOrder::RandomLanesPerCell {
+                lane_order.shuffle(&mut self.rng);
+            }
+            self.lanes_processed.fill(false);
+            for &lane_i in &lane_order {
+                self.lanes_processed[lane_i] = true;
+                if let Some(gap_counters) = &mut self.gap_counters {
+                    gap_counters[lane_i][cell_i] = (self.cells_to_next_cars[lane_i], self.cells_to_next_obstacles[lane_i]);
+                }
+                if self.lanes[lane_i][cell_i].blocked() || (self.lanes[lane_i][cell_i].is_red_light() && !self.runs_red_lights(lane_i, cell_i)) {
                     // skip blocked cells
                     self.note_car_free(lane_i, true);
                     continue;
                 }
 
                 let (left_clear, right_clear) = self.check_sides_clear(lane_i, cell_i);
+                let runs_red_lights = self.runs_red_lights(lane_i, cell_i);
                 // let lane = &mut self.lanes[lane_i];
-                let car = self.lanes[lane_i][cell_i].take_car();
+                let car = self.lanes[lane_i][cell_i].take_car(self.rounds);
                 match car {
                     Some(mut car) => {
                         if !car.flip_flop_unsync(&self.overflow_flip_flop) {
                             // Car has already been moved. This is due to a wrap-around.
                             self.note_car_obstacle(lane_i, 0);
-                            self.lanes[lane_i][cell_i].put_car(car).expect("Cannot put car into a cell that already contains a car. If you see this error message something has gone very wrong. The flip-flop must be broken.");
+                            self.lanes[lane_i][cell_i].put_car(car, self.rounds).expect("Cannot put car into a cell that already contains a car. If you see this error message something has gone very wrong. The flip-flop must be broken.");
                             continue;
                         }
 
                         // -- calculate movement and update car --
                         car.increase_speed();
-                        let stay = Self::occurs(&mut self.rng, self.stay_in_lane_probability);
-                        let best_switch: LaneSwitch = self.determine_best_lane(lane_i, car.speed(), left_clear, right_clear, stay);
+                        if let Some(limit) = self.speed_limit_at(lane_i, cell_i) {
+                            car.limit_speed(limit);
+                        }
+                        let cells_to_reserved = self.cells_to_reserved_ahead(&car, lane_i, cell_i);
+                        let urgency = self.mandatory_lane_change_urgency(lane_i, cell_i, car.max_speed(), cells_to_reserved);
+                        let (left_clear, right_clear) = self.avoid_blocked_lanes(car.vehicle_type(), lane_i, cell_i, left_clear, right_clear);
+                        let yielding = self.yields_to_emergency_vehicle(&car, lane_i, cell_i);
+                        let stay_in_lane_probability = self.stay_in_lane_probability_of(&car);
+                        let stay = car.wrecked() || Self::occurs(&mut self.rng, stay_in_lane_probability * (1.0 - urgency.unwrap_or(0.0)));
+                        // Cars clearing the way for an emergency vehicle only switch to the right.
+                        let best_switch: LaneSwitch = self.determine_best_lane(lane_i, cell_i, &car, left_clear && !yielding, right_clear, stay && !yielding, urgency.is_some() || yielding);
+                        let best_switch = self.signal_lane_change(&mut car, lane_i, cell_i, left_clear, right_clear, best_switch);
                         let is_switch = best_switch.is_switch();
-                        car.finish(best_switch.driveable(), !is_switch && Self::occurs(&mut self.rng, self.dilly_dally_probability));
+                        if is_switch {
+                            self.record_lane_change(&mut car, lane_i, best_switch.to_offset());
+                            if urgency.is_some() {
+                                self.n_mandatory_lane_changes += 1;
+                            }
+                        } else if car.speed() as u32 - best_switch.driveable() > self.near_miss_braking as u32
+                            && self.cells_to_next_cars[lane_i] == self.cells_to_next_obstacles[lane_i]
+                            && self.car_ahead_changed_lane(lane_i, cell_i, self.cells_to_next_cars[lane_i] as usize)
+                        {
+                            self.near_misses += 1;
+                        }
+                        let driveable = match is_switch {
+                            true => best_switch.driveable(),
+                            false => {
+                                let driveable = match runs_red_lights {
+                                    true => cmp::min(self.cells_to_obstacle_past_lights(lane_i, cell_i, car.speed() as u32), car.speed() as u32),
+                                    false => best_switch.driveable(),
+                                };
+                                let blocked = driveable < car.speed() as u32
+                                    && self.blocked_ahead(lane_i, cell_i, self.cells_to_next_cars[lane_i], self.cells_to_next_obstacles[lane_i]);
+                                let driveable = self.yield_to_merging_cars(&car, lane_i, cell_i, driveable, blocked);
+                                cells_to_reserved.map_or(driveable, |cells| cmp::min(driveable, cells))
+                            },
+                        };
+                        self.limit_deceleration(&mut car, driveable);
+                        let dilly_dally_probability = self.dilly_dally_probability_at(&car, lane_i, cell_i);
+                        car.finish(driveable as u8, !is_switch && (yielding || Self::occurs(&mut self.rng, dilly_dally_probability)));
+                        if car.record_congestion(self.congestion_threshold) {
+                            congested_cars += 1;
+                        }
                         self.note_car_obstacle(lane_i, 0);
+                        if !is_switch {
+                            self.note_car_speed(lane_i, &car);
+                        }
 
                         // -- place car into new cell and record cell passage --
-                        if is_switch && car.speed() > 1 {
-                            self.lanes[lane_i][(cell_i + 1) % length].pass();
+                        let open = self.open();
+                        if is_switch && car.speed() > 1 && (cell_i + 1 < length || !open) {
+                            self.lanes[lane_i][(cell_i + 1) % length].pass(car.speed());
                         }
                         let target_i = cell_i + car.speed() as usize;
                         let target_lane_i = (lane_i as isize + best_switch.to_offset()) as usize;
                         if is_switch && car.speed() > 0 {
-                            self.note_car_obstacle(target_lane_i, car.speed() - 1);
-                        }
-                        let target_lane = &mut self.lanes[target_lane_i];
-                        for passed_cell_i in (cell_i + 1)..=target_i {
-                            target_lane[passed_cell_i % length].pass();
+                            self.note_car_obstacle(target_lane_i, car.speed() as u32 - 1);
+                        } else if is_switch && self.lanes_processed[target_lane_i] {
+                            // The car moves alongside and the target lane has already passed this cell.
+                            self.note_car_obstacle(target_lane_i, 0);
                         }
-                        if let Err(PutCarErrorInformation { cell_blocked, new_car }) = target_lane[target_i % length].put_car(car) {
+                        if let Err(PutCarErrorInformation { cell_blocked, new_car }) = self.place_car(car, cell_i, target_lane_i) {
                             panic!(
                                 "FATAL: Cannot put car into a cell that {}!\nDEBUG INFO:\n  Round: {}\n  Car: {}:{} (lane_index:cell_index)\n  Speed: {}\n  Cells to next cars by lane: {:?}\n  Cells to next obstacles by lane: {:?}\n  LaneSwitch: {:?}\n    Target: {}:{} (lane_index:cell_index)",
                                 if cell_blocked { "is blocked" } else { "already contains a car" },
@@ -360,33 +2082,478 @@ impl Road {
                         self.note_car_free(lane_i, false);
                     }
                 }
+                self.note_stop_signs(lane_i, cell_i);
             }
         }
-        // Flip the flop to keep track of which cars have already been moved in a round.
-        self.overflow_flip_flop.flip_flop();
+        congested_cars
+    }
+
+    /// Updates the cars of a road with a single lane like `update_cars_sequentially`, but without
+    /// the bookkeeping for lane changes, as there is no other lane to switch to, merge from or
+    /// yield to. Returns the number of congested cars.
+    fn update_cars_in_single_lane(&mut self) -> u32 {
+        let mut congested_cars: u32 = 0;
+        let length = self.length() as usize;
+
+        self.prepare_cells_to_next_obstacles_for_wrap_around();
+
+        for cell_i in (0..length).rev() {
+            if let Some(gap_counters) = &mut self.gap_counters {
+                gap_counters[0][cell_i] = (self.cells_to_next_cars[0], self.cells_to_next_obstacles[0]);
+            }
+            if self.lanes[0][cell_i].blocked() || (self.lanes[0][cell_i].is_red_light() && !self.runs_red_lights(0, cell_i)) {
+                self.note_car_free(0, true);
+                continue;
+            }
+            // Only take cars out of cells that hold one, as writing to every cell is slow on long roads.
+            if self.lanes[0][cell_i].car().is_none() {
+                self.note_car_free(0, false);
+                self.note_stop_signs(0, cell_i);
+                continue;
+            }
+            let runs_red_lights = self.runs_red_lights(0, cell_i);
+            let mut car = self.lanes[0][cell_i].take_car(self.rounds).unwrap();
+            if !car.flip_flop_unsync(&self.overflow_flip_flop) {
+                // Car has already been moved. This is due to a wrap-around.
+                self.note_car_obstacle(0, 0);
+                self.lanes[0][cell_i].put_car(car, self.rounds).expect("Cannot put car into a cell that already contains a car. If you see this error message something has gone very wrong. The flip-flop must be broken.");
+                continue;
+            }
+
+            car.increase_speed();
+            if let Some(limit) = self.speed_limit_at(0, cell_i) {
+                car.limit_speed(limit);
+            }
+            let cells_to_obstacle = match runs_red_lights {
+                true => self.cells_to_obstacle_past_lights(0, cell_i, car.speed() as u32),
+                false => self.cells_to_anticipated_obstacle(car.vehicle_class(), 0),
+            };
+            let driveable = cmp::min(cells_to_obstacle, car.speed() as u32) as u8;
+            let driveable = self.cells_to_reserved_ahead(&car, 0, cell_i).map_or(driveable, |cells| cmp::min(driveable, cells as u8));
+            self.limit_deceleration(&mut car, driveable as u32);
+            let dilly_dally_probability = self.dilly_dally_probability_at(&car, 0, cell_i);
+            // There is no lane to clear, so cars only slow down for emergency vehicles behind them.
+            let yielding = self.yields_to_emergency_vehicle(&car, 0, cell_i);
+            car.finish(driveable, yielding || Self::occurs(&mut self.rng, dilly_dally_probability));
+            if car.record_congestion(self.congestion_threshold) {
+                congested_cars += 1;
+            }
+            self.note_car_obstacle(0, 0);
+            self.note_car_speed(0, &car);
+            if let Err(PutCarErrorInformation { cell_blocked, new_car }) = self.place_car(car, cell_i, 0) {
+                panic!(
+                    "FATAL: Cannot put car into a cell that {}!\nDEBUG INFO:\n  Round: {}\n  Car: 0:{} (lane_index:cell_index)\n  Speed: {}",
+                    if cell_blocked { "is blocked" } else { "already contains a car" },
+                    self.rounds,
+                    cell_i,
+                    new_car.speed()
+                );
+            }
+            self.note_stop_signs(0, cell_i);
+        }
+        congested_cars
+    }
+
+    /// Returns the number of free cells in front of every cell up to the next car and up to the
+    /// next obstacle, lane by lane. Distances are `u32::MAX` if there is nothing ahead.
+    fn gaps(&self) -> Vec<Vec<(u32, u32)>> {
+        let length = self.length() as usize;
+        // On a closed road, sweeping twice lets the distances wrap around the end.
+        let sweep = if self.open() { length } else { 2 * length };
+        self.lanes
+            .iter()
+            .map(|lane| {
+                let mut gaps = vec![(u32::MAX, u32::MAX); length];
+                let mut cells_to_next_car = u32::MAX;
+                let mut cells_to_next_obstacle = u32::MAX;
+                for i in (0..sweep).rev() {
+                    let cell = &lane[i % length];
+                    if i < length {
+                        gaps[i] = (cells_to_next_car, cells_to_next_obstacle);
+                    }
+                    if cell.car().is_some() {
+                        cells_to_next_car = 0;
+                        cells_to_next_obstacle = 0;
+                    } else if !Self::passable(lane, i % length, self.open()) {
+                        cells_to_next_car = cells_to_next_car.saturating_add(1);
+                        cells_to_next_obstacle = 0;
+                    } else {
+                        cells_to_next_car = cells_to_next_car.saturating_add(1);
+                        cells_to_next_obstacle = cells_to_next_obstacle.saturating_add(1);
+                    }
+                }
+                gaps
+            })
+            .collect()
+    }
+
+    /// Updates all cars at once in two phases. First, all cars decide whether to switch lanes
+    /// based on the state at the start of the round and switch at the same time. If two cars want
+    /// to switch into the same cell, the car from the left lane gets to. Then, all cars drive
+    /// forward based on the state after switching lanes. Returns the number of congested cars.
+    fn update_cars_in_parallel(&mut self) -> u32 {
+        let length = self.length() as usize;
+        let n_lanes = self.lanes.len();
+
+        // -- phase 1: switch lanes --
+        // No car has driven yet, so there is nothing to anticipate.
+        self.next_car_speeds.fill(0);
+        let gaps = self.gaps();
+        let mut switches: Vec<(usize, usize, usize)> = Vec::new();
+        for cell_i in (0..length).rev() {
+            for lane_i in 0..n_lanes {
+                let cell = &self.lanes[lane_i][cell_i];
+                if cell.car().is_none() || (cell.is_red_light() && !self.runs_red_lights(lane_i, cell_i)) {
+                    continue;
+                }
+                for (other_lane_i, other_gaps) in gaps.iter().enumerate() {
+                    (self.cells_to_next_cars[other_lane_i], self.cells_to_next_obstacles[other_lane_i]) = other_gaps[cell_i];
+                }
+                let (left_clear, right_clear) = self.check_sides_clear(lane_i, cell_i);
+                let speed_limit = self.speed_limit_at(lane_i, cell_i);
+                let mut car = self.lanes[lane_i][cell_i].take_car(self.rounds).unwrap();
+                car.increase_speed();
+                if let Some(limit) = speed_limit {
+                    car.limit_speed(limit);
+                }
+                let cells_to_reserved = self.cells_to_reserved_ahead(&car, lane_i, cell_i);
+                let urgency = self.mandatory_lane_change_urgency(lane_i, cell_i, car.max_speed(), cells_to_reserved);
+                let (left_clear, right_clear) = self.avoid_blocked_lanes(car.vehicle_type(), lane_i, cell_i, left_clear, right_clear);
+                let yielding = self.yields_to_emergency_vehicle(&car, lane_i, cell_i);
+                let stay_in_lane_probability = self.stay_in_lane_probability_of(&car);
+                let stay = car.wrecked() || Self::occurs(&mut self.rng, stay_in_lane_probability * (1.0 - urgency.unwrap_or(0.0)));
+                let best_switch = self.determine_best_lane(lane_i, cell_i, &car, left_clear && !yielding, right_clear, stay && !yielding, urgency.is_some() || yielding);
+                let best_switch = self.signal_lane_change(&mut car, lane_i, cell_i, left_clear, right_clear, best_switch);
+                self.lanes[lane_i][cell_i].put_car(car, self.rounds).unwrap();
+                if best_switch.is_switch() {
+                    let target_lane_i = (lane_i as isize + best_switch.to_offset()) as usize;
+                    if !switches.iter().any(|(_, other_cell_i, other_target_lane_i)| *other_cell_i == cell_i && *other_target_lane_i == target_lane_i) {
+                        switches.push((lane_i, cell_i, target_lane_i));
+                        if urgency.is_some() {
+                            self.n_mandatory_lane_changes += 1;
+                        }
+                    }
+                }
+            }
+        }
+        for (lane_i, cell_i, target_lane_i) in &switches {
+            let mut car = self.lanes[*lane_i][*cell_i].take_car(self.rounds).unwrap();
+            self.record_lane_change(&mut car, *lane_i, *target_lane_i as isize - *lane_i as isize);
+            self.lanes[*target_lane_i][*cell_i].put_car(car, self.rounds).unwrap();
+        }
+
+        // -- phase 2: drive forward --
+        let gaps = self.gaps();
+        if let Some(gap_counters) = &mut self.gap_counters {
+            gap_counters.clone_from(&gaps);
+        }
+        self.signal_cells.fill(None);
+        self.merge_cells.fill(None);
+        // Cars are taken from the road during the sweep, so look behind and past the lights first.
+        let yielding_cars: Vec<(usize, usize)> = self.iter_cars()
+            .filter(|(_, lane_i, cell_i, car)| self.yields_to_emergency_vehicle(car, *lane_i, *cell_i))
+            .map(|(_, lane_i, cell_i, _)| (lane_i, cell_i))
+            .collect();
+        let red_light_runs: Vec<(usize, usize, u32)> = self.iter_cars()
+            .filter(|(_, lane_i, cell_i, _)| self.runs_red_lights(*lane_i, *cell_i))
+            .map(|(_, lane_i, cell_i, car)| (lane_i, cell_i, self.cells_to_obstacle_past_lights(lane_i, cell_i, car.speed() as u32)))
+            .collect();
+        let mut congested_cars: u32 = 0;
+        let mut moving_cars: Vec<(Box<Car>, usize, usize)> = Vec::new();
+        for cell_i in (0..length).rev() {
+            for (lane_i, lane_gaps) in gaps.iter().enumerate() {
+                let red_light_run = red_light_runs.iter().find(|(run_lane_i, run_cell_i, _)| *run_lane_i == lane_i && *run_cell_i == cell_i);
+                if self.lanes[lane_i][cell_i].is_red_light() && red_light_run.is_none() {
+                    self.next_car_speeds[lane_i] = 0;
+                    continue;
+                }
+                let Some(mut car) = self.lanes[lane_i][cell_i].take_car(self.rounds) else { continue; };
+                car.flip_flop_unsync(&self.overflow_flip_flop);
+                let switched = switches.iter().any(|(_, switch_cell_i, target_lane_i)| *switch_cell_i == cell_i && *target_lane_i == lane_i);
+                let (cells_to_next_car, cells_to_next_obstacle) = lane_gaps[cell_i];
+                let anticipated_cells = self.anticipated_cells(car.vehicle_class(), lane_i, cells_to_next_car, cells_to_next_obstacle);
+                let driveable = match red_light_run {
+                    Some((_, _, cells_to_obstacle)) => cmp::min(*cells_to_obstacle, car.speed() as u32),
+                    None => cmp::min(cells_to_next_obstacle.saturating_add(anticipated_cells), car.speed() as u32),
+                };
+                let driveable = match switched {
+                    true => driveable,
+                    false => {
+                        let blocked = driveable < car.speed() as u32 && self.blocked_ahead(lane_i, cell_i, cells_to_next_car, cells_to_next_obstacle);
+                        self.yield_to_merging_cars(&car, lane_i, cell_i, driveable, blocked)
+                    },
+                };
+                let driveable = self.cells_to_reserved_ahead(&car, lane_i, cell_i).map_or(driveable, |cells| cmp::min(driveable, cells));
+                if !switched && car.speed() as u32 - driveable > self.near_miss_braking as u32 && cells_to_next_car == cells_to_next_obstacle {
+                    // The car ahead has been taken from the road already, so look it up in the switches.
+                    let ahead_i = cell_i + cells_to_next_car as usize + 1;
+                    if (ahead_i < length || !self.open())
+                        && switches.iter().any(|(_, switch_cell_i, target_lane_i)| *switch_cell_i == ahead_i % length && *target_lane_i == lane_i)
+                    {
+                        self.near_misses += 1;
+                    }
+                }
+                self.limit_deceleration(&mut car, driveable);
+                let dilly_dally_probability = self.dilly_dally_probability_at(&car, lane_i, cell_i);
+                let yielding = yielding_cars.contains(&(lane_i, cell_i));
+                car.finish(driveable as u8, !switched && (yielding || Self::occurs(&mut self.rng, dilly_dally_probability)));
+                if car.record_congestion(self.congestion_threshold) {
+                    congested_cars += 1;
+                }
+                self.next_car_speeds[lane_i] = 0;
+                self.note_car_speed(lane_i, &car);
+                moving_cars.push((car, lane_i, cell_i));
+            }
+        }
+        for (car, lane_i, cell_i) in moving_cars {
+            if let Err(PutCarErrorInformation { cell_blocked, new_car }) = self.place_car(car, cell_i, lane_i) {
+                panic!(
+                    "FATAL: Cannot put car into a cell that {}!\nDEBUG INFO:\n  Round: {}\n  Car: {}:{} (lane_index:cell_index)\n  Speed: {}",
+                    if cell_blocked { "is blocked" } else { "already contains a car" },
+                    self.rounds,
+                    lane_i, cell_i,
+                    new_car.speed()
+                );
+            }
+        }
+        congested_cars
+    }
+
+    /// Records that the car leaves the lane for the lane at the offset (`-1` for left, `1` for
+    /// right).
+    fn record_lane_change(&mut self, car: &mut Car, lane_i: usize, offset: isize) {
+        car.change_lane(self.rounds);
+        let lane_changes = &mut self.lane_change_counts[lane_i][car.vehicle_type()];
+        match offset {
+            -1 => lane_changes.left += 1,
+            _ => lane_changes.right += 1,
+        }
+    }
+
+    /// Holds lane changes back by a round when cars use turn signals. A car that wants to switch
+    /// lanes stays in its lane and announces the switch instead, unless it has already announced
+    /// the same switch. The announcement is withdrawn once nothing holds the car up anymore.
+    fn signal_lane_change(&self, car: &mut Car, lane_i: usize, cell_i: usize, left_clear: bool, right_clear: bool, best_switch: LaneSwitch) -> LaneSwitch {
+        if !self.turn_signals {
+            return best_switch;
+        }
+        if best_switch.is_switch() {
+            if car.signal() == Some(best_switch.to_offset()) {
+                car.set_signal(None);
+                return best_switch;
+            }
+            car.set_signal(Some(best_switch.to_offset()));
+            return self.determine_best_lane(lane_i, cell_i, car, left_clear, right_clear, true, false);
+        }
+        if best_switch.driveable() >= car.speed() as u32 {
+            car.set_signal(None);
+        }
+        best_switch
+    }
+
+    /// Returns whether the next obstacle `cells_to_next_obstacle` free cells ahead of a cell is a
+    /// blocked cell rather than a car or a red light.
+    fn blocked_ahead(&self, lane_i: usize, cell_i: usize, cells_to_next_car: u32, cells_to_next_obstacle: u32) -> bool {
+        let length = self.length() as usize;
+        let ahead_i = cell_i + cells_to_next_obstacle as usize + 1;
+        if cells_to_next_obstacle >= cells_to_next_car || (ahead_i >= length && self.open()) {
+            return false;
+        }
+        self.lanes[lane_i][ahead_i % length].blocked()
+    }
+
+    /// Returns how urgently a car has to leave its lane, from `0.0` when the blocked cell ahead is
+    /// at least the urgency distance away to `1.0` right in front of it, or `None` if the car is not
+    /// aware of a blocked cell ahead or mandatory lane changes are not told apart. The urgency
+    /// distance is the blockage look-ahead or else a round at maximum speed. Cells reserved for
+    /// other vehicles `cells_to_reserved` ahead always make the car leave its lane, with a round
+    /// at maximum speed as the urgency distance.
+    fn mandatory_lane_change_urgency(&self, lane_i: usize, cell_i: usize, max_speed: u8, cells_to_reserved: Option<u32>) -> Option<f32> {
+        let urgency = |cells: u32, urgency_cells: u32| 1.0 - (cells as f32 / cmp::max(urgency_cells, 1) as f32).min(1.0);
+        let block_urgency = self.cells_to_known_block(lane_i, cell_i)
+            .map(|cells_to_block| urgency(cells_to_block, self.blockage_look_ahead.unwrap_or(max_speed as u32)));
+        let reserved_urgency = cells_to_reserved.map(|cells_to_reserved| urgency(cells_to_reserved, max_speed as u32));
+        match (block_urgency, reserved_urgency) {
+            (Some(block_urgency), Some(reserved_urgency)) => Some(block_urgency.max(reserved_urgency)),
+            (block_urgency, reserved_urgency) => block_urgency.or(reserved_urgency),
+        }
+    }
+
+    /// Returns the number of cells in front of a car up to the next cell in its lane that is
+    /// reserved for other vehicles, if there is one that the car could reach in a round at maximum
+    /// speed. Cars in such a cell already, e.g. because the cells were reserved around them, may
+    /// drive on through it.
+    fn cells_to_reserved_ahead(&self, car: &Car, lane_i: usize, cell_i: usize) -> Option<u32> {
+        if !self.reserved_cells || self.lanes[lane_i][cell_i].reserved_against(car.vehicle_class()) {
+            return None;
+        }
+        self.cells_to_reserved_within(car.vehicle_class(), lane_i, cell_i, car.max_speed() as u32)
+    }
+
+    /// Returns the number of cells in front of a cell up to the next cell in the lane that is
+    /// reserved against the vehicle class, if there is one at most `look_ahead` cells away.
+    fn cells_to_reserved_within(&self, vehicle_class: VehicleClass, lane_i: usize, cell_i: usize, look_ahead: u32) -> Option<u32> {
+        let length = self.length() as usize;
+        let lane = &self.lanes[lane_i];
+        (0..cmp::min(look_ahead, self.length()))
+            .map(|cells| (cells, cell_i + cells as usize + 1))
+            .take_while(|(_, ahead_i)| *ahead_i < length || !self.open())
+            .find(|(_, ahead_i)| lane[ahead_i % length].reserved_against(vehicle_class))
+            .map(|(cells, _)| cells)
+    }
+
+    /// Returns whether a car of the vehicle type may switch into the lane next to it, as it may use
+    /// the lane and the cell alongside and the cells that it could reach from there in a round are
+    /// not reserved for other vehicles.
+    fn may_switch_into(&self, vehicle_type: usize, target_lane_i: usize, cell_i: usize) -> bool {
+        let vehicle_blueprint = &self.vehicle_blueprints[vehicle_type];
+        if !vehicle_blueprint.allows_lane(target_lane_i) {
+            return false;
+        }
+        if !self.reserved_cells {
+            return true;
+        }
+        let vehicle_class = vehicle_blueprint.vehicle_class();
+        !self.lanes[target_lane_i][cell_i].reserved_against(vehicle_class)
+            && self.cells_to_reserved_within(vehicle_class, target_lane_i, cell_i, self.active_max_speeds[vehicle_type] as u32).is_none()
+    }
+
+    /// Returns the number of cells in front of a cell up to the blocked cell ahead that a car in it
+    /// is aware of with mandatory lane changes.
+    fn cells_to_known_block(&self, lane_i: usize, cell_i: usize) -> Option<u32> {
+        if !self.mandatory_lane_changes {
+            return None;
+        }
+        match self.blockage_look_ahead {
+            Some(look_ahead) => self.cells_to_block_within(lane_i, cell_i, look_ahead),
+            None => {
+                let cells_to_next_obstacle = self.cells_to_next_obstacles[lane_i];
+                self.blocked_ahead(lane_i, cell_i, self.cells_to_next_cars[lane_i], cells_to_next_obstacle)
+                    .then_some(cells_to_next_obstacle)
+            },
+        }
     }
 
-    /// Determines the best lane to switch to (or stay on) based on surrounding traffic, 
-    /// available_speed and the stay in late probability.
-    fn determine_best_lane(&self, lane_i: usize, available_speed: u8, left_clear: bool, right_clear: bool, stay: bool) -> LaneSwitch {
+    /// Keeps cars from switching into lanes next to them that they know to be blocked ahead, as
+    /// they would have to leave them again, and into lanes or cells that they may not use.
+    fn avoid_blocked_lanes(&self, vehicle_type: usize, lane_i: usize, cell_i: usize, left_clear: bool, right_clear: bool) -> (bool, bool) {
+        (
+            left_clear && self.may_switch_into(vehicle_type, lane_i - 1, cell_i) && self.cells_to_known_block(lane_i - 1, cell_i).is_none(),
+            right_clear && self.may_switch_into(vehicle_type, lane_i + 1, cell_i) && self.cells_to_known_block(lane_i + 1, cell_i).is_none(),
+        )
+    }
+
+    /// Returns the number of cells in front of a cell up to the next blocked cell in the lane, if
+    /// there is one at most `look_ahead` cells away.
+    fn cells_to_block_within(&self, lane_i: usize, cell_i: usize, look_ahead: u32) -> Option<u32> {
+        let length = self.length() as usize;
+        let lane = &self.lanes[lane_i];
+        (0..cmp::min(look_ahead, self.length()))
+            .map(|cells| (cells, cell_i + cells as usize + 1))
+            .take_while(|(_, ahead_i)| *ahead_i < length || !self.open())
+            .find(|(_, ahead_i)| lane[ahead_i % length].blocked())
+            .map(|(cells, _)| cells)
+    }
+
+    /// Returns whether a car in a cell notices an emergency vehicle behind it in its lane and
+    /// clears the way for it. Emergency vehicles and wrecked cars do not.
+    fn yields_to_emergency_vehicle(&self, car: &Car, lane_i: usize, cell_i: usize) -> bool {
+        let Some(yield_distance) = self.emergency_yield_distance else { return false; };
+        if car.vehicle_class() == VehicleClass::Emergency || car.wrecked() {
+            return false;
+        }
+        let length = self.length() as usize;
+        let lane = &self.lanes[lane_i];
+        (1..=cmp::min(yield_distance as usize, length - 1))
+            .take_while(|behind| *behind <= cell_i || !self.open())
+            .any(|behind| lane[(cell_i + length - behind) % length].car().is_some_and(|car| car.vehicle_class() == VehicleClass::Emergency))
+    }
+
+    /// Returns whether the car in a cell is an emergency vehicle that drives through red lights.
+    fn runs_red_lights(&self, lane_i: usize, cell_i: usize) -> bool {
+        self.emergency_red_light_running
+            && self.lanes[lane_i][cell_i].car().is_some_and(|car| car.vehicle_class() == VehicleClass::Emergency)
+    }
+
+    /// Returns the number of free cells in front of a cell up to the next car or blocked cell in
+    /// the lane, at most `look_ahead`. Unlike for other cars, traffic lights and stop signs are no
+    /// obstacles.
+    fn cells_to_obstacle_past_lights(&self, lane_i: usize, cell_i: usize, look_ahead: u32) -> u32 {
+        let length = self.length() as usize;
+        let lane = &self.lanes[lane_i];
+        (0..cmp::min(look_ahead, self.length() - 1))
+            .map(|cells| cell_i + cells as usize + 1)
+            .take_while(|ahead_i| (*ahead_i >= length && self.open()) || {
+                let cell = &lane[ahead_i % length];
+                !cell.blocked() && cell.car().is_none()
+            })
+            .count() as u32
+    }
+
+    /// Limits the cells a car that stays in its lane may drive so that it stays behind the nearest
+    /// car ahead that announces a lane change into its lane and, with the courtesy probability,
+    /// behind the nearest blocked car ahead in a lane next to it. Cars are swept in reverse, so
+    /// the merging cars behind the car are noted only after it has been updated.
+    fn yield_to_merging_cars(&mut self, car: &Car, lane_i: usize, cell_i: usize, driveable: u32, blocked: bool) -> u32 {
+        let mut driveable = driveable;
+        if let Some(signal_i) = self.signal_cells[lane_i] {
+            let gap = signal_i.saturating_sub(cell_i + 1) as u32;
+            if signal_i > cell_i && gap < driveable {
+                driveable = gap;
+                self.yields += 1;
+            }
+        }
+        if let Some(merge_i) = self.merge_cells[lane_i] {
+            let gap = merge_i.saturating_sub(cell_i + 1) as u32;
+            if merge_i > cell_i && gap < driveable && Self::occurs(&mut self.rng, self.courtesy_probability) {
+                driveable = gap;
+                self.yields += 1;
+            }
+        }
+        if let Some(offset) = car.signal() {
+            self.signal_cells[(lane_i as isize + offset) as usize] = Some(cell_i);
+        }
+        if blocked && self.courtesy_probability > 0.0 {
+            if lane_i > 0 {
+                self.merge_cells[lane_i - 1] = Some(cell_i);
+            }
+            if lane_i + 1 < self.lanes.len() {
+                self.merge_cells[lane_i + 1] = Some(cell_i);
+            }
+        }
+        driveable
+    }
+
+    /// Determines the best lane to switch to (or stay on) based on surrounding traffic, the speed
+    /// of the car and the stay in late probability. A mandatory lane change takes any gap
+    /// instead of only those with more space than the current lane. Under the keep-right rules,
+    /// cars only switch to the right to return to a lane in which they can drive on at full speed,
+    /// not to gain space. (See: `LaneRules`) Cars may pass on the right where a lane is separated
+    /// from the lane to its left because only one of them is reserved, e.g. in a bus lane.
+    #[allow(clippy::too_many_arguments)]
+    fn determine_best_lane(&self, lane_i: usize, cell_i: usize, car: &Car, left_clear: bool, right_clear: bool, stay: bool, mandatory: bool) -> LaneSwitch {
+        let available_speed = car.speed() as u32;
         let driveable_without_passing_on_right = |target_lane_offset: isize| {
             let left_index = lane_i as isize + target_lane_offset - 1;
             let target_lane_index = (lane_i as isize + target_lane_offset) as usize;
-            let mut distance = if left_index < 0 {
+            let separated = left_index >= 0
+                && self.reserved_cells
+                && self.lanes[left_index as usize][cell_i].reserved_for() != self.lanes[target_lane_index][cell_i].reserved_for();
+            let mut distance = if left_index < 0 || separated {
                 // no lane to left to check
-                self.cells_to_next_obstacles[target_lane_index]
+                self.cells_to_anticipated_obstacle(car.vehicle_class(), target_lane_index)
             } else {
                 // check lane to left of target
                 cmp::min(
                     // distance to get alongside car in left lane from target
-                    cmp::min(self.cells_to_next_cars[left_index as usize], 254) + 1,
-                    self.cells_to_next_obstacles[target_lane_index]
+                    self.cells_to_next_cars[left_index as usize].saturating_add(1),
+                    self.cells_to_anticipated_obstacle(car.vehicle_class(), target_lane_index)
                 )
             };
 
             // Required because lanes that have already incremented their distance
             // counters are one cell closer when switching lanes.
-            if target_lane_offset < 0 && distance > 0 {
+            if target_lane_offset != 0 && self.lanes_processed[target_lane_index] && distance > 0 {
                 distance -= 1 
             }
             distance
@@ -394,17 +2561,23 @@ impl Road {
 
         let front_space = cmp::min(driveable_without_passing_on_right(0), available_speed);
         let mut best_option = LaneSwitch::Stay(front_space);
+        let mut best_space = if mandatory { 0 } else { front_space };
 
-        if !stay && (front_space >= 1 || available_speed <= 1) {
+        if !stay && (mandatory || front_space >= 1 || available_speed <= 1) {
             if left_clear {
                 let left_space = cmp::min(driveable_without_passing_on_right(-1), available_speed);
-                if left_space > 0 && left_space > best_option.driveable() {
+                if left_space > 0 && left_space > best_space {
                     best_option = LaneSwitch::Left(left_space);
+                    best_space = left_space;
                 }
             }
             if right_clear {
                 let right_space = driveable_without_passing_on_right(1);
-                if right_space > 0 && right_space >= best_option.driveable() {
+                let right_better = match self.lane_rules {
+                    LaneRules::KeepRight if !mandatory => right_space >= available_speed,
+                    _ => right_space >= best_space,
+                };
+                if right_space > 0 && right_better {
                     best_option = LaneSwitch::Right(cmp::min(right_space, available_speed));
                 }
             }
@@ -413,41 +2586,3 @@ impl Road {
         best_option
     }
 }
-
-impl fmt::Display for Road {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut road = String::with_capacity((self.lanes.len() + 1) * (self.length + 3) as usize);
-        let colored_digits: Vec<String> = (0..10).map(|n| format!("{}",
-            n.to_string().truecolor(
-                ((1.0 - n as f32 / 10.0) * 255.0).floor() as u8,
-                ((1.0 - n as f32 / 10.0 / 2.5) * 255.0).floor() as u8,
-                255
-            )
-        )).collect();
-        road += "  ";
-        for row in 0..self.length {
-            road += &colored_digits[(row % 10) as usize];
-        }
-        road += "\n";
-        for (index, lane) in self.cells().iter().enumerate() {
-            road += &(colored_digits[index % 10].clone() + " ");
-            for cell in lane {
-                if let Some(car) = cell.car() {
-                    let [r, g, b] = car.speed_rgb();
-                    road += &format!("{}", car.speed().to_string().truecolor(r, g, b));
-                } else if cell.blocked() {
-                    road += "x";
-                } else if cell.is_red_light(self.traffic_lights_red()) {
-                    road += "#";
-                } else {
-                    road += "_";
-                }
-            }
-            if index + 1 < self.n_lanes as usize {
-                road += "\n";
-            }
-        }
-        write!(f, "{}", road)
-    }
-}
-
@@ -0,0 +1,53 @@
+use crate::cell::CellLocation;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// Records the mean speed and the density of the cars and the flow at every monitored cell after
+/// every round. The flow is not averaged over several rounds, so it is a multiple of 60 cars per
+/// minute. The mean speed of a round without cars is `NaN`.
+#[derive(Debug)]
+pub struct RoundSeries {
+    monitors: Vec<CellLocation>,
+    cars_passed: Vec<i32>,
+    pub mean_speeds_kilometers_per_hour: Vec<f64>,
+    pub densities_cars_per_km_per_lane: Vec<f64>,
+    pub monitor_flows_cars_per_minute: Vec<Vec<f64>>,
+}
+
+impl RoundSeries {
+    /// Starts the series from the current state of the road, which is only needed to count what
+    /// happens in the next round.
+    pub fn new(road: &Road, monitors: &[CellLocation]) -> Self {
+        let mut series = Self {
+            monitors: monitors.to_vec(),
+            cars_passed: Vec::new(),
+            mean_speeds_kilometers_per_hour: Vec::new(),
+            densities_cars_per_km_per_lane: Vec::new(),
+            monitor_flows_cars_per_minute: Vec::new(),
+        };
+        series.cars_passed = series.cars_passed(road);
+        series
+    }
+
+    fn cars_passed(&self, road: &Road) -> Vec<i32> {
+        self.monitors.iter().map(|monitor| road.cells()[monitor.lane()][monitor.index()].cars_passed()).collect()
+    }
+
+    /// Records the metrics of a round.
+    pub fn take_snapshot(&mut self, road: &Road) {
+        let (speed_sum, cars) = road.iter_cars().fold((0u64, 0u64), |(speed_sum, cars), (_, _, _, car)| (speed_sum + car.speed() as u64, cars + 1));
+        let road_km = road.lanes() as f64 * road.length() as f64 * CELL_M / 1000.0;
+        self.mean_speeds_kilometers_per_hour.push(speed_sum as f64 / cars as f64 * (CELL_M / ROUND_S) * 3.6);
+        self.densities_cars_per_km_per_lane.push(cars as f64 / road_km);
+        let cars_passed = self.cars_passed(road);
+        self.monitor_flows_cars_per_minute.push(
+            cars_passed.iter().zip(&self.cars_passed).map(|(passed, passed_before)| (passed - passed_before) as f64 / ROUND_S * 60.0).collect()
+        );
+        self.cars_passed = cars_passed;
+    }
+
+    /// Returns the bytes taken up by the recorded series.
+    pub fn buffer_bytes(&self) -> usize {
+        (self.mean_speeds_kilometers_per_hour.len() + self.densities_cars_per_km_per_lane.len()) * size_of::<f64>()
+            + self.monitor_flows_cars_per_minute.iter().map(Vec::len).sum::<usize>() * size_of::<f64>()
+    }
+}
@@ -0,0 +1,89 @@
+use serde::Serialize;
+use crate::{Road, ROUND_S};
+
+/// Records surrogate safety metrics after every round. The time-to-collision (TTC) of a car that
+/// is faster than the car ahead of it in its lane is the time until it would run into that car if
+/// both kept their speeds: the free cells between them divided by the difference of their speeds.
+/// A hard braking is a round in which a car slows down by at least `hard_braking` cells per round.
+#[derive(Debug)]
+pub struct SafetyMonitor {
+    ttc_threshold_rounds: f64,
+    hard_braking: u8,
+    car_rounds: u64,
+    critical_rounds: u64,
+    min_ttc_rounds: f64,
+    hard_brakings: u64,
+}
+
+/// The surrogate safety metrics of a run. The time exposed is the time that cars spent with a TTC
+/// below the threshold, summed up over the cars. Without any car approaching another, the minimum
+/// TTC is `NaN`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SafetyMetrics {
+    pub ttc_threshold_s: f64,
+    pub time_exposed_ttc_s: f64,
+    pub time_exposed_ttc_s_per_car_per_round: f64,
+    pub min_ttc_s: f64,
+    pub hard_braking_cells_per_round: u8,
+    pub hard_brakings: u64,
+    pub hard_brakings_n_per_car_per_round: f64,
+}
+
+impl SafetyMonitor {
+    pub fn new(ttc_threshold_s: f64, hard_braking: u8) -> Self {
+        if hard_braking == 0 {
+            panic!("A hard braking must slow a car down by at least one cell per round.");
+        }
+        Self {
+            ttc_threshold_rounds: ttc_threshold_s / ROUND_S,
+            hard_braking,
+            car_rounds: 0,
+            critical_rounds: 0,
+            min_ttc_rounds: f64::NAN,
+            hard_brakings: 0,
+        }
+    }
+
+    /// Measures the TTC of every car and counts the hard brakings after a round.
+    pub fn take_snapshot(&mut self, road: &Road) {
+        let length = road.length() as usize;
+        for lane in road.cells() {
+            let cars: Vec<(usize, u8, u8)> = lane
+                .iter()
+                .enumerate()
+                .filter_map(|(cell_i, cell)| cell.car().map(|car| (cell_i, car.speed(), car.deceleration())))
+                .collect();
+            self.car_rounds += cars.len() as u64;
+            self.hard_brakings += cars.iter().filter(|(_, _, deceleration)| *deceleration >= self.hard_braking).count() as u64;
+            for (i, (cell_i, speed, _)) in cars.iter().enumerate() {
+                // On a closed road, the car ahead of the last one is the first one.
+                let leader = match cars.get(i + 1) {
+                    Some((leader_i, leader_speed, _)) => (*leader_i, *leader_speed),
+                    None if !road.open() && cars.len() > 1 => (cars[0].0 + length, cars[0].1),
+                    None => continue,
+                };
+                let (leader_i, leader_speed) = leader;
+                if speed <= &leader_speed {
+                    continue;
+                }
+                let ttc = (leader_i - cell_i - 1) as f64 / (speed - leader_speed) as f64;
+                self.min_ttc_rounds = self.min_ttc_rounds.min(ttc);
+                if ttc < self.ttc_threshold_rounds {
+                    self.critical_rounds += 1;
+                }
+            }
+        }
+    }
+
+    pub fn metrics(self) -> SafetyMetrics {
+        SafetyMetrics {
+            ttc_threshold_s: self.ttc_threshold_rounds * ROUND_S,
+            time_exposed_ttc_s: self.critical_rounds as f64 * ROUND_S,
+            time_exposed_ttc_s_per_car_per_round: self.critical_rounds as f64 * ROUND_S / self.car_rounds as f64,
+            min_ttc_s: self.min_ttc_rounds * ROUND_S,
+            hard_braking_cells_per_round: self.hard_braking,
+            hard_brakings: self.hard_brakings,
+            hard_brakings_n_per_car_per_round: self.hard_brakings as f64 / self.car_rounds as f64,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use serde::Serialize;
+use crate::car::{Car, CarId};
+use crate::ROUND_S;
+
+/// A measurement section that stretches from its start cell to its end cell in all lanes. Cars
+/// enter it when they reach the start cell and leave it when they reach the end cell, which is
+/// timed to a fraction of a round from how far into their move they got there. On a closed road,
+/// a section whose end comes before its start wraps around the end of the road.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    start: usize,
+    end: usize,
+    /// The times in rounds at which the cars in the section entered it.
+    entries: HashMap<CarId, f64>,
+    travel_times: Vec<f64>,
+    delays: Vec<f64>,
+}
+
+/// The travel times and delays of all cars that drove through a section. The delay of a car is
+/// the time that it took longer than driving through at its maximum speed. Without any cars, all
+/// times are `NaN`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SectionResult {
+    pub start: usize,
+    pub end: usize,
+    pub cars: usize,
+    pub mean_travel_time_s: f64,
+    pub median_travel_time_s: f64,
+    pub percentile_95_travel_time_s: f64,
+    pub mean_delay_s: f64,
+    pub median_delay_s: f64,
+    pub percentile_95_delay_s: f64,
+}
+
+impl Section {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end, entries: HashMap::new(), travel_times: Vec::new(), delays: Vec::new() }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the travel times in rounds of the cars that drove through the section.
+    pub fn travel_times(&self) -> &Vec<f64> {
+        &self.travel_times
+    }
+
+    /// Records that the car passed the `cells` cells after `cell_i` in the round, wrapping around
+    /// the end of the road of the length. The times are interpolated by the speed of the car.
+    pub fn record_move(&mut self, car: &Car, cell_i: usize, cells: usize, round: u32, length: usize) {
+        if cells == 0 {
+            return;
+        }
+        let speed = car.speed() as f64;
+        // The number of cells the car has to drive from `cell_i` to reach the cell.
+        let cells_to = |cell: usize| (cell + length - cell_i - 1) % length + 1;
+        let (to_start, to_end) = (cells_to(self.start), cells_to(self.end));
+        let time_at = |cells: usize| (round - 1) as f64 + cells as f64 / speed;
+        if to_end <= cells && to_end < to_start {
+            self.exit(car, time_at(to_end), length);
+        }
+        if to_start <= cells {
+            self.entries.insert(car.id(), time_at(to_start));
+        }
+        if to_end <= cells && to_end > to_start {
+            self.exit(car, time_at(to_end), length);
+        }
+    }
+
+    fn exit(&mut self, car: &Car, time: f64, length: usize) {
+        let Some(entry) = self.entries.remove(&car.id()) else { return; };
+        let cells = (self.end + length - self.start) % length;
+        let travel_time = time - entry;
+        self.travel_times.push(travel_time);
+        self.delays.push(travel_time - cells as f64 / car.max_speed() as f64);
+    }
+
+    /// Forgets a car that left the road inside the section.
+    pub fn forget(&mut self, id: CarId) {
+        self.entries.remove(&id);
+    }
+
+    /// Forgets the travel times and delays recorded so far.
+    pub fn reset_records(&mut self) {
+        self.travel_times.clear();
+        self.delays.clear();
+    }
+
+    pub fn result(&self) -> SectionResult {
+        let (mean_travel_time_s, median_travel_time_s, percentile_95_travel_time_s) = statistics(&self.travel_times);
+        let (mean_delay_s, median_delay_s, percentile_95_delay_s) = statistics(&self.delays);
+        SectionResult {
+            start: self.start,
+            end: self.end,
+            cars: self.travel_times.len(),
+            mean_travel_time_s,
+            median_travel_time_s,
+            percentile_95_travel_time_s,
+            mean_delay_s,
+            median_delay_s,
+            percentile_95_delay_s,
+        }
+    }
+}
+
+/// Returns the mean, the median and the 95th percentile of the durations in rounds in seconds.
+fn statistics(rounds: &[f64]) -> (f64, f64, f64) {
+    let mut times: Vec<f64> = rounds.iter().map(|rounds| rounds * ROUND_S).collect();
+    times.sort_by(f64::total_cmp);
+    let percentile = |p: f64| {
+        if times.is_empty() {
+            f64::NAN
+        } else {
+            times[((times.len() - 1) as f64 * p).round() as usize]
+        }
+    };
+    (times.iter().sum::<f64>() / times.len() as f64, percentile(0.5), percentile(0.95))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseSectionError;
+
+impl FromStr for Section {
+    type Err = ParseSectionError;
+
+    /// Parses a section in the format `(start_cell_index, end_cell_index)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let (start, end) = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|s| s.split_once(','))
+            .ok_or(ParseSectionError)?;
+        let start = start.parse::<usize>().map_err(|_| ParseSectionError)?;
+        let end = end.parse::<usize>().map_err(|_| ParseSectionError)?;
+        if start == end {
+            return Err(ParseSectionError);
+        }
+        Ok(Self::new(start, end))
+    }
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use serde::Serialize;
+use crate::{run_sim, SimulationConfig};
+
+/// The outcome of running the scenario again and comparing its state hashes to those of the first
+/// run, round by round.
+#[derive(Serialize, Debug)]
+pub struct SelfTestCheck {
+    pub run: String,
+    pub threads: usize,
+    pub compared_rounds: u32,
+    pub first_diverging_round: Option<u32>,
+    pub passed: bool,
+}
+
+/// The outcome of the determinism self-check. It passes if every run reproduced the state hashes
+/// of the first run for all of its rounds.
+#[derive(Serialize, Debug)]
+pub struct SelfTestReport {
+    pub seed: u64,
+    pub rounds: u32,
+    pub checks: Vec<SelfTestCheck>,
+    pub passed: bool,
+}
+
+/// Runs the seeded scenario three times: once to dump the state hashes of every round, once more
+/// to compare them on the same thread pool and once to compare them on a thread pool with another
+/// number of threads. Without a seed, a random one is drawn and reported. Outputs are not written.
+pub fn run(args: &SimulationConfig) -> SelfTestReport {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let hashes_path = std::env::temp_dir().join(format!("traffic-selftest-{}.hashes", std::process::id()));
+    let args = SimulationConfig { seed: Some(seed), ..args.clone().without_outputs() };
+    run_sim(SimulationConfig { dump_state_hashes: Some(hashes_path.clone()), ..args.clone() });
+    let rounds = fs::read_to_string(&hashes_path)
+        .expect("Unable to read the state hashes of the first run.")
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count() as u32;
+
+    let compare = |run: &str, threads: usize| {
+        let result = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("Unable to create a thread pool.")
+            .install(|| run_sim(SimulationConfig { compare_state_hashes: Some(hashes_path.clone()), ..args.clone() }));
+        let comparison = result.state_hashes.expect("The state hashes were not compared.");
+        SelfTestCheck {
+            run: run.to_string(),
+            threads,
+            compared_rounds: comparison.compared_rounds,
+            first_diverging_round: comparison.first_diverging_round,
+            passed: comparison.first_diverging_round.is_none() && comparison.compared_rounds == rounds,
+        }
+    };
+    let threads = rayon::current_num_threads();
+    let checks = vec![
+        compare("repeated", threads),
+        compare("other thread count", if threads == 1 { 2 } else { 1 }),
+    ];
+    let _ = fs::remove_file(&hashes_path);
+
+    SelfTestReport {
+        seed,
+        rounds,
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    }
+}
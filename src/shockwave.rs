@@ -0,0 +1,166 @@
+use serde::Serialize;
+use crate::cell::Cell;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// The furthest a jam front may move upstream (negative) or downstream (positive) in one round
+/// and still be the same front.
+const MAX_FRONT_STEP_UPSTREAM: isize = -3;
+const MAX_FRONT_STEP_DOWNSTREAM: isize = 1;
+/// The minimum number of rounds a jam front must be tracked for its speed to be estimated.
+const MIN_FRONT_ROUNDS: usize = 10;
+
+/// Records where jams end in every round (the space-time data) and estimates the speed with which
+/// jams propagate from it. Only the heads of jams are kept, so that the data of long roads, which
+/// are mostly free, stays small.
+#[derive(Debug)]
+pub struct ShockwaveEstimator {
+    open: bool,
+    length: usize,
+    /// The cells of the jam heads by round and lane.
+    jam_heads: Vec<Vec<Vec<u32>>>,
+}
+
+/// The estimated propagation speeds of the downstream fronts of jams. Negative speeds mean that
+/// the jams move upstream. Without any jam fronts, all speeds are `NaN`.
+#[derive(Serialize, Debug)]
+pub struct ShockwaveEstimate {
+    pub jam_fronts: usize,
+    pub average_speed_kilometers_per_hour: f64,
+    pub min_speed_kilometers_per_hour: f64,
+    pub max_speed_kilometers_per_hour: f64,
+}
+
+/// A jam front that is followed from round to round. Positions are unwrapped, so that they keep
+/// counting past the end of a closed road.
+#[derive(Debug)]
+struct JamFront {
+    lane: usize,
+    positions: Vec<(usize, isize)>,
+}
+
+impl JamFront {
+    fn last_position(&self) -> isize {
+        self.positions.last().unwrap().1
+    }
+
+    /// Fits a line through the positions over time and returns its slope in cells per round.
+    fn speed(&self) -> f64 {
+        let n = self.positions.len() as f64;
+        let mean_t = self.positions.iter().map(|(t, _)| *t as f64).sum::<f64>() / n;
+        let mean_x = self.positions.iter().map(|(_, x)| *x as f64).sum::<f64>() / n;
+        let mut covariance = 0.0;
+        let mut variance = 0.0;
+        for (t, x) in &self.positions {
+            covariance += (*t as f64 - mean_t) * (*x as f64 - mean_x);
+            variance += (*t as f64 - mean_t).powi(2);
+        }
+        covariance / variance
+    }
+}
+
+impl ShockwaveEstimator {
+    pub fn new(road: &Road) -> Self {
+        Self {
+            open: road.open(),
+            length: road.length() as usize,
+            jam_heads: Vec::new(),
+        }
+    }
+
+    pub fn take_snapshot(&mut self, road: &Road) {
+        let jam_heads = road.cells().iter().map(|lane| self.find_jam_heads(lane)).collect();
+        self.jam_heads.push(jam_heads);
+    }
+
+    /// Returns the number of bytes of the recorded space-time data.
+    pub fn buffer_bytes(&self) -> usize {
+        self.jam_heads.iter().flatten().map(Vec::len).sum::<usize>() * size_of::<u32>()
+    }
+
+    /// Returns the cells in which a jam ends, meaning that a stopped car is not followed by another
+    /// stopped car.
+    fn find_jam_heads(&self, lane: &[Cell]) -> Vec<u32> {
+        let stopped = |cell_i: usize| lane[cell_i].car().is_some_and(|car| car.speed() == 0);
+        (0..self.length)
+            .filter(|cell_i| {
+                let next_stopped = if cell_i + 1 < self.length {
+                    stopped(cell_i + 1)
+                } else {
+                    !self.open && stopped(0)
+                };
+                stopped(*cell_i) && !next_stopped
+            })
+            .map(|cell_i| cell_i as u32)
+            .collect()
+    }
+
+    /// Follows the heads of all jams through the recorded rounds. A head is continued by the
+    /// closest head in the next round that is within the allowed step. Heads that cannot be
+    /// continued end their front and new heads start a new one.
+    fn track_jam_fronts(&self) -> Vec<JamFront> {
+        let mut finished: Vec<JamFront> = Vec::new();
+        let mut active: Vec<JamFront> = Vec::new();
+        for (round, lanes) in self.jam_heads.iter().enumerate() {
+            let mut continued: Vec<JamFront> = Vec::new();
+            for (lane_i, lane_heads) in lanes.iter().enumerate() {
+                let mut heads: Vec<usize> = lane_heads.iter().map(|head| *head as usize).collect();
+                for front in active.iter_mut().filter(|front| front.lane == lane_i) {
+                    let last = front.last_position();
+                    let closest = heads
+                        .iter()
+                        .enumerate()
+                        .map(|(i, head)| (i, self.step(last, *head)))
+                        .filter(|(_, step)| (MAX_FRONT_STEP_UPSTREAM..=MAX_FRONT_STEP_DOWNSTREAM).contains(step))
+                        .min_by_key(|(_, step)| step.abs());
+                    if let Some((i, step)) = closest {
+                        heads.remove(i);
+                        front.positions.push((round, last + step));
+                    }
+                }
+                for head in heads {
+                    continued.push(JamFront { lane: lane_i, positions: vec![(round, head as isize)] });
+                }
+            }
+            for front in active.drain(..) {
+                if front.positions.last().unwrap().0 == round {
+                    continued.push(front);
+                } else {
+                    finished.push(front);
+                }
+            }
+            active = continued;
+        }
+        finished.append(&mut active);
+        finished.retain(|front| front.positions.len() >= MIN_FRONT_ROUNDS);
+        finished
+    }
+
+    /// Returns how far a front moved from its unwrapped position to a cell. On a closed road, the
+    /// shortest way around the ring is taken.
+    fn step(&self, from: isize, to: usize) -> isize {
+        let length = self.length as isize;
+        let step = to as isize - from.rem_euclid(length);
+        if self.open {
+            step
+        } else if step > length / 2 {
+            step - length
+        } else if step < -length / 2 {
+            step + length
+        } else {
+            step
+        }
+    }
+
+    pub fn estimate(&self) -> ShockwaveEstimate {
+        let speeds: Vec<f64> = self.track_jam_fronts()
+            .iter()
+            .map(|front| front.speed() * (CELL_M / ROUND_S) * 3.6)
+            .collect();
+        ShockwaveEstimate {
+            jam_fronts: speeds.len(),
+            average_speed_kilometers_per_hour: speeds.iter().sum::<f64>() / speeds.len() as f64,
+            min_speed_kilometers_per_hour: speeds.iter().copied().reduce(f64::min).unwrap_or(f64::NAN),
+            max_speed_kilometers_per_hour: speeds.iter().copied().reduce(f64::max).unwrap_or(f64::NAN),
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+use crate::car::{CarRecords, VehicleClass};
+use crate::observer::Observer;
+use crate::terminal::{AsciiWriter, Camera, VerbosePrinter};
+#[cfg(feature = "terminal")]
+use crate::terminal::Animator;
+#[cfg(feature = "image")]
+use crate::image_drawer::{AnimationDrawer, FrameDrawer, HeatmapDrawer, ImageDrawer};
+#[cfg(feature = "image")]
+use crate::ImageMode;
+use crate::matsim::MatsimEventsWriter;
+use crate::trip::TripWriter;
+use crate::timeseries::TimeSeriesWriter;
+use crate::event_log::EventLogWriter;
+use crate::correlation::DetectorCorrelator;
+use crate::state_hash::StateHasher;
+use crate::shockwave::ShockwaveEstimator;
+use crate::spillback::SpillbackDetector;
+use crate::breakdown::BreakdownDetector;
+use crate::steady_state::SteadyStateDetector;
+use crate::queue::QueueDetector;
+use crate::section::Section;
+use crate::safety::SafetyMonitor;
+use crate::speed_histogram::SpeedHistogram;
+use crate::round_series::RoundSeries;
+#[cfg(feature = "arrow")]
+use crate::arrow_stream::ArrowStreamWriter;
+#[cfg(feature = "plot")]
+use crate::chart::TimeSeriesCharts;
+use crate::{report, LaneChangeStatistics, LapTimeDistribution, MemoryReport, Road, SimulationConfig, SimulationResult, SpawnMode, VehicleTypeResult, CELL_M, ROUND_S};
+
+/// Creates the observers for all outputs requested by the arguments.
+pub(crate) fn create_observers(args: &SimulationConfig, road: &Road) -> Vec<Box<dyn Observer>> {
+    let mut observers: Vec<Box<dyn Observer>> = Vec::new();
+    let mut camera = Camera::new(args.view());
+    if let Some(follow) = args.follow() {
+        camera = camera.with_follow(follow);
+    }
+    if args.debug_gaps {
+        camera = camera.with_gap_overlay();
+    }
+    if args.animate {
+        #[cfg(feature = "terminal")]
+        observers.push(Box::new(Animator::new(camera).with_frame_delay(std::time::Duration::from_millis(args.frame_delay_ms))));
+    } else if args.verbose {
+        observers.push(Box::new(VerbosePrinter::new(camera)));
+    }
+    #[cfg(feature = "image")]
+    if args.image {
+        match args.image_mode {
+            ImageMode::SpaceTime => {
+                let mut image_drawer = ImageDrawer::new(road, args.rounds + 1, args.image_light_strip, args.out_path.clone()).with_pixel_scale(args.pixel_scale);
+                if args.image_axes {
+                    image_drawer = image_drawer.with_axes(road);
+                }
+                observers.push(Box::new(image_drawer));
+            },
+            mode => observers.push(Box::new(
+                HeatmapDrawer::new(road, mode, args.out_path.clone()).with_pixel_scale(args.pixel_scale)
+            )),
+        }
+    }
+    #[cfg(feature = "image")]
+    if let Some(dir) = &args.frames_dir {
+        let frame_drawer = FrameDrawer::new(dir.clone()).expect("Unable to create frames directory.");
+        observers.push(Box::new(frame_drawer.with_pixel_scale(args.pixel_scale)));
+    }
+    #[cfg(feature = "image")]
+    if let Some(path) = &args.gif {
+        let animation_drawer = AnimationDrawer::new(path, args.frame_delay_ms).expect("Unable to create animated GIF file.");
+        observers.push(Box::new(animation_drawer.with_pixel_scale(args.pixel_scale)));
+    }
+    #[cfg(feature = "plot")]
+    if args.charts {
+        observers.push(Box::new(TimeSeriesCharts::new(args.chart_path("speed"), args.chart_path("flow"), &args.monitor())));
+    }
+    if let Some(path) = &args.ascii_out {
+        let ascii_writer = AsciiWriter::new(path).expect("Unable to create ASCII output file.");
+        observers.push(Box::new(ascii_writer));
+    }
+    if let Some(path) = &args.matsim_events {
+        let matsim_events = MatsimEventsWriter::new(road, args.matsim_link_length, path.clone())
+            .expect("Unable to create MATSim events file.");
+        observers.push(Box::new(matsim_events));
+    }
+    if let Some(path) = &args.trips {
+        observers.push(Box::new(TripWriter::new(path).expect("Unable to create trips file.")));
+    }
+    if let Some(path) = &args.timeseries {
+        observers.push(Box::new(TimeSeriesWriter::new(path, &args.monitor()).expect("Unable to create time series file.")));
+    }
+    if let Some(path) = &args.event_log {
+        observers.push(Box::new(EventLogWriter::new(path, road).expect("Unable to create event log file.")));
+    }
+    #[cfg(feature = "arrow")]
+    if let Some(target) = &args.arrow_stream {
+        let arrow_stream = ArrowStreamWriter::new(target).expect("Unable to open Arrow stream.");
+        observers.push(Box::new(arrow_stream));
+    }
+    observers
+}
+
+pub fn run_sim(args: SimulationConfig) -> SimulationResult {
+    // Parse data here so that the program fails immediately if anything is wrong.
+    let args_vehicles = args.vehicles();
+    let args_monitors = args.monitor();
+    let args_block = args.block();
+    let args_speed_limits = args.speed_limit();
+    let args_reserved_zones = args.reserve();
+    let args_traffic_lights = args.traffic_lights();
+    let args_green_waves = args.green_waves();
+    let args_layout = args.layout();
+    let args_demand = args.demand();
+    if args.arrow_stream.as_deref() == Some("-") && (args.verbose || args.animate) {
+        panic!("Cannot print the road while streaming Arrow record batches to stdout.");
+    }
+    #[cfg(not(feature = "terminal"))]
+    if args.animate {
+        panic!("Animating requires the simulator to be built with the `terminal` feature.");
+    }
+    #[cfg(not(feature = "image"))]
+    if args.image {
+        panic!("Creating images requires the simulator to be built with the `image` feature.");
+    }
+    #[cfg(not(feature = "arrow"))]
+    if args.arrow_stream.is_some() {
+        panic!("Streaming Arrow record batches requires the simulator to be built with the `arrow` feature.");
+    }
+
+    // setup
+    let start = Instant::now();
+    let mut road = match args_layout {
+        Some(layout) => Road::from_layout(
+            &layout,
+            &args_vehicles,
+            args.dilly_dally_probability,
+            args.stay_in_lane_probability,
+            args.seed,
+        ).expect("Failed to parse road layout."),
+        None => Road::new(
+            args.lanes,
+            args.length,
+            &args_vehicles,
+            args.dilly_dally_probability,
+            args.stay_in_lane_probability,
+            &args_block,
+            &args_traffic_lights,
+            args.seed,
+        ),
+    };
+    for monitor in &args_monitors {
+        if monitor.lane() >= road.lanes() as usize || monitor.index() >= road.length() as usize {
+            panic!("The monitored cell `{}` is not on the road, which has {} lanes of {} cells.", monitor, road.lanes(), road.length());
+        }
+    }
+    road = road.with_density_monitors(&args_monitors, args.density_window).with_sections(args.sections());
+    let args_cars = args.cars();
+    let args_lane_densities = args.lane_densities();
+    if !args_cars.is_empty() && !args_lane_densities.is_empty() {
+        panic!("Car counts and lane densities cannot be used together.");
+    }
+    if !args_lane_densities.is_empty() {
+        road = road.with_lane_densities(&args_lane_densities);
+    } else if let [cars] = args_cars[..] {
+        road = road.with_total_cars(cars);
+    } else if !args_cars.is_empty() {
+        road = road.with_car_counts(&args_cars);
+    } else if args.spawn_mode == SpawnMode::Exact {
+        road = road.with_exact_spawning();
+    }
+    if !args_reserved_zones.is_empty() {
+        road = road.with_reserved_zones(&args_reserved_zones);
+    }
+    if let Some(demand) = args_demand {
+        road = road
+            .with_demand(demand)
+            .with_arrival_process(args.arrival_process, args.arrival_batch_size);
+    }
+    if let Some(density_schedule) = args.target_density() {
+        road = road.with_density_schedule(density_schedule);
+    }
+    if let Some(parameter_profile) = args.parameter_profile() {
+        road = road.with_parameter_profile(parameter_profile);
+    }
+    if let Some(driver_distributions) = args.driver_distributions() {
+        road = road.with_driver_distributions(driver_distributions);
+    }
+    if let Some(weather_schedule) = args.weather_schedule() {
+        road = road.with_weather_schedule(weather_schedule);
+    }
+    road = road
+        .with_speed_limits(&args_speed_limits)
+        .with_congestion_threshold(args.congestion_threshold)
+        .with_near_miss_braking(args.near_miss_braking)
+        .with_courtesy_probability(args.courtesy_probability)
+        .with_update_order(args.update_order)
+        .with_driving_model(args.model)
+        .with_lane_rules(args.lane_rules);
+    if args.turn_signals {
+        road = road.with_turn_signals();
+    }
+    if args.mandatory_lane_changes {
+        road = road.with_mandatory_lane_changes();
+    }
+    if let Some(max_deceleration) = args.max_deceleration {
+        road = road
+            .with_max_deceleration(max_deceleration, args.braking_overrun, args.emergency_braking_factor)
+            .with_collision_rounds(args.collision_rounds);
+    }
+    if let Some(look_ahead) = args.blockage_look_ahead {
+        road = road.with_blockage_look_ahead(look_ahead);
+    }
+    for green_wave in &args_green_waves {
+        road = road.with_green_wave(green_wave);
+    }
+    if let Some(preemption_distance) = args.preemption_distance {
+        road = road.with_preemption_distance(preemption_distance);
+    }
+    if let Some(yield_distance) = args.emergency_yield_distance {
+        road = road.with_emergency_yielding(yield_distance);
+    }
+    if args.emergency_runs_red_lights {
+        road = road.with_emergency_red_light_running();
+    }
+    if args.light_failure_probability > 0.0 {
+        road = road.with_light_failures(args.light_failure_probability, args.light_failure_rounds);
+    }
+    if args.incident_probability > 0.0 {
+        road = road.with_incidents(args.incident_probability, args.incident_rounds);
+    }
+    if args.rubbernecking_speed.is_some() || args.rubbernecking_dilly_dally_probability.is_some() {
+        road = road.with_rubbernecking(args.rubbernecking_speed, args.rubbernecking_dilly_dally_probability);
+    }
+    if args.debug_gaps {
+        road = road.with_gap_recording();
+    }
+    if args.trips.is_some() {
+        road = road.with_trip_recording();
+    }
+    let initial_cars_per_lane_by_vehicle_type = road.cars_per_lane_by_vehicle_type();
+
+    // warm up
+    for _ in 0..args.warmup {
+        road.round();
+    }
+    road.reset_records();
+    let mut congested_fraction_per_round = args.congestion_series.then(Vec::new);
+    let mut cars_per_lane_per_round = args.cars_per_lane_series.then(Vec::new);
+    let mut round_series = args.detailed.then(|| RoundSeries::new(&road, &args_monitors));
+
+    // setup outputs
+    let mut observers = create_observers(&args, &road);
+    for observer in observers.iter_mut() {
+        observer.start(&road).expect("Unable to write simulation output.");
+    }
+    let mut shockwave_estimator = args.shockwaves.then(|| ShockwaveEstimator::new(&road));
+    if let Some(estimator) = &mut shockwave_estimator {
+        estimator.take_snapshot(&road);
+    }
+    let mut detector_correlator = args.detector_correlation.map(|bin_rounds| DetectorCorrelator::new(&road, &args_monitors, bin_rounds));
+    if let Some(correlator) = &mut detector_correlator {
+        correlator.take_snapshot(&road);
+    }
+    let mut state_hasher = (args.dump_state_hashes.is_some() || args.compare_state_hashes.is_some()).then(|| {
+        StateHasher::new(args.dump_state_hashes.as_ref(), args.compare_state_hashes.as_ref())
+            .expect("Unable to open state hash files.")
+    });
+    if let Some(hasher) = &mut state_hasher {
+        hasher.take_snapshot(&road).expect("Unable to write state hashes.");
+    }
+
+    let spillback_points = args.spillback_points();
+    let mut spillback_detector = (!spillback_points.is_empty()).then(|| SpillbackDetector::new(spillback_points));
+    let mut breakdown_detector = args.breakdown_jam_cars.map(|jam_cars| BreakdownDetector::new(jam_cars, args.breakdown_rounds));
+    let mut steady_state_detector = args.steady_state_window.map(|window| SteadyStateDetector::new(window, args.steady_state_tolerance));
+    let mut queue_detector = args.jam_rounds.map(QueueDetector::new);
+    let mut safety_monitor = args.ttc_threshold.map(|ttc_threshold| SafetyMonitor::new(ttc_threshold as f64, args.hard_braking));
+    let mut speed_histogram = args.speed_histogram.then(SpeedHistogram::new);
+
+    // run simulator
+    for _ in args.warmup..args.rounds {
+        road.round();
+        if let Some(detector) = &mut spillback_detector {
+            detector.take_snapshot(&road);
+        }
+        if let Some(detector) = &mut breakdown_detector {
+            detector.take_snapshot(&road);
+        }
+        if let Some(detector) = &mut queue_detector {
+            detector.take_snapshot(&road);
+        }
+        if let Some(monitor) = &mut safety_monitor {
+            monitor.take_snapshot(&road);
+        }
+        if let Some(histogram) = &mut speed_histogram {
+            histogram.take_snapshot(&road);
+        }
+        if let Some(estimator) = &mut shockwave_estimator {
+            estimator.take_snapshot(&road);
+        }
+        if let Some(correlator) = &mut detector_correlator {
+            correlator.take_snapshot(&road);
+        }
+        if let Some(hasher) = &mut state_hasher {
+            hasher.take_snapshot(&road).expect("Unable to write state hashes.");
+        }
+        if let Some(series) = &mut congested_fraction_per_round {
+            series.push(road.congested_fraction());
+        }
+        if let Some(series) = &mut cars_per_lane_per_round {
+            series.push(road.cars_per_lane());
+        }
+        if let Some(series) = &mut round_series {
+            series.take_snapshot(&road);
+        }
+        for observer in observers.iter_mut() {
+            observer.observe(&road).expect("Unable to write simulation output.");
+        }
+        if args.flush_every.is_some_and(|every| road.rounds() % every == 0) {
+            for observer in observers.iter_mut() {
+                observer.flush().expect("Unable to write simulation output.");
+            }
+            if let Some(hasher) = &mut state_hasher {
+                hasher.flush().expect("Unable to write state hashes.");
+            }
+            collect_result(&args, &road, start, &initial_cars_per_lane_by_vehicle_type)
+                .save(&args.partial_result_path())
+                .expect("Unable to write partial result.");
+        }
+        if let Some(detector) = &mut steady_state_detector {
+            if detector.take_snapshot(&road) {
+                break;
+            }
+        }
+    }
+    let memory = args.memory_report.then(|| {
+        MemoryReport::new(
+            road.cell_grid_bytes(),
+            observers.iter().map(|observer| observer.buffer_bytes()).sum(),
+            shockwave_estimator.as_ref().map_or(0, ShockwaveEstimator::buffer_bytes)
+                + detector_correlator.as_ref().map_or(0, DetectorCorrelator::buffer_bytes)
+                + congested_fraction_per_round.as_ref().map_or(0, |series| series.len() * size_of::<f64>())
+                + cars_per_lane_per_round.as_ref().map_or(0, |series| series.iter().map(Vec::len).sum::<usize>() * size_of::<u32>())
+                + round_series.as_ref().map_or(0, RoundSeries::buffer_bytes),
+        )
+    });
+    // clean-up
+    for observer in observers.iter_mut() {
+        observer.finish(&road).expect("Unable to write simulation output.");
+    }
+
+    let state_hashes = state_hasher.and_then(|hasher| hasher.finish().expect("Unable to write state hashes."));
+
+    let (average_speed_kilometers_per_hour_per_round, density_cars_per_km_per_lane_per_round, monitor_cells_flow_cars_per_minute_per_round) = match round_series {
+        Some(series) => (Some(series.mean_speeds_kilometers_per_hour), Some(series.densities_cars_per_km_per_lane), Some(series.monitor_flows_cars_per_minute)),
+        None => (None, None, None),
+    };
+    let result = SimulationResult {
+        cars_per_lane_per_round,
+        congested_fraction_per_round,
+        average_speed_kilometers_per_hour_per_round,
+        density_cars_per_km_per_lane_per_round,
+        monitor_cells_flow_cars_per_minute_per_round,
+        shockwaves: shockwave_estimator.map(|estimator| estimator.estimate()),
+        detector_correlations: detector_correlator.map(|correlator| correlator.correlate()),
+        state_hashes,
+        spillbacks: spillback_detector.map(|detector| detector.spillbacks()),
+        breakdown: breakdown_detector.map(|detector| detector.breakdown()),
+        steady_state: steady_state_detector.map(|detector| detector.steady_state()),
+        queues: queue_detector.map(|detector| detector.statistics()),
+        safety: safety_monitor.map(|monitor| monitor.metrics()),
+        speed_histogram: speed_histogram.map(SpeedHistogram::bins),
+        memory,
+        ..collect_result(&args, &road, start, &initial_cars_per_lane_by_vehicle_type)
+    };
+    if let Some(path) = &args.report {
+        report::write(path, &args, &result).expect("Unable to write report.");
+    }
+    result
+}
+
+/// Collects the settings and the metrics of the road after the rounds run so far. The series and
+/// the analyses that run alongside the simulation are left out.
+fn collect_result(args: &SimulationConfig, road: &Road, start: Instant, initial_cars_per_lane_by_vehicle_type: &[Vec<u32>]) -> SimulationResult {
+    let monitors = args.monitor();
+    let flows_cars_per_minute = monitors
+        .iter()
+        .map(|cl| road.cells()[cl.lane()][cl.index()].flow(road.measured_rounds()) / ROUND_S * 60.0)
+        .collect();
+    let occupancies = monitors
+        .iter()
+        .map(|cl| road.cells()[cl.lane()][cl.index()].occupancy(road.rounds(), road.measured_rounds()))
+        .collect();
+
+    let flow_map_cars_per_minute = args.flow_map.then(|| {
+        road.cells()
+            .iter()
+            .map(|lane| lane.iter().map(|cell| cell.flow(road.measured_rounds()) / ROUND_S * 60.0).collect())
+            .collect()
+    });
+    let speed_map_kilometers_per_hour = args.flow_map.then(|| {
+        road.cells()
+            .iter()
+            .map(|lane| lane.iter().map(|cell| cell.mean_speed() * (CELL_M / ROUND_S) * 3.6).collect())
+            .collect()
+    });
+    let occupancy_map = args.flow_map.then(|| {
+        road.cells()
+            .iter()
+            .map(|lane| lane.iter().map(|cell| cell.occupancy(road.rounds(), road.measured_rounds())).collect())
+            .collect()
+    });
+    let mut records_by_vehicle_class: BTreeMap<VehicleClass, CarRecords> = BTreeMap::new();
+    for (vehicle, records) in road.vehicle_blueprints().iter().zip(road.car_records_by_vehicle_type()) {
+        records_by_vehicle_class.entry(vehicle.vehicle_class()).or_default().merge(&records);
+    }
+    let average_speed_kilometers_per_hour_by_vehicle_class = records_by_vehicle_class
+        .into_iter()
+        .map(|(vehicle_class, records)| (vehicle_class, records.distance as f64 / records.rounds as f64 * (CELL_M / ROUND_S) * 3.6))
+        .collect();
+    let vehicle_types = road.vehicle_blueprints()
+        .iter()
+        .zip(road.car_records_by_vehicle_type())
+        .enumerate()
+        .map(|(vehicle_type, (vehicle, records))| {
+            let car_rounds = records.rounds as f64;
+            VehicleTypeResult {
+                vehicle: vehicle.clone(),
+                cars: records.cars,
+                initial_cars_per_lane: initial_cars_per_lane_by_vehicle_type.iter().map(|lane| lane[vehicle_type]).collect(),
+                average_speed_kilometers_per_hour: records.distance as f64 / car_rounds * (CELL_M / ROUND_S) * 3.6,
+                average_accelerations_n_per_car_per_round: records.accelerations as f64 / car_rounds,
+                average_deaccelerations_n_per_car_per_round: records.deaccelerations as f64 / car_rounds,
+                average_stops_n_per_car_per_round: records.stops as f64 / car_rounds,
+                average_lane_changes_n_per_car_per_round: records.lane_changes as f64 / car_rounds,
+            }
+        })
+        .collect();
+
+    SimulationResult {
+        // Settings
+        rounds: road.rounds(),
+        warmup: args.warmup,
+        lanes: road.lanes(),
+        length: road.length(),
+        cars: road.cars(),
+        open: road.open(),
+        dilly_dally_probability: road.dilly_dally_probability(),
+        stay_in_lane_probability: road.stay_in_lane_probability(),
+        update_order: road.update_order(),
+        model: road.driving_model(),
+        lane_rules: road.lane_rules(),
+        seed: args.seed,
+        // Metrics
+        runtime_s: start.elapsed().as_secs_f64(),
+        average_speed_kilometers_per_hour: road.average_speed() * (CELL_M / ROUND_S) * 3.6,
+        monitor_cells_flow_cars_per_minute: flows_cars_per_minute,
+        monitor_cells_occupancy: occupancies,
+        monitor_cells_density_cars_per_km: road.monitor_densities().iter().map(|density| density / CELL_M * 1000.0).collect(),
+        average_accelerations_n_per_car_per_round: road.average_accelerations(),
+        average_deaccelerations_n_per_car_per_round: road.average_deaccelerations(),
+        exited_cars: road.exited_cars(),
+        removed_cars: road.removed_cars(),
+        waiting_cars: road.waiting_cars(),
+        flow_map_cars_per_minute,
+        speed_map_kilometers_per_hour,
+        occupancy_map,
+        initial_cars_per_lane: initial_cars_per_lane_by_vehicle_type.iter().map(|lane| lane.iter().sum()).collect(),
+        cars_per_lane_per_round: None,
+        vehicle_types,
+        average_speed_kilometers_per_hour_by_vehicle_class,
+        laps: road.laps(),
+        lap_times_s: LapTimeDistribution::new(road.lap_times()),
+        emergency_travel_times_s: road.vehicle_blueprints()
+            .iter()
+            .any(|vb| vb.vehicle_class() == VehicleClass::Emergency)
+            .then(|| LapTimeDistribution::new(road.emergency_travel_times())),
+        congested_time_s: road.congested_rounds() as f64 * ROUND_S,
+        average_congested_time_s_per_car: road.congested_rounds() as f64 * ROUND_S / road.cars() as f64,
+        congested_fraction_per_round: None,
+        average_speed_kilometers_per_hour_per_round: None,
+        density_cars_per_km_per_lane_per_round: None,
+        monitor_cells_flow_cars_per_minute_per_round: None,
+        shockwaves: None,
+        detector_correlations: None,
+        state_hashes: None,
+        spillbacks: None,
+        breakdown: None,
+        steady_state: None,
+        queues: None,
+        safety: None,
+        speed_histogram: None,
+        signal_plan: (!args.green_waves().is_empty()).then(|| road.traffic_lights().clone()),
+        incidents: (args.incident_probability > 0.0).then(|| road.incidents().to_vec()),
+        sections: (!road.sections().is_empty()).then(|| road.sections().iter().map(Section::result).collect()),
+        preempted_light_rounds: road.preempted_light_rounds(),
+        light_failures: road.light_failures(),
+        failed_light_rounds: road.failed_light_rounds(),
+        memory: None,
+        near_misses: road.near_misses(),
+        near_misses_per_lane_change: road.near_misses_per_lane_change(),
+        yields: road.yields(),
+        mandatory_lane_changes: road.mandatory_lane_changes(),
+        lane_changes: LaneChangeStatistics::new(road),
+        collisions: road.collisions(),
+        emergency_brakings: road.emergency_brakings(),
+    }
+}
@@ -0,0 +1,131 @@
+use serde::Serialize;
+use crate::car::VehicleBlueprint;
+use crate::Road;
+
+/// The outcome of a canonical scenario. The deviations describe every expectation that was not met.
+#[derive(Serialize, Debug)]
+pub struct SmokeCheck {
+    pub scenario: &'static str,
+    pub passed: bool,
+    pub deviations: Vec<String>,
+}
+
+impl SmokeCheck {
+    fn new(scenario: &'static str, deviations: Vec<String>) -> Self {
+        Self { scenario, passed: deviations.is_empty(), deviations }
+    }
+}
+
+/// Runs a handful of canonical scenarios whose outcomes are known and checks them, as a sanity
+/// check of a build. None of the scenarios depend on chance, as drivers neither dawdle nor stay in
+/// their lanes by chance, so they play out the same on every run and platform.
+pub fn run() -> Vec<SmokeCheck> {
+    vec![
+        SmokeCheck::new("single car free flow", single_car_free_flow()),
+        SmokeCheck::new("megajam dissolution", megajam_dissolution()),
+        SmokeCheck::new("bottleneck", bottleneck()),
+        SmokeCheck::new("signal corridor", signal_corridor()),
+    ]
+}
+
+/// Creates a closed road from a layout with cars of a single type that never dawdle.
+fn road(layout: &str) -> Road {
+    let vehicles: Vec<VehicleBlueprint> = vec!["(5, 1, 0.1)".parse().unwrap()];
+    Road::from_layout(layout, &vehicles, 0.0, 0.0, None).expect("The layout of a smoke scenario is invalid.")
+}
+
+/// Checks that no car got lost and that no car is on a blocked cell.
+fn check_conservation(road: &Road, cars: usize, deviations: &mut Vec<String>) {
+    let cars_on_road = road.iter_cars().count();
+    if cars_on_road != cars {
+        deviations.push(format!("Round {}: there are {} cars on the road instead of {}.", road.rounds(), cars_on_road, cars));
+    }
+    if let Some((_, lane, index, _)) = road.iter_cars().find(|(_, lane, index, _)| road.cells()[*lane][*index].blocked()) {
+        deviations.push(format!("Round {}: there is a car on the blocked cell ({}, {}).", road.rounds(), lane, index));
+    }
+}
+
+/// A single car speeds up by one cell per round until it reaches its maximum speed and then keeps
+/// going at it.
+fn single_car_free_flow() -> Vec<String> {
+    let mut road = road(&format!("o{}\n", "_".repeat(199)));
+    let mut deviations = Vec::new();
+    let mut expected_index = 0;
+    for round in 1..=50 {
+        road.round();
+        let expected_speed = round.min(5) as u8;
+        expected_index = (expected_index + expected_speed as usize) % 200;
+        let (_, _, index, car) = road.iter_cars().next().unwrap();
+        if (index, car.speed()) != (expected_index, expected_speed) {
+            deviations.push(format!("Round {}: the car is in cell {} at speed {} instead of cell {} at speed {}.", round, index, car.speed(), expected_index, expected_speed));
+        }
+    }
+    deviations
+}
+
+/// Cars packed bumper to bumper below the critical density of a sixth of the cells leave the jam
+/// one after another until all of them drive at their maximum speed.
+fn megajam_dissolution() -> Vec<String> {
+    let mut road = road(&format!("{}{}\n", "o".repeat(30), "_".repeat(170)));
+    let mut deviations = Vec::new();
+    for _ in 0..300 {
+        road.round();
+        check_conservation(&road, 30, &mut deviations);
+    }
+    let slow_cars = road.iter_cars().filter(|(_, _, _, car)| car.speed() < 5).count();
+    if slow_cars > 0 {
+        deviations.push(format!("{} cars are still slower than their maximum speed after 300 rounds.", slow_cars));
+    }
+    deviations
+}
+
+/// Blocking one of two lanes over a stretch of the road slows traffic down compared to the same
+/// road without the block.
+fn bottleneck() -> Vec<String> {
+    let lane = "o__".repeat(40);
+    let second_lane = |stretch: &str| format!("{}{}{}", "o__".repeat(10), stretch.repeat(30), "o__".repeat(20));
+    let mut open_road = road(&format!("{}\n{}\n", lane, second_lane("_")));
+    let mut narrowed_road = road(&format!("{}\n{}\n", lane, second_lane("x")));
+    let mut deviations = Vec::new();
+    for _ in 0..300 {
+        open_road.round();
+        narrowed_road.round();
+        check_conservation(&narrowed_road, 70, &mut deviations);
+    }
+    if narrowed_road.average_speed() >= open_road.average_speed() {
+        deviations.push(format!(
+            "The average speed with the bottleneck ({:.3} cells per round) is not below the one without it ({:.3} cells per round).",
+            narrowed_road.average_speed(),
+            open_road.average_speed()
+        ));
+    }
+    deviations
+}
+
+/// No car passes a traffic light while it is red, and traffic flows through it while it is green.
+fn signal_corridor() -> Vec<String> {
+    let mut road = road(&format!("{}#{}#{}\n", "o____".repeat(10), "_".repeat(99), "_".repeat(49)));
+    let light_indexes = [50, 150];
+    let cars_passed = |road: &Road| light_indexes.map(|index| road.cells()[0][index].cars_passed());
+    let mut deviations = Vec::new();
+    let mut passed_at_phase_start = cars_passed(&road);
+    for round in 1..=400 {
+        road.round();
+        check_conservation(&road, 10, &mut deviations);
+        // The lights are green for the first 100 rounds of every 200 and red for the rest.
+        if round % 100 != 99 {
+            continue;
+        }
+        let passed = cars_passed(&road);
+        let red = round % 200 == 199;
+        for ((index, before), after) in light_indexes.iter().zip(passed_at_phase_start).zip(passed) {
+            if red && after != before {
+                deviations.push(format!("{} cars passed the red light at cell {} up to round {}.", after - before, index, round));
+            } else if !red && after == before {
+                deviations.push(format!("No car passed the green light at cell {} up to round {}.", index, round));
+            }
+        }
+        passed_at_phase_start = passed;
+    }
+    deviations
+}
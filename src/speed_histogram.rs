@@ -0,0 +1,50 @@
+use serde::Serialize;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// Counts how often the cars on the road drove at each speed, over all rounds.
+#[derive(Debug, Default)]
+pub struct SpeedHistogram {
+    /// The number of cars that drove at each speed in cells per round, summed up over the rounds.
+    car_rounds: Vec<u64>,
+}
+
+/// The cars that drove at a speed, summed up over the rounds, and their fraction of all cars.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SpeedHistogramBin {
+    pub speed_cells_per_round: u8,
+    pub speed_kilometers_per_hour: f64,
+    pub car_rounds: u64,
+    pub fraction: f64,
+}
+
+impl SpeedHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the speeds of the cars after a round.
+    pub fn take_snapshot(&mut self, road: &Road) {
+        for (_, _, _, car) in road.iter_cars() {
+            let speed = car.speed() as usize;
+            if speed >= self.car_rounds.len() {
+                self.car_rounds.resize(speed + 1, 0);
+            }
+            self.car_rounds[speed] += 1;
+        }
+    }
+
+    /// Returns a bin for every speed from standing still up to the highest speed driven.
+    pub fn bins(self) -> Vec<SpeedHistogramBin> {
+        let total: u64 = self.car_rounds.iter().sum();
+        self.car_rounds
+            .into_iter()
+            .enumerate()
+            .map(|(speed, car_rounds)| SpeedHistogramBin {
+                speed_cells_per_round: speed as u8,
+                speed_kilometers_per_hour: speed as f64 * (CELL_M / ROUND_S) * 3.6,
+                car_rounds,
+                fraction: car_rounds as f64 / total as f64,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Serialize;
+use crate::cell::CellLocation;
+use crate::{Road, ROUND_S};
+
+/// Watches protection points (e.g. the start of the road or the entry of a turn lane) for queues
+/// that grow back from a red traffic light or a blocked cell.
+#[derive(Debug)]
+pub struct SpillbackDetector {
+    points: Vec<CellLocation>,
+    first_rounds: Vec<Option<u32>>,
+    spillback_rounds: Vec<u32>,
+}
+
+/// When and for how long the queue reached a protection point. Without any spillback, the first
+/// round is `None`.
+#[derive(Serialize, Debug)]
+pub struct Spillback {
+    pub location: CellLocation,
+    pub first_round: Option<u32>,
+    pub duration_s: f64,
+}
+
+impl SpillbackDetector {
+    pub fn new(points: Vec<CellLocation>) -> Self {
+        let n_points = points.len();
+        Self {
+            points,
+            first_rounds: vec![None; n_points],
+            spillback_rounds: vec![0; n_points],
+        }
+    }
+
+    /// Checks the protection points after a round. (Cars start standing still, so the state before
+    /// the first round is not checked.)
+    pub fn take_snapshot(&mut self, road: &Road) {
+        for (i, point) in self.points.iter().enumerate() {
+            if spilled_back(road, point) {
+                self.first_rounds[i].get_or_insert(road.rounds());
+                self.spillback_rounds[i] += 1;
+            }
+        }
+    }
+
+    pub fn spillbacks(self) -> Vec<Spillback> {
+        self.points
+            .into_iter()
+            .zip(self.first_rounds)
+            .zip(self.spillback_rounds)
+            .map(|((location, first_round), rounds)| Spillback {
+                location,
+                first_round,
+                duration_s: rounds as f64 * ROUND_S,
+            })
+            .collect()
+    }
+}
+
+/// Returns whether a stopped car is in the cell, which is part of an unbroken queue of stopped cars
+/// that reaches up to a red traffic light or a blocked cell. Cells that are not on the road never
+/// spill back.
+fn spilled_back(road: &Road, point: &CellLocation) -> bool {
+    let length = road.length() as usize;
+    let Some(lane) = road.cells().get(point.lane()).filter(|_| point.index() < length) else {
+        return false;
+    };
+    for step in 0..length {
+        let cell_i = point.index() + step;
+        if cell_i >= length && road.open() {
+            // The queue reaches the end of the road, where cars leave freely.
+            return false;
+        }
+        let cell = &lane[cell_i % length];
+        if cell.blocked() || cell.is_red_light() {
+            // A car that was caught on the traffic light when it turned red waits there as well.
+            return step > 0;
+        }
+        if cell.car().is_none_or(|car| car.speed() != 0) {
+            return false;
+        }
+    }
+    false
+}
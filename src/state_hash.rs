@@ -0,0 +1,117 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use serde::Serialize;
+use crate::Road;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Computes a hash of the full state of the road. The hash only depends on the state, so it is the
+/// same on every machine and for every build of the simulator. (FNV-1a)
+pub fn hash_road(road: &Road) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    write(&road.rounds().to_le_bytes());
+    for light in road.traffic_lights() {
+        write(&[road.cells()[light.location().lane()][light.location().index()].red() as u8]);
+    }
+    write(&road.waiting_cars().to_le_bytes());
+    for lane in road.cells() {
+        for cell in lane {
+            match cell.car() {
+                Some(car) => {
+                    write(&[1]);
+                    write(&car.id().to_le_bytes());
+                    write(&[car.speed()]);
+                },
+                None => write(&[0]),
+            }
+        }
+    }
+    hash
+}
+
+/// Dumps the state hash of every round or compares them to the hashes of an earlier dump.
+#[derive(Debug)]
+pub struct StateHasher {
+    dump: Option<BufWriter<File>>,
+    expected: Option<Vec<u64>>,
+    compared_rounds: u32,
+    first_diverging_round: Option<u32>,
+}
+
+/// The outcome of comparing the state hashes to an earlier dump.
+#[derive(Serialize, Debug)]
+pub struct StateHashComparison {
+    pub compared_rounds: u32,
+    pub first_diverging_round: Option<u32>,
+}
+
+impl StateHasher {
+    /// Creates the hasher. Hashes are dumped to `dump_path` with one hexadecimal hash per line,
+    /// starting with the state before the first round. If `compare_path` is given, the hashes are
+    /// compared to the ones in that file.
+    pub fn new(dump_path: Option<&PathBuf>, compare_path: Option<&PathBuf>) -> io::Result<Self> {
+        let dump = match dump_path {
+            Some(path) => Some(BufWriter::new(File::create(path)?)),
+            None => None,
+        };
+        let expected = match compare_path {
+            Some(path) => Some(
+                fs::read_to_string(path)?
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| u64::from_str_radix(line.trim(), 16).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid state hash.")))
+                    .collect::<io::Result<Vec<u64>>>()?
+            ),
+            None => None,
+        };
+        Ok(Self {
+            dump,
+            expected,
+            compared_rounds: 0,
+            first_diverging_round: None,
+        })
+    }
+
+    pub fn take_snapshot(&mut self, road: &Road) -> io::Result<()> {
+        let hash = hash_road(road);
+        if let Some(dump) = &mut self.dump {
+            writeln!(dump, "{:016x}", hash)?;
+        }
+        if let Some(expected) = &self.expected {
+            if let Some(expected_hash) = expected.get(road.rounds() as usize) {
+                self.compared_rounds += 1;
+                if *expected_hash != hash && self.first_diverging_round.is_none() {
+                    self.first_diverging_round = Some(road.rounds());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered hashes to the dump.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.dump {
+            Some(dump) => dump.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Flushes the dump and returns the comparison if hashes were compared.
+    pub fn finish(mut self) -> io::Result<Option<StateHashComparison>> {
+        if let Some(dump) = &mut self.dump {
+            dump.flush()?;
+        }
+        Ok(self.expected.map(|_| StateHashComparison {
+            compared_rounds: self.compared_rounds,
+            first_diverging_round: self.first_diverging_round,
+        }))
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+use serde::Serialize;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// Detects when traffic has settled into a steady state: once the mean speed of the cars on the
+/// road, averaged over a window of `window` rounds, differs from that of the window before by no
+/// more than the relative `tolerance`. Rounds without cars on the road are skipped.
+#[derive(Debug)]
+pub struct SteadyStateDetector {
+    window: usize,
+    tolerance: f64,
+    mean_speeds: VecDeque<f64>,
+    reached: Option<(u32, f64)>,
+}
+
+/// Whether and in which round traffic settled into a steady state, and the mean speed of the cars
+/// in the last window.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SteadyState {
+    pub reached: bool,
+    pub round: Option<u32>,
+    pub mean_speed_kilometers_per_hour: Option<f64>,
+}
+
+impl SteadyStateDetector {
+    pub fn new(window: u32, tolerance: f32) -> Self {
+        if window == 0 || tolerance < 0.0 {
+            panic!("The window must span at least one round and the tolerance must not be negative.");
+        }
+        Self {
+            window: window as usize,
+            tolerance: tolerance as f64,
+            mean_speeds: VecDeque::with_capacity(2 * window as usize),
+            reached: None,
+        }
+    }
+
+    /// Records the mean speed of the cars after a round. Returns whether the steady state has been
+    /// reached.
+    pub fn take_snapshot(&mut self, road: &Road) -> bool {
+        if self.reached.is_some() {
+            return true;
+        }
+        let (speed_sum, cars) = road.iter_cars().fold((0u64, 0u64), |(speed_sum, cars), (_, _, _, car)| (speed_sum + car.speed() as u64, cars + 1));
+        if cars == 0 {
+            return false;
+        }
+        if self.mean_speeds.len() == 2 * self.window {
+            self.mean_speeds.pop_front();
+        }
+        self.mean_speeds.push_back(speed_sum as f64 / cars as f64);
+        if self.mean_speeds.len() < 2 * self.window {
+            return false;
+        }
+        let previous = self.mean_speeds.iter().take(self.window).sum::<f64>() / self.window as f64;
+        let last = self.mean_speeds.iter().skip(self.window).sum::<f64>() / self.window as f64;
+        if (last - previous).abs() <= self.tolerance * previous {
+            self.reached = Some((road.rounds(), last));
+        }
+        self.reached.is_some()
+    }
+
+    pub fn steady_state(self) -> SteadyState {
+        SteadyState {
+            reached: self.reached.is_some(),
+            round: self.reached.map(|(round, _)| round),
+            mean_speed_kilometers_per_hour: self.reached.map(|(_, mean_speed)| mean_speed * (CELL_M / ROUND_S) * 3.6),
+        }
+    }
+}
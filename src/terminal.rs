@@ -0,0 +1,419 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::ops::Range;
+use std::str::FromStr;
+#[cfg(feature = "terminal")]
+use std::{io::{Stdout, stdout}, thread, time::Duration};
+#[cfg(feature = "terminal")]
+use colored::Colorize;
+#[cfg(feature = "terminal")]
+use crossterm::{QueueableCommand, cursor, terminal, ExecutableCommand};
+use crate::car::CarId;
+use crate::cell::{CellLocation, ParseCellLocationError};
+use crate::observer::Observer;
+use crate::Road;
+
+/// The number of cells shown when following a car without a view.
+const FOLLOW_WIDTH: usize = 80;
+
+/// A window of the road made up of a range of lanes and a range of cells. Like blocked cells, the
+/// ranges are written as `(lane_start-lane_end, cell_start-cell_end)` with exclusive ends and a
+/// single number selects a single lane or cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View {
+    lanes: Range<usize>,
+    cells: Range<usize>,
+}
+
+impl View {
+    /// Returns a view of the whole road.
+    pub fn full(road: &Road) -> Self {
+        Self {
+            lanes: 0..road.lanes() as usize,
+            cells: 0..road.length() as usize,
+        }
+    }
+
+    /// Returns the part of the view that lies on the road.
+    fn clamp(&self, road: &Road) -> Self {
+        let clamp_range = |range: &Range<usize>, max: usize| {
+            let end = range.end.min(max);
+            range.start.min(end)..end
+        };
+        Self {
+            lanes: clamp_range(&self.lanes, road.lanes() as usize),
+            cells: clamp_range(&self.cells, road.length() as usize),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseViewError;
+
+/// Parses `start-end` or a single number into a range.
+fn parse_range(s: &str) -> Result<Range<usize>, ParseViewError> {
+    match s.split_once('-') {
+        Some((start, end)) => {
+            let start = start.parse::<usize>().map_err(|_| ParseViewError)?;
+            let end = end.parse::<usize>().map_err(|_| ParseViewError)?;
+            Ok(start..end)
+        },
+        None => {
+            let single = s.parse::<usize>().map_err(|_| ParseViewError)?;
+            Ok(single..single + 1)
+        }
+    }
+}
+
+impl FromStr for View {
+    type Err = ParseViewError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let (lanes, cells) = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|s| s.split_once(','))
+            .ok_or(ParseViewError)?;
+
+        Ok(View { lanes: parse_range(lanes)?, cells: parse_range(cells)? })
+    }
+}
+
+/// The car to follow, given either by its ID or by the cell it is in before the first round as
+/// `(lane, index)`.
+#[derive(Debug, PartialEq)]
+pub enum FollowTarget {
+    Id(CarId),
+    Location(CellLocation),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseFollowTargetError;
+
+impl From<ParseCellLocationError> for ParseFollowTargetError {
+    fn from(_: ParseCellLocationError) -> Self {
+        ParseFollowTargetError
+    }
+}
+
+impl FromStr for FollowTarget {
+    type Err = ParseFollowTargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with('(') {
+            Ok(FollowTarget::Location(s.parse::<CellLocation>()?))
+        } else {
+            Ok(FollowTarget::Id(s.trim().parse::<u32>().map_err(|_| ParseFollowTargetError)?))
+        }
+    }
+}
+
+/// Decides which part of the road is shown. Without a car to follow, this is the view (or the whole
+/// road). While following a car, the cells of the view are moved so that the car stays in their
+/// center, only stopping at the start and end of the road.
+#[derive(Debug)]
+pub struct Camera {
+    view: Option<View>,
+    follow: Option<FollowTarget>,
+    followed_car: Option<CarId>,
+    gap_overlay: bool,
+}
+
+impl Camera {
+    pub fn new(view: Option<View>) -> Self {
+        Self { view, follow: None, followed_car: None, gap_overlay: false }
+    }
+
+    pub fn with_follow(mut self, follow: FollowTarget) -> Self {
+        self.follow = Some(follow);
+        self
+    }
+
+    /// Shows the gap counters recorded by the road below each lane.
+    pub fn with_gap_overlay(mut self) -> Self {
+        self.gap_overlay = true;
+        self
+    }
+
+    /// Looks up the followed car before the first round.
+    pub fn start(&mut self, road: &Road) {
+        self.followed_car = match &self.follow {
+            Some(FollowTarget::Id(id)) => Some(*id),
+            Some(FollowTarget::Location(location)) => road.car_at(location.lane(), location.index()).map(|car| car.id()),
+            None => None,
+        };
+    }
+
+    /// Returns the view to show of the road in its current state.
+    pub fn view(&self, road: &Road) -> Option<View> {
+        let Some((_, index, _)) = self.followed_car.and_then(|id| road.find_car(id)) else {
+            return self.view.clone();
+        };
+        let length = road.length() as usize;
+        let view = self.view.clone().unwrap_or(View {
+            lanes: 0..road.lanes() as usize,
+            cells: 0..FOLLOW_WIDTH,
+        });
+        let width = view.cells.len().min(length);
+        let start = index.saturating_sub(width / 2).min(length - width);
+        Some(View { lanes: view.lanes, cells: start..start + width })
+    }
+
+    pub fn render(&self, road: &Road) -> String {
+        render_with_paint(road, self.view(road).as_ref(), paint, self.gap_overlay)
+    }
+
+    /// Draws the road like `render`, but keeps the columns apart.
+    #[cfg(feature = "terminal")]
+    pub fn frame(&self, road: &Road) -> TextFrame {
+        frame_with_paint(road, self.view(road).as_ref(), paint, self.gap_overlay)
+    }
+}
+
+/// Writes the uncolored state of the road of every round to a text file, which makes up a
+/// space-time diagram that can be diffed and searched. Rounds are separated by empty lines.
+#[derive(Debug)]
+pub struct AsciiWriter {
+    file: BufWriter<File>,
+}
+
+impl AsciiWriter {
+    pub fn new(path: &PathBuf) -> io::Result<Self> {
+        Ok(Self { file: BufWriter::new(File::create(path)?) })
+    }
+}
+
+impl Observer for AsciiWriter {
+    fn start(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        writeln!(self.file, "{}", render_plain(road))?;
+        Ok(())
+    }
+
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        writeln!(self.file, "\n{}", render_plain(road))?;
+        Ok(())
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Prints the state of the road to stdout every round.
+#[derive(Debug)]
+pub struct VerbosePrinter {
+    camera: Camera,
+}
+
+impl VerbosePrinter {
+    pub fn new(camera: Camera) -> Self {
+        Self { camera }
+    }
+}
+
+impl Observer for VerbosePrinter {
+    fn start(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        self.camera.start(road);
+        println!("{}", self.camera.render(road));
+        Ok(())
+    }
+
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        println!("\n{}", self.camera.render(road));
+        Ok(())
+    }
+}
+
+/// Animates the road in the terminal by drawing every round over the last frame. Only the columns
+/// that changed are rewritten, unless the size of the frame changed.
+#[cfg(feature = "terminal")]
+#[derive(Debug)]
+pub struct Animator {
+    stdout: Stdout,
+    camera: Camera,
+    frame_delay: Duration,
+    shown_frame: Option<TextFrame>,
+}
+
+#[cfg(feature = "terminal")]
+impl Animator {
+    pub fn new(camera: Camera) -> Self {
+        Self { stdout: stdout(), camera, frame_delay: Duration::from_millis(50), shown_frame: None }
+    }
+
+    /// Sets how long each frame is shown. A delay of zero animates as fast as possible.
+    pub fn with_frame_delay(mut self, frame_delay: Duration) -> Self {
+        self.frame_delay = frame_delay;
+        self
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl Observer for Animator {
+    fn start(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        self.camera.start(road);
+        self.stdout.queue(cursor::Hide)?;
+        self.stdout.execute(cursor::SavePosition)?;
+        Ok(())
+    }
+
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        let frame = self.camera.frame(road);
+        match self.shown_frame.as_ref().and_then(|shown_frame| frame_changes(shown_frame, &frame)) {
+            Some(changes) => {
+                for (row_i, column_i, text) in changes {
+                    self.stdout.queue(cursor::RestorePosition)?;
+                    if row_i > 0 {
+                        self.stdout.queue(cursor::MoveDown(row_i as u16))?;
+                    }
+                    self.stdout.queue(cursor::MoveToColumn(column_i as u16))?;
+                    self.stdout.write_all(text.as_bytes())?;
+                }
+            },
+            None => {
+                self.stdout.queue(cursor::RestorePosition)?;
+                self.stdout.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+                self.stdout.write_all(join_frame(&frame).as_bytes())?;
+            },
+        }
+        self.stdout.flush()?;
+        self.shown_frame = Some(frame);
+        thread::sleep(self.frame_delay);
+        Ok(())
+    }
+
+    fn finish(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        self.stdout.queue(cursor::RestorePosition)?;
+        self.stdout.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        self.stdout.execute(cursor::Show)?;
+        println!("{}", self.camera.render(road));
+        Ok(())
+    }
+}
+
+impl fmt::Display for Road {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", render(self, None))
+    }
+}
+
+/// Draws the lanes and cells of the road that are in the `view` or the whole road if there is no
+/// view. Lanes and cells are labeled with the last digit of their index.
+pub fn render(road: &Road, view: Option<&View>) -> String {
+    render_with_paint(road, view, paint, false)
+}
+
+/// Draws the whole road like `render`, but without colors.
+pub fn render_plain(road: &Road) -> String {
+    render_with_paint(road, None, |text, _rgb| text, false)
+}
+
+/// Writes a gap counter as a single character. Counters from 0 to 35 are written as digits and
+/// letters and larger counters as `+`.
+fn gap_char(counter: u32) -> char {
+    char::from_digit(counter, 36).unwrap_or('+')
+}
+
+/// A drawing of the road as rows of columns. Every column is a single character that may be
+/// colored.
+pub type TextFrame = Vec<Vec<String>>;
+
+/// Joins the rows of a frame into lines.
+fn join_frame(frame: &TextFrame) -> String {
+    frame.iter().map(|row| row.concat()).collect::<Vec<String>>().join("\n")
+}
+
+/// Returns the runs of columns that differ between the shown frame and the next one as the row,
+/// the first column and the text to write there, or `None` if the frames differ in size and the
+/// next one has to be drawn in full.
+#[cfg(feature = "terminal")]
+pub fn frame_changes(shown_frame: &TextFrame, frame: &TextFrame) -> Option<Vec<(usize, usize, String)>> {
+    if shown_frame.len() != frame.len() || shown_frame.iter().zip(frame).any(|(shown_row, row)| shown_row.len() != row.len()) {
+        return None;
+    }
+    let mut changes = Vec::new();
+    for (row_i, (shown_row, row)) in shown_frame.iter().zip(frame).enumerate() {
+        let mut column_i = 0;
+        while column_i < row.len() {
+            if shown_row[column_i] == row[column_i] {
+                column_i += 1;
+                continue;
+            }
+            let run_end = (column_i..row.len()).find(|i| shown_row[*i] == row[*i]).unwrap_or(row.len());
+            changes.push((row_i, column_i, row[column_i..run_end].concat()));
+            column_i = run_end;
+        }
+    }
+    Some(changes)
+}
+
+/// Draws the road like `render`. With `gap_overlay`, each lane is followed by a row labeled `c`
+/// with the cells to the next car and a row labeled `o` with the cells to the next obstacle that
+/// the last update saw in each cell, if the road records them.
+fn render_with_paint(road: &Road, view: Option<&View>, paint: fn(String, [u8; 3]) -> String, gap_overlay: bool) -> String {
+    join_frame(&frame_with_paint(road, view, paint, gap_overlay))
+}
+
+/// Draws the road like `render_with_paint`, but keeps the rows and columns apart.
+fn frame_with_paint(road: &Road, view: Option<&View>, paint: fn(String, [u8; 3]) -> String, gap_overlay: bool) -> TextFrame {
+    let view = view.map_or_else(|| View::full(road), |view| view.clamp(road));
+    let mut frame = Vec::new();
+    let colored_digits: Vec<String> = (0..10).map(|n| paint(
+        n.to_string(),
+        [
+            ((1.0 - n as f32 / 10.0) * 255.0).floor() as u8,
+            ((1.0 - n as f32 / 10.0 / 2.5) * 255.0).floor() as u8,
+            255
+        ]
+    )).collect();
+    let mut header = vec![" ".to_string(); 2];
+    header.extend(view.cells.clone().map(|row| colored_digits[row % 10].clone()));
+    frame.push(header);
+    for index in view.lanes.clone() {
+        let mut row = vec![colored_digits[index % 10].clone(), " ".to_string()];
+        for cell in &road.cells()[index][view.cells.clone()] {
+            if let Some(car) = cell.car() {
+                row.push(paint(car.speed().to_string(), car.speed_rgb()));
+            } else if cell.blocked() {
+                row.push("x".to_string());
+            } else if cell.is_red_light() {
+                row.push("#".to_string());
+            } else {
+                row.push("_".to_string());
+            }
+        }
+        frame.push(row);
+        if let Some(gap_counters) = road.gap_counters().filter(|_| gap_overlay) {
+            let counters = &gap_counters[index][view.cells.clone()];
+            let gap_row = |label: &str, counter: fn(&(u32, u32)) -> u32| {
+                let mut row = vec![label.to_string(), " ".to_string()];
+                row.extend(counters.iter().map(|counters| gap_char(counter(counters)).to_string()));
+                row
+            };
+            frame.push(gap_row("c", |(to_next_car, _)| *to_next_car));
+            frame.push(gap_row("o", |(_, to_next_obstacle)| *to_next_obstacle));
+        }
+    }
+    frame
+}
+
+/// Colors the text for the terminal.
+#[cfg(feature = "terminal")]
+fn paint(text: String, [r, g, b]: [u8; 3]) -> String {
+    text.truecolor(r, g, b).to_string()
+}
+
+/// Leaves the text as is, since the simulator was built without terminal support.
+#[cfg(not(feature = "terminal"))]
+fn paint(text: String, _rgb: [u8; 3]) -> String {
+    text
+}
@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::cell::CellLocation;
+use crate::observer::Observer;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// Writes the state of the road after every round to a CSV file, one row per round: the cars on the
+/// road, their density and mean speed, the lane changes of the round and the flow at every
+/// monitored cell. The flow is not averaged over several rounds, so it is a multiple of 60 cars per
+/// minute. The mean speed of a round without cars is left empty.
+#[derive(Debug)]
+pub struct TimeSeriesWriter {
+    writer: BufWriter<File>,
+    monitors: Vec<(usize, usize)>,
+    cars_passed: Vec<i32>,
+    lane_changes: u64,
+}
+
+impl TimeSeriesWriter {
+    pub fn new(filepath: &Path, monitors: &[CellLocation]) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        write!(writer, "round,time_s,cars,density_cars_per_km_per_lane,mean_speed_km_per_h,lane_changes")?;
+        for monitor in monitors {
+            write!(writer, ",flow_{}_{}_cars_per_minute", monitor.lane(), monitor.index())?;
+        }
+        writeln!(writer)?;
+        Ok(Self {
+            writer,
+            monitors: monitors.iter().map(|monitor| (monitor.lane(), monitor.index())).collect(),
+            cars_passed: vec![0; monitors.len()],
+            lane_changes: 0,
+        })
+    }
+
+    /// Returns the cars that have passed each monitored cell so far. Monitored cells that are not on
+    /// the road are never passed.
+    fn cars_passed(&self, road: &Road) -> Vec<i32> {
+        self.monitors
+            .iter()
+            .map(|(lane, index)| road.cells().get(*lane).and_then(|lane| lane.get(*index)).map_or(0, |cell| cell.cars_passed()))
+            .collect()
+    }
+}
+
+impl Observer for TimeSeriesWriter {
+    fn start(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        // The initial state is only needed to count what happens in round one.
+        self.cars_passed = self.cars_passed(road);
+        self.lane_changes = road.lane_changes();
+        Ok(())
+    }
+
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        let (speed_sum, cars) = road.iter_cars().fold((0u64, 0u64), |(speed_sum, cars), (_, _, _, car)| (speed_sum + car.speed() as u64, cars + 1));
+        let road_km = road.lanes() as f64 * road.length() as f64 * CELL_M / 1000.0;
+        let mean_speed = match cars {
+            0 => String::new(),
+            _ => (speed_sum as f64 / cars as f64 * CELL_M / ROUND_S * 3.6).to_string(),
+        };
+        let lane_changes = road.lane_changes();
+        write!(
+            self.writer,
+            "{},{},{},{},{},{}",
+            road.rounds(),
+            road.rounds() as f64 * ROUND_S,
+            cars,
+            cars as f64 / road_km,
+            mean_speed,
+            lane_changes - self.lane_changes
+        )?;
+        let cars_passed = self.cars_passed(road);
+        for (passed, passed_before) in cars_passed.iter().zip(&self.cars_passed) {
+            write!(self.writer, ",{}", (passed - passed_before) as f64 / ROUND_S * 60.0)?;
+        }
+        writeln!(self.writer)?;
+        self.cars_passed = cars_passed;
+        self.lane_changes = lane_changes;
+        Ok(())
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.writer.flush()?)
+    }
+}
@@ -0,0 +1,310 @@
+use std::str::FromStr;
+use serde::Serialize;
+use crate::cell::CellLocation;
+
+/// When a traffic light is green. Every `cycle` rounds the light is green for `green` rounds and
+/// red for the rest. The offset delays the start of the cycle by that many rounds, so that lights
+/// along a road can turn green one after another.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightTiming {
+    cycle: u32,
+    green: u32,
+    offset: u32,
+}
+
+impl Default for LightTiming {
+    /// Green for 100 rounds and red for the next 100.
+    fn default() -> Self {
+        Self { cycle: 200, green: 100, offset: 0 }
+    }
+}
+
+impl LightTiming {
+    pub fn new(cycle: u32, green: u32, offset: u32) -> Self {
+        if cycle == 0 || green > cycle {
+            panic!("A traffic light cycle must last at least one round and cannot be shorter than its green phase.");
+        }
+        Self { cycle, green, offset }
+    }
+
+    pub fn cycle(&self) -> u32 {
+        self.cycle
+    }
+
+    pub fn green(&self) -> u32 {
+        self.green
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns whether the light is red in the given round.
+    pub fn red(&self, round: u32) -> bool {
+        let phase = (round as u64 + self.cycle as u64 - (self.offset % self.cycle) as u64) % self.cycle as u64;
+        phase >= self.green as u64
+    }
+}
+
+/// How an actuated traffic light extends its green phase. Once a light has been green for
+/// `min_green` rounds, it turns red as soon as no car has been detected for `gap` rounds at the
+/// detector `detector` cells in front of it, but at the latest after `max_green` rounds. A car
+/// is detected when it passes or stands in the detector cell.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Actuation {
+    min_green: u32,
+    max_green: u32,
+    gap: u32,
+    detector: u32,
+}
+
+impl Default for Actuation {
+    fn default() -> Self {
+        Self { min_green: 10, max_green: 100, gap: 3, detector: 10 }
+    }
+}
+
+impl Actuation {
+    pub fn new(min_green: u32, max_green: u32, gap: u32, detector: u32) -> Self {
+        if max_green == 0 || min_green > max_green || gap == 0 {
+            panic!("An actuated traffic light must be green for at least one round, cannot have a minimum green phase longer than its maximum and needs a gap of at least one round.");
+        }
+        Self { min_green, max_green, gap, detector }
+    }
+
+    pub fn min_green(&self) -> u32 {
+        self.min_green
+    }
+
+    pub fn max_green(&self) -> u32 {
+        self.max_green
+    }
+
+    pub fn gap(&self) -> u32 {
+        self.gap
+    }
+
+    /// Returns the number of cells between the detector and the light.
+    pub fn detector(&self) -> u32 {
+        self.detector
+    }
+}
+
+/// The phase of an actuated traffic light.
+#[derive(Debug, Clone, Default)]
+pub struct LightState {
+    red: bool,
+    phase_rounds: u32,
+    gap_rounds: u32,
+    detector_cars_passed: i32,
+}
+
+impl LightState {
+    pub fn red(&self) -> bool {
+        self.red
+    }
+
+    /// Returns the number of cars that had passed the detector when it was last read.
+    pub fn detector_cars_passed(&self) -> i32 {
+        self.detector_cars_passed
+    }
+
+    /// Forgets the cars that had passed the detector, as its records have been reset.
+    pub fn reset_detector(&mut self) {
+        self.detector_cars_passed = 0;
+    }
+
+    /// Advances the phase by one round. A red phase lasts as long as the red phase of the timing
+    /// (`cycle - green`) and a green phase as long as the actuation extends it.
+    pub fn advance(&mut self, timing: &LightTiming, actuation: &Actuation, detected: bool, detector_cars_passed: i32) {
+        self.phase_rounds += 1;
+        self.detector_cars_passed = detector_cars_passed;
+        self.gap_rounds = if detected { 0 } else { self.gap_rounds + 1 };
+        let switch = if self.red {
+            self.phase_rounds >= timing.cycle() - timing.green()
+        } else {
+            self.phase_rounds >= actuation.max_green || self.phase_rounds >= actuation.min_green && self.gap_rounds >= actuation.gap
+        };
+        if switch {
+            self.red = !self.red;
+            self.phase_rounds = 0;
+            self.gap_rounds = 0;
+        }
+    }
+}
+
+/// A traffic light at a cell with its own timing. Actuated lights only use the timing for the
+/// length of their red phase.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrafficLight {
+    location: CellLocation,
+    timing: LightTiming,
+    actuation: Option<Actuation>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseTrafficLightError;
+
+impl TrafficLight {
+    pub fn new(location: CellLocation, timing: LightTiming) -> Self {
+        Self { location, timing, actuation: None }
+    }
+
+    /// Makes the traffic light extend its green phase while cars keep arriving. (See: `Actuation`)
+    pub fn with_actuation(mut self, actuation: Actuation) -> Self {
+        self.actuation = Some(actuation);
+        self
+    }
+
+    pub fn location(&self) -> &CellLocation {
+        &self.location
+    }
+
+    pub fn timing(&self) -> &LightTiming {
+        &self.timing
+    }
+
+    pub fn actuation(&self) -> Option<&Actuation> {
+        self.actuation.as_ref()
+    }
+
+    /// Parses a traffic light in the format `(lane, index[, cycle=rounds][, green=rounds][, offset=rounds])`,
+    /// whose lane may also be `*` or `all`, which stands for a light at the same cell index in each
+    /// of the `lanes` lanes. Left out timings keep their defaults. (See: `LightTiming`) The light
+    /// is actuated if `actuated` or any of `min_green=rounds`, `max_green=rounds`, `gap=rounds`
+    /// and `detector=cells` is added. (See: `Actuation`)
+    pub fn parse_in_lanes(s: &str, lanes: u32) -> Result<Vec<Self>, ParseTrafficLightError> {
+        let s: String = s.replace(' ', "");
+        let inner = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or(ParseTrafficLightError)?;
+        let mut split = inner.split(',');
+        let (Some(lane), Some(index)) = (split.next(), split.next()) else {
+            return Err(ParseTrafficLightError);
+        };
+        let locations = CellLocation::parse_in_lanes(&format!("({},{})", lane, index), lanes).map_err(|_| ParseTrafficLightError)?;
+
+        let default = LightTiming::default();
+        let (mut cycle, mut green, mut offset) = (default.cycle, default.green, default.offset);
+        let mut actuation = None;
+        for option in split {
+            if option == "actuated" {
+                actuation.get_or_insert_with(Actuation::default);
+                continue;
+            }
+            let (name, rounds) = option.split_once('=').ok_or(ParseTrafficLightError)?;
+            let rounds = rounds.parse::<u32>().map_err(|_| ParseTrafficLightError)?;
+            match name {
+                "cycle" => cycle = rounds,
+                "green" => green = rounds,
+                "offset" => offset = rounds,
+                "min_green" => actuation.get_or_insert_with(Actuation::default).min_green = rounds,
+                "max_green" => actuation.get_or_insert_with(Actuation::default).max_green = rounds,
+                "gap" => actuation.get_or_insert_with(Actuation::default).gap = rounds,
+                "detector" => actuation.get_or_insert_with(Actuation::default).detector = rounds,
+                _ => return Err(ParseTrafficLightError),
+            }
+        }
+        if cycle == 0 || green > cycle {
+            return Err(ParseTrafficLightError);
+        }
+        if actuation.is_some_and(|actuation| actuation.max_green == 0 || actuation.min_green > actuation.max_green || actuation.gap == 0) {
+            return Err(ParseTrafficLightError);
+        }
+        let timing = LightTiming::new(cycle, green, offset);
+        Ok(locations
+            .into_iter()
+            .map(|location| TrafficLight { location, timing, actuation })
+            .collect())
+    }
+}
+
+/// A corridor of traffic lights that share a cycle and turn green one after another, so that cars
+/// driving at the progression speed meet green lights all along the corridor.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GreenWave {
+    start: usize,
+    end: usize,
+    speed: f64,
+    cycle: u32,
+    green: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseGreenWaveError;
+
+impl GreenWave {
+    pub fn new(start: usize, end: usize, speed: f64, cycle: u32, green: u32) -> Self {
+        if speed <= 0.0 {
+            panic!("The progression speed of a green wave must be greater than 0.");
+        }
+        LightTiming::new(cycle, green, 0);
+        Self { start, end, speed, cycle, green }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns whether the corridor covers the cell index. It covers the cell indexes from `start`
+    /// (inclusive) to `end` (exclusive) in every lane.
+    pub fn contains(&self, index: usize) -> bool {
+        (self.start..self.end).contains(&index)
+    }
+
+    /// Retimes the traffic lights of the corridor. The first light keeps an offset of 0 and every
+    /// other light turns green as many rounds later as a car at the progression speed needs to
+    /// get there from the first one.
+    pub fn coordinate(&self, traffic_lights: &mut [TrafficLight]) {
+        let Some(first) = traffic_lights.iter().filter(|light| self.contains(light.location().index())).map(|light| light.location().index()).min() else { return; };
+        for light in traffic_lights.iter_mut().filter(|light| self.contains(light.location().index())) {
+            let travel_rounds = ((light.location().index() - first) as f64 / self.speed).round() as u32;
+            light.timing = LightTiming::new(self.cycle, self.green, travel_rounds % self.cycle);
+        }
+    }
+}
+
+impl FromStr for GreenWave {
+    type Err = ParseGreenWaveError;
+
+    /// Parses a green wave in the format `(start-end, speed[, cycle=rounds][, green=rounds])`.
+    /// The cycle defaults to that of a traffic light without a timing and the green phase to half
+    /// of the cycle.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let inner = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or(ParseGreenWaveError)?;
+        let mut split = inner.split(',');
+        let (Some(indexes), Some(speed)) = (split.next(), split.next()) else {
+            return Err(ParseGreenWaveError);
+        };
+        let (start, end) = indexes.split_once('-').ok_or(ParseGreenWaveError)?;
+        let start = start.parse::<usize>().map_err(|_| ParseGreenWaveError)?;
+        let end = end.parse::<usize>().map_err(|_| ParseGreenWaveError)?;
+        let speed = speed.parse::<f64>().map_err(|_| ParseGreenWaveError)?;
+
+        let mut cycle = LightTiming::default().cycle;
+        let mut green = None;
+        for option in split {
+            let (name, rounds) = option.split_once('=').ok_or(ParseGreenWaveError)?;
+            let rounds = rounds.parse::<u32>().map_err(|_| ParseGreenWaveError)?;
+            match name {
+                "cycle" => cycle = rounds,
+                "green" => green = Some(rounds),
+                _ => return Err(ParseGreenWaveError),
+            }
+        }
+        let green = green.unwrap_or(cycle / 2);
+        if start >= end || !speed.is_finite() || speed <= 0.0 || cycle == 0 || green > cycle {
+            return Err(ParseGreenWaveError);
+        }
+        Ok(Self::new(start, end, speed, cycle, green))
+    }
+}
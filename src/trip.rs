@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::car::{Car, CarId};
+use crate::observer::Observer;
+use crate::{Road, CELL_M, ROUND_S};
+
+/// The trip of a car that drove past the end of an open road.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trip {
+    pub car: CarId,
+    pub vehicle_type: usize,
+    /// The round in which the car entered the road, or `None` if it was on the road from the start.
+    pub departure_round: Option<u32>,
+    pub arrival_round: u32,
+    /// The lane on which the car entered the road, or `None` if it was on the road from the start.
+    pub entry_lane: Option<usize>,
+    pub exit_lane: usize,
+    pub lane_changes: u32,
+    /// The distance driven in cells.
+    pub distance: u32,
+    pub stops: u32,
+}
+
+impl Trip {
+    pub fn new(car: &Car, arrival_round: u32, exit_lane: usize) -> Self {
+        Self {
+            car: car.id(),
+            vehicle_type: car.vehicle_type(),
+            departure_round: car.departure().map(|(round, _)| round),
+            arrival_round,
+            entry_lane: car.departure().map(|(_, lane)| lane),
+            exit_lane,
+            lane_changes: car.lane_changes(),
+            distance: car.distance(),
+            stops: car.stops(),
+        }
+    }
+
+    /// Returns the number of rounds from the departure to the arrival, or `None` if the car was on
+    /// the road from the start.
+    pub fn travel_rounds(&self) -> Option<u32> {
+        self.departure_round.map(|departure_round| self.arrival_round - departure_round)
+    }
+}
+
+/// Writes the trips of all cars that leave an open road to a CSV file, one row per trip in the
+/// order of arrival. Fields that are unknown for cars that were on the road from the start are
+/// left empty.
+#[derive(Debug)]
+pub struct TripWriter {
+    writer: BufWriter<File>,
+}
+
+impl TripWriter {
+    pub fn new(filepath: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writeln!(writer, "car,vehicle_type,departure_round,arrival_round,travel_time_s,entry_lane,exit_lane,lane_changes,distance_m,stops")?;
+        Ok(Self { writer })
+    }
+
+    fn write_trip(&mut self, trip: &Trip) -> io::Result<()> {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{},{}",
+            trip.car,
+            trip.vehicle_type,
+            optional(trip.departure_round.map(|round| round.to_string())),
+            trip.arrival_round,
+            optional(trip.travel_rounds().map(|rounds| (rounds as f64 * ROUND_S).to_string())),
+            optional(trip.entry_lane.map(|lane| lane.to_string())),
+            trip.exit_lane,
+            trip.lane_changes,
+            trip.distance as f64 * CELL_M,
+            trip.stops
+        )
+    }
+}
+
+impl Observer for TripWriter {
+    fn start(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn observe(&mut self, road: &Road) -> Result<(), Box<dyn Error>> {
+        for trip in road.arrived_trips() {
+            self.write_trip(trip)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _road: &Road) -> Result<(), Box<dyn Error>> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.writer.flush()?)
+    }
+}
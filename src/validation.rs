@@ -0,0 +1,387 @@
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use serde::Serialize;
+use crate::car::VehicleBlueprint;
+use crate::cell::{CellLocation, CellLocationRange, ReservedZone, SpeedLimitZone};
+use crate::demand::DemandProfile;
+use crate::driver::ProbabilityDistribution;
+use crate::event_log::EventLog;
+use crate::intersection::{SignalPhases, TurnProbabilities};
+use crate::density::{DensityKeyframe, LaneDensity};
+use crate::layout::RoadLayout;
+use crate::profile::ParameterProfile;
+use crate::section::Section;
+use crate::terminal::{FollowTarget, View};
+use crate::traffic_light::{GreenWave, TrafficLight};
+use crate::weather::WeatherChange;
+use crate::cli::Cli;
+use crate::{read_settings_file, SimulationConfig};
+
+/// The problems found in a scenario without running it. Errors make the simulation fail or give
+/// meaningless results, while warnings point out settings that are likely unintended.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error(&mut self, error: String) {
+        self.errors.push(error);
+    }
+
+    fn warning(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    /// Parses every non-empty tuple on its own and reports those that cannot be parsed.
+    fn parse_tuples<D: FromStr>(&mut self, option: &str, stringified_tuples: &[String]) -> Vec<D> {
+        let mut tuples = Vec::new();
+        for string in stringified_tuples.iter().filter(|string| !string.trim().is_empty()) {
+            match string.parse::<D>() {
+                Ok(tuple) => tuples.push(tuple),
+                Err(_) => self.error(format!("`{}` of `{}` cannot be parsed.", string.trim(), option)),
+            }
+        }
+        tuples
+    }
+
+    fn parse_locations_in_lanes(&mut self, option: &str, strings: &[String], lanes: u32) -> Vec<CellLocation> {
+        let mut locations = Vec::new();
+        for string in strings.iter().filter(|string| !string.trim().is_empty()) {
+            match CellLocation::parse_in_lanes(string, lanes) {
+                Ok(mut parsed) => locations.append(&mut parsed),
+                Err(_) => self.error(format!("`{}` of `{}` cannot be parsed.", string.trim(), option)),
+            }
+        }
+        locations
+    }
+
+    fn parse_file<D: FromStr>(&mut self, option: &str, path: &Path) -> Option<D> where <D as FromStr>::Err: Debug {
+        match fs::read_to_string(path) {
+            Ok(contents) => match contents.parse::<D>() {
+                Ok(parsed) => Some(parsed),
+                Err(error) => {
+                    self.error(format!("The file `{}` of `{}` cannot be parsed: {:?}", path.display(), option, error));
+                    None
+                },
+            },
+            Err(error) => {
+                self.error(format!("The file `{}` of `{}` cannot be read: {}", path.display(), option, error));
+                None
+            },
+        }
+    }
+
+    fn check_probability(&mut self, option: &str, probability: f32) {
+        if !(0.0..=1.0).contains(&probability) {
+            self.error(format!("`{}` must be a number between 0 and 1, but is {}.", option, probability));
+        }
+    }
+}
+
+/// The cells of the road that the locations of a scenario are checked against.
+struct Grid {
+    lanes: u32,
+    length: u32,
+    blocked: Vec<Vec<bool>>,
+}
+
+impl Grid {
+    fn contains(&self, location: &CellLocation) -> bool {
+        location.lane() < self.lanes as usize && location.index() < self.length as usize
+    }
+
+    fn blocked(&self, location: &CellLocation) -> bool {
+        self.contains(location) && self.blocked[location.lane()][location.index()]
+    }
+}
+
+/// Checks a scenario for consistency without running it: that every option can be parsed, that
+/// probabilities and densities are in range, that all locations are on the road, that blocks do
+/// not overlap and that traffic lights and monitored cells are not blocked, as well as the
+/// combinations of options that the simulation rejects.
+pub fn validate(args: &SimulationConfig) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    // vehicles and probabilities
+    let mut vehicles: Vec<VehicleBlueprint> = report.parse_tuples("vehicles", &args.vehicles);
+    args.add_automated_vehicles(&mut vehicles);
+    let density_sum: f32 = vehicles.iter().map(|vehicle| vehicle.traffic_density()).sum();
+    if !(0.0..=1.0).contains(&density_sum) {
+        report.error(format!("The sum of all traffic densities must be a number between 0 and 1, but is {}.", density_sum));
+    }
+    report.check_probability("dilly_dally_probability", args.dilly_dally_probability);
+    report.check_probability("stay_in_lane_probability", args.stay_in_lane_probability);
+    report.check_probability("automated_share", args.automated_share);
+    for (option, distribution) in [("dilly_dally_distribution", &args.dilly_dally_distribution), ("stay_in_lane_distribution", &args.stay_in_lane_distribution)] {
+        if let Some(distribution) = distribution {
+            report.parse_tuples::<ProbabilityDistribution>(option, std::slice::from_ref(distribution));
+        }
+    }
+    report.check_probability("courtesy_probability", args.courtesy_probability);
+    report.check_probability("congestion_threshold", args.congestion_threshold);
+    report.check_probability("light_failure_probability", args.light_failure_probability);
+    if args.light_failure_rounds == 0 {
+        report.error("Light failures must last at least one round on average.".to_string());
+    }
+    report.check_probability("incident_probability", args.incident_probability);
+    if let Some(probability) = args.rubbernecking_dilly_dally_probability {
+        report.check_probability("rubbernecking_dilly_dally_probability", probability);
+    }
+    if args.incident_rounds == 0 {
+        report.error("Incidents must last at least one round on average.".to_string());
+    }
+    if args.warmup >= args.rounds {
+        report.error(format!("The warm-up of {} rounds must be shorter than the {} rounds.", args.warmup, args.rounds));
+    }
+
+    // road
+    let mut grid = Grid { lanes: args.lanes, length: args.length, blocked: Vec::new() };
+    let mut blocks = Vec::new();
+    let mut traffic_lights = Vec::new();
+    match &args.layout {
+        Some(path) => {
+            if !args.block.iter().all(|block| block.trim().is_empty()) || !args.traffic_lights.iter().all(|light| light.trim().is_empty()) {
+                report.warning("`block` and `traffic_lights` are ignored because a `layout` is used.".to_string());
+            }
+            if let Some(layout) = report.parse_file::<RoadLayout>("layout", path) {
+                grid.lanes = layout.lanes();
+                grid.length = layout.length();
+                blocks = layout.block().iter().map(|block| CellLocationRange::new(block.lane(), block.indexes().start, block.indexes().end)).collect();
+                traffic_lights = layout.traffic_lights().iter().map(|light| CellLocation::new(light.lane(), light.index())).collect();
+            }
+        },
+        None => {
+            blocks = report.parse_tuples("block", &args.block);
+            for string in args.traffic_lights.iter().filter(|string| !string.trim().is_empty()) {
+                match TrafficLight::parse_in_lanes(string, args.lanes) {
+                    Ok(parsed) => traffic_lights.extend(parsed.iter().map(|light| light.location().clone())),
+                    Err(_) => report.error(format!("`{}` of `traffic_lights` cannot be parsed. A traffic light's cycle must last at least one round and its green phase no longer than the cycle.", string.trim())),
+                }
+            }
+        },
+    }
+    if grid.lanes == 0 || grid.length == 0 {
+        report.error("The road must have at least one lane and one cell per lane.".to_string());
+    }
+    grid.blocked = vec![vec![false; grid.length as usize]; grid.lanes as usize];
+    for block in &blocks {
+        let indexes = block.indexes();
+        if block.lane() >= grid.lanes as usize || indexes.end > grid.length as usize {
+            report.error(format!("The block of cells {:?} in lane {} is not on the road.", indexes, block.lane()));
+            continue;
+        }
+        if indexes.is_empty() {
+            report.warning(format!("The block of cells {:?} in lane {} is empty.", indexes, block.lane()));
+        }
+        let lane = &mut grid.blocked[block.lane()];
+        if lane[indexes.clone()].iter().any(|blocked| *blocked) {
+            report.error(format!("The block of cells {:?} in lane {} overlaps another block.", indexes, block.lane()));
+        }
+        lane[indexes].fill(true);
+    }
+    let reserved_zones: Vec<ReservedZone> = report.parse_tuples("reserve", &args.reserve);
+    for zone in &reserved_zones {
+        let indexes = zone.indexes(grid.length as usize);
+        if zone.lane() >= grid.lanes as usize || indexes.end > grid.length as usize {
+            report.error(format!("The reserved cells {:?} in lane {} are not on the road.", indexes, zone.lane()));
+        }
+    }
+    let speed_limits: Vec<SpeedLimitZone> = report.parse_tuples("speed_limit", &args.speed_limit);
+    for zone in &speed_limits {
+        let (lane, indexes) = (zone.range().lane(), zone.range().indexes());
+        if lane >= grid.lanes as usize || indexes.end > grid.length as usize {
+            report.error(format!("The speed limit zone of cells {:?} in lane {} is not on the road.", indexes, lane));
+        } else if zone.limit() == 0 {
+            report.error(format!("The speed limit of the zone of cells {:?} in lane {} must be at least 1.", indexes, lane));
+        } else if indexes.is_empty() {
+            report.warning(format!("The speed limit zone of cells {:?} in lane {} is empty.", indexes, lane));
+        }
+    }
+    for (light_i, traffic_light) in traffic_lights.iter().enumerate() {
+        if !grid.contains(traffic_light) {
+            report.error(format!("The traffic light {} is not on the road.", traffic_light));
+        } else if grid.blocked(traffic_light) {
+            report.error(format!("The traffic light {} is on a blocked cell.", traffic_light));
+        } else if traffic_lights[..light_i].contains(traffic_light) {
+            report.warning(format!("There is more than one traffic light at {}.", traffic_light));
+        }
+    }
+    let green_waves: Vec<GreenWave> = report.parse_tuples("green_waves", &args.green_waves);
+    for green_wave in &green_waves {
+        let mut indexes: Vec<usize> = traffic_lights.iter().map(CellLocation::index).filter(|index| green_wave.contains(*index)).collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        if indexes.len() < 2 {
+            report.warning(format!("The green wave from cell {} to cell {} coordinates fewer than two traffic lights.", green_wave.start(), green_wave.end()));
+        }
+    }
+    if grid.blocked.iter().any(|lane| lane.iter().all(|blocked| *blocked)) {
+        report.warning("A lane is blocked entirely.".to_string());
+    }
+
+    // locations
+    let monitors: Vec<CellLocation> = report.parse_tuples("monitor", &args.monitor);
+    for monitor in &monitors {
+        if !grid.contains(monitor) {
+            report.error(format!("The monitored cell {} is not on the road.", monitor));
+        } else if grid.blocked(monitor) {
+            report.warning(format!("The monitored cell {} is blocked, so no car will pass it.", monitor));
+        }
+    }
+    for section in report.parse_tuples::<Section>("sections", &args.sections) {
+        if section.start() >= grid.length as usize || section.end() >= grid.length as usize {
+            report.error(format!("The section from cell {} to cell {} is not on the road.", section.start(), section.end()));
+        } else if args.demand.is_some() && section.end() < section.start() {
+            report.error(format!("The section from cell {} to cell {} ends before it starts, which only a closed road allows.", section.start(), section.end()));
+        }
+    }
+    for spillback_point in report.parse_locations_in_lanes("spillback_points", &args.spillback_points, grid.lanes) {
+        if !grid.contains(&spillback_point) {
+            report.error(format!("The spillback point {} is not on the road.", spillback_point));
+        }
+    }
+    if let Some(view) = &args.view {
+        if view.parse::<View>().is_err() {
+            report.error(format!("`{}` of `view` cannot be parsed.", view));
+        }
+    }
+    if let Some(follow) = &args.follow {
+        if follow.parse::<FollowTarget>().is_err() {
+            report.error(format!("`{}` of `follow` cannot be parsed.", follow));
+        }
+    }
+
+    // cars
+    let cars: Vec<u32> = report.parse_tuples("cars", &args.cars);
+    let lane_densities: Vec<LaneDensity> = report.parse_tuples("lane_densities", &args.lane_densities);
+    if !cars.is_empty() && !lane_densities.is_empty() {
+        report.error("Car counts and lane densities cannot be used together.".to_string());
+    }
+    if cars.len() > 1 && cars.len() != vehicles.len() {
+        report.error(format!("There are {} car counts for {} vehicle types.", cars.len(), vehicles.len()));
+    }
+    if !lane_densities.is_empty() && lane_densities.len() != grid.lanes as usize {
+        report.error(format!("There are {} lane densities for {} lanes.", lane_densities.len(), grid.lanes));
+    }
+    for (vehicle_type, vehicle) in vehicles.iter().enumerate() {
+        if vehicle.lane_weights().is_some_and(|weights| weights.len() != grid.lanes as usize) {
+            report.error(format!("The number of lane weights of vehicle type {} does not match the {} lanes.", vehicle_type, grid.lanes));
+        }
+        if vehicle.lane_densities().is_some_and(|densities| densities.len() != grid.lanes as usize) {
+            report.error(format!("The number of lane densities of vehicle type {} does not match the {} lanes.", vehicle_type, grid.lanes));
+        }
+        if vehicle.allowed_lanes().is_some_and(|lanes| lanes.is_empty() || lanes.iter().any(|lane_i| *lane_i >= grid.lanes as usize)) {
+            report.error(format!("The allowed lanes of vehicle type {} are not all lanes of the road.", vehicle_type));
+        }
+    }
+    if vehicles.is_empty() && (!cars.is_empty() || args.demand.is_some()) {
+        report.error("At least one vehicle type is required to place or let in cars.".to_string());
+    }
+
+    // files and open roads
+    if let Some(path) = &args.demand {
+        if let Some(demand) = report.parse_file::<DemandProfile>("demand", path) {
+            if demand.bins().iter().any(|bin| !bin.shares().is_empty() && bin.shares().len() != vehicles.len()) {
+                report.error(format!("The number of vehicle type shares in the demand does not match the {} vehicle types.", vehicles.len()));
+            }
+        }
+    }
+    if let Some(path) = &args.parameter_profile {
+        if let Some(profile) = report.parse_file::<ParameterProfile>("parameter_profile", path) {
+            if profile.bins().iter().any(|bin| bin.max_speeds().len() > vehicles.len()) {
+                report.error("The parameter profile sets the maximum speeds of more vehicle types than there are.".to_string());
+            }
+        }
+    }
+    let _: Vec<WeatherChange> = report.parse_tuples("weather_schedule", &args.weather_schedule);
+    let keyframes: Vec<DensityKeyframe> = report.parse_tuples("target_density", &args.target_density);
+    if !keyframes.is_empty() && args.demand.is_some() {
+        report.error("Density schedules are only supported on closed roads.".to_string());
+    }
+    if args.trips.is_some() && args.demand.is_none() {
+        report.error("Trips can only be recorded on open roads. Use `demand` to open the road.".to_string());
+    }
+    if args.arrow_stream.as_deref() == Some("-") && (args.verbose || args.animate) {
+        report.error("Cannot print the road while streaming Arrow record batches to stdout.".to_string());
+    }
+    if args.breakdown_jam_cars == Some(0) || args.breakdown_jam_cars.is_some() && args.breakdown_rounds == 0 {
+        report.error("A jam must consist of at least one car and persist for at least one round.".to_string());
+    }
+    if args.ttc_threshold.is_some_and(|ttc_threshold| ttc_threshold <= 0.0) || args.ttc_threshold.is_some() && args.hard_braking == 0 {
+        report.error("The time-to-collision threshold must be positive and a hard braking must slow a car down by at least one cell per round.".to_string());
+    }
+    if args.jam_rounds == Some(0) {
+        report.error("Cars must be slow for at least one round to be jammed.".to_string());
+    }
+    if args.steady_state_window == Some(0) || args.steady_state_window.is_some() && args.steady_state_tolerance < 0.0 {
+        report.error("The steady state window must span at least one round and its tolerance must not be negative.".to_string());
+    }
+    if args.max_deceleration == Some(0) {
+        report.error("Cars must be able to brake by at least one cell per round.".to_string());
+    }
+    if args.emergency_braking_factor < 1.0 {
+        report.error(format!("`emergency_braking_factor` must be at least 1, but is {}.", args.emergency_braking_factor));
+    }
+    if args.flush_every == Some(0) {
+        report.error("The partial result must be saved every one or more rounds.".to_string());
+    }
+    if args.rounds == 0 {
+        report.warning("The simulation runs for no rounds.".to_string());
+    }
+
+    report
+}
+
+/// Checks the scenario of the command line like `validate` and also the options that pick what is
+/// done with it, e.g. that a batch manifest can be read and that it is not combined with a density
+/// sweep.
+pub fn validate_command_line(cli: &Cli) -> ValidationReport {
+    let mut report = validate(&cli.scenario);
+    if cli.density_sweep.is_some() && cli.scenario.demand.is_some() {
+        report.error("A density sweep needs a closed road, so it cannot be combined with `demand`.".to_string());
+    }
+    if let Some(path) = &cli.batch {
+        if let Err(error) = read_settings_file(path) {
+            report.error(format!("The batch manifest `{}` cannot be read: {}", path.display(), error));
+        }
+        if cli.density_sweep.is_some() || cli.intersection.is_some() {
+            report.error("A batch cannot be combined with a density sweep or an intersection.".to_string());
+        }
+    }
+    if let Some(replications) = cli.replications {
+        if replications < 2 {
+            report.error(format!("At least two replications are needed to estimate the spread of the metrics, but there are {}.", replications));
+        }
+        if cli.batch.is_some() || cli.density_sweep.is_some() || cli.intersection.is_some() {
+            report.error("Replications cannot be combined with a batch, a density sweep or an intersection.".to_string());
+        }
+    }
+    if let Some(path) = &cli.replay {
+        if let Err(error) = EventLog::read(path) {
+            report.error(format!("The event log `{}` cannot be replayed: {}", path.display(), error));
+        }
+    }
+    if let Some(turns) = &cli.intersection {
+        report.parse_tuples::<TurnProbabilities>("intersection", std::slice::from_ref(turns));
+        report.parse_tuples::<SignalPhases>("intersection_phases", std::slice::from_ref(&cli.intersection_phases));
+        if cli.scenario.vehicles.iter().all(|vehicles| vehicles.is_empty()) {
+            report.error("At least one vehicle type is required to let cars into the intersection.".to_string());
+        }
+        if cli.scenario.length < 2 {
+            report.error("The approaches of the intersection need at least two cells.".to_string());
+        }
+        if cli.turning_movement_interval == 0 {
+            report.error("Turning movements must be counted over intervals of at least one round.".to_string());
+        }
+    }
+
+    report
+}
@@ -0,0 +1,119 @@
+use wasm_bindgen::prelude::*;
+use crate::car::VehicleBlueprint;
+use crate::Road;
+
+pub use crate::cell::{BLOCKED, FREE, OCCUPIED, RED_LIGHT};
+
+/// A road that JavaScript can run round by round, e.g. to drive a visualization in the browser.
+///
+/// The state of the cells is kept in buffers in the memory of the module with one byte per cell,
+/// lane by lane: the occupancy (`FREE`, `OCCUPIED`, `BLOCKED` or `RED_LIGHT`) and the speed and
+/// maximum speed of the car in the cell (0 without a car). They are updated by every `step` and can be viewed
+/// without copying as typed arrays, e.g.
+/// `new Uint8Array(memory.buffer, simulation.speeds_ptr(), simulation.cells())`. Views have to
+/// be created again after the memory of the module has grown.
+#[wasm_bindgen]
+pub struct Simulation {
+    road: Road,
+    occupancy: Vec<u8>,
+    speeds: Vec<u8>,
+    max_speeds: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Simulation {
+    /// Creates a road with cars placed according to the vehicle types, given in the format of the
+    /// `vehicles` option, e.g. `["(5, 1, 0.2)", "(3, 2, 0.05)"]`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        lanes: u32,
+        length: u32,
+        vehicles: Vec<String>,
+        dilly_dally_probability: f32,
+        stay_in_lane_probability: f32,
+        seed: Option<u64>,
+    ) -> Result<Simulation, String> {
+        let vehicles = vehicles
+            .iter()
+            .map(|vehicle| vehicle.parse::<VehicleBlueprint>().map_err(|_| format!("`{}` is not a vehicle type.", vehicle)))
+            .collect::<Result<Vec<VehicleBlueprint>, String>>()?;
+        if lanes == 0 || length == 0 {
+            return Err("The road must have at least one lane and one cell per lane.".to_string());
+        }
+        if !(0.0..=1.0).contains(&dilly_dally_probability) || !(0.0..=1.0).contains(&stay_in_lane_probability) {
+            return Err("Probabilities must be numbers between 0 and 1.".to_string());
+        }
+        let road = Road::new(lanes, length, &vehicles, dilly_dally_probability, stay_in_lane_probability, &[], &[], seed);
+        let cells = (lanes * length) as usize;
+        let mut simulation = Simulation { road, occupancy: vec![FREE; cells], speeds: vec![0; cells], max_speeds: vec![0; cells] };
+        simulation.update_buffers();
+        Ok(simulation)
+    }
+
+    /// Runs the given number of rounds and updates the buffers.
+    pub fn step(&mut self, rounds: u32) {
+        for _ in 0..rounds {
+            self.road.round();
+        }
+        self.update_buffers();
+    }
+
+    pub fn lanes(&self) -> u32 {
+        self.road.lanes()
+    }
+
+    pub fn length(&self) -> u32 {
+        self.road.length()
+    }
+
+    /// Returns the number of cells, which is the length of each buffer.
+    pub fn cells(&self) -> usize {
+        self.occupancy.len()
+    }
+
+    pub fn rounds(&self) -> u32 {
+        self.road.rounds()
+    }
+
+    /// Returns the pointer to the occupancy of the cells.
+    pub fn occupancy_ptr(&self) -> *const u8 {
+        self.occupancy.as_ptr()
+    }
+
+    /// Returns the pointer to the speeds of the cars in the cells.
+    pub fn speeds_ptr(&self) -> *const u8 {
+        self.speeds.as_ptr()
+    }
+
+    /// Returns the pointer to the maximum speeds of the cars in the cells, e.g. for coloring them
+    /// by their relative speed.
+    pub fn max_speeds_ptr(&self) -> *const u8 {
+        self.max_speeds.as_ptr()
+    }
+
+    /// Returns a copy of the occupancy of the cells, for when a view is not needed.
+    pub fn occupancy(&self) -> Vec<u8> {
+        self.occupancy.clone()
+    }
+
+    /// Returns a copy of the speeds of the cars in the cells.
+    pub fn speeds(&self) -> Vec<u8> {
+        self.speeds.clone()
+    }
+}
+
+impl Simulation {
+    fn update_buffers(&mut self) {
+        let cells = self.road.cells().iter().flatten();
+        for (i, cell) in cells.enumerate() {
+            self.occupancy[i] = cell.occupancy_code();
+            self.speeds[i] = cell.car().map_or(0, |car| car.speed());
+            self.max_speeds[i] = cell.car().map_or(0, |car| car.max_speed());
+        }
+    }
+
+    /// Returns the road, e.g. to inspect it from Rust.
+    pub fn road(&self) -> &Road {
+        &self.road
+    }
+}
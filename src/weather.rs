@@ -0,0 +1,55 @@
+use std::str::FromStr;
+use clap::ValueEnum;
+use crate::Weather;
+
+/// A change of the weather at a round.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeatherChange {
+    round: u32,
+    weather: Weather,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseWeatherChangeError;
+
+impl FromStr for WeatherChange {
+    type Err = ParseWeatherChangeError;
+
+    /// Parses `(round, weather)`, e.g. `(500, rain)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.replace(' ', "");
+        let (round, weather) = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|s| s.split_once(','))
+            .ok_or(ParseWeatherChangeError)?;
+
+        let round = round.parse::<u32>().map_err(|_| ParseWeatherChangeError)?;
+        let weather = Weather::from_str(weather, true).map_err(|_| ParseWeatherChangeError)?;
+
+        Ok(WeatherChange { round, weather })
+    }
+}
+
+/// The weather over time: the weather at the start and the changes from their rounds on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeatherSchedule {
+    initial: Weather,
+    changes: Vec<WeatherChange>,
+}
+
+impl WeatherSchedule {
+    pub fn new(initial: Weather, mut changes: Vec<WeatherChange>) -> Self {
+        changes.sort_by_key(|change| change.round);
+        Self { initial, changes }
+    }
+
+    /// Returns the weather during the given round.
+    pub fn weather_at(&self, round: u32) -> Weather {
+        self.changes
+            .iter()
+            .rev()
+            .find(|change| change.round <= round)
+            .map_or(self.initial, |change| change.weather)
+    }
+}